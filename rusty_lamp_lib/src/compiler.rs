@@ -0,0 +1,757 @@
+/*
+ * Copyright © 2002-2017 Bluebeam Software, Inc. All Rights Reserved.
+ * Creator: Jonathan Rothberg
+ */
+
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use parser::lexer::Lexer;
+use parser::parser::{Parser, ParserOptions, ParseError};
+use parser::program::Program;
+use parser::ast::{InterfaceType, Statement, StatementKind};
+use parser::token::Token;
+use generator::resolver::{Resolver, ResolveError, program_uses_annotation, program_uses_future, program_uses_functions,
+                          program_uses_modules, program_uses_throws};
+use generator::generator::Generator;
+use generator::cpp_generator::CppGenerator;
+use generator::java_generator::JavaGenerator;
+use generator::kotlin_generator::KotlinGenerator;
+use generator::jni_generator::JniGenerator;
+use generator::objc_generator::ObjcGenerator;
+use generator::rust_generator::RustGenerator;
+use generator::rust_bridge_generator::RustBridgeGenerator;
+use generator::c_generator::CGenerator;
+use generator::swift_generator::SwiftGenerator;
+use generator::wasm_generator::WasmGenerator;
+use generator::python_generator::PythonGenerator;
+use generator::csharp_generator::CSharpGenerator;
+use generator::dart_generator::DartGenerator;
+use generator::node_generator::NodeGenerator;
+use generator::output::GeneratedOutput;
+use generator::spec::Spec;
+use config::{Config, ConfigError};
+use generator::typer::Typer;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Severity {
+    Error,
+    Warning
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let printable = match *self {
+            Severity::Error => "error",
+            Severity::Warning => "warning"
+        };
+
+        write!(f, "{}", printable)
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    pub message: String
+}
+
+impl Diagnostic {
+    pub fn error<S: Into<String>>(file: Option<String>, message: S) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            file: file,
+            line: None,
+            message: message.into()
+        }
+    }
+
+    pub fn warning<S: Into<String>>(file: Option<String>, message: S) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            file: file,
+            line: None,
+            message: message.into()
+        }
+    }
+
+    pub fn from_parse_error(name: &str, e: &ParseError) -> Diagnostic {
+        let mut diagnostic = Diagnostic::error(Some(e.file.clone().unwrap_or(name.into())), e.message.clone());
+        diagnostic.line = e.line;
+        diagnostic
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.file, self.line) {
+            (&Some(ref file), Some(line)) => write!(f, "{}: {}:{}: {}", self.severity, file, line, self.message),
+            (&Some(ref file), None) => write!(f, "{}: {}: {}", self.severity, file, self.message),
+            (&None, Some(line)) => write!(f, "{}: line {}: {}", self.severity, line, self.message),
+            (&None, None) => write!(f, "{}: {}", self.severity, self.message)
+        }
+    }
+}
+
+impl From<ResolveError> for Diagnostic {
+    fn from(err: ResolveError) -> Diagnostic {
+        Diagnostic::error(None, format!("{}", err))
+    }
+}
+
+impl From<ConfigError> for Diagnostic {
+    fn from(err: ConfigError) -> Diagnostic {
+        Diagnostic::error(None, format!("{}", err))
+    }
+}
+
+pub struct CompilerOptions {
+    pub spec: Spec,
+    pub include_paths: Vec<PathBuf>
+}
+
+impl CompilerOptions {
+    pub fn new(spec: Spec) -> CompilerOptions {
+        CompilerOptions {
+            spec: spec,
+            include_paths: Vec::new()
+        }
+    }
+
+    pub fn from_config(config: Config) -> Result<CompilerOptions, ConfigError> {
+        let include_paths = config.idl_include_paths.iter().map(PathBuf::from).collect();
+        let spec = Spec::new(config)?;
+
+        Ok(CompilerOptions {
+            spec: spec,
+            include_paths: include_paths
+        })
+    }
+}
+
+enum Source {
+    Memory(String, String),
+    File(PathBuf)
+}
+
+pub struct Compiler {
+    options: CompilerOptions,
+    sources: Vec<Source>
+}
+
+impl Compiler {
+    pub fn new(options: CompilerOptions) -> Compiler {
+        Compiler {
+            options: options,
+            sources: Vec::new()
+        }
+    }
+
+    pub fn source<N: Into<String>, C: Into<String>>(mut self, name: N, contents: C) -> Compiler {
+        self.sources.push(Source::Memory(name.into(), contents.into()));
+        self
+    }
+
+    pub fn file<P: AsRef<Path>>(mut self, path: P) -> Compiler {
+        self.sources.push(Source::File(path.as_ref().to_path_buf()));
+        self
+    }
+
+    pub fn include_path<P: AsRef<Path>>(mut self, path: P) -> Compiler {
+        self.options.include_paths.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn spec(&self) -> &Spec {
+        &self.options.spec
+    }
+
+    pub fn parse(&self) -> Result<Program, Vec<Diagnostic>> {
+        let mut program = Program::new();
+        let mut diagnostics = Vec::new();
+
+        for source in &self.sources {
+            let (name, contents, base_dir) = match *source {
+                Source::Memory(ref name, ref contents) => (name.clone(), contents.clone(), None),
+                Source::File(ref path) => {
+                    let mut contents = String::new();
+                    let read = File::open(path).and_then(|mut f| f.read_to_string(&mut contents));
+                    if let Err(e) = read {
+                        diagnostics.push(Diagnostic::error(Some(path.display().to_string()), format!("unable to read file: {}", e)));
+                        continue;
+                    }
+
+                    (path.display().to_string(), contents, path.parent().map(|p| p.to_path_buf()))
+                }
+            };
+
+            let parser_options = ParserOptions {
+                process_imports: true,
+                base_dir: base_dir,
+                include_paths: self.options.include_paths.clone()
+            };
+
+            let mut parser = Parser::new_with_options(Lexer::new(contents), parser_options);
+            let parsed = parser.parse_program().unwrap_or_default();
+            for e in parser.errors() {
+                diagnostics.push(Diagnostic::from_parse_error(&name, e));
+            }
+
+            // A source's module doesn't carry over into the sources after it.
+            let nested = parsed.statements.iter().any(|s| match s.stmtKind { StatementKind::Module(..) => true, _ => false });
+            program.statements.extend(parsed.statements);
+            if nested {
+                program.statements.push(Statement { stmtKind: StatementKind::Module(Token::Module, "".into()), annotations: Vec::new() });
+            }
+        }
+
+        if diagnostics.len() > 0 {
+            return Err(diagnostics);
+        }
+
+        Ok(program)
+    }
+
+    pub fn resolve(&self, program: &Program) -> Result<Typer, Vec<Diagnostic>> {
+        self.resolve_program(program).map(|(typer, _)| typer)
+    }
+
+    /// The typer and the program with qualified names and aliases expanded, for the generators.
+    fn resolve_program(&self, program: &Program) -> Result<(Typer, Program), Vec<Diagnostic>> {
+        let mut resolver = Resolver::new(self.options.spec.typer.clone());
+        let typer = resolver.resolve(program).map_err(|e| vec![Diagnostic::from(e)])?;
+        Ok((typer, resolver.program()))
+    }
+
+    pub fn compile(self) -> Result<GeneratedOutput, Vec<Diagnostic>> {
+        let program = self.parse()?;
+        Compiler::check_outputs(&self.options.spec.config, &program)?;
+        let (typer, program) = self.resolve_program(&program)?;
+
+        let mut spec = self.options.spec;
+        spec.typer = typer;
+        Compiler::check_features(&spec, &program)?;
+
+        let mut output = GeneratedOutput::new();
+        if Compiler::enabled(&spec.config.cpp.out) {
+            Generator::new(CppGenerator::new()).generate(&spec, &program, &mut output);
+        }
+
+        if Compiler::enabled(&spec.config.java.out) {
+            Generator::new(JavaGenerator::new()).generate(&spec, &program, &mut output);
+        }
+
+        if Compiler::enabled(&spec.config.kotlin.out) {
+            Generator::new(KotlinGenerator::new()).generate(&spec, &program, &mut output);
+        }
+
+        if Compiler::enabled(&spec.config.jni.out) {
+            Generator::new(JniGenerator::new()).generate(&spec, &program, &mut output);
+        }
+
+        if Compiler::enabled(&spec.config.objc.out) {
+            Generator::new(ObjcGenerator::new()).generate(&spec, &program, &mut output);
+        }
+
+        if Compiler::enabled(&spec.config.swift.out) {
+            Generator::new(SwiftGenerator::new()).generate(&spec, &program, &mut output);
+        }
+
+        if Compiler::enabled(&spec.config.rust.out) {
+            Generator::new(RustGenerator::new()).generate(&spec, &program, &mut output);
+        }
+
+        if Compiler::enabled(&spec.config.rust.bridge_out) {
+            Generator::new(RustBridgeGenerator::new()).generate(&spec, &program, &mut output);
+        }
+
+        if Compiler::enabled(&spec.config.c.out) {
+            Generator::new(CGenerator::new()).generate(&spec, &program, &mut output);
+        }
+
+        if Compiler::enabled(&spec.config.csharp.out) {
+            Generator::new(CSharpGenerator::new()).generate(&spec, &program, &mut output);
+        }
+
+        if Compiler::enabled(&spec.config.dart.out) {
+            Generator::new(DartGenerator::new()).generate(&spec, &program, &mut output);
+        }
+
+        if Compiler::enabled(&spec.config.wasm.out) {
+            Generator::new(WasmGenerator::new()).generate(&spec, &program, &mut output);
+        }
+
+        if Compiler::enabled(&spec.config.node.out) {
+            Generator::new(NodeGenerator::new()).generate(&spec, &program, &mut output);
+        }
+
+        if Compiler::enabled(&spec.config.python.out) {
+            Generator::new(PythonGenerator::new()).generate(&spec, &program, &mut output);
+        }
+
+        Ok(output)
+    }
+
+    /// Some generators wrap the output of another one, which has to be generated as well.  The
+    /// Objective-C class of a `+c` interface is implemented by the Objective-C++ layer, which
+    /// isn't generated, so a Swift proxy calling it couldn't link.
+    fn check_outputs(config: &Config, program: &Program) -> Result<(), Vec<Diagnostic>> {
+        let wrappers = vec![
+            ("rust.bridge_out", &config.rust.bridge_out, "rust.out", &config.rust.out, "Rust"),
+            ("swift.out", &config.swift.out, "objc.out", &config.objc.out, "Objective-C"),
+            ("csharp.out", &config.csharp.out, "c.out", &config.c.out, "C"),
+            ("dart.out", &config.dart.out, "c.out", &config.c.out, "C")
+        ];
+
+        let mut diagnostics = Vec::new();
+        for (out, folder, wrapped_out, wrapped_folder, wrapped) in wrappers {
+            if Compiler::enabled(folder) && !Compiler::enabled(wrapped_folder) {
+                diagnostics.push(Diagnostic::error(None, format!("{} is set without {}, the {} output it wraps", out, wrapped_out, wrapped)));
+            }
+        }
+
+        if Compiler::enabled(&config.swift.out) {
+            for stmt in &program.statements {
+                if let StatementKind::Interface(_, ref i, ref it, _, _) = stmt.stmtKind {
+                    if it.contains(&InterfaceType::Cpp) && !it.contains(&InterfaceType::ObjectiveC) {
+                        diagnostics.push(Diagnostic::error(None, format!("swift.out doesn't support the +c interface {}: there is no \
+                                                                          Objective-C++ implementation of its Objective-C class", i.value)));
+                    }
+                }
+            }
+        }
+
+        if diagnostics.len() > 0 {
+            return Err(diagnostics);
+        }
+
+        Ok(())
+    }
+
+    /// Some generators have no way to express a feature yet, such as handing a pending result or
+    /// a typed error across the C API, so programs using it are rejected rather than generated
+    /// wrong.
+    fn check_features(spec: &Spec, program: &Program) -> Result<(), Vec<Diagnostic>> {
+        let config = &spec.config;
+        let bridges = vec![
+            ("Swift", Compiler::enabled(&config.swift.out)),
+            ("Rust", Compiler::enabled(&config.rust.out)),
+            ("Rust bridge", Compiler::enabled(&config.rust.bridge_out)),
+            ("C", Compiler::enabled(&config.c.out)),
+            ("WebAssembly", Compiler::enabled(&config.wasm.out)),
+            ("Node.js", Compiler::enabled(&config.node.out)),
+            ("Python", Compiler::enabled(&config.python.out))
+        ];
+        // Their marshaling converts strings, binaries and dates from the standard C++ types, which
+        // a `[types.<name>] cpp` override replaces with a type it knows nothing about.
+        let cpp_override = |name: &str| config.types.get(name).map_or(false, |o| o.cpp.is_some());
+        let converters = vec!["Rust bridge", "C", "WebAssembly", "Node.js", "Python"];
+        let features = vec![
+            ("future<T>", program_uses_future(program), vec!["Swift", "Rust bridge", "C", "Python"]),
+            ("throws", program_uses_throws(program), vec!["Swift", "Rust bridge", "C", "WebAssembly", "Node.js", "Python"]),
+            ("fn types", program_uses_functions(program), vec!["Swift", "Rust bridge", "C", "WebAssembly", "Node.js", "Python"]),
+            ("@cpp_name", program_uses_annotation(program, "cpp_name"), vec!["Rust bridge", "C", "WebAssembly", "Node.js", "Python"]),
+            ("@nonnull", program_uses_annotation(program, "nonnull"), vec!["Swift"]),
+            ("module", program_uses_modules(program), vec!["Swift", "Rust", "Rust bridge", "C", "WebAssembly", "Node.js", "Python"]),
+            ("[types.string] cpp", cpp_override("string"), converters.clone()),
+            ("[types.binary] cpp", cpp_override("binary"), converters.clone()),
+            ("[types.date] cpp", cpp_override("date"), converters)
+        ];
+
+        let mut diagnostics = Vec::new();
+        for (feature, used, unsupported) in features {
+            for &(name, enabled) in &bridges {
+                if used && enabled && unsupported.contains(&name) {
+                    diagnostics.push(Diagnostic::error(None, format!("{} is not supported by the {} generator", feature, name)));
+                }
+            }
+        }
+
+        if diagnostics.len() > 0 {
+            return Err(diagnostics);
+        }
+
+        Ok(())
+    }
+
+    fn enabled(folder: &Option<String>) -> bool {
+        folder.as_ref().map_or(false, |f| f.len() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::TypeOverride;
+    use generator::testing;
+
+    #[test]
+    fn test_compile_source() {
+        let input = r#"my_record = record {
+                           id: i32;
+                           info: string;
+                       }"#;
+
+        let mut config = Config::default();
+        config.cpp.out = Some("out/cpp".into());
+
+        let output = Compiler::new(CompilerOptions::from_config(config).unwrap())
+            .source("test.djinni", input)
+            .compile()
+            .expect("compile failed");
+
+        let file = output.get("out/cpp/my_record.hpp").expect("missing my_record.hpp");
+        assert!(file.contents.contains("struct my_record {"), "unexpected contents: {}", file.contents);
+        assert!(file.contents.contains("std::string info;"), "unexpected contents: {}", file.contents);
+    }
+
+    #[test]
+    fn test_compile_reports_resolve_errors() {
+        let input = r#"my_record = record {
+                           other: missing_type;
+                       }"#;
+
+        let result = Compiler::new(CompilerOptions::new(Spec::default()))
+            .source("test.djinni", input)
+            .compile();
+
+        match result {
+            Err(diagnostics) => {
+                assert!(diagnostics.len() == 1, "expected one diagnostic, got {}", diagnostics.len());
+                assert!(diagnostics[0].message == "type not found: missing_type", "unexpected message: {}", diagnostics[0].message);
+            },
+            Ok(_) => assert!(false, "expected compile to fail")
+        }
+
+        let result = Compiler::new(CompilerOptions::new(Spec::default()))
+            .source("test.djinni", "store = interface +c { load(ids: map<string, list<missing_type>>); }")
+            .compile();
+        match result {
+            Err(diagnostics) => assert!(diagnostics[0].message == "type not found: missing_type", "{}", diagnostics[0].message),
+            Ok(_) => assert!(false, "expected compile to fail")
+        }
+    }
+
+    #[test]
+    fn test_compile_reports_missing_import() {
+        let result = Compiler::new(CompilerOptions::new(Spec::default()))
+            .source("test.djinni", "@import \"does_not_exist.djinni\"")
+            .compile();
+
+        match result {
+            Err(diagnostics) => {
+                assert!(diagnostics[0].file == Some("test.djinni".into()), "unexpected file: {:?}", diagnostics[0].file);
+            },
+            Ok(_) => assert!(false, "expected compile to fail")
+        }
+    }
+
+    #[test]
+    fn test_compile_throws() {
+        let input = "store_error = enum { missing; }\n\
+                     store = interface +c { load(id: i64): string throws store_error; fetch(): future<i32> throws store_error; }\n";
+
+        let mut config = Config::default();
+        config.cpp.out = Some("cpp".into());
+        let output = testing::compile(config, input);
+
+        let header = &output.get("cpp/store.hpp").expect("missing store.hpp").contents;
+        assert!(header.contains("#include <expected>"), "{}", header);
+        assert!(header.contains("virtual std::expected<std::string, store_error> load(int64_t id) = 0;"), "{}", header);
+        assert!(header.contains("virtual std::future<std::expected<int32_t, store_error>> fetch() = 0;"), "{}", header);
+    }
+
+    #[test]
+    fn test_compile_checks_features() {
+        let mut config = Config::default();
+        config.cpp.out = Some("cpp".into());
+        config.c.out = Some("c".into());
+        let diagnostics = testing::compile_errors(config, "store = interface +c { fetch(): future<string>; }");
+        assert!(diagnostics.len() == 1, "expected one diagnostic, got {}", diagnostics.len());
+        assert!(diagnostics[0].message == "future<T> is not supported by the C generator", "{}", diagnostics[0].message);
+
+        // Primitives stay arithmetic types, so only the other overrides are rejected.
+        let mut config = Config::default();
+        config.cpp.out = Some("cpp".into());
+        config.node.out = Some("node".into());
+        config.types.insert("i64".into(), TypeOverride { cpp: Some("long long".into()), ..TypeOverride::default() });
+        config.types.insert("date".into(), TypeOverride { cpp: Some("bb::timestamp".into()), ..TypeOverride::default() });
+        let diagnostics = testing::compile_errors(config, "key = record { id: i64; at: date; }");
+        assert!(diagnostics.len() == 1, "expected one diagnostic, got {}", diagnostics.len());
+        assert!(diagnostics[0].message == "[types.date] cpp is not supported by the Node.js generator", "{}", diagnostics[0].message);
+    }
+
+    #[test]
+    fn test_compile_primitive_overrides() {
+        let mut config = Config::default();
+        config.cpp.out = Some("cpp".into());
+        config.c.out = Some("c".into());
+        config.rust.out = Some("rust".into());
+        config.rust.bridge_out = Some("bridge".into());
+        config.types.insert("i64".into(), TypeOverride { cpp: Some("long long".into()), ..TypeOverride::default() });
+        let output = testing::compile(config, "key = record { id: i64; ids: list<i64>; }\n\
+                                               store = interface +c { static create(): store; get(id: i64): key; }\n");
+
+        let source = &output.get("c/lamp.cpp").expect("missing lamp.cpp").contents;
+        assert!(source.contains("inline std::vector<long long> list_i64_from_c(const lamp_list_i64 & v)"), "{}", source);
+        testing::check_cpp("c_overrides", &output, "cpp", "#include \"c/lamp.cpp\"\n");
+        testing::check_cpp("bridge_overrides", &output, "cpp", "#include \"bridge/rust_bridge.cpp\"\n");
+    }
+
+    #[test]
+    fn test_compile_function_types() {
+        let input = "listener = interface +c { watch(filter: fn(i32, string) -> bool, done: optional<fn()>); }\n";
+
+        let mut config = Config::default();
+        config.cpp.out = Some("cpp".into());
+        config.kotlin.out = Some("kotlin".into());
+        config.objc.out = Some("objc".into());
+        let output = testing::compile(config, input);
+
+        let header = &output.get("cpp/listener.hpp").expect("missing listener.hpp").contents;
+        assert!(header.contains("#include <functional>"), "{}", header);
+        assert!(header.contains("virtual void watch(const std::function<bool(int32_t, std::string)> & filter, const std::function<void()> & done) = 0;"), "{}", header);
+
+        let kotlin = &output.get("kotlin/Listener.kt").expect("missing Listener.kt").contents;
+        assert!(kotlin.contains("abstract fun watch(filter: (Int, String) -> Boolean, done: (() -> Unit)?)"), "{}", kotlin);
+
+        let objc = &output.get("objc/Listener.h").expect("missing Listener.h").contents;
+        assert!(objc.contains("- (void)watch:(nonnull BOOL (^)(int32_t, NSString * _Nonnull))filter done:(nullable void (^)(void))done\n"), "{}", objc);
+    }
+
+    #[test]
+    fn test_compile_annotations() {
+        let input = "@deprecated(\"use store\") @since(\"2.3\")\n\
+                     cache = interface +c {\n\
+                         @cpp_name(\"Get\") @java_name(\"fetch\") get(@nonnull owner: cache, @tracked key: string): i32;\n\
+                         @deprecated clear();\n\
+                     }\n";
+
+        let mut config = Config::default();
+        config.cpp.out = Some("cpp".into());
+        config.kotlin.out = Some("kotlin".into());
+        config.objc.out = Some("objc".into());
+        let output = testing::compile(config, input);
+
+        let header = &output.get("cpp/cache.hpp").expect("missing cache.hpp").contents;
+        assert!(header.contains("/** @since 2.3 */\nclass [[deprecated(\"use store\")]] cache {"), "{}", header);
+        assert!(header.contains("virtual int32_t Get(const std::shared_ptr<cache> & owner, const std::string & key) = 0;"), "{}", header);
+        assert!(header.contains("[[deprecated]] virtual void clear() = 0;"), "{}", header);
+
+        let kotlin = &output.get("kotlin/Cache.kt").expect("missing Cache.kt").contents;
+        assert!(kotlin.contains("@Deprecated(\"use store\")\nabstract class Cache {"), "{}", kotlin);
+        assert!(kotlin.contains("abstract fun fetch(owner: Cache, key: String): Int"), "{}", kotlin);
+        assert!(kotlin.contains("    @Deprecated(\"\")\n    abstract fun clear()"), "{}", kotlin);
+
+        let objc = &output.get("objc/Cache.h").expect("missing Cache.h").contents;
+        assert!(objc.contains("NS_SWIFT_NAME(Cache) __attribute__((deprecated(\"use store\")))\n@interface Cache"), "{}", objc);
+        assert!(objc.contains("- (int32_t)get:(nonnull Cache *)owner key:(nonnull NSString *)key"), "{}", objc);
+        assert!(objc.contains("NS_SWIFT_NAME(clear()) __attribute__((deprecated));"), "{}", objc);
+    }
+
+    #[test]
+    fn test_compile_record_defaults() {
+        let input = "settings = record { name: string; retries: i32 = 3; ratio: f64 = 2; label: optional<string> = \"none\"; }";
+
+        let mut config = Config::default();
+        config.cpp.out = Some("cpp".into());
+        config.kotlin.out = Some("kotlin".into());
+        config.objc.out = Some("objc".into());
+        let output = testing::compile(config, input);
+
+        let header = &output.get("cpp/settings.hpp").expect("missing settings.hpp").contents;
+        assert!(header.contains("int32_t retries = 3;\ndouble ratio = 2;\nstd::optional<std::string> label = \"none\";"), "{}", header);
+
+        let kotlin = &output.get("kotlin/Settings.kt").expect("missing Settings.kt").contents;
+        assert!(kotlin.contains("data class Settings @JvmOverloads constructor("), "{}", kotlin);
+        assert!(kotlin.contains("    val ratio: Double = 2.0,\n    val label: String? = \"none\"\n"), "{}", kotlin);
+
+        let objc = &output.get("objc/Settings.h").expect("missing Settings.h").contents;
+        assert!(objc.contains("- (nonnull instancetype)initWithName:(nonnull NSString *)name\n    NS_SWIFT_NAME(init(name:));"), "{}", objc);
+        let objc = &output.get("objc/Settings.m").expect("missing Settings.m").contents;
+        assert!(objc.contains("return [self initWithName:name retries:3 ratio:2 label:@\"none\"];"), "{}", objc);
+    }
+
+    #[test]
+    fn test_compile_aliases() {
+        let input = "device_info = record { id: string; }\n\
+                     device_map = alias map<string, list<device_info>>;\n\
+                     maybe_map = alias optional<device_map>;\n\
+                     registry = interface +c { replace(devices: maybe_map): device_map; }\n";
+
+        let mut config = Config::default();
+        config.cpp.out = Some("cpp".into());
+        config.cpp.aliases = true;
+        config.kotlin.out = Some("kotlin".into());
+        let output = testing::compile(config, input);
+
+        let header = &output.get("cpp/registry.hpp").expect("missing registry.hpp").contents;
+        assert!(header.contains("virtual std::unordered_map<std::string, std::vector<device_info>> replace(\
+                                 const std::optional<std::unordered_map<std::string, std::vector<device_info>>> & devices) = 0;"), "{}", header);
+        let alias = &output.get("cpp/maybe_map.hpp").expect("missing maybe_map.hpp").contents;
+        assert!(alias.contains("using maybe_map = std::optional<std::unordered_map<std::string, std::vector<device_info>>>;"), "{}", alias);
+
+        let kotlin = &output.get("kotlin/Registry.kt").expect("missing Registry.kt").contents;
+        assert!(kotlin.contains("abstract fun replace(devices: HashMap<String, ArrayList<DeviceInfo>>?): HashMap<String, ArrayList<DeviceInfo>>"), "{}", kotlin);
+        assert!(output.get("kotlin/DeviceMap.kt").is_none(), "kotlin aliases are off by default");
+    }
+
+    #[test]
+    fn test_compile_modules() {
+        let net = "module net;\nconfig = record { host: string; }\n";
+        let store = "module store;\nconfig = record { path: string; }\n";
+        let app = "settings = record { net: net.config; store: store.config; }\n";
+
+        let mut config = Config::default();
+        config.cpp.out = Some("cpp".into());
+        config.cpp.namespace = "core".into();
+        config.kotlin.out = Some("kotlin".into());
+        config.java.package = Some("com.bb".into());
+        config.objc.out = Some("objc".into());
+        config.objc.type_prefix = "BB".into();
+        let output = Compiler::new(CompilerOptions::from_config(config).unwrap())
+            .source("net.djinni", net)
+            .source("store.djinni", store)
+            .source("app.djinni", app)
+            .compile()
+            .expect("compile failed");
+
+        let header = &output.get("cpp/net/config.hpp").expect("missing net/config.hpp").contents;
+        assert!(header.contains("namespace core::net {"), "{}", header);
+        let settings = &output.get("cpp/settings.hpp").expect("missing settings.hpp").contents;
+        assert!(settings.contains("#include \"net/config.hpp\""), "{}", settings);
+        assert!(settings.contains("store::config store;"), "{}", settings);
+
+        let kotlin = &output.get("kotlin/store/Config.kt").expect("missing store/Config.kt").contents;
+        assert!(kotlin.contains("package com.bb.store"), "{}", kotlin);
+        let settings = &output.get("kotlin/Settings.kt").expect("missing Settings.kt").contents;
+        assert!(settings.contains("val net: com.bb.net.Config"), "{}", settings);
+
+        assert!(output.get("objc/BBNetConfig.h").is_some(), "missing BBNetConfig.h");
+    }
+
+    #[test]
+    fn test_compile_arrays() {
+        let input = "sensor_frame = record { samples: array<f32>; }\n\
+                     sensor = interface +c { write(data: array<i16>): optional<array<f64>>; }\n";
+
+        let mut config = Config::default();
+        config.cpp.out = Some("cpp".into());
+        config.kotlin.out = Some("kotlin".into());
+        config.objc.out = Some("objc".into());
+        let output = testing::compile(config, input);
+
+        let header = &output.get("cpp/sensor.hpp").expect("missing sensor.hpp").contents;
+        assert!(header.contains("virtual std::optional<std::vector<double>> write(const std::vector<int16_t> & data) = 0;"), "{}", header);
+        let kotlin = &output.get("kotlin/Sensor.kt").expect("missing Sensor.kt").contents;
+        assert!(kotlin.contains("abstract fun write(data: ShortArray): DoubleArray?"), "{}", kotlin);
+        let objc = &output.get("objc/SensorFrame.h").expect("missing SensorFrame.h").contents;
+        assert!(objc.contains("@property (nonatomic, readonly, nonnull) NSData *samples;"), "{}", objc);
+
+        // Everything over C++ marshals an array like a list, since both are a `std::vector`.
+        let mut config = Config::default();
+        config.cpp.out = Some("cpp".into());
+        config.c.out = Some("c".into());
+        config.csharp.out = Some("csharp".into());
+        config.dart.out = Some("dart".into());
+        config.rust.out = Some("rust".into());
+        config.rust.bridge_out = Some("bridge".into());
+        config.wasm.out = Some("wasm".into());
+        config.node.out = Some("node".into());
+        config.python.out = Some("python".into());
+        let output = testing::compile(config, input);
+
+        let mut config = Config::default();
+        config.objc.out = Some("objc".into());
+        config.swift.out = Some("swift".into());
+        let swift_output = testing::compile(config, "sensor_frame = record { samples: array<f32>; }\n");
+        let swift = &swift_output.get("swift/SensorFrame.swift").expect("missing SensorFrame.swift").contents;
+        assert!(swift.contains("samples: objc.samples.withUnsafeBytes { Array($0.bindMemory(to: Float.self)) }"), "{}", swift);
+        assert!(swift.contains("samples: samples.withUnsafeBufferPointer { Data(buffer: $0) }"), "{}", swift);
+        let c = &output.get("c/lamp.h").expect("missing lamp.h").contents;
+        assert!(c.contains("typedef struct lamp_array_f32 {\n    float * data;\n    size_t len;\n} lamp_array_f32;"), "{}", c);
+        assert!(c.contains("lamp_opt_array_f64 lamp_sensor_write(lamp_sensor * self, const lamp_array_i16 * data);"), "{}", c);
+        let csharp = &output.get("csharp/Marshaling.cs").expect("missing Marshaling.cs").contents;
+        assert!(csharp.contains("internal static short[] ArrayI16FromNative(Native.ArrayI16 v)"), "{}", csharp);
+        let dart = &output.get("dart/lamp.dart").expect("missing lamp.dart").contents;
+        assert!(dart.contains("  return Float32List.fromList(v.data.asTypedList(v.len));"), "{}", dart);
+        let rust = &output.get("rust/bridge/sensor.rs").expect("missing bridge/sensor.rs").contents;
+        assert!(rust.contains("fn write(&self, data: Vec<i16>) -> Option<Vec<f64>> {"), "{}", rust);
+        let wasm = &output.get("wasm/lamp.d.ts").expect("missing lamp.d.ts").contents;
+        assert!(wasm.contains("write(data: number[]): number[] | undefined;"), "{}", wasm);
+        let node = &output.get("node/index.d.ts").expect("missing index.d.ts").contents;
+        assert!(node.contains("samples: number[];"), "{}", node);
+        let python = &output.get("python/lamp.pyi").expect("missing lamp.pyi").contents;
+        assert!(python.contains("def write(self, data: List[int]) -> Optional[List[float]]: ..."), "{}", python);
+
+        testing::check_cpp("c_arrays", &output, "cpp", "#include \"c/lamp.cpp\"\n");
+        testing::check_cpp("bridge_arrays", &output, "cpp", "#include \"bridge/rust_bridge.cpp\"\n");
+    }
+
+    #[test]
+    fn test_compile_derives() {
+        let input = "point = record { x: i32; label: optional<string>; } deriving (eq, hash, show, json)\n\
+                     shape = record { shape_id: i8; points: list<point>; } deriving (json)\n";
+
+        let mut config = Config::default();
+        config.cpp.out = Some("cpp".into());
+        config.kotlin.out = Some("kotlin".into());
+        let output = testing::compile(config, input);
+
+        let point = &output.get("cpp/point.hpp").expect("missing point.hpp").contents;
+        assert!(point.contains("struct hash<::point> {"), "{}", point);
+        assert!(point.contains("result = result * 31 + (value.label ? std::hash<std::string>()(*value.label) : 0);"), "{}", point);
+        assert!(point.contains("inline std::ostream & operator<<(std::ostream & os, const point & value) {"), "{}", point);
+        assert!(point.contains("inline void to_json(nlohmann::json & j, const point & value) {"), "{}", point);
+        let shape = &output.get("cpp/shape.hpp").expect("missing shape.hpp").contents;
+        assert!(shape.contains("j.at(\"points\").get_to(value.points);"), "{}", shape);
+
+        let kotlin = &output.get("kotlin/Shape.kt").expect("missing Shape.kt").contents;
+        assert!(kotlin.contains("@kotlinx.serialization.Serializable\ndata class Shape("), "{}", kotlin);
+        assert!(kotlin.contains("    @kotlinx.serialization.SerialName(\"shape_id\")\n    val shapeId: Byte,"), "{}", kotlin);
+    }
+
+    #[test]
+    fn test_interface_field_in_container() {
+        let input = "job = record { listeners: list<listener>; by_name: map<string, listener>; }\n\
+                     listener = interface +c { done(); }\n";
+
+        let mut config = Config::default();
+        config.cpp.out = Some("cpp".into());
+        let output = testing::compile(config, input);
+
+        let job = &output.get("cpp/job.hpp").expect("missing job.hpp").contents;
+        assert!(job.contains("std::vector<std::shared_ptr<listener>> listeners;"), "{}", job);
+    }
+
+    #[test]
+    fn test_compile_requires_wrapped_outputs() {
+        let mut config = Config::default();
+        config.rust.bridge_out = Some("bridge".into());
+        config.swift.out = Some("swift".into());
+        config.csharp.out = Some("csharp".into());
+        config.dart.out = Some("dart".into());
+        let diagnostics = testing::compile_errors(config, "point = record { x: i32; }");
+        let messages = diagnostics.iter().map(|d| d.message.as_str()).collect::<Vec<_>>();
+        assert!(messages == vec!["rust.bridge_out is set without rust.out, the Rust output it wraps",
+                                 "swift.out is set without objc.out, the Objective-C output it wraps",
+                                 "csharp.out is set without c.out, the C output it wraps",
+                                 "dart.out is set without c.out, the C output it wraps"], "{:?}", messages);
+    }
+
+    #[test]
+    fn test_compile_rejects_swift_cpp_interfaces() {
+        let mut config = Config::default();
+        config.objc.out = Some("objc".into());
+        config.swift.out = Some("swift".into());
+        let diagnostics = testing::compile_errors(config, "listener = interface +o { done(); }\n\
+                                                           both = interface +c +o { done(); }\n\
+                                                           key_store = interface +c { static create(): key_store; }\n");
+        let messages = diagnostics.iter().map(|d| d.message.as_str()).collect::<Vec<_>>();
+        assert!(messages == vec!["swift.out doesn't support the +c interface key_store: there is no Objective-C++ implementation \
+                                  of its Objective-C class"], "{:?}", messages);
+    }
+}
@@ -0,0 +1,576 @@
+/*
+ * Copyright © 2002-2017 Bluebeam Software, Inc. All Rights Reserved.
+ * Creator: Jonathan Rothberg
+ */
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use toml;
+
+pub const CONFIG_FILE_NAME: &'static str = "rusty_lamp.toml";
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum ConfigError {
+    Io(String, String),
+    Parse(String),
+    InvalidIdentStyle(String, String),
+    InvalidTypeOverride(String, String)
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::Io(ref path, ref e) => write!(f, "unable to read {}: {}", path, e),
+            ConfigError::Parse(ref e) => write!(f, "invalid configuration: {}", e),
+            ConfigError::InvalidIdentStyle(ref key, ref e) => write!(f, "invalid ident spec for {}: {}", key, e),
+            ConfigError::InvalidTypeOverride(ref ty, ref e) => write!(f, "invalid override for type {}: {}", ty, e)
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IdentConfig {
+    #[serde(rename = "type")]
+    pub ty: Option<String>,
+    pub enum_type: Option<String>,
+    pub type_param: Option<String>,
+    pub method: Option<String>,
+    pub field: Option<String>,
+    pub local: Option<String>,
+    #[serde(rename = "enum")]
+    pub enm: Option<String>,
+    #[serde(rename = "const")]
+    pub cnst: Option<String>,
+    pub file: Option<String>,
+    pub class: Option<String>
+}
+
+/// Replaces the generated spelling of a builtin type, e.g. `[types.i64] cpp = "long long"`.
+#[derive(PartialEq, Eq, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TypeOverride {
+    pub java: Option<String>,
+    pub java_boxed: Option<String>,
+    pub jni: Option<String>,
+    pub jni_signature: Option<String>,
+    pub cpp: Option<String>,
+    pub cpp_header: Option<String>,
+    pub objc: Option<String>,
+    pub objc_boxed: Option<String>
+}
+
+impl TypeOverride {
+    fn fields(&self) -> Vec<(&'static str, &Option<String>)> {
+        vec![("java", &self.java), ("java_boxed", &self.java_boxed), ("jni", &self.jni),
+             ("jni_signature", &self.jni_signature), ("cpp", &self.cpp), ("cpp_header", &self.cpp_header),
+             ("objc", &self.objc), ("objc_boxed", &self.objc_boxed)]
+    }
+
+    pub fn find_empty(&self) -> Option<&'static str> {
+        self.fields().into_iter()
+            .find(|&(_, v)| v.as_ref().map_or(false, |s| s.trim().is_empty()))
+            .map(|(k, _)| k)
+    }
+
+    /// Boxing and jni spellings only exist for primitives.
+    pub fn find_primitive_only(&self) -> Option<&'static str> {
+        self.fields().into_iter()
+            .find(|&(k, v)| v.is_some() && ["java_boxed", "jni", "jni_signature", "objc_boxed"].contains(&k))
+            .map(|(k, _)| k)
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JavaConfig {
+    pub out: Option<String>,
+    pub package: Option<String>,
+    pub class_access_modifier: String,
+    pub cpp_exception: String,
+    pub annotation: Option<String>,
+    pub nullable_annotation: Option<String>,
+    pub nonnull_annotation: Option<String>,
+    pub use_final_for_record: bool,
+    pub ident: IdentConfig
+}
+
+impl Default for JavaConfig {
+    fn default() -> JavaConfig {
+        JavaConfig {
+            out: None,
+            package: None,
+            class_access_modifier: "public".into(),
+            cpp_exception: "java.lang.RuntimeException".into(),
+            annotation: None,
+            nullable_annotation: None,
+            nonnull_annotation: None,
+            use_final_for_record: true,
+            ident: IdentConfig::default()
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CppConfig {
+    pub out: Option<String>,
+    pub header_out: Option<String>,
+    pub include_prefix: String,
+    pub extended_record_include_prefix: String,
+    pub namespace: String,
+    pub ext: String,
+    pub header_ext: String,
+    pub optional_template: String,
+    pub optional_header: String,
+    pub future_template: String,
+    pub future_header: String,
+    pub expected_template: String,
+    pub expected_header: String,
+    pub json_type: String,
+    pub json_header: String,
+    pub enum_hash_workaround: bool,
+    pub nn_header: Option<String>,
+    pub nn_type: Option<String>,
+    pub nn_check_expression: Option<String>,
+    pub use_wide_strings: bool,
+    pub aliases: bool,
+    pub ident: IdentConfig
+}
+
+impl CppConfig {
+    pub fn header_out(&self) -> Option<&String> {
+        self.header_out.as_ref().or(self.out.as_ref())
+    }
+}
+
+impl Default for CppConfig {
+    fn default() -> CppConfig {
+        CppConfig {
+            out: None,
+            header_out: None,
+            include_prefix: "".into(),
+            extended_record_include_prefix: "".into(),
+            namespace: "".into(),
+            ext: "cpp".into(),
+            header_ext: "hpp".into(),
+            optional_template: "std::optional".into(),
+            optional_header: "<optional>".into(),
+            future_template: "std::future".into(),
+            future_header: "<future>".into(),
+            expected_template: "std::expected".into(),
+            expected_header: "<expected>".into(),
+            json_type: "nlohmann::json".into(),
+            json_header: "<nlohmann/json.hpp>".into(),
+            enum_hash_workaround: true,
+            nn_header: None,
+            nn_type: None,
+            nn_check_expression: None,
+            use_wide_strings: false,
+            aliases: false,
+            ident: IdentConfig::default()
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JniConfig {
+    pub out: Option<String>,
+    pub header_out: Option<String>,
+    pub include_prefix: String,
+    pub include_cpp_prefix: String,
+    pub namespace: String,
+    pub base_lib_include_prefix: String,
+    pub ident: IdentConfig
+}
+
+impl JniConfig {
+    pub fn header_out(&self) -> Option<&String> {
+        self.header_out.as_ref().or(self.out.as_ref())
+    }
+}
+
+impl Default for JniConfig {
+    fn default() -> JniConfig {
+        JniConfig {
+            out: None,
+            header_out: None,
+            include_prefix: "".into(),
+            include_cpp_prefix: "".into(),
+            namespace: "djinni_generated".into(),
+            base_lib_include_prefix: "".into(),
+            ident: IdentConfig::default()
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ObjcConfig {
+    pub out: Option<String>,
+    pub h_ext: String,
+    pub type_prefix: String,
+    pub include_prefix: String,
+    pub extended_record_include_prefix: String,
+    pub base_lib_include_prefix: String,
+    pub ident: IdentConfig
+}
+
+impl Default for ObjcConfig {
+    fn default() -> ObjcConfig {
+        ObjcConfig {
+            out: None,
+            h_ext: "h".into(),
+            type_prefix: "".into(),
+            include_prefix: "".into(),
+            extended_record_include_prefix: "".into(),
+            base_lib_include_prefix: "".into(),
+            ident: IdentConfig::default()
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ObjcppConfig {
+    pub out: Option<String>,
+    pub ext: String,
+    pub include_prefix: String,
+    pub include_cpp_prefix: String,
+    pub include_objc_prefix: Option<String>,
+    pub namespace: String
+}
+
+impl ObjcppConfig {
+    pub fn include_objc_prefix(&self) -> &String {
+        self.include_objc_prefix.as_ref().unwrap_or(&self.include_prefix)
+    }
+}
+
+impl Default for ObjcppConfig {
+    fn default() -> ObjcppConfig {
+        ObjcppConfig {
+            out: None,
+            ext: "mm".into(),
+            include_prefix: "".into(),
+            include_cpp_prefix: "".into(),
+            include_objc_prefix: None,
+            namespace: "djinni_generated".into()
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RustConfig {
+    pub out: Option<String>,
+    pub bridge_out: Option<String>,
+    pub ident: IdentConfig
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CConfig {
+    pub out: Option<String>,
+    pub header_out: Option<String>,
+    pub file: String,
+    pub prefix: String,
+    pub include_prefix: String,
+    pub ident: IdentConfig
+}
+
+impl CConfig {
+    pub fn header_out(&self) -> Option<&String> {
+        self.header_out.as_ref().or(self.out.as_ref())
+    }
+}
+
+impl Default for CConfig {
+    fn default() -> CConfig {
+        CConfig {
+            out: None,
+            header_out: None,
+            file: "lamp".into(),
+            prefix: "lamp_".into(),
+            include_prefix: "".into(),
+            ident: IdentConfig::default()
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SwiftConfig {
+    pub out: Option<String>,
+    pub aliases: bool,
+    pub ident: IdentConfig
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KotlinConfig {
+    pub out: Option<String>,
+    pub aliases: bool,
+    pub ident: IdentConfig
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WasmConfig {
+    pub out: Option<String>,
+    pub ts_out: Option<String>,
+    pub module: String,
+    pub ident: IdentConfig
+}
+
+impl WasmConfig {
+    pub fn ts_out(&self) -> Option<&String> {
+        self.ts_out.as_ref().or(self.out.as_ref())
+    }
+}
+
+impl Default for WasmConfig {
+    fn default() -> WasmConfig {
+        WasmConfig {
+            out: None,
+            ts_out: None,
+            module: "lamp".into(),
+            ident: IdentConfig::default()
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NodeConfig {
+    pub out: Option<String>,
+    pub ts_out: Option<String>,
+    pub module: String,
+    pub ident: IdentConfig
+}
+
+impl NodeConfig {
+    pub fn ts_out(&self) -> Option<&String> {
+        self.ts_out.as_ref().or(self.out.as_ref())
+    }
+}
+
+impl Default for NodeConfig {
+    fn default() -> NodeConfig {
+        NodeConfig {
+            out: None,
+            ts_out: None,
+            module: "lamp".into(),
+            ident: IdentConfig::default()
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PythonConfig {
+    pub out: Option<String>,
+    pub module: String,
+    pub ident: IdentConfig
+}
+
+impl Default for PythonConfig {
+    fn default() -> PythonConfig {
+        PythonConfig {
+            out: None,
+            module: "lamp".into(),
+            ident: IdentConfig::default()
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CSharpConfig {
+    pub out: Option<String>,
+    pub namespace: String,
+    pub library: Option<String>,
+    pub ident: IdentConfig
+}
+
+impl Default for CSharpConfig {
+    fn default() -> CSharpConfig {
+        CSharpConfig {
+            out: None,
+            namespace: "Lamp".into(),
+            library: None,
+            ident: IdentConfig::default()
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DartConfig {
+    pub out: Option<String>,
+    pub library: Option<String>,
+    pub ident: IdentConfig
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct YamlConfig {
+    pub out: Option<String>,
+    pub out_file: Option<String>,
+    pub prefix: String
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub idl: Option<String>,
+    pub idl_include_paths: Vec<String>,
+    pub list_in_files: Option<String>,
+    pub list_out_files: Option<String>,
+    pub skip_generation: bool,
+    pub java: JavaConfig,
+    pub kotlin: KotlinConfig,
+    pub cpp: CppConfig,
+    pub jni: JniConfig,
+    pub objc: ObjcConfig,
+    pub objcpp: ObjcppConfig,
+    pub swift: SwiftConfig,
+    pub wasm: WasmConfig,
+    pub node: NodeConfig,
+    pub python: PythonConfig,
+    pub csharp: CSharpConfig,
+    pub dart: DartConfig,
+    pub yaml: YamlConfig,
+    pub rust: RustConfig,
+    pub c: CConfig,
+    pub types: BTreeMap<String, TypeOverride>
+}
+
+impl Config {
+    pub fn new() -> Config {
+        Config::default()
+    }
+
+    pub fn from_toml(input: &str) -> Result<Config, ConfigError> {
+        toml::from_str(input).map_err(|e| ConfigError::Parse(format!("{}", e)))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Config, ConfigError> {
+        let path = path.as_ref();
+        let mut contents = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut contents))
+            .map_err(|e| ConfigError::Io(path.display().to_string(), format!("{}", e)))?;
+
+        Config::from_toml(&contents)
+    }
+
+    pub fn find<P: AsRef<Path>>(dir: P) -> Option<PathBuf> {
+        let path = dir.as_ref().join(CONFIG_FILE_NAME);
+        if path.is_file() {
+            Some(path)
+        }
+        else {
+            None
+        }
+    }
+
+    pub fn to_toml(&self) -> Result<String, ConfigError> {
+        toml::to_string(self).map_err(|e| ConfigError::Parse(format!("{}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults() {
+        let config = Config::from_toml("").unwrap();
+
+        assert!(config == Config::default());
+        assert!(config.cpp.optional_template == "std::optional", "{}", config.cpp.optional_template);
+        assert!(config.jni.namespace == "djinni_generated", "{}", config.jni.namespace);
+        assert!(config.java.use_final_for_record);
+    }
+
+    #[test]
+    fn test_language_sections() {
+        let input = r#"
+            idl = "all.djinni"
+
+            [java]
+            out = "generated-src/java"
+            package = "com.example"
+
+            [java.ident]
+            field = "mFooBar"
+
+            [cpp]
+            out = "generated-src/cpp"
+            namespace = "example"
+            enum_hash_workaround = false
+
+            [objc]
+            type_prefix = "EX"
+        "#;
+
+        let config = Config::from_toml(input).unwrap();
+
+        assert!(config.idl == Some("all.djinni".into()));
+        assert!(config.java.package == Some("com.example".into()));
+        assert!(config.java.ident.field == Some("mFooBar".into()));
+        assert!(config.java.class_access_modifier == "public", "{}", config.java.class_access_modifier);
+        assert!(config.cpp.header_out() == Some(&"generated-src/cpp".to_string()));
+        assert!(config.cpp.namespace == "example");
+        assert!(!config.cpp.enum_hash_workaround);
+        assert!(config.objc.type_prefix == "EX");
+    }
+
+    #[test]
+    fn test_unknown_key() {
+        let input = r#"
+            [cpp]
+            name_space = "example"
+        "#;
+
+        match Config::from_toml(input) {
+            Err(ConfigError::Parse(_)) => {},
+            r => assert!(false, "expected a parse error, got={:?}", r)
+        }
+    }
+
+    #[test]
+    fn test_type_overrides() {
+        let input = r#"
+            [types.i64]
+            cpp = "long long"
+
+            [types.date]
+            cpp = "my::time"
+            cpp_header = "<my/time.hpp>"
+        "#;
+
+        let config = Config::from_toml(input).unwrap();
+
+        assert!(config.types["i64"].cpp == Some("long long".into()));
+        assert!(config.types["i64"].java == None);
+        assert!(config.types["date"].cpp_header == Some("<my/time.hpp>".into()));
+        assert!(config.types["date"].find_primitive_only() == None);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut config = Config::default();
+        config.cpp.out = Some("out".into());
+        config.objcpp.include_objc_prefix = Some("objc/".into());
+        config.types.insert("i64".into(), TypeOverride { cpp: Some("long long".into()), ..TypeOverride::default() });
+
+        let toml = config.to_toml().unwrap();
+        assert!(Config::from_toml(&toml).unwrap() == config, "{}", toml);
+    }
+}
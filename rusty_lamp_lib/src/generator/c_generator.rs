@@ -0,0 +1,757 @@
+/*
+ * Copyright © 2002-2017 Bluebeam Software, Inc. All Rights Reserved.
+ * Creator: Jonathan Rothberg
+ */
+
+use std::io::{Write};
+use generator::generator::{Generate};
+use generator::spec::Spec;
+use generator::output::GeneratedOutput;
+use generator::c_marshaler::CMarshaler;
+use generator::cpp_marshaler::CppMarshaler;
+use generator::typer::UserObjectKind;
+use parser::program::Program;
+use parser::ast::{Statement, StatementKind, DataTypeStatement, FunctionModifier, Identifier, InterfaceType, BlockStatement};
+
+/// Wraps the generated C++ classes in a plain C API, written as a single header and source
+/// pair since C needs every type declared before it is used.  Records become structs,
+/// containers become typed arrays, and interfaces become opaque handles that each own one
+/// `std::shared_ptr` reference.
+pub struct CGenerator {
+
+}
+
+impl CGenerator {
+    pub fn new() -> CGenerator {
+        CGenerator {
+
+        }
+    }
+
+    fn write_header(&self, w: &mut Write) {
+        writeln!(w, "// AUTOGENERATED FILE - DO NOT MODIFY!");
+        writeln!(w, "// This file was generated by rusty_lamp");
+    }
+
+    fn find_record<'p>(&self, program: &'p Program, i: &Identifier) -> Option<&'p BlockStatement> {
+        program.statements.iter()
+            .filter_map(|s| match s.stmtKind {
+                StatementKind::Record(_, ref ri, ref bs, _) if ri.value == i.value => Some(bs),
+                _ => None
+            })
+            .next()
+    }
+
+    /// Records and containers ordered so that each one comes after the types it contains.
+    fn collect(&self, m: &CMarshaler, program: &Program, dts: &DataTypeStatement, types: &mut Vec<DataTypeStatement>) {
+        let dts = m.strip_optional(dts);
+        if types.iter().any(|t| m.mangle(t) == m.mangle(dts)) {
+            return;
+        }
+
+        match *dts {
+            DataTypeStatement::List(ref t) | DataTypeStatement::Array(ref t) | DataTypeStatement::Set(ref t) | DataTypeStatement::Optional(ref t) => {
+                self.collect(m, program, t, types);
+            },
+            DataTypeStatement::Map(ref k, ref v) => {
+                self.collect(m, program, k, types);
+                self.collect(m, program, v, types);
+            },
+            DataTypeStatement::Object(ref i) => {
+                match (m.user_object_kind(i), self.find_record(program, i)) {
+                    (Some(UserObjectKind::Record(..)), Some(bs)) => {
+                        for f in self.fields(bs) {
+                            self.collect(m, program, f.1, types);
+                        }
+                    },
+                    _ => return
+                }
+            },
+            _ => return
+        }
+
+        types.push(dts.clone());
+    }
+
+    /// Every record, container and optional the C API declares a struct for, in declaration order.
+    pub fn collect_all(&self, m: &CMarshaler, program: &Program) -> Vec<DataTypeStatement> {
+        let mut types = Vec::new();
+        for stmt in &program.statements {
+            match stmt.stmtKind {
+                StatementKind::Record(_, ref i, _, _) => {
+                    self.collect(m, program, &DataTypeStatement::Object(i.clone()), &mut types);
+                },
+                StatementKind::Interface(_, _, ref it, ref bs, _) if it.contains(&InterfaceType::Cpp) => {
+                    for f in &bs.statements {
+                        if let StatementKind::Function(_, _, _, ref params, ref ret, _) = f.stmtKind {
+                            self.collect(m, program, ret, &mut types);
+                            for p in params {
+                                self.collect(m, program, &p.data_type, &mut types);
+                            }
+                        }
+                    }
+                },
+                _ => {}
+            }
+        }
+        types
+    }
+
+    fn fields<'b>(&self, bs: &'b BlockStatement) -> Vec<(&'b Identifier, &'b DataTypeStatement)> {
+        bs.statements.iter()
+            .filter_map(|s| match s.stmtKind { StatementKind::RecordMember(_, ref i, ref dts, _) => Some((i, dts)), _ => None })
+            .collect()
+    }
+
+    fn interfaces<'p>(&self, program: &'p Program) -> Vec<(&'p Identifier, &'p Vec<InterfaceType>, &'p BlockStatement)> {
+        program.statements.iter()
+            .filter_map(|s| match s.stmtKind {
+                StatementKind::Interface(_, ref i, ref it, ref bs, _) => Some((i, it, bs)),
+                _ => None
+            })
+            .collect()
+    }
+
+    fn write_consts(&self, w: &mut Write, m: &CMarshaler, ty: &Identifier, statements: &Vec<Statement>) {
+        for s in statements {
+            if let StatementKind::Const(_, ref i, ref dts, ref v) = s.stmtKind {
+                let value = match (dts, &v.stmtKind) {
+                    (_, &StatementKind::StringLiteral(_, ref s)) => format!("{:?}", s),
+                    (_, &StatementKind::Boolean(_, b)) => format!("{}", b),
+                    (_, &StatementKind::NumberLiteral(_, ref n)) => n.clone(),
+                    _ => {
+                        writeln!(w, "// const {}: {} is not representable as a C constant", i.value, dts);
+                        continue;
+                    }
+                };
+                writeln!(w, "#define {} {}", m.const_name(ty, i), value);
+            }
+        }
+    }
+
+    fn write_comments(&self, w: &mut Write, indent: &str, statements: &Vec<Statement>) {
+        for s in statements {
+            if let StatementKind::Comment(_, ref c) = s.stmtKind {
+                writeln!(w, "{}//{}", indent, c);
+            }
+        }
+    }
+
+    fn write_type_declaration(&self, w: &mut Write, m: &CMarshaler, program: &Program, dts: &DataTypeStatement) {
+        let name = m.type_name(dts);
+        writeln!(w, "");
+        writeln!(w, "typedef struct {} {{", name);
+        match *dts {
+            DataTypeStatement::List(ref t) | DataTypeStatement::Array(ref t) | DataTypeStatement::Set(ref t) => {
+                writeln!(w, "    {} * data;", m.type_name(t));
+                writeln!(w, "    size_t len;");
+            },
+            DataTypeStatement::Map(ref k, ref v) => {
+                writeln!(w, "    {} * keys;", m.type_name(k));
+                writeln!(w, "    {} * values;", m.type_name(v));
+                writeln!(w, "    size_t len;");
+            },
+            DataTypeStatement::Optional(ref t) => {
+                writeln!(w, "    bool has_value;");
+                writeln!(w, "    {} value;", m.type_name(t));
+            },
+            DataTypeStatement::Object(ref i) => {
+                let bs = self.find_record(program, i).unwrap();
+                for f in &bs.statements {
+                    match f.stmtKind {
+                        StatementKind::RecordMember(_, ref fi, ref fdts, _) => {
+                            writeln!(w, "    {} {};", m.type_name(fdts), m.field_ident(fi));
+                        },
+                        StatementKind::Comment(_, ref c) => {
+                            writeln!(w, "    //{}", c);
+                        },
+                        _ => {}
+                    }
+                }
+            },
+            _ => {}
+        }
+        writeln!(w, "}} {};", name);
+        writeln!(w, "");
+
+        match *dts {
+            DataTypeStatement::Object(ref i) => {
+                writeln!(w, "void {}_release({} * v);", name, name);
+                self.write_consts(w, m, i, &self.find_record(program, i).unwrap().statements);
+            },
+            DataTypeStatement::Optional(..) => {
+                writeln!(w, "void {}_release({} * v);", name, name);
+            },
+            _ => {
+                // Containers are allocated zeroed, the caller fills in the elements.
+                writeln!(w, "{} {}_new(size_t len);", name, name);
+                writeln!(w, "void {}_release({} * v);", name, name);
+            }
+        }
+    }
+
+    fn write_c_header(&self, w: &mut Write, spec: &Spec, program: &Program, types: &Vec<DataTypeStatement>) {
+        let m = CMarshaler::new(spec);
+        let prefix = m.prefix().to_string();
+
+        self.write_header(w);
+        writeln!(w, "#pragma once");
+        writeln!(w, "");
+        writeln!(w, "#include <stdbool.h>");
+        writeln!(w, "#include <stddef.h>");
+        writeln!(w, "#include <stdint.h>");
+        writeln!(w, "");
+        writeln!(w, "#ifdef __cplusplus");
+        writeln!(w, "extern \"C\" {{");
+        writeln!(w, "#endif");
+        writeln!(w, "");
+        writeln!(w, "// Values returned by these functions are owned by the caller and must be released with the");
+        writeln!(w, "// matching `_release` function, arguments are only borrowed.  After a call that failed");
+        writeln!(w, "// `{}last_error` returns the message of the C++ exception, otherwise it returns NULL.", prefix);
+        writeln!(w, "const char * {}last_error(void);", prefix);
+        writeln!(w, "");
+        writeln!(w, "// UTF-8, the data is NUL terminated as well.");
+        writeln!(w, "typedef struct {}string {{", prefix);
+        writeln!(w, "    char * data;");
+        writeln!(w, "    size_t len;");
+        writeln!(w, "}} {}string;", prefix);
+        writeln!(w, "");
+        writeln!(w, "{}string {}string_new(const char * data, size_t len);", prefix, prefix);
+        writeln!(w, "void {}string_release({}string * v);", prefix, prefix);
+        writeln!(w, "");
+        writeln!(w, "typedef struct {}binary {{", prefix);
+        writeln!(w, "    uint8_t * data;");
+        writeln!(w, "    size_t len;");
+        writeln!(w, "}} {}binary;", prefix);
+        writeln!(w, "");
+        writeln!(w, "{}binary {}binary_new(const uint8_t * data, size_t len);", prefix, prefix);
+        writeln!(w, "void {}binary_release({}binary * v);", prefix, prefix);
+
+        for (i, _, bs) in self.interfaces(program) {
+            let name = m.type_ident(i);
+            writeln!(w, "");
+            self.write_comments(w, "", &bs.statements);
+            writeln!(w, "typedef struct {} {};", name, name);
+            writeln!(w, "{} * {}({} * v);", name, m.function_name(i, "retain"), name);
+            writeln!(w, "void {}({} * v);", m.function_name(i, "release"), name);
+        }
+
+        for stmt in &program.statements {
+            if let StatementKind::Enum(_, ref i, ref bs) = stmt.stmtKind {
+                let name = m.type_ident(i);
+                writeln!(w, "");
+                writeln!(w, "typedef enum {} {{", name);
+                let mut n = 0;
+                for o in &bs.statements {
+                    match o.stmtKind {
+                        StatementKind::EnumMember(_, ref oi) => {
+                            writeln!(w, "    {} = {},", m.enum_member(i, oi), n);
+                            n += 1;
+                        },
+                        StatementKind::Comment(_, ref c) => {
+                            writeln!(w, "    //{}", c);
+                        },
+                        _ => {}
+                    }
+                }
+                writeln!(w, "}} {};", name);
+            }
+        }
+
+        for dts in types {
+            self.write_type_declaration(w, &m, program, dts);
+        }
+
+        for (i, it, bs) in self.interfaces(program) {
+            if !it.contains(&InterfaceType::Cpp) {
+                continue;
+            }
+
+            writeln!(w, "");
+            for f in &bs.statements {
+                if let StatementKind::Function(_, ref modifier, ref fi, ref params, ref ret, _) = f.stmtKind {
+                    let mut args = Vec::new();
+                    if *modifier != FunctionModifier::Static {
+                        args.push(format!("{} * self", m.type_ident(i)));
+                    }
+                    for p in params {
+                        args.push(format!("{} {}", m.param_type(&p.data_type), m.local_ident(&p.ident)));
+                    }
+                    if args.len() == 0 {
+                        args.push("void".into());
+                    }
+                    writeln!(w, "{} {}({});", m.type_name(ret), m.function_name(i, &m.method_ident(fi)), args.join(", "));
+                }
+            }
+            self.write_consts(w, &m, i, &bs.statements);
+        }
+
+        writeln!(w, "");
+        writeln!(w, "#ifdef __cplusplus");
+        writeln!(w, "}}");
+        writeln!(w, "#endif");
+    }
+
+    fn to_c(&self, m: &CMarshaler, dts: &DataTypeStatement, expr: &str) -> String {
+        let dts = m.strip_optional(dts);
+        match *dts {
+            DataTypeStatement::Object(ref i) if m.user_object_kind(i) == Some(UserObjectKind::Enum) => {
+                format!("static_cast<{}>({})", m.type_name(dts), expr)
+            },
+            DataTypeStatement::String | DataTypeStatement::Binary | DataTypeStatement::Date | DataTypeStatement::List(..) |
+            DataTypeStatement::Array(..) | DataTypeStatement::Set(..) | DataTypeStatement::Map(..) | DataTypeStatement::Optional(..) | DataTypeStatement::Object(..) => {
+                format!("{}_to_c({})", m.mangle(dts), expr)
+            },
+            _ => expr.to_string()
+        }
+    }
+
+    fn from_c(&self, m: &CMarshaler, cpp: &CppMarshaler, dts: &DataTypeStatement, expr: &str) -> String {
+        let dts = m.strip_optional(dts);
+        match *dts {
+            DataTypeStatement::Object(ref i) if m.user_object_kind(i) == Some(UserObjectKind::Enum) => {
+                format!("static_cast<{}>({})", cpp.fq_type_name(dts), expr)
+            },
+            DataTypeStatement::String | DataTypeStatement::Binary | DataTypeStatement::Date | DataTypeStatement::List(..) |
+            DataTypeStatement::Array(..) | DataTypeStatement::Set(..) | DataTypeStatement::Map(..) | DataTypeStatement::Optional(..) | DataTypeStatement::Object(..) => {
+                format!("{}_from_c({})", m.mangle(dts), expr)
+            },
+            _ => expr.to_string()
+        }
+    }
+
+    fn conversion_signatures(&self, m: &CMarshaler, cpp: &CppMarshaler, dts: &DataTypeStatement) -> (String, String) {
+        let c = m.type_name(dts);
+        let from = if m.by_value(dts) { c.clone() } else { format!("const {} &", c) };
+        let cpp_name = cpp.fq_type_name(dts);
+        (format!("inline {} {}_to_c(const {} & v)", c, m.mangle(dts), cpp_name),
+         format!("inline {} {}_from_c({} v)", cpp_name, m.mangle(dts), from))
+    }
+
+    fn write_conversions(&self, w: &mut Write, m: &CMarshaler, cpp: &CppMarshaler, program: &Program, dts: &DataTypeStatement) {
+        let (to, from) = self.conversion_signatures(m, cpp, dts);
+        let c = m.type_name(dts);
+        let cpp_name = cpp.fq_type_name(dts);
+
+        writeln!(w, "");
+        writeln!(w, "{} {{", to);
+        match *dts {
+            DataTypeStatement::String => {
+                writeln!(w, "    return {}string_new(v.data(), v.size());", m.prefix());
+            },
+            DataTypeStatement::Binary => {
+                writeln!(w, "    return {}binary_new(v.data(), v.size());", m.prefix());
+            },
+            DataTypeStatement::Date => {
+                writeln!(w, "    return std::chrono::duration_cast<std::chrono::milliseconds>(v.time_since_epoch()).count();");
+            },
+            DataTypeStatement::List(ref t) | DataTypeStatement::Array(ref t) | DataTypeStatement::Set(ref t) => {
+                writeln!(w, "    {} r = {}_new(v.size());", c, c);
+                writeln!(w, "    size_t i = 0;");
+                writeln!(w, "    for (const auto & item : v) {{");
+                writeln!(w, "        r.data[i++] = {};", self.to_c(m, t, "item"));
+                writeln!(w, "    }}");
+                writeln!(w, "    return r;");
+            },
+            DataTypeStatement::Map(ref k, ref mv) => {
+                writeln!(w, "    {} r = {}_new(v.size());", c, c);
+                writeln!(w, "    size_t i = 0;");
+                writeln!(w, "    for (const auto & item : v) {{");
+                writeln!(w, "        r.keys[i] = {};", self.to_c(m, k, "item.first"));
+                writeln!(w, "        r.values[i++] = {};", self.to_c(m, mv, "item.second"));
+                writeln!(w, "    }}");
+                writeln!(w, "    return r;");
+            },
+            DataTypeStatement::Optional(ref t) => {
+                writeln!(w, "    {} r = {}();", c, c);
+                writeln!(w, "    if (v) {{");
+                writeln!(w, "        r.has_value = true;");
+                writeln!(w, "        r.value = {};", self.to_c(m, t, "*v"));
+                writeln!(w, "    }}");
+                writeln!(w, "    return r;");
+            },
+            DataTypeStatement::Object(ref i) if m.is_interface(i) => {
+                writeln!(w, "    return v ? new {} {{ v }} : nullptr;", m.type_ident(i));
+            },
+            DataTypeStatement::Object(ref i) => {
+                writeln!(w, "    {} r = {}();", c, c);
+                for (fi, fdts) in self.fields(self.find_record(program, i).unwrap()) {
+                    writeln!(w, "    r.{} = {};", m.field_ident(fi), self.to_c(m, fdts, &format!("v.{}", fi.value)));
+                }
+                writeln!(w, "    return r;");
+            },
+            _ => {}
+        }
+        writeln!(w, "}}");
+
+        writeln!(w, "");
+        writeln!(w, "{} {{", from);
+        match *dts {
+            DataTypeStatement::String => {
+                writeln!(w, "    return std::string(v.data ? v.data : \"\", v.len);");
+            },
+            DataTypeStatement::Binary => {
+                writeln!(w, "    return std::vector<uint8_t>(v.data, v.data + v.len);");
+            },
+            DataTypeStatement::Date => {
+                writeln!(w, "    return std::chrono::system_clock::time_point(std::chrono::duration_cast<std::chrono::system_clock::duration>(std::chrono::milliseconds(v)));");
+            },
+            DataTypeStatement::List(ref t) | DataTypeStatement::Array(ref t) | DataTypeStatement::Set(ref t) => {
+                let add = if let DataTypeStatement::Set(..) = *dts { "insert" } else { "push_back" };
+                writeln!(w, "    {} r;", cpp_name);
+                writeln!(w, "    for (size_t i = 0; i < v.len; ++i) {{");
+                writeln!(w, "        r.{}({});", add, self.from_c(m, cpp, t, "v.data[i]"));
+                writeln!(w, "    }}");
+                writeln!(w, "    return r;");
+            },
+            DataTypeStatement::Map(ref k, ref mv) => {
+                writeln!(w, "    {} r;", cpp_name);
+                writeln!(w, "    for (size_t i = 0; i < v.len; ++i) {{");
+                writeln!(w, "        r.emplace({}, {});", self.from_c(m, cpp, k, "v.keys[i]"), self.from_c(m, cpp, mv, "v.values[i]"));
+                writeln!(w, "    }}");
+                writeln!(w, "    return r;");
+            },
+            DataTypeStatement::Optional(ref t) => {
+                writeln!(w, "    if (!v.has_value) {{");
+                writeln!(w, "        return {{}};");
+                writeln!(w, "    }}");
+                writeln!(w, "    return {};", self.from_c(m, cpp, t, "v.value"));
+            },
+            DataTypeStatement::Object(ref i) if m.is_interface(i) => {
+                writeln!(w, "    return v ? v->ptr : nullptr;");
+            },
+            DataTypeStatement::Object(ref i) => {
+                writeln!(w, "    {} r;", cpp_name);
+                for (fi, fdts) in self.fields(self.find_record(program, i).unwrap()) {
+                    writeln!(w, "    r.{} = {};", fi.value, self.from_c(m, cpp, fdts, &format!("v.{}", m.field_ident(fi))));
+                }
+                writeln!(w, "    return r;");
+            },
+            _ => {}
+        }
+        writeln!(w, "}}");
+    }
+
+    fn write_ownership(&self, w: &mut Write, m: &CMarshaler, program: &Program, dts: &DataTypeStatement) {
+        let c = m.type_name(dts);
+
+        let (arrays, elements) = match *dts {
+            DataTypeStatement::List(ref t) | DataTypeStatement::Array(ref t) | DataTypeStatement::Set(ref t) => (vec![("data", &**t)], true),
+            DataTypeStatement::Map(ref k, ref v) => (vec![("keys", &**k), ("values", &**v)], true),
+            _ => (vec![], false)
+        };
+
+        if elements {
+            writeln!(w, "");
+            writeln!(w, "{} {}_new(size_t len) {{", c, c);
+            writeln!(w, "    {} r = {}();", c, c);
+            for &(a, t) in &arrays {
+                writeln!(w, "    r.{} = static_cast<{} *>(std::calloc(len > 0 ? len : 1, sizeof({})));", a, m.type_name(t), m.type_name(t));
+            }
+            writeln!(w, "    r.len = len;");
+            writeln!(w, "    return r;");
+            writeln!(w, "}}");
+        }
+
+        writeln!(w, "");
+        writeln!(w, "void {}_release({} * v) {{", c, c);
+        if elements {
+            let releases = arrays.iter()
+                .filter_map(|&(a, t)| m.release(t, &format!("v->{}[i]", a)))
+                .collect::<Vec<_>>();
+            if releases.len() > 0 {
+                writeln!(w, "    for (size_t i = 0; i < v->len; ++i) {{");
+                for r in releases {
+                    writeln!(w, "        {}", r);
+                }
+                writeln!(w, "    }}");
+            }
+            for &(a, _) in &arrays {
+                writeln!(w, "    std::free(v->{});", a);
+            }
+        }
+        else if let DataTypeStatement::Optional(ref t) = *dts {
+            if let Some(r) = m.release(t, "v->value") {
+                writeln!(w, "    if (v->has_value) {{");
+                writeln!(w, "        {}", r);
+                writeln!(w, "    }}");
+            }
+        }
+        else if let DataTypeStatement::Object(ref i) = *dts {
+            for (fi, fdts) in self.fields(self.find_record(program, i).unwrap()) {
+                if let Some(r) = m.release(fdts, &format!("v->{}", m.field_ident(fi))) {
+                    writeln!(w, "    {}", r);
+                }
+            }
+        }
+        writeln!(w, "    *v = {}();", c);
+        writeln!(w, "}}");
+    }
+
+    fn write_methods(&self, w: &mut Write, m: &CMarshaler, cpp: &CppMarshaler, i: &Identifier, bs: &BlockStatement) {
+        for f in &bs.statements {
+            if let StatementKind::Function(_, ref modifier, ref fi, ref params, ref ret, _) = f.stmtKind {
+                let mut args = Vec::new();
+                let target = if *modifier == FunctionModifier::Static {
+                    format!("{}::", cpp.fq_name(&i.value))
+                }
+                else {
+                    args.push(format!("{} * self", m.type_ident(i)));
+                    "self->ptr->".to_string()
+                };
+                for p in params {
+                    args.push(format!("{} {}", m.param_type(&p.data_type), m.local_ident(&p.ident)));
+                }
+                if args.len() == 0 {
+                    args.push("void".into());
+                }
+
+                let call_args = params.iter()
+                    .map(|p| {
+                        let local = m.local_ident(&p.ident);
+                        let value = if m.by_value(&p.data_type) { local } else { format!("*{}", local) };
+                        self.from_c(m, cpp, &p.data_type, &value)
+                    })
+                    .collect::<Vec<_>>();
+                let call = format!("{}{}({})", target, fi.value, call_args.join(", "));
+
+                writeln!(w, "");
+                writeln!(w, "{} {}({}) {{", m.type_name(ret), m.function_name(i, &m.method_ident(fi)), args.join(", "));
+                match *ret {
+                    DataTypeStatement::None => writeln!(w, "    guard([&] {{ {}; }});", call),
+                    _ => writeln!(w, "    return guard([&] {{ return {}; }});", self.to_c(m, ret, &call))
+                };
+                writeln!(w, "}}");
+            }
+        }
+    }
+
+    fn write_c_source(&self, w: &mut Write, spec: &Spec, program: &Program, types: &Vec<DataTypeStatement>) {
+        let m = CMarshaler::new(spec);
+        let cpp = CppMarshaler::new(spec);
+        let prefix = m.prefix().to_string();
+        let interfaces = self.interfaces(program);
+
+        // Every type the conversions cover: strings, binaries and dates are always available.
+        let mut converted = vec![DataTypeStatement::String, DataTypeStatement::Binary, DataTypeStatement::Date];
+        converted.extend(interfaces.iter().map(|&(i, _, _)| DataTypeStatement::Object(i.clone())));
+        converted.extend(types.iter().cloned());
+
+        self.write_header(w);
+        writeln!(w, "#include \"{}{}.h\"", spec.config.c.include_prefix, spec.config.c.file);
+        for stmt in &program.statements {
+            match stmt.stmtKind {
+                StatementKind::Enum(_, ref i, _) | StatementKind::Record(_, ref i, _, _) | StatementKind::Interface(_, ref i, _, _, _) => {
+                    writeln!(w, "#include {}", cpp.header_include(&i.value));
+                },
+                _ => {}
+            }
+        }
+        for include in &["<chrono>", "<cstdlib>", "<cstring>", "<exception>", "<memory>", "<string>", "<unordered_map>", "<unordered_set>", "<vector>"] {
+            writeln!(w, "#include {}", include);
+        }
+        writeln!(w, "");
+
+        for &(i, _, _) in &interfaces {
+            writeln!(w, "struct {} {{", m.type_ident(i));
+            writeln!(w, "    std::shared_ptr<{}> ptr;", cpp.fq_name(&i.value));
+            writeln!(w, "}};");
+            writeln!(w, "");
+        }
+
+        writeln!(w, "namespace {{");
+        writeln!(w, "");
+        writeln!(w, "thread_local bool has_error = false;");
+        writeln!(w, "thread_local std::string last_error;");
+        writeln!(w, "");
+        writeln!(w, "// Runs `f`, turning an exception into the last error and a zeroed result.");
+        writeln!(w, "template <typename F>");
+        writeln!(w, "auto guard(F && f) -> decltype(f()) {{");
+        writeln!(w, "    has_error = false;");
+        writeln!(w, "    try {{");
+        writeln!(w, "        return f();");
+        writeln!(w, "    }} catch (const std::exception & e) {{");
+        writeln!(w, "        last_error = e.what();");
+        writeln!(w, "    }} catch (...) {{");
+        writeln!(w, "        last_error = \"unknown C++ exception\";");
+        writeln!(w, "    }}");
+        writeln!(w, "    has_error = true;");
+        writeln!(w, "    return decltype(f())();");
+        writeln!(w, "}}");
+        writeln!(w, "");
+
+        for dts in &converted {
+            let (to, from) = self.conversion_signatures(&m, &cpp, dts);
+            writeln!(w, "{};", to);
+            writeln!(w, "{};", from);
+        }
+        for dts in &converted {
+            self.write_conversions(w, &m, &cpp, program, dts);
+        }
+        writeln!(w, "");
+        writeln!(w, "}} // namespace");
+        writeln!(w, "");
+
+        writeln!(w, "extern \"C\" {{");
+        writeln!(w, "");
+        writeln!(w, "const char * {}last_error(void) {{", prefix);
+        writeln!(w, "    return has_error ? last_error.c_str() : nullptr;");
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "{}string {}string_new(const char * data, size_t len) {{", prefix, prefix);
+        writeln!(w, "    {}string r {{ static_cast<char *>(std::malloc(len + 1)), len }};", prefix);
+        writeln!(w, "    if (len > 0) {{");
+        writeln!(w, "        std::memcpy(r.data, data, len);");
+        writeln!(w, "    }}");
+        writeln!(w, "    r.data[len] = '\\0';");
+        writeln!(w, "    return r;");
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "void {}string_release({}string * v) {{", prefix, prefix);
+        writeln!(w, "    std::free(v->data);");
+        writeln!(w, "    *v = {}string();", prefix);
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "{}binary {}binary_new(const uint8_t * data, size_t len) {{", prefix, prefix);
+        writeln!(w, "    {}binary r {{ static_cast<uint8_t *>(std::malloc(len > 0 ? len : 1)), len }};", prefix);
+        writeln!(w, "    if (len > 0) {{");
+        writeln!(w, "        std::memcpy(r.data, data, len);");
+        writeln!(w, "    }}");
+        writeln!(w, "    return r;");
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "void {}binary_release({}binary * v) {{", prefix, prefix);
+        writeln!(w, "    std::free(v->data);");
+        writeln!(w, "    *v = {}binary();", prefix);
+        writeln!(w, "}}");
+
+        for &(i, _, _) in &interfaces {
+            let name = m.type_ident(i);
+            writeln!(w, "");
+            writeln!(w, "{} * {}({} * v) {{", name, m.function_name(i, "retain"), name);
+            writeln!(w, "    return v ? new {} {{ v->ptr }} : nullptr;", name);
+            writeln!(w, "}}");
+            writeln!(w, "");
+            writeln!(w, "void {}({} * v) {{", m.function_name(i, "release"), name);
+            writeln!(w, "    delete v;");
+            writeln!(w, "}}");
+        }
+
+        for dts in types {
+            self.write_ownership(w, &m, program, dts);
+        }
+
+        for &(i, it, bs) in &interfaces {
+            if it.contains(&InterfaceType::Cpp) {
+                self.write_methods(w, &m, &cpp, i, bs);
+            }
+        }
+
+        writeln!(w, "");
+        writeln!(w, "}}");
+    }
+}
+
+impl Generate for CGenerator {
+    // Everything is written by `finish` since the header has to order the types itself.
+    fn write_enum(&self, _e: &StatementKind, _spec: &Spec, _output: &mut GeneratedOutput) {
+    }
+
+    fn write_record(&self, _r: &StatementKind, _spec: &Spec, _output: &mut GeneratedOutput) {
+    }
+
+    fn write_interface(&self, _i: &StatementKind, _spec: &Spec, _output: &mut GeneratedOutput) {
+    }
+
+    fn finish(&self, spec: &Spec, program: &Program, output: &mut GeneratedOutput) {
+        let m = CMarshaler::new(spec);
+        let types = self.collect_all(&m, program);
+
+        let mut w = Vec::new();
+        self.write_c_header(&mut w, spec, program, &types);
+        output.add_file(self.make_path(spec.config.c.header_out().map_or("", |s| s.as_str()), &spec.config.c.file, "h"), w);
+
+        let mut w = Vec::new();
+        self.write_c_source(&mut w, spec, program, &types);
+        output.add_file(self.make_path(spec.config.c.out.as_ref().map_or("", |s| s.as_str()), &spec.config.c.file, &spec.config.cpp.ext), w);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use generator::testing;
+    use config::Config;
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.cpp.namespace = "demo".into();
+        config.c.out = Some("out".into());
+
+        config
+    }
+
+    #[test]
+    fn test_write_header() {
+        let output = testing::compile(config(), r#"
+            color = enum {
+                dark_blue;
+            }
+
+            key = record {
+                name: string;
+                scores: map<string, list<f64>>;
+                const max_id: i64 = 99;
+            }
+
+            key_store = interface +c {
+                static create(): key_store;
+                store(values: list<key>, color: color): i32;
+            }
+        "#);
+
+        let header = &output.get("out/lamp.h").expect("missing lamp.h").contents;
+        assert!(header.contains("typedef struct lamp_key_store lamp_key_store;"), "{}", header);
+        assert!(header.contains("    LAMP_COLOR_DARK_BLUE = 0,\n} lamp_color;"), "{}", header);
+        assert!(header.contains("typedef struct lamp_list_f64 {\n    double * data;\n    size_t len;\n} lamp_list_f64;"), "{}", header);
+        assert!(header.contains("    lamp_string * keys;\n    lamp_list_f64 * values;"), "{}", header);
+        assert!(header.contains("    lamp_map_string_list_f64 scores;\n} lamp_key;"), "{}", header);
+        assert!(header.contains("#define LAMP_KEY_MAX_ID 99"), "{}", header);
+        assert!(header.contains("lamp_key_store * lamp_key_store_create(void);"), "{}", header);
+        assert!(header.contains("int32_t lamp_key_store_store(lamp_key_store * self, const lamp_list_key * values, lamp_color color);"), "{}", header);
+
+        // Containers are declared after their elements and before the records using them.
+        let list = header.find("} lamp_list_f64;").unwrap();
+        assert!(list < header.find("} lamp_map_string_list_f64;").unwrap());
+        assert!(header.find("} lamp_key;").unwrap() < header.find("} lamp_list_key;").unwrap());
+    }
+
+    #[test]
+    fn test_write_source() {
+        let output = testing::compile(config(), r#"
+            key = record {
+                name: string;
+                tags: set<string>;
+            }
+
+            listener = interface +j {
+                on_key(k: key);
+            }
+
+            key_store = interface +c {
+                lookup(name: string, l: listener): key;
+            }
+        "#);
+
+        let source = &output.get("out/lamp.cpp").expect("missing lamp.cpp").contents;
+        assert!(source.contains("#include \"lamp.h\"\n#include \"key.hpp\""), "{}", source);
+        assert!(source.contains("struct lamp_key_store {\n    std::shared_ptr<::demo::key_store> ptr;\n};"), "{}", source);
+        assert!(source.contains("    r.name = string_to_c(v.name);"), "{}", source);
+        assert!(source.contains("        r.insert(string_from_c(v.data[i]));"), "{}", source);
+        assert!(source.contains("lamp_key lamp_key_store_lookup(lamp_key_store * self, const lamp_string * name, lamp_listener * l) {\n    return guard([&] { return key_to_c(self->ptr->lookup(string_from_c(*name), listener_from_c(l))); });"), "{}", source);
+        assert!(source.contains("void lamp_key_release(lamp_key * v) {\n    lamp_string_release(&v->name);\n    lamp_set_string_release(&v->tags);\n    *v = lamp_key();"), "{}", source);
+
+        // Only +c interfaces get methods, every interface gets a handle.
+        assert!(source.contains("void lamp_listener_release(lamp_listener * v) {"), "{}", source);
+        assert!(!source.contains("lamp_listener_on_key"), "{}", source);
+    }
+}
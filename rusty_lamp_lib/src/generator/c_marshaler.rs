@@ -0,0 +1,172 @@
+/*
+ * Copyright © 2002-2017 Bluebeam Software, Inc. All Rights Reserved.
+ * Creator: Jonathan Rothberg
+ */
+
+use parser::ast::{DataTypeStatement, Identifier};
+use generator::spec::Spec;
+use generator::typer::{TypeDefinitionKind, UserObjectKind};
+
+pub struct CMarshaler<'a> {
+    spec: &'a Spec
+}
+
+impl<'a> CMarshaler<'a> {
+    pub fn new(spec: &'a Spec) -> CMarshaler<'a> {
+        CMarshaler {
+            spec: spec
+        }
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.spec.config.c.prefix
+    }
+
+    pub fn type_ident(&self, i: &Identifier) -> String {
+        format!("{}{}", self.prefix(), (self.spec.c_ident_style.ty)(i.value.clone()))
+    }
+
+    /// `{type}_{name}`, the naming of every function that belongs to a type.
+    pub fn function_name(&self, i: &Identifier, name: &str) -> String {
+        format!("{}_{}", self.type_ident(i), name)
+    }
+
+    pub fn method_ident(&self, i: &Identifier) -> String {
+        (self.spec.c_ident_style.method)(i.value.clone())
+    }
+
+    pub fn field_ident(&self, i: &Identifier) -> String {
+        (self.spec.c_ident_style.field)(i.value.clone())
+    }
+
+    pub fn local_ident(&self, i: &Identifier) -> String {
+        (self.spec.c_ident_style.local)(i.value.clone())
+    }
+
+    pub fn enum_member(&self, ty: &Identifier, member: &Identifier) -> String {
+        let name = format!("{}_{}", (self.spec.c_ident_style.ty)(ty.value.clone()), member.value);
+        format!("{}{}", self.prefix().to_uppercase(), (self.spec.c_ident_style.enm)(name))
+    }
+
+    pub fn const_name(&self, ty: &Identifier, i: &Identifier) -> String {
+        let name = format!("{}_{}", (self.spec.c_ident_style.ty)(ty.value.clone()), i.value);
+        format!("{}{}", self.prefix().to_uppercase(), (self.spec.c_ident_style.cnst)(name))
+    }
+
+    /// Interface handles may already be NULL, so an optional interface is just the interface.
+    pub fn strip_optional<'b>(&self, dts: &'b DataTypeStatement) -> &'b DataTypeStatement {
+        match *dts {
+            DataTypeStatement::Optional(ref t) => {
+                match **t {
+                    DataTypeStatement::Object(ref i) if self.is_interface(i) => t,
+                    _ => dts
+                }
+            },
+            _ => dts
+        }
+    }
+
+    /// A name for `dts` that is unique among the types of a program, e.g. `map_string_list_i32`.
+    /// Container structs are named after it since C has no generics.
+    pub fn mangle(&self, dts: &DataTypeStatement) -> String {
+        match *self.strip_optional(dts) {
+            DataTypeStatement::None => "void".into(),
+            DataTypeStatement::String => "string".into(),
+            DataTypeStatement::Binary => "binary".into(),
+            DataTypeStatement::Date => "date".into(),
+            DataTypeStatement::Integer8 => "i8".into(),
+            DataTypeStatement::Integer16 => "i16".into(),
+            DataTypeStatement::Integer32 => "i32".into(),
+            DataTypeStatement::Integer64 => "i64".into(),
+            DataTypeStatement::Float32 => "f32".into(),
+            DataTypeStatement::Float64 => "f64".into(),
+            DataTypeStatement::Bool => "bool".into(),
+            DataTypeStatement::List(ref t) => format!("list_{}", self.mangle(t)),
+            DataTypeStatement::Array(ref t) => format!("array_{}", self.mangle(t)),
+            DataTypeStatement::Set(ref t) => format!("set_{}", self.mangle(t)),
+            DataTypeStatement::Map(ref k, ref v) => format!("map_{}_{}", self.mangle(k), self.mangle(v)),
+            DataTypeStatement::Optional(ref t) => format!("opt_{}", self.mangle(t)),
+            DataTypeStatement::Future(ref t) => format!("future_{}", self.mangle(t)),
+            DataTypeStatement::Function(ref p, ref r) => {
+                let params: Vec<String> = p.iter().map(|t| self.mangle(t)).collect();
+                format!("fn_{}_{}", params.join("_"), self.mangle(r))
+            },
+            DataTypeStatement::Object(ref i) => (self.spec.c_ident_style.ty)(i.value.clone())
+        }
+    }
+
+    // Dates are milliseconds since the Unix epoch and interfaces are opaque handles.
+    pub fn type_name(&self, dts: &DataTypeStatement) -> String {
+        let dts = self.strip_optional(dts);
+        match *dts {
+            DataTypeStatement::None => "void".into(),
+            DataTypeStatement::Integer8 => "int8_t".into(),
+            DataTypeStatement::Integer16 => "int16_t".into(),
+            DataTypeStatement::Integer32 => "int32_t".into(),
+            DataTypeStatement::Integer64 | DataTypeStatement::Date => "int64_t".into(),
+            DataTypeStatement::Float32 => "float".into(),
+            DataTypeStatement::Float64 => "double".into(),
+            DataTypeStatement::Bool => "bool".into(),
+            DataTypeStatement::Object(ref i) if self.is_interface(i) => format!("{} *", self.type_ident(i)),
+            _ => format!("{}{}", self.prefix(), self.mangle(dts))
+        }
+    }
+
+    /// Arguments are borrowed: anything that owns memory is passed by pointer to const.
+    pub fn param_type(&self, dts: &DataTypeStatement) -> String {
+        if self.by_value(dts) {
+            self.type_name(dts)
+        }
+        else {
+            format!("const {} *", self.type_name(dts))
+        }
+    }
+
+    pub fn by_value(&self, dts: &DataTypeStatement) -> bool {
+        match *self.strip_optional(dts) {
+            DataTypeStatement::String | DataTypeStatement::Binary | DataTypeStatement::List(..) | DataTypeStatement::Array(..) |
+            DataTypeStatement::Set(..) | DataTypeStatement::Map(..) | DataTypeStatement::Optional(..) => false,
+            DataTypeStatement::Object(ref i) => {
+                match self.user_object_kind(i) {
+                    Some(UserObjectKind::Record(..)) => false,
+                    _ => true
+                }
+            },
+            _ => true
+        }
+    }
+
+    /// Whether a value of `dts` owns memory that has to be released.
+    pub fn is_owned(&self, dts: &DataTypeStatement) -> bool {
+        match *self.strip_optional(dts) {
+            DataTypeStatement::Object(ref i) => self.is_interface(i) || !self.by_value(dts),
+            _ => !self.by_value(dts)
+        }
+    }
+
+    /// The statement releasing what `lvalue` owns, if it owns anything.
+    pub fn release(&self, dts: &DataTypeStatement, lvalue: &str) -> Option<String> {
+        let dts = self.strip_optional(dts);
+        match *dts {
+            DataTypeStatement::Object(ref i) if self.is_interface(i) => {
+                Some(format!("{}({});", self.function_name(i, "release"), lvalue))
+            },
+            _ if self.is_owned(dts) => Some(format!("{}_release(&{});", self.type_name(dts), lvalue)),
+            _ => None
+        }
+    }
+
+    pub fn is_interface(&self, i: &Identifier) -> bool {
+        match self.user_object_kind(i) {
+            Some(UserObjectKind::Interface(..)) => true,
+            _ => false
+        }
+    }
+
+    pub fn user_object_kind(&self, i: &Identifier) -> Option<UserObjectKind> {
+        match self.spec.typer.get(&i.value) {
+            TypeDefinitionKind::UserObject(_, k) => Some(k),
+            _ => None
+        }
+    }
+}
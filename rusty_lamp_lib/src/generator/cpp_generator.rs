@@ -3,32 +3,39 @@
  * Creator: Jonathan Rothberg
  */
 
-use std::sync::Arc;
-use std::io::{Write, BufWriter};
-use std::fs::{File};
-use std::collections::{HashSet};
+use std::io::{Write};
+use std::collections::{BTreeSet};
 use generator::generator::{Generate};
 use generator::spec::Spec;
+use generator::output::GeneratedOutput;
 use generator::cpp_marshaler::CppMarshaler;
-use parser::program::Program;
-use parser::ast::{StatementKind};
+use parser::ast::{Annotation, StatementKind, DataTypeStatement, DeriveType, FunctionModifier, Identifier, Statement, find_annotation};
 
 pub struct CppGenerator {
-    
+
 }
 
 struct CppRefs {
-    hpp_includes: HashSet<String>,
-    hpp_fwds: HashSet<String>,
-    cpp_includes: HashSet<String>
+    hpp_includes: BTreeSet<String>,
+    hpp_fwds: BTreeSet<String>,
+    cpp_includes: BTreeSet<String>
 }
 
 impl CppRefs {
     fn new() -> CppRefs {
         CppRefs {
-            hpp_includes: HashSet::new(),
-            hpp_fwds: HashSet::new(),
-            cpp_includes: HashSet::new()
+            hpp_includes: BTreeSet::new(),
+            hpp_fwds: BTreeSet::new(),
+            cpp_includes: BTreeSet::new()
+        }
+    }
+
+    fn add(&mut self, marshaler: &CppMarshaler, self_name: &String, dts: &DataTypeStatement) {
+        let own_header = marshaler.header_include(self_name);
+        for include in marshaler.includes(dts) {
+            if include != own_header {
+                self.hpp_includes.insert(format!("#include {}", include));
+            }
         }
     }
 }
@@ -36,7 +43,7 @@ impl CppRefs {
 impl CppGenerator {
     pub fn new() -> CppGenerator {
         CppGenerator {
-            
+
         }
     }
 
@@ -47,36 +54,231 @@ impl CppGenerator {
         writeln!(w, "#pragma once");
     }
 
+    fn write_includes(&self, w: &mut Write, refs: &CppRefs) {
+        for inc in &refs.hpp_includes {
+            writeln!(w, "{}", inc);
+        }
+        writeln!(w, "");
+    }
+
     fn wrap_with_namespace<F>(&self, w: &mut Write, ns: String, block: F) where F: Fn(&mut Write)  {
+        if ns.len() == 0 {
+            block(w);
+            return;
+        }
+
         writeln!(w, "namespace {} {{", ns);
         block(w);
         writeln!(w, "}} // {}", ns);
     }
+
+    /// Default member initializers keep the record an aggregate.  `float` literals need the `f`
+    /// suffix, which C++ only accepts after a fraction.
+    fn default_value(&self, dts: &DataTypeStatement, value: &StatementKind) -> String {
+        match (dts, value) {
+            (&DataTypeStatement::Optional(ref t), _) => self.default_value(t, value),
+            (_, &StatementKind::StringLiteral(_, ref s)) => format!("{:?}", s),
+            (_, &StatementKind::Boolean(_, b)) => format!("{}", b),
+            (&DataTypeStatement::Float32, &StatementKind::NumberLiteral(_, ref n)) if n.contains('.') => format!("{}f", n),
+            (_, &StatementKind::NumberLiteral(_, ref n)) => n.clone(),
+            _ => value.to_string()
+        }
+    }
+
+    fn record_fields<'b>(&self, statements: &'b Vec<Statement>) -> Vec<(&'b Identifier, &'b DataTypeStatement, &'b Vec<Annotation>)> {
+        statements.iter()
+            .filter_map(|s| match s.stmtKind {
+                StatementKind::RecordMember(_, ref i, ref dts, _) => Some((i, dts, &s.annotations)),
+                _ => None
+            })
+            .collect()
+    }
+
+    /// `deriving(eq)` and `deriving(ord)` compare the fields in declaration order.  `==` comes with
+    /// either, since a record deriving eq may hold one that only derives ord.
+    fn write_record_compare(&self, w: &mut Write, marshaler: &CppMarshaler, name: &String, statements: &Vec<Statement>, ord: bool) {
+        let type_name = marshaler.type_ident(name);
+        let members = self.record_fields(statements).into_iter()
+            .map(|(i, _, annotations)| marshaler.member_ident(annotations, i))
+            .collect::<Vec<_>>();
+        let tie = |side: &str| format!("std::tie({})", members.iter().map(|m| format!("{}.{}", side, m)).collect::<Vec<_>>().join(", "));
+
+        writeln!(w, "inline bool operator==(const {0} & lhs, const {0} & rhs) {{", type_name);
+        writeln!(w, "return {} == {};", tie("lhs"), tie("rhs"));
+        writeln!(w, "}}");
+        writeln!(w, "inline bool operator!=(const {0} & lhs, const {0} & rhs) {{", type_name);
+        writeln!(w, "return !(lhs == rhs);");
+        writeln!(w, "}}");
+        if !ord {
+            return;
+        }
+
+        writeln!(w, "inline bool operator<(const {0} & lhs, const {0} & rhs) {{", type_name);
+        writeln!(w, "return {} < {};", tie("lhs"), tie("rhs"));
+        writeln!(w, "}}");
+        writeln!(w, "inline bool operator>(const {0} & lhs, const {0} & rhs) {{", type_name);
+        writeln!(w, "return rhs < lhs;");
+        writeln!(w, "}}");
+        writeln!(w, "inline bool operator<=(const {0} & lhs, const {0} & rhs) {{", type_name);
+        writeln!(w, "return !(rhs < lhs);");
+        writeln!(w, "}}");
+        writeln!(w, "inline bool operator>=(const {0} & lhs, const {0} & rhs) {{", type_name);
+        writeln!(w, "return !(lhs < rhs);");
+        writeln!(w, "}}");
+    }
+
+    /// `deriving(hash)` specializes `std::hash`, mixing the hashes of the fields the way
+    /// `hashCode` does in Java.
+    fn write_record_hash(&self, w: &mut Write, marshaler: &CppMarshaler, name: &String, statements: &Vec<Statement>) {
+        let fq_name = marshaler.fq_name(name);
+        self.wrap_with_namespace(w, "std".into(), |w| {
+            writeln!(w, "template<>");
+            writeln!(w, "struct hash<{}> {{", fq_name);
+            writeln!(w, "size_t operator()(const {} & value) const {{", fq_name);
+            writeln!(w, "size_t result = 17;");
+            for (i, dts, annotations) in self.record_fields(statements) {
+                let field = format!("value.{}", marshaler.member_ident(annotations, i));
+                let hash = match *dts {
+                    DataTypeStatement::Optional(ref t) => {
+                        format!("({} ? std::hash<{}>()(*{}) : 0)", field, marshaler.fq_type_name(t), field)
+                    },
+                    _ => format!("std::hash<{}>()({})", marshaler.fq_type_name(dts), field)
+                };
+                writeln!(w, "result = result * 31 + {};", hash);
+            }
+            writeln!(w, "return result;");
+            writeln!(w, "}}");
+            writeln!(w, "}};");
+        });
+    }
+
+    /// `deriving(show)` prints `name(field=value, ...)` like a Kotlin data class.  Enums print
+    /// their number, and `int8_t` would otherwise print as a character.
+    fn write_record_show(&self, w: &mut Write, marshaler: &CppMarshaler, name: &String, statements: &Vec<Statement>) {
+        let type_name = marshaler.type_ident(name);
+        writeln!(w, "inline std::ostream & operator<<(std::ostream & os, const {} & value) {{", type_name);
+        writeln!(w, "os << \"{}(\";", type_name);
+        for (n, (i, dts, annotations)) in self.record_fields(statements).into_iter().enumerate() {
+            let member = marshaler.member_ident(annotations, i);
+            let separator = if n == 0 { "" } else { ", " };
+            match *dts {
+                DataTypeStatement::Optional(ref t) => {
+                    writeln!(w, "os << \"{}{}=\";", separator, member);
+                    writeln!(w, "if (value.{}) {{", member);
+                    writeln!(w, "os << {};", self.show_value(marshaler, t, &format!("*value.{}", member)));
+                    writeln!(w, "}} else {{");
+                    writeln!(w, "os << \"null\";");
+                    writeln!(w, "}}");
+                },
+                _ => {
+                    writeln!(w, "os << \"{}{}=\" << {};", separator, member, self.show_value(marshaler, dts, &format!("value.{}", member)));
+                }
+            }
+        }
+        writeln!(w, "return os << \")\";");
+        writeln!(w, "}}");
+    }
+
+    fn show_value(&self, marshaler: &CppMarshaler, dts: &DataTypeStatement, value: &String) -> String {
+        match *dts {
+            DataTypeStatement::Integer8 => format!("static_cast<int>({})", value),
+            DataTypeStatement::Bool => format!("({} ? \"true\" : \"false\")", value),
+            DataTypeStatement::Object(ref i) if marshaler.is_enum(i) => format!("static_cast<int>({})", value),
+            _ => value.clone()
+        }
+    }
+
+    /// `deriving(json)` writes the `to_json` and `from_json` pair the JSON library finds by ADL.
+    /// Keys are the idl field names so every language agrees on them, and an empty optional is
+    /// written as `null`.
+    fn write_record_json(&self, w: &mut Write, spec: &Spec, marshaler: &CppMarshaler, name: &String, statements: &Vec<Statement>) {
+        let json = &spec.config.cpp.json_type;
+        let type_name = marshaler.type_ident(name);
+        let fields = self.record_fields(statements);
+
+        writeln!(w, "inline void to_json({} & j, const {} & value) {{", json, type_name);
+        writeln!(w, "j = {}::object();", json);
+        for &(i, dts, annotations) in &fields {
+            let member = marshaler.member_ident(annotations, i);
+            match *dts {
+                DataTypeStatement::Optional(_) => {
+                    writeln!(w, "if (value.{}) {{", member);
+                    writeln!(w, "j[\"{}\"] = *value.{};", i.value, member);
+                    writeln!(w, "}} else {{");
+                    writeln!(w, "j[\"{}\"] = nullptr;", i.value);
+                    writeln!(w, "}}");
+                },
+                _ => {
+                    writeln!(w, "j[\"{}\"] = value.{};", i.value, member);
+                }
+            }
+        }
+        writeln!(w, "}}");
+        writeln!(w, "");
+
+        writeln!(w, "inline void from_json(const {} & j, {} & value) {{", json, type_name);
+        for &(i, dts, annotations) in &fields {
+            let member = marshaler.member_ident(annotations, i);
+            match *dts {
+                DataTypeStatement::Optional(ref t) => {
+                    writeln!(w, "if (j.contains(\"{}\") && !j.at(\"{}\").is_null()) {{", i.value, i.value);
+                    writeln!(w, "value.{} = j.at(\"{}\").get<{}>();", member, i.value, marshaler.type_name(t));
+                    writeln!(w, "}} else {{");
+                    writeln!(w, "value.{} = {{}};", member);
+                    writeln!(w, "}}");
+                },
+                _ => {
+                    writeln!(w, "j.at(\"{}\").get_to(value.{});", i.value, member);
+                }
+            }
+        }
+        writeln!(w, "}}");
+    }
+
+    /// A doc comment for `@since`, which C++ has no attribute for.
+    fn write_since(&self, w: &mut Write, since: Option<&Annotation>) {
+        if let Some(v) = since.and_then(|a| a.value.as_ref()) {
+            writeln!(w, "/** @since {} */", v);
+        }
+    }
+
+    /// `[[deprecated]] ` in front of a declaration, or nothing.
+    fn deprecated_prefix(&self, marshaler: &CppMarshaler, annotations: &Vec<Annotation>) -> String {
+        marshaler.deprecated(find_annotation(annotations, "deprecated")).map_or("".into(), |d| format!("{} ", d))
+    }
+
+    /// ` [[deprecated]]` after the class key of a type, or nothing.
+    fn type_attributes(&self, spec: &Spec, marshaler: &CppMarshaler, name: &String) -> String {
+        marshaler.deprecated(spec.typer.get_annotation(name, "deprecated")).map_or("".into(), |d| format!(" {}", d))
+    }
+
+    fn header_path(&self, spec: &Spec, name: &String) -> ::std::path::PathBuf {
+        let marshaler = CppMarshaler::new(spec);
+        self.make_path(spec.config.cpp.header_out().map_or("", |s| s.as_str()), &marshaler.file_name(name), &spec.config.cpp.header_ext)
+    }
 }
 
 impl Generate for CppGenerator {
-    fn write_enum(&self, e: &StatementKind, spec: &Spec) {
+    fn write_enum(&self, e: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
         if let StatementKind::Enum(_, ref i, ref b) = *e {
-            println!("Generating Enum: {}", i.value);
-            let mut w = self.make_writer(spec, &i.value);
+            let marshaler = CppMarshaler::new(spec);
+            let mut w = Vec::new();
             self.write_header(&mut w);
 
             let mut cpp_refs = CppRefs::new();
             cpp_refs.hpp_includes.insert("#include <functional>".into());
-            
-            cpp_refs.hpp_includes.iter()
-                .filter(|x| x.len() > 0)
-                .inspect(|inc| writeln!(w, "{}", inc).unwrap_or_default())
-                .collect::<Vec<_>>();
-            
-            self.wrap_with_namespace(&mut w, "namespace_gen".into(), |w| {
-                writeln!(w, "enum class {} : int {{", i.value);
+            self.write_includes(&mut w, &cpp_refs);
+
+            self.wrap_with_namespace(&mut w, marshaler.namespace(&i.value), |w| {
+                self.write_since(w, spec.typer.get_annotation(&i.value, "since"));
+                writeln!(w, "enum class{} {} : int {{", self.type_attributes(spec, &marshaler, &i.value), marshaler.type_ident(&i.value));
                 b.statements
                     .iter()
                     .inspect(|o| {
                         match o.stmtKind {
                             StatementKind::EnumMember(_, ref oi) => {
-                                writeln!(w, "{},", oi.value);
+                                let deprecated = marshaler.deprecated(o.annotation("deprecated")).map_or("".into(), |d| format!(" {}", d));
+                                writeln!(w, "{}{},", marshaler.member_ident(&o.annotations, oi), deprecated);
                             },
                             _ => {}
                         }
@@ -85,80 +287,210 @@ impl Generate for CppGenerator {
                 writeln!(w, "}};");
             });
 
+            let fq_name = marshaler.fq_name(&i.value);
             self.wrap_with_namespace(&mut w, "std".into(), |w| {
                 writeln!(w, "template<>");
-                writeln!(w, "struct hash<::namespace_gen::{}> {{", i.value);
-                writeln!(w, "size_t operator()(::namespace_gen::{} type) const {{", i.value);
+                writeln!(w, "struct hash<{}> {{", fq_name);
+                writeln!(w, "size_t operator()({} type) const {{", fq_name);
                 writeln!(w, "return std::hash<int>()(static_cast<int>(type));");
                 writeln!(w, "}}");
                 writeln!(w, "}};");
             });
-            
+
+            output.add_file(self.header_path(spec, &i.value), w);
         }
     }
 
-    fn write_record(&self, r: &StatementKind, spec: &Spec) {
+    fn write_record(&self, r: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
         if let StatementKind::Record(_, ref i, ref bs, ref dt) = *r {
-            let marshaler = CppMarshaler::new();
-            println!("Generating Record: {}", i.value);
-            let mut w = self.make_writer(spec, &i.value);
+            let marshaler = CppMarshaler::new(spec);
+            let mut w = Vec::new();
 
             let mut cpp_refs = CppRefs::new();
-
-            bs.statements
-                .iter()
-                .inspect(|stmt| {
-                match stmt.stmtKind {
-                    StatementKind::RecordMember(_, ref id, ref dts) => {
-                        println!("{}", dts);
-                        let include = marshaler.include(dts);
-                        if include.len() > 0 {
-                            cpp_refs.hpp_includes.insert(format!("#include {}", include));
-                        }
-                    },
-                    _ => {}
+            for stmt in &bs.statements {
+                if let StatementKind::RecordMember(_, _, ref dts, _) = stmt.stmtKind {
+                    cpp_refs.add(&marshaler, &i.value, dts);
                 }
-                })
-                .collect::<Vec<_>>();
+            }
+
+            let compare = dt.contains(&DeriveType::Eq) || dt.contains(&DeriveType::Ord);
+            if compare {
+                cpp_refs.hpp_includes.insert("#include <tuple>".into());
+            }
+            if dt.contains(&DeriveType::Hash) {
+                cpp_refs.hpp_includes.insert("#include <functional>".into());
+            }
+            if dt.contains(&DeriveType::Show) {
+                cpp_refs.hpp_includes.insert("#include <ostream>".into());
+            }
+            if dt.contains(&DeriveType::Json) {
+                cpp_refs.hpp_includes.insert(format!("#include {}", spec.config.cpp.json_header));
+            }
 
             self.write_header(&mut w);
-            cpp_refs.hpp_includes
-                .iter()
-                .filter(|x| x.len() > 0)
-                .map(|inc| writeln!(w, "{}", inc))
-                .collect::<Vec<_>>();
-
-            writeln!(w, "");
-            self.wrap_with_namespace(&mut w, "namespace_gen".into(), |w| {
-                writeln!(w, "struct {} {{", i.value);
+            self.write_includes(&mut w, &cpp_refs);
+
+            self.wrap_with_namespace(&mut w, marshaler.namespace(&i.value), |w| {
+                self.write_since(w, spec.typer.get_annotation(&i.value, "since"));
+                writeln!(w, "struct{} {} {{", self.type_attributes(spec, &marshaler, &i.value), marshaler.type_ident(&i.value));
                 for f in &bs.statements {
                     match f.stmtKind {
-                        StatementKind::RecordMember(_, ref id, ref dts) => {
-                            let t = spec.typer.get(&dts.get_name());
-                            writeln!(w, "{} {};", marshaler.get_type_name(t), id.value);
+                        StatementKind::RecordMember(_, ref id, ref dts, ref default) => {
+                            let default = match *default {
+                                Some(ref v) => format!(" = {}", self.default_value(dts, &v.stmtKind)),
+                                None => String::new()
+                            };
+                            self.write_since(w, f.annotation("since"));
+                            writeln!(w, "{}{} {}{};", self.deprecated_prefix(&marshaler, &f.annotations), marshaler.type_name(dts),
+                                     marshaler.member_ident(&f.annotations, id), default);
                         },
                         _ => {}
                     }
                 }
                 writeln!(w, "}};");
+
+                if compare {
+                    writeln!(w, "");
+                    self.write_record_compare(w, &marshaler, &i.value, &bs.statements, dt.contains(&DeriveType::Ord));
+                }
+                if dt.contains(&DeriveType::Show) {
+                    writeln!(w, "");
+                    self.write_record_show(w, &marshaler, &i.value, &bs.statements);
+                }
+                if dt.contains(&DeriveType::Json) {
+                    writeln!(w, "");
+                    self.write_record_json(w, spec, &marshaler, &i.value, &bs.statements);
+                }
             });
+
+            if dt.contains(&DeriveType::Hash) {
+                self.write_record_hash(&mut w, &marshaler, &i.value, &bs.statements);
+            }
+
+            output.add_file(self.header_path(spec, &i.value), w);
         }
     }
 
-    fn write_interface(&self, i: &StatementKind) {
-        
+    fn write_interface(&self, i: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
+        if let StatementKind::Interface(_, ref id, _, ref bs, _) = *i {
+            let marshaler = CppMarshaler::new(spec);
+            let mut w = Vec::new();
+
+            let mut cpp_refs = CppRefs::new();
+            cpp_refs.hpp_includes.insert("#include <memory>".into());
+            for stmt in &bs.statements {
+                if let StatementKind::Function(_, _, _, ref params, ref ret, ref throws) = stmt.stmtKind {
+                    cpp_refs.add(&marshaler, &id.value, ret);
+                    if let Some(ref e) = *throws {
+                        cpp_refs.hpp_includes.insert(format!("#include {}", spec.config.cpp.expected_header));
+                        cpp_refs.add(&marshaler, &id.value, &DataTypeStatement::Object(e.clone()));
+                    }
+                    for p in params {
+                        cpp_refs.add(&marshaler, &id.value, &p.data_type);
+                    }
+                }
+            }
+
+            self.write_header(&mut w);
+            self.write_includes(&mut w, &cpp_refs);
+
+            self.wrap_with_namespace(&mut w, marshaler.namespace(&id.value), |w| {
+                let name = marshaler.type_ident(&id.value);
+                self.write_since(w, spec.typer.get_annotation(&id.value, "since"));
+                writeln!(w, "class{} {} {{", self.type_attributes(spec, &marshaler, &id.value), name);
+                writeln!(w, "public:");
+                writeln!(w, "virtual ~{}() {{}}", name);
+                for f in &bs.statements {
+                    if let StatementKind::Function(_, ref m, ref fi, ref params, ref ret, ref throws) = f.stmtKind {
+                        let params = params.iter()
+                            .map(|p| format!("{} {}", marshaler.param_type(&p.data_type), marshaler.member_ident(&p.annotations, &p.ident)))
+                            .collect::<Vec<_>>()
+                            .join(", ");
+
+                        self.write_since(w, f.annotation("since"));
+                        let deprecated = self.deprecated_prefix(&marshaler, &f.annotations);
+                        let method = marshaler.member_ident(&f.annotations, fi);
+                        if *m == FunctionModifier::Static {
+                            writeln!(w, "{}static {} {}({});", deprecated, marshaler.return_type(ret, throws), method, params);
+                        }
+                        else {
+                            writeln!(w, "{}virtual {} {}({}) = 0;", deprecated, marshaler.return_type(ret, throws), method, params);
+                        }
+                    }
+                }
+                writeln!(w, "}};");
+            });
+
+            output.add_file(self.header_path(spec, &id.value), w);
+        }
+    }
+
+    fn write_alias(&self, a: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
+        if !spec.config.cpp.aliases {
+            return;
+        }
+
+        if let StatementKind::Alias(_, ref i, ref dts) = *a {
+            let marshaler = CppMarshaler::new(spec);
+            let mut w = Vec::new();
+
+            let mut cpp_refs = CppRefs::new();
+            cpp_refs.add(&marshaler, &i.value, dts);
+
+            self.write_header(&mut w);
+            self.write_includes(&mut w, &cpp_refs);
+
+            self.wrap_with_namespace(&mut w, marshaler.namespace(&i.value), |w| {
+                self.write_since(w, spec.typer.get_annotation(&i.value, "since"));
+                writeln!(w, "using {}{} = {};", marshaler.type_ident(&i.value), self.type_attributes(spec, &marshaler, &i.value), marshaler.type_name(dts));
+            });
+
+            output.add_file(self.header_path(spec, &i.value), w);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::Config;
+    use generator::testing;
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.cpp.out = Some("cpp".into());
+
+        config
     }
 
-    // fn make_writer(&self, spec: &Spec, file_name: String) -> Write{
-    //     // Open a file in write-only mode, returns `io::Result<File>`
-    //     let path = format!("{}/{}/{}.hpp", spec.root, spec.cpp_root, file_name);
-    //     let mut file = match File::create(&path) {
-    //         Err(why) => panic!("couldn't create {}: {}",
-    //                         path,
-    //                         why.description()),
-    //         Ok(file) => file,
-    //     };
+    #[test]
+    fn test_write_record_compare() {
+        let input = "color = enum { red; dark_blue; }\n\
+                     point = record { x: i32; col: color; label: optional<string>; } deriving (eq, hash)\n\
+                     span = record { from: i64; tags: list<string>; } deriving (ord)\n\
+                     shape = record { extent: span; points: map<string, point>; data: binary; } deriving (eq)\n";
+        let output = testing::compile(config(), input);
 
-    //     file
-    // }
+        let point = &output.get("cpp/point.hpp").unwrap().contents;
+        assert!(point.contains("#include <tuple>"), "{}", point);
+        assert!(point.contains("inline bool operator==(const point & lhs, const point & rhs) {\n\
+                                return std::tie(lhs.x, lhs.col, lhs.label) == std::tie(rhs.x, rhs.col, rhs.label);\n}"), "{}", point);
+        assert!(point.contains("inline bool operator!=(const point & lhs, const point & rhs) {\nreturn !(lhs == rhs);\n}"), "{}", point);
+        assert!(!point.contains("operator<"), "{}", point);
+
+        let span = &output.get("cpp/span.hpp").unwrap().contents;
+        assert!(span.contains("return std::tie(lhs.from, lhs.tags) < std::tie(rhs.from, rhs.tags);"), "{}", span);
+        assert!(span.contains("inline bool operator>=(const span & lhs, const span & rhs) {\nreturn !(lhs < rhs);\n}"), "{}", span);
+
+        // The hashed record has to work as the key of the unordered containers.
+        testing::check_cpp("record_compare", &output, "cpp", "#include <string>\n\
+                                                             #include <unordered_set>\n\
+                                                             #include \"point.hpp\"\n\
+                                                             #include \"shape.hpp\"\n\
+                                                             int main() {\n\
+                                                                 std::unordered_set<point> points;\n\
+                                                                 points.insert(point { 1, color::red, std::string(\"a\") });\n\
+                                                                 shape a, b;\n\
+                                                                 return points.count(point { 1, color::red, {} }) + (a == b) + (a.extent <= b.extent);\n\
+                                                             }\n");
+    }
 }
@@ -3,38 +3,245 @@
  * Creator: Jonathan Rothberg
  */
 
-use parser::ast::{DataTypeStatement};
-use generator::typer::{TypeDefinitionKind};
+use parser::ast::{DataTypeStatement, Identifier, Annotation, find_annotation};
+use generator::spec::Spec;
+use generator::typer::{TypeDefinitionKind, UserObjectKind, local_name, module_name};
 
-pub struct CppMarshaler {
-    
+pub struct CppMarshaler<'a> {
+    spec: &'a Spec
 }
 
-impl CppMarshaler {
-    pub fn new() -> CppMarshaler {
+impl<'a> CppMarshaler<'a> {
+    pub fn new(spec: &'a Spec) -> CppMarshaler<'a> {
         CppMarshaler {
-            
+            spec: spec
         }
     }
 
-    pub fn include(&self, dts: &DataTypeStatement) -> String {
-        let include_file = match *dts {
-            DataTypeStatement::Map(..) => {
+    /// The `#include` targets needed to use `dts`, including the headers of user types.
+    pub fn includes(&self, dts: &DataTypeStatement) -> Vec<String> {
+        let include_file = match self.spec.typer.get_from_data_type(dts) {
+            TypeDefinitionKind::Map => {
                 "<unordered_map>".into()
             },
-            DataTypeStatement::Set(..) => {
+            TypeDefinitionKind::Set => {
                 "<unordered_set>".into()
             },
-            DataTypeStatement::List(..) => {
+            TypeDefinitionKind::List | TypeDefinitionKind::Array => {
                 "<vector>".into()
             },
-            DataTypeStatement::String => {
-                "<string>".into()
+            TypeDefinitionKind::Binary => {
+                self.override_header("binary").unwrap_or("<vector>".into())
+            },
+            TypeDefinitionKind::String => {
+                self.override_header("string").unwrap_or("<string>".into())
+            },
+            TypeDefinitionKind::Date => {
+                self.override_header("date").unwrap_or("<chrono>".into())
+            },
+            TypeDefinitionKind::Primitive(ref p) => {
+                p.cpp_header.clone().unwrap_or_default()
+            },
+            TypeDefinitionKind::UserObject(ref n, _) => {
+                self.header_include(n)
+            },
+            TypeDefinitionKind::Optional if !self.is_nullable(dts) => {
+                self.spec.config.cpp.optional_header.clone()
+            },
+            TypeDefinitionKind::Future => {
+                self.spec.config.cpp.future_header.clone()
+            },
+            TypeDefinitionKind::Function => {
+                "<functional>".into()
             },
             _ => {"".into()}
         };
 
-        include_file
+        let mut result = vec![include_file];
+        match *dts {
+            DataTypeStatement::Object(ref i) if self.is_interface(i) => result.push("<memory>".into()),
+            DataTypeStatement::Set(ref t) | DataTypeStatement::List(ref t) | DataTypeStatement::Array(ref t) | DataTypeStatement::Optional(ref t) |
+            DataTypeStatement::Future(ref t) => result.extend(self.includes(t)),
+            DataTypeStatement::Map(ref k, ref v) => {
+                result.extend(self.includes(k));
+                result.extend(self.includes(v));
+            },
+            DataTypeStatement::Function(ref p, ref r) => {
+                for t in p {
+                    result.extend(self.includes(t));
+                }
+                result.extend(self.includes(r));
+            },
+            _ => {}
+        }
+
+        result.retain(|i| i.len() > 0);
+        result
+    }
+
+    pub fn header_include(&self, name: &String) -> String {
+        format!("\"{}{}.{}\"", self.spec.config.cpp.include_prefix, self.file_name(name), self.spec.config.cpp.header_ext)
+    }
+
+    /// The header of a type in a module goes into the module's folder, `net/Config`.
+    pub fn file_name(&self, name: &String) -> String {
+        name.replace('.', "/")
+    }
+
+    /// The namespace a user type is declared in: the configured one, with the type's module
+    /// nested inside it.
+    pub fn namespace(&self, name: &String) -> String {
+        let ns = &self.spec.config.cpp.namespace;
+        match module_name(name) {
+            Some(m) if ns.len() > 0 => format!("{}::{}", ns, m.replace('.', "::")),
+            Some(m) => m.replace('.', "::"),
+            None => ns.clone()
+        }
+    }
+
+    pub fn type_name(&self, dts: &DataTypeStatement) -> String {
+        self.name(dts, false)
+    }
+
+    /// The type name qualified with the configured namespace, for use outside of it.
+    pub fn fq_type_name(&self, dts: &DataTypeStatement) -> String {
+        self.name(dts, true)
+    }
+
+    pub fn fq_name(&self, name: &String) -> String {
+        match self.namespace(name).as_str() {
+            "" => format!("::{}", self.type_ident(name)),
+            ns => format!("::{}::{}", ns, self.type_ident(name))
+        }
+    }
+
+    /// The C++ name of the user type `name`, which `@cpp_name` can change.
+    pub fn type_ident(&self, name: &String) -> String {
+        self.spec.typer.get_annotation(name, "cpp_name")
+            .and_then(|a| a.value.clone())
+            .unwrap_or(local_name(name).to_string())
+    }
+
+    /// The name of a user type inside the configured namespace.  Types in a module are
+    /// qualified with it, which finds them from the namespace of any other module too.
+    fn relative_name(&self, name: &String) -> String {
+        match module_name(name) {
+            Some(m) => format!("{}::{}", m.replace('.', "::"), self.type_ident(name)),
+            None => self.type_ident(name)
+        }
+    }
+
+    /// The C++ name of a member or parameter, which `@cpp_name` can change.
+    pub fn member_ident(&self, annotations: &Vec<Annotation>, i: &Identifier) -> String {
+        find_annotation(annotations, "cpp_name")
+            .and_then(|a| a.value.clone())
+            .unwrap_or(i.value.clone())
+    }
+
+    /// `[[deprecated("reason")]]` for declarations annotated `@deprecated`.
+    pub fn deprecated(&self, deprecated: Option<&Annotation>) -> Option<String> {
+        deprecated.map(|a| match a.value {
+            Some(ref v) => format!("[[deprecated(\"{}\")]]", v),
+            None => "[[deprecated]]".into()
+        })
+    }
+
+    // Primitives and enums are passed by value, everything else by const reference.
+    pub fn param_type(&self, dts: &DataTypeStatement) -> String {
+        let name = self.type_name(dts);
+        if self.by_value(dts) {
+            name
+        }
+        else {
+            format!("const {} &", name)
+        }
+    }
+
+    /// `param_type` qualified with the configured namespace, for overriding methods outside of it.
+    pub fn fq_param_type(&self, dts: &DataTypeStatement) -> String {
+        let name = self.fq_type_name(dts);
+        if self.by_value(dts) {
+            name
+        }
+        else {
+            format!("const {} &", name)
+        }
+    }
+
+    /// A method that throws returns its result or error as `expected_template<T, E>`, and an async
+    /// one delivers that through its future.
+    pub fn return_type(&self, ret: &DataTypeStatement, throws: &Option<Identifier>) -> String {
+        match *throws {
+            None => self.type_name(ret),
+            Some(ref e) => {
+                let expected = |t: &DataTypeStatement| {
+                    format!("{}<{}, {}>", self.spec.config.cpp.expected_template, self.type_name(t), self.type_name(&DataTypeStatement::Object(e.clone())))
+                };
+                match *ret {
+                    DataTypeStatement::Future(ref t) => format!("{}<{}>", self.spec.config.cpp.future_template, expected(t)),
+                    _ => expected(ret)
+                }
+            }
+        }
+    }
+
+    pub fn is_interface(&self, i: &Identifier) -> bool {
+        match self.spec.typer.get(&i.value) {
+            TypeDefinitionKind::UserObject(_, UserObjectKind::Interface(..)) => true,
+            _ => false
+        }
+    }
+
+    pub fn is_enum(&self, i: &Identifier) -> bool {
+        match self.spec.typer.get(&i.value) {
+            TypeDefinitionKind::UserObject(_, UserObjectKind::Enum) => true,
+            _ => false
+        }
+    }
+
+    /// Interfaces are already nullable through their `std::shared_ptr`, and functions through an
+    /// empty `std::function`, so making them optional doesn't change the type.
+    pub fn is_nullable(&self, dts: &DataTypeStatement) -> bool {
+        match *dts {
+            DataTypeStatement::Object(ref i) => self.is_interface(i),
+            DataTypeStatement::Function(..) => true,
+            DataTypeStatement::Optional(ref t) => self.is_nullable(t),
+            _ => false
+        }
+    }
+
+    fn by_value(&self, dts: &DataTypeStatement) -> bool {
+        match self.spec.typer.get_from_data_type(dts) {
+            TypeDefinitionKind::Primitive(..) => true,
+            TypeDefinitionKind::UserObject(_, UserObjectKind::Enum) => true,
+            _ => false
+        }
+    }
+
+    fn name(&self, dts: &DataTypeStatement, qualified: bool) -> String {
+        match *dts {
+            DataTypeStatement::None => "void".into(),
+            DataTypeStatement::Set(ref t) => format!("std::unordered_set<{}>", self.name(t, qualified)),
+            DataTypeStatement::List(ref t) | DataTypeStatement::Array(ref t) => format!("std::vector<{}>", self.name(t, qualified)),
+            DataTypeStatement::Map(ref k, ref v) => format!("std::unordered_map<{}, {}>", self.name(k, qualified), self.name(v, qualified)),
+            DataTypeStatement::Optional(ref t) if self.is_nullable(t) => self.name(t, qualified),
+            DataTypeStatement::Optional(ref t) => format!("{}<{}>", self.spec.config.cpp.optional_template, self.name(t, qualified)),
+            DataTypeStatement::Future(ref t) => format!("{}<{}>", self.spec.config.cpp.future_template, self.name(t, qualified)),
+            DataTypeStatement::Function(ref p, ref r) => {
+                let params: Vec<String> = p.iter().map(|t| self.name(t, qualified)).collect();
+                format!("std::function<{}({})>", self.name(r, qualified), params.join(", "))
+            },
+            DataTypeStatement::Object(ref i) => {
+                let name = if qualified { self.fq_name(&i.value) } else { self.relative_name(&i.value) };
+                if self.is_interface(i) {
+                    format!("std::shared_ptr<{}>", name)
+                }
+                else {
+                    name
+                }
+            },
+            _ => self.get_type_name(self.spec.typer.get_from_data_type(dts))
+        }
     }
 
     pub fn get_type_name(&self, t: TypeDefinitionKind) -> String {
@@ -42,10 +249,20 @@ impl CppMarshaler {
             TypeDefinitionKind::Map => "std::unordered_map".into(),
             TypeDefinitionKind::Set => "std::unordered_set".into(),
             TypeDefinitionKind::List => "std::vector".into(),
-            TypeDefinitionKind::String => "std::string".into(),
-            TypeDefinitionKind::Primitive(_, _, _, ref ct, _, _, _, _) => ct.clone(),
-            TypeDefinitionKind::UserObject(ref n) => n.clone(),
+            TypeDefinitionKind::String => self.override_type("string").unwrap_or("std::string".into()),
+            TypeDefinitionKind::Binary => self.override_type("binary").unwrap_or("std::vector<uint8_t>".into()),
+            TypeDefinitionKind::Date => self.override_type("date").unwrap_or("std::chrono::system_clock::time_point".into()),
+            TypeDefinitionKind::Primitive(ref p) => p.cpp.clone(),
+            TypeDefinitionKind::UserObject(ref n, _) => n.clone(),
             _ => "".into()
         }
     }
+
+    fn override_type(&self, name: &str) -> Option<String> {
+        self.spec.typer.get_override(name).and_then(|o| o.cpp.clone())
+    }
+
+    fn override_header(&self, name: &str) -> Option<String> {
+        self.spec.typer.get_override(name).and_then(|o| o.cpp_header.clone())
+    }
 }
@@ -0,0 +1,709 @@
+/*
+ * Copyright © 2002-2017 Bluebeam Software, Inc. All Rights Reserved.
+ * Creator: Jonathan Rothberg
+ */
+
+use std::io::{Write};
+use generator::generator::{Generate};
+use generator::spec::Spec;
+use generator::output::GeneratedOutput;
+use generator::c_generator::CGenerator;
+use generator::csharp_marshaler::CSharpMarshaler;
+use parser::program::Program;
+use parser::ast::{Statement, StatementKind, DataTypeStatement, FunctionModifier, Identifier, InterfaceType, BlockStatement, Parameter};
+
+/// P/Invoke bindings over the API written by the C generator.  `Native.cs` mirrors the C
+/// structs and functions, `Marshaling.cs` converts between them and the C# types, and every
+/// type gets its own file: enums, record classes, and `IDisposable` wrappers around a
+/// `SafeHandle` for interfaces.
+pub struct CSharpGenerator {
+
+}
+
+impl CSharpGenerator {
+    pub fn new() -> CSharpGenerator {
+        CSharpGenerator {
+
+        }
+    }
+
+    fn write_header(&self, w: &mut Write, spec: &Spec, usings: &[&str]) {
+        writeln!(w, "// AUTOGENERATED FILE - DO NOT MODIFY!");
+        writeln!(w, "// This file was generated by rusty_lamp");
+        writeln!(w, "");
+        for using in usings {
+            writeln!(w, "using {};", using);
+        }
+        if usings.len() > 0 {
+            writeln!(w, "");
+        }
+        writeln!(w, "namespace {}", spec.config.csharp.namespace);
+        writeln!(w, "{{");
+    }
+
+    fn add_file(&self, spec: &Spec, name: &String, w: Vec<u8>, output: &mut GeneratedOutput) {
+        output.add_file(self.make_path(spec.config.csharp.out.as_ref().map_or("", |s| s.as_str()), name, "cs"), w);
+    }
+
+    fn write_lines(&self, w: &mut Write, indent: &str, lines: &Vec<String>) {
+        for l in lines {
+            writeln!(w, "{}{}", indent, l);
+        }
+    }
+
+    fn find_record<'p>(&self, program: &'p Program, i: &Identifier) -> Option<&'p BlockStatement> {
+        program.statements.iter()
+            .filter_map(|s| match s.stmtKind {
+                StatementKind::Record(_, ref ri, ref bs, _) if ri.value == i.value => Some(bs),
+                _ => None
+            })
+            .next()
+    }
+
+    fn fields<'b>(&self, bs: &'b BlockStatement) -> Vec<(&'b Identifier, &'b DataTypeStatement)> {
+        bs.statements.iter()
+            .filter_map(|s| match s.stmtKind { StatementKind::RecordMember(_, ref i, ref dts, _) => Some((i, dts)), _ => None })
+            .collect()
+    }
+
+    fn interfaces<'p>(&self, program: &'p Program) -> Vec<(&'p Identifier, &'p Vec<InterfaceType>, &'p BlockStatement)> {
+        program.statements.iter()
+            .filter_map(|s| match s.stmtKind {
+                StatementKind::Interface(_, ref i, ref it, ref bs, _) => Some((i, it, bs)),
+                _ => None
+            })
+            .collect()
+    }
+
+    fn consts(&self, m: &CSharpMarshaler, statements: &Vec<Statement>) -> Vec<String> {
+        let mut result = Vec::new();
+        for s in statements {
+            if let StatementKind::Const(_, ref i, ref dts, ref v) = s.stmtKind {
+                let value = match v.stmtKind {
+                    StatementKind::StringLiteral(_, ref s) => format!("{:?}", s),
+                    StatementKind::Boolean(_, b) => format!("{}", b),
+                    StatementKind::NumberLiteral(_, ref n) if *dts == DataTypeStatement::Float32 => format!("{}f", n),
+                    StatementKind::NumberLiteral(_, ref n) => n.clone(),
+                    _ => {
+                        result.push(format!("// const {}: {} is not representable as a C# constant", i.value, dts));
+                        continue;
+                    }
+                };
+                result.push(format!("public const {} {} = {};", m.type_name(dts), m.const_ident(i), value));
+            }
+        }
+        result
+    }
+
+    fn import(&self, w: &mut Write, ret: &str, name: &str, args: &str) {
+        writeln!(w, "");
+        writeln!(w, "        [DllImport(Library, CallingConvention = CallingConvention.Cdecl)]");
+        writeln!(w, "        internal static extern {} {}({});", ret, name, args);
+    }
+
+    fn write_struct(&self, w: &mut Write, m: &CSharpMarshaler, program: &Program, dts: &DataTypeStatement) {
+        let mut fields = Vec::new();
+        match *dts {
+            DataTypeStatement::List(..) | DataTypeStatement::Array(..) | DataTypeStatement::Set(..) => {
+                fields.push(("IntPtr".to_string(), "Data".to_string()));
+                fields.push(("UIntPtr".to_string(), "Len".to_string()));
+            },
+            DataTypeStatement::Map(..) => {
+                fields.push(("IntPtr".to_string(), "Keys".to_string()));
+                fields.push(("IntPtr".to_string(), "Values".to_string()));
+                fields.push(("UIntPtr".to_string(), "Len".to_string()));
+            },
+            DataTypeStatement::Optional(ref t) => {
+                fields.push(("byte".to_string(), "HasValue".to_string()));
+                fields.push((m.native_type_name(t), "Value".to_string()));
+            },
+            DataTypeStatement::Object(ref i) => {
+                for (fi, fdts) in self.fields(self.find_record(program, i).unwrap()) {
+                    fields.push((m.native_type_name(fdts), m.field_ident(fi)));
+                }
+            },
+            _ => {}
+        }
+
+        writeln!(w, "");
+        writeln!(w, "        [StructLayout(LayoutKind.Sequential)]");
+        writeln!(w, "        internal struct {}", m.helper_ident(dts));
+        writeln!(w, "        {{");
+        for (ty, name) in fields {
+            writeln!(w, "            public {} {};", ty, name);
+        }
+        writeln!(w, "        }}");
+    }
+
+    fn write_native(&self, w: &mut Write, spec: &Spec, program: &Program, types: &Vec<DataTypeStatement>) {
+        let m = CSharpMarshaler::new(spec);
+        let c = m.c();
+        let prefix = c.prefix().to_string();
+        let library = spec.config.csharp.library.clone().unwrap_or(spec.config.c.file.clone());
+
+        self.write_header(w, spec, &["System", "System.Runtime.InteropServices"]);
+        writeln!(w, "    // Thrown for a C++ exception that reached the C API.");
+        writeln!(w, "    public sealed class NativeException : Exception");
+        writeln!(w, "    {{");
+        writeln!(w, "        public NativeException(string message) : base(message)");
+        writeln!(w, "        {{");
+        writeln!(w, "        }}");
+        writeln!(w, "    }}");
+        writeln!(w, "");
+        writeln!(w, "    // The structs and functions of the C API, named exactly like in the C header.");
+        writeln!(w, "    internal static class Native");
+        writeln!(w, "    {{");
+        writeln!(w, "        internal const string Library = {:?};", library);
+        for name in &["String", "Binary"] {
+            writeln!(w, "");
+            writeln!(w, "        [StructLayout(LayoutKind.Sequential)]");
+            writeln!(w, "        internal struct {}", name);
+            writeln!(w, "        {{");
+            writeln!(w, "            public IntPtr Data;");
+            writeln!(w, "            public UIntPtr Len;");
+            writeln!(w, "        }}");
+        }
+        for dts in types {
+            self.write_struct(w, &m, program, dts);
+        }
+
+        self.import(w, "IntPtr", &format!("{}last_error", prefix), "");
+        for &(name, data) in &[("string", "String"), ("binary", "Binary")] {
+            self.import(w, data, &format!("{}{}_new", prefix, name), "byte[] data, UIntPtr len");
+            self.import(w, "void", &format!("{}{}_release", prefix, name), &format!("ref {} v", data));
+        }
+        for (i, _, _) in self.interfaces(program) {
+            self.import(w, "IntPtr", &c.function_name(i, "retain"), "IntPtr v");
+            self.import(w, "void", &c.function_name(i, "release"), "IntPtr v");
+        }
+        for dts in types {
+            let name = c.type_name(dts);
+            let native = m.native_type_name(dts);
+            match *dts {
+                DataTypeStatement::List(..) | DataTypeStatement::Array(..) | DataTypeStatement::Set(..) | DataTypeStatement::Map(..) => {
+                    self.import(w, &native, &format!("{}_new", name), "UIntPtr len");
+                },
+                _ => {}
+            }
+            self.import(w, "void", &format!("{}_release", name), &format!("ref {} v", native));
+        }
+        for (i, it, bs) in self.interfaces(program) {
+            if !it.contains(&InterfaceType::Cpp) {
+                continue;
+            }
+            for f in &bs.statements {
+                if let StatementKind::Function(_, ref modifier, ref fi, ref params, ref ret, _) = f.stmtKind {
+                    let mut args = Vec::new();
+                    if *modifier != FunctionModifier::Static {
+                        args.push(format!("{} self", m.handle_ident(i)));
+                    }
+                    for p in params {
+                        args.push(format!("{} {}", m.native_param_type(&p.data_type), m.local_ident(&p.ident)));
+                    }
+                    let ret = match *ret {
+                        DataTypeStatement::None => "void".to_string(),
+                        _ => m.native_type_name(ret)
+                    };
+                    self.import(w, &ret, &c.function_name(i, &c.method_ident(fi)), &args.join(", "));
+                }
+            }
+        }
+
+        writeln!(w, "");
+        writeln!(w, "        internal static void ThrowIfError()");
+        writeln!(w, "        {{");
+        writeln!(w, "            IntPtr error = {}last_error();", prefix);
+        writeln!(w, "            if (error != IntPtr.Zero)");
+        writeln!(w, "            {{");
+        writeln!(w, "                throw new NativeException(Marshal.PtrToStringAnsi(error));");
+        writeln!(w, "            }}");
+        writeln!(w, "        }}");
+        writeln!(w, "    }}");
+        writeln!(w, "}}");
+    }
+
+    fn write_conversions(&self, w: &mut Write, m: &CSharpMarshaler, program: &Program, dts: &DataTypeStatement) {
+        let h = m.helper_ident(dts);
+        let ty = m.type_name(dts);
+        let native = m.native_type_name(dts);
+
+        let (to, from) = match *dts {
+            DataTypeStatement::List(ref t) | DataTypeStatement::Set(ref t) => {
+                (vec![
+                    format!("var r = Native.{}_new((UIntPtr)v.Count);", m.c().type_name(dts)),
+                    "int i = 0;".to_string(),
+                    "foreach (var item in v)".to_string(),
+                    "{".to_string(),
+                    format!("    Write(r.Data, i++, {});", m.to_native(t, "item")),
+                    "}".to_string(),
+                    "return r;".to_string()
+                ], vec![
+                    format!("var r = new {}();", ty),
+                    "for (int i = 0; i < (int)v.Len; i++)".to_string(),
+                    "{".to_string(),
+                    format!("    r.Add({});", m.from_native(t, &format!("Read<{}>(v.Data, i)", m.native_type_name(t)))),
+                    "}".to_string(),
+                    "return r;".to_string()
+                ])
+            },
+            DataTypeStatement::Array(ref t) => {
+                (vec![
+                    format!("var r = Native.{}_new((UIntPtr)v.Length);", m.c().type_name(dts)),
+                    "for (int i = 0; i < v.Length; i++)".to_string(),
+                    "{".to_string(),
+                    format!("    Write(r.Data, i, {});", m.to_native(t, "v[i]")),
+                    "}".to_string(),
+                    "return r;".to_string()
+                ], vec![
+                    format!("var r = new {}[(int)v.Len];", m.type_name(t)),
+                    "for (int i = 0; i < r.Length; i++)".to_string(),
+                    "{".to_string(),
+                    format!("    r[i] = {};", m.from_native(t, &format!("Read<{}>(v.Data, i)", m.native_type_name(t)))),
+                    "}".to_string(),
+                    "return r;".to_string()
+                ])
+            },
+            DataTypeStatement::Map(ref k, ref mv) => {
+                (vec![
+                    format!("var r = Native.{}_new((UIntPtr)v.Count);", m.c().type_name(dts)),
+                    "int i = 0;".to_string(),
+                    "foreach (var item in v)".to_string(),
+                    "{".to_string(),
+                    format!("    Write(r.Keys, i, {});", m.to_native(k, "item.Key")),
+                    format!("    Write(r.Values, i++, {});", m.to_native(mv, "item.Value")),
+                    "}".to_string(),
+                    "return r;".to_string()
+                ], vec![
+                    format!("var r = new {}();", ty),
+                    "for (int i = 0; i < (int)v.Len; i++)".to_string(),
+                    "{".to_string(),
+                    format!("    r[{}] = {};",
+                            m.from_native(k, &format!("Read<{}>(v.Keys, i)", m.native_type_name(k))),
+                            m.from_native(mv, &format!("Read<{}>(v.Values, i)", m.native_type_name(mv)))),
+                    "}".to_string(),
+                    "return r;".to_string()
+                ])
+            },
+            DataTypeStatement::Optional(ref t) => {
+                let value = if m.is_value_type(t) { "v.Value" } else { "v" };
+                (vec![
+                    format!("var r = new {}();", native),
+                    "if (v != null)".to_string(),
+                    "{".to_string(),
+                    "    r.HasValue = 1;".to_string(),
+                    format!("    r.Value = {};", m.to_native(t, value)),
+                    "}".to_string(),
+                    "return r;".to_string()
+                ], vec![
+                    "if (v.HasValue == 0)".to_string(),
+                    "{".to_string(),
+                    "    return null;".to_string(),
+                    "}".to_string(),
+                    format!("return {};", m.from_native(t, "v.Value"))
+                ])
+            },
+            DataTypeStatement::Object(ref i) => {
+                let fields = self.fields(self.find_record(program, i).unwrap());
+                let mut to = vec![format!("var r = new {}();", native)];
+                for &(fi, fdts) in &fields {
+                    let name = m.field_ident(fi);
+                    to.push(format!("r.{} = {};", name, m.to_native(fdts, &format!("v.{}", name))));
+                }
+                to.push("return r;".to_string());
+                let args = fields.iter()
+                    .map(|&(fi, fdts)| m.from_native(fdts, &format!("v.{}", m.field_ident(fi))))
+                    .collect::<Vec<_>>();
+                (to, vec![format!("return new {}({});", ty, args.join(", "))])
+            },
+            _ => return
+        };
+
+        writeln!(w, "");
+        writeln!(w, "        internal static {} {}ToNative({} v)", native, h, ty);
+        writeln!(w, "        {{");
+        self.write_lines(w, "            ", &to);
+        writeln!(w, "        }}");
+        writeln!(w, "");
+        writeln!(w, "        internal static {} {}FromNative({} v)", ty, h, native);
+        writeln!(w, "        {{");
+        self.write_lines(w, "            ", &from);
+        writeln!(w, "        }}");
+    }
+
+    fn write_marshaling(&self, w: &mut Write, spec: &Spec, program: &Program, types: &Vec<DataTypeStatement>) {
+        let m = CSharpMarshaler::new(spec);
+        let prefix = m.c().prefix().to_string();
+
+        self.write_header(w, spec, &["System", "System.Collections.Generic", "System.Runtime.InteropServices", "System.Text"]);
+        writeln!(w, "    // Conversions between the C# types and the structs of the C API.  Values made by `ToNative`");
+        writeln!(w, "    // are released with the native `_release` functions, `FromNative` only copies.");
+        writeln!(w, "    internal static class Marshaling");
+        writeln!(w, "    {{");
+        writeln!(w, "        private static T Read<T>(IntPtr data, int i) where T : struct");
+        writeln!(w, "        {{");
+        writeln!(w, "            return Marshal.PtrToStructure<T>(data + i * Marshal.SizeOf<T>());");
+        writeln!(w, "        }}");
+        writeln!(w, "");
+        writeln!(w, "        private static void Write<T>(IntPtr data, int i, T value) where T : struct");
+        writeln!(w, "        {{");
+        writeln!(w, "            Marshal.StructureToPtr(value, data + i * Marshal.SizeOf<T>(), false);");
+        writeln!(w, "        }}");
+        writeln!(w, "");
+        writeln!(w, "        private static byte[] Copy(IntPtr data, UIntPtr len)");
+        writeln!(w, "        {{");
+        writeln!(w, "            var r = new byte[(int)len];");
+        writeln!(w, "            if (r.Length > 0)");
+        writeln!(w, "            {{");
+        writeln!(w, "                Marshal.Copy(data, r, 0, r.Length);");
+        writeln!(w, "            }}");
+        writeln!(w, "            return r;");
+        writeln!(w, "        }}");
+        writeln!(w, "");
+        writeln!(w, "        internal static Native.String StringToNative(string v)");
+        writeln!(w, "        {{");
+        writeln!(w, "            var bytes = Encoding.UTF8.GetBytes(v);");
+        writeln!(w, "            return Native.{}string_new(bytes, (UIntPtr)bytes.Length);", prefix);
+        writeln!(w, "        }}");
+        writeln!(w, "");
+        writeln!(w, "        internal static string StringFromNative(Native.String v)");
+        writeln!(w, "        {{");
+        writeln!(w, "            return Encoding.UTF8.GetString(Copy(v.Data, v.Len));");
+        writeln!(w, "        }}");
+        writeln!(w, "");
+        writeln!(w, "        internal static Native.Binary BinaryToNative(byte[] v)");
+        writeln!(w, "        {{");
+        writeln!(w, "            return Native.{}binary_new(v, (UIntPtr)v.Length);", prefix);
+        writeln!(w, "        }}");
+        writeln!(w, "");
+        writeln!(w, "        internal static byte[] BinaryFromNative(Native.Binary v)");
+        writeln!(w, "        {{");
+        writeln!(w, "            return Copy(v.Data, v.Len);");
+        writeln!(w, "        }}");
+
+        for (i, _, _) in self.interfaces(program) {
+            let ty = m.type_ident(i);
+            writeln!(w, "");
+            writeln!(w, "        internal static IntPtr {}ToNative({} v)", ty, ty);
+            writeln!(w, "        {{");
+            writeln!(w, "            return v == null ? IntPtr.Zero : v.Retain();");
+            writeln!(w, "        }}");
+            writeln!(w, "");
+            writeln!(w, "        internal static {} {}FromNative(IntPtr v)", ty, ty);
+            writeln!(w, "        {{");
+            writeln!(w, "            return v == IntPtr.Zero ? null : new {}(new {}(Native.{}(v)));", ty, m.handle_ident(i), m.c().function_name(i, "retain"));
+            writeln!(w, "        }}");
+        }
+
+        for dts in types {
+            self.write_conversions(w, &m, program, dts);
+        }
+        writeln!(w, "    }}");
+        writeln!(w, "}}");
+    }
+
+    /// `body` run in a `try` block, with `finally` run however it exits.
+    fn try_finally(&self, body: Vec<String>, finally: Vec<String>) -> Vec<String> {
+        let mut lines = vec!["try".to_string(), "{".to_string()];
+        lines.extend(body.into_iter().map(|l| format!("    {}", l)));
+        lines.push("}".to_string());
+        lines.push("finally".to_string());
+        lines.push("{".to_string());
+        lines.extend(finally.into_iter().map(|l| format!("    {}", l)));
+        lines.push("}".to_string());
+        lines
+    }
+
+    /// Arguments that own native memory are converted up front and released once the call
+    /// returned, the result is copied before it is released.
+    fn method_body(&self, m: &CSharpMarshaler, i: &Identifier, modifier: &FunctionModifier, fi: &Identifier,
+                   params: &Vec<Parameter>, ret: &DataTypeStatement) -> Vec<String> {
+        let c = m.c();
+        let mut locals = Vec::new();
+        let mut releases = Vec::new();
+        let mut args = Vec::new();
+        if *modifier != FunctionModifier::Static {
+            args.push("Handle".to_string());
+        }
+        for p in params {
+            let local = m.local_ident(&p.ident);
+            match m.release(&p.data_type, &m.native_local_ident(&p.ident)) {
+                Some(r) => {
+                    let native = m.native_local_ident(&p.ident);
+                    locals.push(format!("var {} = {};", native, m.to_native(&p.data_type, &local)));
+                    releases.push(r);
+                    args.push(if c.by_value(&p.data_type) { native } else { format!("ref {}", native) });
+                },
+                None => args.push(m.to_native(&p.data_type, &local))
+            }
+        }
+
+        let call = format!("Native.{}({})", c.function_name(i, &c.method_ident(fi)), args.join(", "));
+        let mut body = Vec::new();
+        match *ret {
+            DataTypeStatement::None => {
+                body.push(format!("{};", call));
+                body.push("Native.ThrowIfError();".to_string());
+            },
+            _ => {
+                body.push(format!("var result = {};", call));
+                body.push("Native.ThrowIfError();".to_string());
+                let value = format!("return {};", m.from_native(ret, "result"));
+                match m.release(ret, "result") {
+                    Some(r) => body.extend(self.try_finally(vec![value], vec![r])),
+                    None => body.push(value)
+                }
+            }
+        }
+
+        if releases.len() > 0 {
+            body = self.try_finally(body, releases);
+        }
+        locals.extend(body);
+        locals
+    }
+
+    fn write_handle(&self, w: &mut Write, m: &CSharpMarshaler, i: &Identifier) {
+        let handle = m.handle_ident(i);
+        writeln!(w, "    // Owns one reference to the native object.");
+        writeln!(w, "    internal sealed class {} : SafeHandle", handle);
+        writeln!(w, "    {{");
+        writeln!(w, "        internal {}(IntPtr handle) : base(IntPtr.Zero, true)", handle);
+        writeln!(w, "        {{");
+        writeln!(w, "            SetHandle(handle);");
+        writeln!(w, "        }}");
+        writeln!(w, "");
+        writeln!(w, "        public override bool IsInvalid");
+        writeln!(w, "        {{");
+        writeln!(w, "            get {{ return handle == IntPtr.Zero; }}");
+        writeln!(w, "        }}");
+        writeln!(w, "");
+        writeln!(w, "        protected override bool ReleaseHandle()");
+        writeln!(w, "        {{");
+        writeln!(w, "            Native.{}(handle);", m.c().function_name(i, "release"));
+        writeln!(w, "            return true;");
+        writeln!(w, "        }}");
+        writeln!(w, "    }}");
+    }
+}
+
+impl Generate for CSharpGenerator {
+    fn write_enum(&self, e: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
+        if let StatementKind::Enum(_, ref i, ref b) = *e {
+            let m = CSharpMarshaler::new(spec);
+            let mut w = Vec::new();
+            self.write_header(&mut w, spec, &[]);
+            writeln!(w, "    public enum {}", m.type_ident(i));
+            writeln!(w, "    {{");
+            let mut n = 0;
+            for o in &b.statements {
+                match o.stmtKind {
+                    StatementKind::EnumMember(_, ref oi) => {
+                        writeln!(w, "        {} = {},", m.enum_member(oi), n);
+                        n += 1;
+                    },
+                    StatementKind::Comment(_, ref c) => {
+                        writeln!(w, "        //{}", c);
+                    },
+                    _ => {}
+                }
+            }
+            writeln!(w, "    }}");
+            writeln!(w, "}}");
+            self.add_file(spec, &m.type_ident(i), w, output);
+        }
+    }
+
+    fn write_record(&self, r: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
+        if let StatementKind::Record(_, ref i, ref bs, _) = *r {
+            let m = CSharpMarshaler::new(spec);
+            let ty = m.type_ident(i);
+            let fields = self.fields(bs);
+
+            let mut w = Vec::new();
+            self.write_header(&mut w, spec, &["System", "System.Collections.Generic"]);
+            writeln!(w, "    public sealed class {}", ty);
+            writeln!(w, "    {{");
+            let consts = self.consts(&m, &bs.statements);
+            self.write_lines(&mut w, "        ", &consts);
+            if consts.len() > 0 {
+                writeln!(w, "");
+            }
+            for s in &bs.statements {
+                match s.stmtKind {
+                    StatementKind::RecordMember(_, ref fi, ref dts, _) => {
+                        writeln!(w, "        public {} {} {{ get; set; }}", m.type_name(dts), m.field_ident(fi));
+                    },
+                    StatementKind::Comment(_, ref c) => {
+                        writeln!(w, "        //{}", c);
+                    },
+                    _ => {}
+                }
+            }
+            writeln!(w, "");
+            let params = fields.iter()
+                .map(|&(fi, dts)| format!("{} {}", m.type_name(dts), m.local_ident(fi)))
+                .collect::<Vec<_>>();
+            writeln!(w, "        public {}({})", ty, params.join(", "));
+            writeln!(w, "        {{");
+            for &(fi, _) in &fields {
+                writeln!(w, "            {} = {};", m.field_ident(fi), m.local_ident(fi));
+            }
+            writeln!(w, "        }}");
+            writeln!(w, "    }}");
+            writeln!(w, "}}");
+            self.add_file(spec, &ty, w, output);
+        }
+    }
+
+    fn write_interface(&self, i: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
+        if let StatementKind::Interface(_, ref id, ref it, ref bs, _) = *i {
+            let m = CSharpMarshaler::new(spec);
+            let ty = m.type_ident(id);
+            let handle = m.handle_ident(id);
+
+            let mut w = Vec::new();
+            self.write_header(&mut w, spec, &["System", "System.Collections.Generic", "System.Runtime.InteropServices"]);
+            self.write_handle(&mut w, &m, id);
+            writeln!(w, "");
+            writeln!(w, "    public sealed class {} : IDisposable", ty);
+            writeln!(w, "    {{");
+            let consts = self.consts(&m, &bs.statements);
+            self.write_lines(&mut w, "        ", &consts);
+            if consts.len() > 0 {
+                writeln!(w, "");
+            }
+            writeln!(w, "        internal readonly {} Handle;", handle);
+            writeln!(w, "");
+            writeln!(w, "        internal {}({} handle)", ty, handle);
+            writeln!(w, "        {{");
+            writeln!(w, "            Handle = handle;");
+            writeln!(w, "        }}");
+            writeln!(w, "");
+            writeln!(w, "        public void Dispose()");
+            writeln!(w, "        {{");
+            writeln!(w, "            Handle.Dispose();");
+            writeln!(w, "        }}");
+            writeln!(w, "");
+            writeln!(w, "        // A new native reference, owned by the caller.");
+            writeln!(w, "        internal IntPtr Retain()");
+            writeln!(w, "        {{");
+            writeln!(w, "            var r = Native.{}(Handle.DangerousGetHandle());", m.c().function_name(id, "retain"));
+            writeln!(w, "            GC.KeepAlive(this);");
+            writeln!(w, "            return r;");
+            writeln!(w, "        }}");
+
+            // Only interfaces implemented in C++ have functions in the C API.
+            if it.contains(&InterfaceType::Cpp) {
+                let mut after_comment = false;
+                for s in &bs.statements {
+                    match s.stmtKind {
+                        StatementKind::Function(_, ref modifier, ref fi, ref params, ref ret, _) => {
+                            let args = params.iter()
+                                .map(|p| format!("{} {}", m.type_name(&p.data_type), m.local_ident(&p.ident)))
+                                .collect::<Vec<_>>();
+                            let stat = if *modifier == FunctionModifier::Static { "static " } else { "" };
+                            if !after_comment {
+                                writeln!(w, "");
+                            }
+                            after_comment = false;
+                            writeln!(w, "        public {}{} {}({})", stat, m.type_name(ret), m.method_ident(fi), args.join(", "));
+                            writeln!(w, "        {{");
+                            self.write_lines(&mut w, "            ", &self.method_body(&m, id, modifier, fi, params, ret));
+                            writeln!(w, "        }}");
+                        },
+                        StatementKind::Comment(_, ref c) => {
+                            if !after_comment {
+                                writeln!(w, "");
+                            }
+                            after_comment = true;
+                            writeln!(w, "        //{}", c);
+                        },
+                        _ => {}
+                    }
+                }
+            }
+            writeln!(w, "    }}");
+            writeln!(w, "}}");
+            self.add_file(spec, &ty, w, output);
+        }
+    }
+
+    fn finish(&self, spec: &Spec, program: &Program, output: &mut GeneratedOutput) {
+        let m = CSharpMarshaler::new(spec);
+        let types = CGenerator::new().collect_all(m.c(), program);
+
+        let mut w = Vec::new();
+        self.write_native(&mut w, spec, program, &types);
+        self.add_file(spec, &"Native".to_string(), w, output);
+
+        let mut w = Vec::new();
+        self.write_marshaling(&mut w, spec, program, &types);
+        self.add_file(spec, &"Marshaling".to_string(), w, output);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::Config;
+    use generator::testing;
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.cpp.namespace = "demo".into();
+        config.c.out = Some("c".into());
+        config.csharp.out = Some("cs".into());
+
+        config
+    }
+
+    #[test]
+    fn test_write_native() {
+        let output = testing::compile(config(), testing::C_BINDINGS_INPUT);
+        let native = &output.get("cs/Native.cs").unwrap().contents;
+
+        // Structs and functions follow the C header, bools are passed as a single byte.
+        assert!(native.contains("namespace Lamp\n{\n"), "{}", native);
+        assert!(native.contains("internal const string Library = \"lamp\";"), "{}", native);
+        assert!(native.contains("[StructLayout(LayoutKind.Sequential)]\n        internal struct Key\n        {\n            \
+                                 public long Id;\n            public Native.String Name;\n            public int Col;\n            \
+                                 public Native.OptDate From;\n        }"), "{}", native);
+        assert!(native.contains("internal struct OptDate\n        {\n            public byte HasValue;\n            public long Value;\n        }"), "{}", native);
+        assert!(native.contains("internal static extern Native.ListString lamp_list_string_new(UIntPtr len);"), "{}", native);
+        assert!(native.contains("internal static extern void lamp_key_release(ref Native.Key v);"), "{}", native);
+        assert!(native.contains("internal static extern IntPtr lamp_key_store_create();"), "{}", native);
+        assert!(native.contains("internal static extern Native.MapStringKey lamp_key_store_lookup(KeyStoreHandle self, ref Native.ListString names, byte fuzzy);"), "{}", native);
+
+        let marshaling = &output.get("cs/Marshaling.cs").unwrap().contents;
+        assert!(marshaling.contains("internal static Key KeyFromNative(Native.Key v)\n        {\n            \
+                                     return new Key(v.Id, Marshaling.StringFromNative(v.Name), (Color)v.Col, Marshaling.OptDateFromNative(v.From));"), "{}", marshaling);
+        assert!(marshaling.contains("                r.Value = v.Value.ToUnixTimeMilliseconds();"), "{}", marshaling);
+        assert!(marshaling.contains("                Write(r.Keys, i, Marshaling.StringToNative(item.Key));\n                \
+                                     Write(r.Values, i++, Marshaling.KeyToNative(item.Value));"), "{}", marshaling);
+        assert!(marshaling.contains("return v == IntPtr.Zero ? null : new KeyStore(new KeyStoreHandle(Native.lamp_key_store_retain(v)));"), "{}", marshaling);
+    }
+
+    #[test]
+    fn test_write_types() {
+        let output = testing::compile(config(), testing::C_BINDINGS_INPUT);
+        let color = &output.get("cs/Color.cs").unwrap().contents;
+        assert!(color.contains("    public enum Color\n    {\n        Red = 0,\n        DarkBlue = 1,\n    }"), "{}", color);
+
+        let key = &output.get("cs/Key.cs").unwrap().contents;
+        assert!(key.contains("        public const long MaxId = 99;\n\n        public long Id { get; set; }\n"), "{}", key);
+        assert!(key.contains("        public Key(long id, string name, Color col, DateTimeOffset? from)\n"), "{}", key);
+
+        let store = &output.get("cs/KeyStore.cs").unwrap().contents;
+        assert!(store.contains("    internal sealed class KeyStoreHandle : SafeHandle\n"), "{}", store);
+        assert!(store.contains("            Native.lamp_key_store_release(handle);\n"), "{}", store);
+        assert!(store.contains("        public static KeyStore Create()\n"), "{}", store);
+
+        // Arguments are released after the call, the result once it has been copied.
+        assert!(store.contains("        public Dictionary<string, Key> Lookup(List<string> names, bool fuzzy)\n        {\n            \
+                                var namesNative = Marshaling.ListStringToNative(names);\n            try\n            {\n                \
+                                var result = Native.lamp_key_store_lookup(Handle, ref namesNative, (fuzzy ? (byte)1 : (byte)0));\n                \
+                                Native.ThrowIfError();\n                try\n                {\n                    \
+                                return Marshaling.MapStringKeyFromNative(result);\n                }\n                finally\n                {\n                    \
+                                Native.lamp_map_string_key_release(ref result);\n                }\n            }\n            finally\n            {\n                \
+                                Native.lamp_list_string_release(ref namesNative);\n            }\n        }\n"), "{}", store);
+    }
+}
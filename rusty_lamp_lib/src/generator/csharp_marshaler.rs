@@ -0,0 +1,206 @@
+/*
+ * Copyright © 2002-2017 Bluebeam Software, Inc. All Rights Reserved.
+ * Creator: Jonathan Rothberg
+ */
+
+use parser::ast::{DataTypeStatement, Identifier};
+use generator::spec::Spec;
+use generator::c_marshaler::CMarshaler;
+use generator::ident_style::IdentStyle;
+use generator::typer::UserObjectKind;
+
+const KEYWORDS: &'static [&'static str] = &[
+    "abstract", "as", "base", "bool", "break", "byte", "case", "catch", "char", "checked", "class", "const",
+    "continue", "decimal", "default", "delegate", "do", "double", "else", "enum", "event", "explicit", "extern",
+    "false", "finally", "fixed", "float", "for", "foreach", "goto", "if", "implicit", "in", "int", "interface",
+    "internal", "is", "lock", "long", "namespace", "new", "null", "object", "operator", "out", "override",
+    "params", "private", "protected", "public", "readonly", "ref", "return", "sbyte", "sealed", "short",
+    "sizeof", "stackalloc", "static", "string", "struct", "switch", "this", "throw", "true", "try", "typeof",
+    "uint", "ulong", "unchecked", "unsafe", "ushort", "using", "virtual", "void", "volatile", "while"
+];
+
+/// Maps types to C# and to the blittable structs mirroring the C API.  Every type that needs
+/// converting gets `XToNative` and `XFromNative` helpers in `Marshaling`, named after the C
+/// mangling of the type.  `XToNative` returns a value the C# side owns and releases with the
+/// native `_release` function, `XFromNative` only copies.
+pub struct CSharpMarshaler<'a> {
+    spec: &'a Spec,
+    c: CMarshaler<'a>
+}
+
+impl<'a> CSharpMarshaler<'a> {
+    pub fn new(spec: &'a Spec) -> CSharpMarshaler<'a> {
+        CSharpMarshaler {
+            spec: spec,
+            c: CMarshaler::new(spec)
+        }
+    }
+
+    pub fn escape(name: String) -> String {
+        if KEYWORDS.contains(&name.as_str()) {
+            format!("@{}", name)
+        }
+        else {
+            name
+        }
+    }
+
+    pub fn c(&self) -> &CMarshaler<'a> {
+        &self.c
+    }
+
+    pub fn type_ident(&self, i: &Identifier) -> String {
+        (self.spec.csharp_ident_style.ty)(i.value.clone())
+    }
+
+    pub fn method_ident(&self, i: &Identifier) -> String {
+        (self.spec.csharp_ident_style.method)(i.value.clone())
+    }
+
+    pub fn field_ident(&self, i: &Identifier) -> String {
+        (self.spec.csharp_ident_style.field)(i.value.clone())
+    }
+
+    pub fn local_ident(&self, i: &Identifier) -> String {
+        CSharpMarshaler::escape((self.spec.csharp_ident_style.local)(i.value.clone()))
+    }
+
+    pub fn enum_member(&self, i: &Identifier) -> String {
+        (self.spec.csharp_ident_style.enm)(i.value.clone())
+    }
+
+    pub fn const_ident(&self, i: &Identifier) -> String {
+        (self.spec.csharp_ident_style.cnst)(i.value.clone())
+    }
+
+    /// The local holding the native value of a parameter.
+    pub fn native_local_ident(&self, i: &Identifier) -> String {
+        format!("{}Native", (self.spec.csharp_ident_style.local)(i.value.clone()))
+    }
+
+    /// The `SafeHandle` owning one reference to an interface.
+    pub fn handle_ident(&self, i: &Identifier) -> String {
+        format!("{}Handle", self.type_ident(i))
+    }
+
+    /// The suffix of the marshaling helpers for `dts`, e.g. `MapStringListF64`.
+    pub fn helper_ident(&self, dts: &DataTypeStatement) -> String {
+        IdentStyle::camel_upper(self.c.mangle(dts))
+    }
+
+    pub fn type_name(&self, dts: &DataTypeStatement) -> String {
+        match *dts {
+            DataTypeStatement::None => "void".into(),
+            DataTypeStatement::Integer8 => "sbyte".into(),
+            DataTypeStatement::Integer16 => "short".into(),
+            DataTypeStatement::Integer32 => "int".into(),
+            DataTypeStatement::Integer64 => "long".into(),
+            DataTypeStatement::Float32 => "float".into(),
+            DataTypeStatement::Float64 => "double".into(),
+            DataTypeStatement::Bool => "bool".into(),
+            DataTypeStatement::String => "string".into(),
+            DataTypeStatement::Binary => "byte[]".into(),
+            DataTypeStatement::Date => "DateTimeOffset".into(),
+            DataTypeStatement::List(ref t) => format!("List<{}>", self.type_name(t)),
+            DataTypeStatement::Array(ref t) => format!("{}[]", self.type_name(t)),
+            DataTypeStatement::Set(ref t) => format!("HashSet<{}>", self.type_name(t)),
+            DataTypeStatement::Map(ref k, ref v) => format!("Dictionary<{}, {}>", self.type_name(k), self.type_name(v)),
+            DataTypeStatement::Optional(ref t) if self.is_value_type(t) => format!("{}?", self.type_name(t)),
+            DataTypeStatement::Optional(ref t) => self.type_name(t),
+            DataTypeStatement::Future(ref t) => format!("Task<{}>", self.type_name(t)),
+            DataTypeStatement::Function(ref p, ref r) => {
+                let mut params: Vec<String> = p.iter().map(|t| self.type_name(t)).collect();
+                match **r {
+                    DataTypeStatement::None if params.is_empty() => "Action".into(),
+                    DataTypeStatement::None => format!("Action<{}>", params.join(", ")),
+                    _ => {
+                        params.push(self.type_name(r));
+                        format!("Func<{}>", params.join(", "))
+                    }
+                }
+            },
+            DataTypeStatement::Object(ref i) => self.type_ident(i)
+        }
+    }
+
+    /// Whether the C# type of `dts` is a struct, which makes its optional a `Nullable<T>`.
+    pub fn is_value_type(&self, dts: &DataTypeStatement) -> bool {
+        match *dts {
+            DataTypeStatement::String | DataTypeStatement::Binary | DataTypeStatement::List(..) | DataTypeStatement::Array(..) |
+            DataTypeStatement::Set(..) | DataTypeStatement::Map(..) | DataTypeStatement::Optional(..) => false,
+            DataTypeStatement::Object(ref i) => self.c.user_object_kind(i) == Some(UserObjectKind::Enum),
+            _ => true
+        }
+    }
+
+    /// The blittable type of `dts` in the C API.  C `bool`s are a single byte.
+    pub fn native_type_name(&self, dts: &DataTypeStatement) -> String {
+        let dts = self.c.strip_optional(dts);
+        match *dts {
+            DataTypeStatement::Bool => "byte".into(),
+            DataTypeStatement::Date => "long".into(),
+            DataTypeStatement::Object(ref i) if self.c.is_interface(i) => "IntPtr".into(),
+            DataTypeStatement::Object(ref i) if self.c.user_object_kind(i) == Some(UserObjectKind::Enum) => "int".into(),
+            DataTypeStatement::Object(..) | DataTypeStatement::String | DataTypeStatement::Binary | DataTypeStatement::List(..) |
+            DataTypeStatement::Array(..) | DataTypeStatement::Set(..) | DataTypeStatement::Map(..) | DataTypeStatement::Optional(..) => {
+                format!("Native.{}", self.helper_ident(dts))
+            },
+            _ => self.type_name(dts)
+        }
+    }
+
+    /// The parameter of a `DllImport`: borrowed structs are passed by reference like the C pointers.
+    pub fn native_param_type(&self, dts: &DataTypeStatement) -> String {
+        if self.c.by_value(dts) {
+            self.native_type_name(dts)
+        }
+        else {
+            format!("ref {}", self.native_type_name(dts))
+        }
+    }
+
+    /// Converts the C# value `expr` to its native type.
+    pub fn to_native(&self, dts: &DataTypeStatement, expr: &str) -> String {
+        let dts = self.c.strip_optional(dts);
+        match *dts {
+            DataTypeStatement::Bool => format!("({} ? (byte)1 : (byte)0)", expr),
+            DataTypeStatement::Date => format!("{}.ToUnixTimeMilliseconds()", expr),
+            DataTypeStatement::Object(ref i) if self.c.user_object_kind(i) == Some(UserObjectKind::Enum) => format!("(int){}", expr),
+            DataTypeStatement::Object(..) | DataTypeStatement::String | DataTypeStatement::Binary | DataTypeStatement::List(..) |
+            DataTypeStatement::Array(..) | DataTypeStatement::Set(..) | DataTypeStatement::Map(..) | DataTypeStatement::Optional(..) => {
+                format!("Marshaling.{}ToNative({})", self.helper_ident(dts), expr)
+            },
+            _ => expr.into()
+        }
+    }
+
+    /// Converts the native value `expr` to C#, copying anything it points to and retaining
+    /// interfaces.
+    pub fn from_native(&self, dts: &DataTypeStatement, expr: &str) -> String {
+        let dts = self.c.strip_optional(dts);
+        match *dts {
+            DataTypeStatement::Bool => format!("{} != 0", expr),
+            DataTypeStatement::Date => format!("DateTimeOffset.FromUnixTimeMilliseconds({})", expr),
+            DataTypeStatement::Object(ref i) if self.c.user_object_kind(i) == Some(UserObjectKind::Enum) => {
+                format!("({}){}", self.type_ident(i), expr)
+            },
+            DataTypeStatement::Object(..) | DataTypeStatement::String | DataTypeStatement::Binary | DataTypeStatement::List(..) |
+            DataTypeStatement::Array(..) | DataTypeStatement::Set(..) | DataTypeStatement::Map(..) | DataTypeStatement::Optional(..) => {
+                format!("Marshaling.{}FromNative({})", self.helper_ident(dts), expr)
+            },
+            _ => expr.into()
+        }
+    }
+
+    /// Releases the native value `expr`, whether returned by the C API or made by `to_native`.
+    pub fn release(&self, dts: &DataTypeStatement, expr: &str) -> Option<String> {
+        let dts = self.c.strip_optional(dts);
+        match *dts {
+            DataTypeStatement::Object(ref i) if self.c.is_interface(i) => {
+                Some(format!("Native.{}({});", self.c.function_name(i, "release"), expr))
+            },
+            _ if self.c.is_owned(dts) => Some(format!("Native.{}_release(ref {});", self.c.type_name(dts), expr)),
+            _ => None
+        }
+    }
+}
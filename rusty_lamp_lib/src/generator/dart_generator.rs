@@ -0,0 +1,638 @@
+/*
+ * Copyright © 2002-2017 Bluebeam Software, Inc. All Rights Reserved.
+ * Creator: Jonathan Rothberg
+ */
+
+use std::io::{Write};
+use generator::generator::{Generate};
+use generator::spec::Spec;
+use generator::output::GeneratedOutput;
+use generator::c_generator::CGenerator;
+use generator::dart_marshaler::DartMarshaler;
+use parser::program::Program;
+use parser::ast::{Statement, StatementKind, DataTypeStatement, FunctionModifier, Identifier, InterfaceType, BlockStatement, Parameter};
+
+/// `dart:ffi` bindings over the API written by the C generator, as a single library: enums,
+/// record classes and interface classes whose `NativeFinalizer` releases the native handle,
+/// followed by the private structs, function lookups and conversions they are built on.
+pub struct DartGenerator {
+
+}
+
+impl DartGenerator {
+    pub fn new() -> DartGenerator {
+        DartGenerator {
+
+        }
+    }
+
+    fn library(&self, spec: &Spec) -> String {
+        spec.config.dart.library.clone().unwrap_or(spec.config.c.file.clone())
+    }
+
+    fn write_header(&self, w: &mut Write, spec: &Spec) {
+        let library = self.library(spec);
+        writeln!(w, "// AUTOGENERATED FILE - DO NOT MODIFY!");
+        writeln!(w, "// This file was generated by rusty_lamp");
+        writeln!(w, "");
+        writeln!(w, "// ignore_for_file: non_constant_identifier_names");
+        writeln!(w, "");
+        writeln!(w, "import 'dart:convert';");
+        writeln!(w, "import 'dart:ffi';");
+        writeln!(w, "import 'dart:io';");
+        writeln!(w, "import 'dart:typed_data';");
+        writeln!(w, "");
+        writeln!(w, "import 'package:ffi/ffi.dart';");
+        writeln!(w, "");
+        writeln!(w, "final DynamicLibrary _lib = _open();");
+        writeln!(w, "");
+        writeln!(w, "DynamicLibrary _open() {{");
+        writeln!(w, "  if (Platform.isIOS) {{");
+        writeln!(w, "    return DynamicLibrary.process();");
+        writeln!(w, "  }}");
+        writeln!(w, "  if (Platform.isMacOS) {{");
+        writeln!(w, "    return DynamicLibrary.open('lib{}.dylib');", library);
+        writeln!(w, "  }}");
+        writeln!(w, "  if (Platform.isWindows) {{");
+        writeln!(w, "    return DynamicLibrary.open('{}.dll');", library);
+        writeln!(w, "  }}");
+        writeln!(w, "  return DynamicLibrary.open('lib{}.so');", library);
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "/// Thrown for a C++ exception that reached the C API.");
+        writeln!(w, "class NativeException implements Exception {{");
+        writeln!(w, "  NativeException(this.message);");
+        writeln!(w, "");
+        writeln!(w, "  final String message;");
+        writeln!(w, "");
+        writeln!(w, "  @override");
+        writeln!(w, "  String toString() => 'NativeException: $message';");
+        writeln!(w, "}}");
+    }
+
+    fn write_lines(&self, w: &mut Write, indent: &str, lines: &Vec<String>) {
+        for l in lines {
+            writeln!(w, "{}{}", indent, l);
+        }
+    }
+
+    fn find_record<'p>(&self, program: &'p Program, i: &Identifier) -> Option<&'p BlockStatement> {
+        program.statements.iter()
+            .filter_map(|s| match s.stmtKind {
+                StatementKind::Record(_, ref ri, ref bs, _) if ri.value == i.value => Some(bs),
+                _ => None
+            })
+            .next()
+    }
+
+    fn fields<'b>(&self, bs: &'b BlockStatement) -> Vec<(&'b Identifier, &'b DataTypeStatement)> {
+        bs.statements.iter()
+            .filter_map(|s| match s.stmtKind { StatementKind::RecordMember(_, ref i, ref dts, _) => Some((i, dts)), _ => None })
+            .collect()
+    }
+
+    fn interfaces<'p>(&self, program: &'p Program) -> Vec<(&'p Identifier, &'p Vec<InterfaceType>, &'p BlockStatement)> {
+        program.statements.iter()
+            .filter_map(|s| match s.stmtKind {
+                StatementKind::Interface(_, ref i, ref it, ref bs, _) => Some((i, it, bs)),
+                _ => None
+            })
+            .collect()
+    }
+
+    fn consts(&self, m: &DartMarshaler, statements: &Vec<Statement>) -> Vec<String> {
+        let mut result = Vec::new();
+        for s in statements {
+            if let StatementKind::Const(_, ref i, ref dts, ref v) = s.stmtKind {
+                let value = match v.stmtKind {
+                    StatementKind::StringLiteral(_, ref s) => format!("{:?}", s).replace("$", "\\$"),
+                    StatementKind::Boolean(_, b) => format!("{}", b),
+                    StatementKind::NumberLiteral(_, ref n) => n.clone(),
+                    _ => {
+                        result.push(format!("// const {}: {} is not representable as a Dart constant", i.value, dts));
+                        continue;
+                    }
+                };
+                result.push(format!("static const {} {} = {};", m.type_name(dts), m.const_ident(i), value));
+            }
+        }
+        result
+    }
+
+    fn write_enum_class(&self, w: &mut Write, m: &DartMarshaler, i: &Identifier, bs: &BlockStatement) {
+        writeln!(w, "");
+        writeln!(w, "enum {} {{", m.type_ident(i));
+        for o in &bs.statements {
+            match o.stmtKind {
+                StatementKind::EnumMember(_, ref oi) => {
+                    writeln!(w, "  {},", m.enum_member(oi));
+                },
+                StatementKind::Comment(_, ref c) => {
+                    writeln!(w, "  //{}", c);
+                },
+                _ => {}
+            }
+        }
+        writeln!(w, "}}");
+    }
+
+    fn write_record_class(&self, w: &mut Write, m: &DartMarshaler, i: &Identifier, bs: &BlockStatement) {
+        let params = self.fields(bs).iter()
+            .map(|&(fi, dts)| match *dts {
+                DataTypeStatement::Optional(..) => format!("this.{}", m.field_ident(fi)),
+                _ => format!("required this.{}", m.field_ident(fi))
+            })
+            .collect::<Vec<_>>();
+
+        writeln!(w, "");
+        writeln!(w, "class {} {{", m.type_ident(i));
+        writeln!(w, "  {}({{{}}});", m.type_ident(i), params.join(", "));
+        let consts = self.consts(m, &bs.statements);
+        if consts.len() > 0 {
+            writeln!(w, "");
+            self.write_lines(w, "  ", &consts);
+        }
+        writeln!(w, "");
+        for s in &bs.statements {
+            match s.stmtKind {
+                StatementKind::RecordMember(_, ref fi, ref dts, _) => {
+                    writeln!(w, "  {} {};", m.type_name(dts), m.field_ident(fi));
+                },
+                StatementKind::Comment(_, ref c) => {
+                    writeln!(w, "  //{}", c);
+                },
+                _ => {}
+            }
+        }
+        writeln!(w, "}}");
+    }
+
+    /// `body` run in a `try` block, with `finally` run however it exits.
+    fn try_finally(&self, body: Vec<String>, finally: Vec<String>) -> Vec<String> {
+        let mut lines = vec!["try {".to_string()];
+        lines.extend(body.into_iter().map(|l| format!("  {}", l)));
+        lines.push("} finally {".to_string());
+        lines.extend(finally.into_iter().map(|l| format!("  {}", l)));
+        lines.push("}".to_string());
+        lines
+    }
+
+    /// Arguments that own native memory are converted up front and released once the call
+    /// returned, the result is copied before it is released.  Structs passed or returned
+    /// through a pointer live in an `Arena`.
+    fn method_body(&self, m: &DartMarshaler, i: &Identifier, modifier: &FunctionModifier, fi: &Identifier,
+                   params: &Vec<Parameter>, ret: &DataTypeStatement) -> Vec<String> {
+        let c = m.c();
+        let mut arena = false;
+        let mut locals = Vec::new();
+        let mut releases = Vec::new();
+        let mut args = Vec::new();
+        if *modifier != FunctionModifier::Static {
+            args.push("_handle".to_string());
+        }
+        for p in params {
+            let local = m.local_ident(&p.ident);
+            let native = m.native_local_ident(&p.ident);
+            match m.release(&p.data_type, &native) {
+                Some(r) => {
+                    if c.by_value(&p.data_type) {
+                        locals.push(format!("final {} = {};", native, m.to_native(&p.data_type, &local)));
+                    }
+                    else {
+                        arena = true;
+                        locals.push(format!("final {} = arena<{}>()..ref = {};", native, m.ffi_type_name(&p.data_type), m.to_native(&p.data_type, &local)));
+                    }
+                    releases.push(r);
+                    args.push(native);
+                },
+                None => args.push(m.to_native(&p.data_type, &local))
+            }
+        }
+
+        let call = format!("{}({})", m.function_ident(&c.function_name(i, &c.method_ident(fi))), args.join(", "));
+        let mut body = Vec::new();
+        match *ret {
+            DataTypeStatement::None => {
+                body.push(format!("{};", call));
+                body.push("_throwIfError();".to_string());
+            },
+            _ => {
+                let value = if c.by_value(ret) {
+                    body.push(format!("final result = {};", call));
+                    m.from_native(ret, "result")
+                }
+                else {
+                    arena = true;
+                    body.push(format!("final result = arena<{}>()..ref = {};", m.ffi_type_name(ret), call));
+                    m.from_native(ret, "result.ref")
+                };
+                body.push("_throwIfError();".to_string());
+                let value = format!("return {};", value);
+                match m.release(ret, "result") {
+                    Some(r) => body.extend(self.try_finally(vec![value], vec![r])),
+                    None => body.push(value)
+                }
+            }
+        }
+
+        if releases.len() > 0 {
+            body = self.try_finally(body, releases);
+        }
+        locals.extend(body);
+
+        if arena {
+            let using = match *ret {
+                DataTypeStatement::None => "using((arena) {",
+                _ => "return using((arena) {"
+            };
+            let mut lines = vec![using.to_string()];
+            lines.extend(locals.into_iter().map(|l| format!("  {}", l)));
+            lines.push("});".to_string());
+            lines
+        }
+        else {
+            locals
+        }
+    }
+
+    fn write_interface_class(&self, w: &mut Write, m: &DartMarshaler, i: &Identifier, it: &Vec<InterfaceType>, bs: &BlockStatement) {
+        let ty = m.type_ident(i);
+        let finalizer = format!("{}Finalizer", m.helper_ident(&DataTypeStatement::Object(i.clone())));
+
+        writeln!(w, "");
+        writeln!(w, "final {} = NativeFinalizer(_lib.lookup<NativeFinalizerFunction>('{}'));", finalizer, m.c().function_name(i, "release"));
+        writeln!(w, "");
+        writeln!(w, "class {} implements Finalizable {{", ty);
+        writeln!(w, "  {}._(this._handle) {{", ty);
+        writeln!(w, "    {}.attach(this, _handle);", finalizer);
+        writeln!(w, "  }}");
+        let consts = self.consts(m, &bs.statements);
+        if consts.len() > 0 {
+            writeln!(w, "");
+            self.write_lines(w, "  ", &consts);
+        }
+        writeln!(w, "");
+        writeln!(w, "  final Pointer<Void> _handle;");
+        writeln!(w, "");
+        writeln!(w, "  /// A new native reference, owned by the caller.");
+        writeln!(w, "  Pointer<Void> _retain() => {}(_handle);", m.function_ident(&m.c().function_name(i, "retain")));
+
+        // Only interfaces implemented in C++ have functions in the C API.
+        if it.contains(&InterfaceType::Cpp) {
+            let mut after_comment = false;
+            for s in &bs.statements {
+                match s.stmtKind {
+                    StatementKind::Function(_, ref modifier, ref fi, ref params, ref ret, _) => {
+                        let args = params.iter()
+                            .map(|p| format!("{} {}", m.type_name(&p.data_type), m.local_ident(&p.ident)))
+                            .collect::<Vec<_>>();
+                        let stat = if *modifier == FunctionModifier::Static { "static " } else { "" };
+                        if !after_comment {
+                            writeln!(w, "");
+                        }
+                        after_comment = false;
+                        writeln!(w, "  {}{} {}({}) {{", stat, m.type_name(ret), m.method_ident(fi), args.join(", "));
+                        self.write_lines(w, "    ", &self.method_body(m, i, modifier, fi, params, ret));
+                        writeln!(w, "  }}");
+                    },
+                    StatementKind::Comment(_, ref c) => {
+                        if !after_comment {
+                            writeln!(w, "");
+                        }
+                        after_comment = true;
+                        writeln!(w, "  //{}", c);
+                    },
+                    _ => {}
+                }
+            }
+        }
+        writeln!(w, "}}");
+    }
+
+    fn write_struct(&self, w: &mut Write, m: &DartMarshaler, program: &Program, dts: &DataTypeStatement) {
+        let mut fields = Vec::new();
+        match *dts {
+            DataTypeStatement::List(ref t) | DataTypeStatement::Array(ref t) | DataTypeStatement::Set(ref t) => {
+                fields.push(format!("external Pointer<{}> data;", m.ffi_type_name(t)));
+                fields.push("@Size()\n  external int len;".to_string());
+            },
+            DataTypeStatement::Map(ref k, ref v) => {
+                fields.push(format!("external Pointer<{}> keys;", m.ffi_type_name(k)));
+                fields.push(format!("external Pointer<{}> values;", m.ffi_type_name(v)));
+                fields.push("@Size()\n  external int len;".to_string());
+            },
+            DataTypeStatement::Optional(ref t) => {
+                fields.push(m.ffi_field(&DataTypeStatement::Bool, "hasValue"));
+                fields.push(m.ffi_field(t, "value"));
+            },
+            DataTypeStatement::Object(ref i) => {
+                for (fi, fdts) in self.fields(self.find_record(program, i).unwrap()) {
+                    fields.push(m.ffi_field(fdts, &m.field_ident(fi)));
+                }
+            },
+            _ => {}
+        }
+
+        writeln!(w, "");
+        writeln!(w, "final class {} extends Struct {{", m.struct_ident(dts));
+        writeln!(w, "  {}", fields.join("\n\n  "));
+        writeln!(w, "}}");
+    }
+
+    fn lookup(&self, w: &mut Write, m: &DartMarshaler, name: &str, ret: (&str, &str), params: Vec<(String, String)>) {
+        let native = params.iter().map(|p| p.0.clone()).collect::<Vec<_>>().join(", ");
+        let dart = params.iter().map(|p| p.1.clone()).collect::<Vec<_>>().join(", ");
+        writeln!(w, "final {} = _lib.lookupFunction<{} Function({}), {} Function({})>('{}');",
+                 m.function_ident(name), ret.0, native, ret.1, dart, name);
+    }
+
+    fn write_functions(&self, w: &mut Write, m: &DartMarshaler, program: &Program, types: &Vec<DataTypeStatement>) {
+        let c = m.c();
+        let prefix = c.prefix().to_string();
+
+        writeln!(w, "");
+        self.lookup(w, m, &format!("{}last_error", prefix), ("Pointer<Utf8>", "Pointer<Utf8>"), vec![]);
+        for &(name, ty) in &[("string", "_String"), ("binary", "_Binary")] {
+            self.lookup(w, m, &format!("{}{}_new", prefix, name), (ty, ty),
+                        vec![("Pointer<Uint8>".into(), "Pointer<Uint8>".into()), ("Size".into(), "int".into())]);
+            self.lookup(w, m, &format!("{}{}_release", prefix, name), ("Void", "void"),
+                        vec![(format!("Pointer<{}>", ty), format!("Pointer<{}>", ty))]);
+        }
+        for (i, _, _) in self.interfaces(program) {
+            let handle = ("Pointer<Void>".to_string(), "Pointer<Void>".to_string());
+            self.lookup(w, m, &c.function_name(i, "retain"), ("Pointer<Void>", "Pointer<Void>"), vec![handle.clone()]);
+            self.lookup(w, m, &c.function_name(i, "release"), ("Void", "void"), vec![handle]);
+        }
+        for dts in types {
+            let name = c.type_name(dts);
+            let ty = m.struct_ident(dts);
+            match *dts {
+                DataTypeStatement::List(..) | DataTypeStatement::Array(..) | DataTypeStatement::Set(..) | DataTypeStatement::Map(..) => {
+                    self.lookup(w, m, &format!("{}_new", name), (&ty, &ty), vec![("Size".into(), "int".into())]);
+                },
+                _ => {}
+            }
+            let p = format!("Pointer<{}>", ty);
+            self.lookup(w, m, &format!("{}_release", name), ("Void", "void"), vec![(p.clone(), p)]);
+        }
+        for (i, it, bs) in self.interfaces(program) {
+            if !it.contains(&InterfaceType::Cpp) {
+                continue;
+            }
+            for f in &bs.statements {
+                if let StatementKind::Function(_, ref modifier, ref fi, ref params, ref ret, _) = f.stmtKind {
+                    let mut args = Vec::new();
+                    if *modifier != FunctionModifier::Static {
+                        args.push(("Pointer<Void>".to_string(), "Pointer<Void>".to_string()));
+                    }
+                    for p in params {
+                        args.push((m.ffi_param_type(&p.data_type, false), m.ffi_param_type(&p.data_type, true)));
+                    }
+                    self.lookup(w, m, &c.function_name(i, &c.method_ident(fi)), (&m.ffi_type_name(ret), &m.ffi_dart_type_name(ret)), args);
+                }
+            }
+        }
+
+        writeln!(w, "");
+        writeln!(w, "void _throwIfError() {{");
+        writeln!(w, "  final error = _{}last_error();", prefix);
+        writeln!(w, "  if (error != nullptr) {{");
+        writeln!(w, "    throw NativeException(error.toDartString());");
+        writeln!(w, "  }}");
+        writeln!(w, "}}");
+    }
+
+    fn write_conversions(&self, w: &mut Write, m: &DartMarshaler, program: &Program, dts: &DataTypeStatement) {
+        let h = m.helper_ident(dts);
+        let ty = m.type_name(dts);
+        let native = m.struct_ident(dts);
+        let new = m.function_ident(&format!("{}_new", m.c().type_name(dts)));
+
+        let (to, from) = match *dts {
+            DataTypeStatement::List(ref t) | DataTypeStatement::Set(ref t) => {
+                let (open, close) = if let DataTypeStatement::Set(..) = *dts { ("{", "}") } else { ("[", "]") };
+                (vec![
+                    format!("final r = {}(v.length);", new),
+                    "var i = 0;".to_string(),
+                    "for (final item in v) {".to_string(),
+                    format!("  r.data[i++] = {};", m.to_native(t, "item")),
+                    "}".to_string(),
+                    "return r;".to_string()
+                ], vec![
+                    format!("return {}for (var i = 0; i < v.len; i++) {}{};", open, m.from_native(t, "v.data[i]"), close)
+                ])
+            },
+            DataTypeStatement::Array(ref t) => {
+                (vec![
+                    format!("final r = {}(v.length);", new),
+                    "r.data.asTypedList(v.length).setAll(0, v);".to_string(),
+                    "return r;".to_string()
+                ], vec![
+                    format!("return {}List.fromList(v.data.asTypedList(v.len));", m.typed_data_element(t))
+                ])
+            },
+            DataTypeStatement::Map(ref k, ref mv) => {
+                (vec![
+                    format!("final r = {}(v.length);", new),
+                    "var i = 0;".to_string(),
+                    "for (final item in v.entries) {".to_string(),
+                    format!("  r.keys[i] = {};", m.to_native(k, "item.key")),
+                    format!("  r.values[i++] = {};", m.to_native(mv, "item.value")),
+                    "}".to_string(),
+                    "return r;".to_string()
+                ], vec![
+                    format!("return {{for (var i = 0; i < v.len; i++) {}: {}}};", m.from_native(k, "v.keys[i]"), m.from_native(mv, "v.values[i]"))
+                ])
+            },
+            DataTypeStatement::Optional(ref t) => {
+                (vec![
+                    format!("final r = Struct.create<{}>();", native),
+                    "if (v != null) {".to_string(),
+                    "  r.hasValue = true;".to_string(),
+                    format!("  r.value = {};", m.to_native(t, "v")),
+                    "}".to_string(),
+                    "return r;".to_string()
+                ], vec![
+                    format!("return v.hasValue ? {} : null;", m.from_native(t, "v.value"))
+                ])
+            },
+            DataTypeStatement::Object(ref i) => {
+                let fields = self.fields(self.find_record(program, i).unwrap());
+                let mut to = vec![format!("final r = Struct.create<{}>();", native)];
+                for &(fi, fdts) in &fields {
+                    let name = m.field_ident(fi);
+                    to.push(format!("r.{} = {};", name, m.to_native(fdts, &format!("v.{}", name))));
+                }
+                to.push("return r;".to_string());
+                let args = fields.iter()
+                    .map(|&(fi, fdts)| {
+                        let name = m.field_ident(fi);
+                        format!("{}: {}", name, m.from_native(fdts, &format!("v.{}", name)))
+                    })
+                    .collect::<Vec<_>>();
+                (to, vec![format!("return {}({});", ty, args.join(", "))])
+            },
+            _ => return
+        };
+
+        writeln!(w, "");
+        writeln!(w, "{} {}ToNative({} v) {{", native, h, ty);
+        self.write_lines(w, "  ", &to);
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "{} {}FromNative({} v) {{", ty, h, native);
+        self.write_lines(w, "  ", &from);
+        writeln!(w, "}}");
+    }
+
+    fn write_library(&self, w: &mut Write, spec: &Spec, program: &Program) {
+        let m = DartMarshaler::new(spec);
+        let c = m.c();
+        let prefix = c.prefix().to_string();
+        let types = CGenerator::new().collect_all(c, program);
+
+        self.write_header(w, spec);
+        for stmt in &program.statements {
+            match stmt.stmtKind {
+                StatementKind::Enum(_, ref i, ref bs) => self.write_enum_class(w, &m, i, bs),
+                StatementKind::Record(_, ref i, ref bs, _) => self.write_record_class(w, &m, i, bs),
+                StatementKind::Interface(_, ref i, ref it, ref bs, _) => self.write_interface_class(w, &m, i, it, bs),
+                _ => {}
+            }
+        }
+
+        writeln!(w, "");
+        writeln!(w, "// The structs and functions of the C API.");
+        for name in &["String", "Binary"] {
+            writeln!(w, "");
+            writeln!(w, "final class _{} extends Struct {{", name);
+            writeln!(w, "  external Pointer<Uint8> data;");
+            writeln!(w, "");
+            writeln!(w, "  @Size()");
+            writeln!(w, "  external int len;");
+            writeln!(w, "}}");
+        }
+        for dts in &types {
+            self.write_struct(w, &m, program, dts);
+        }
+        self.write_functions(w, &m, program, &types);
+
+        writeln!(w, "");
+        writeln!(w, "// Conversions between the Dart types and the structs of the C API.  Values made by `ToNative`");
+        writeln!(w, "// are released with the native `_release` functions, `FromNative` only copies.");
+        writeln!(w, "");
+        writeln!(w, "_String _stringToNative(String v) {{");
+        writeln!(w, "  final bytes = utf8.encode(v);");
+        writeln!(w, "  return using((arena) {{");
+        writeln!(w, "    final data = arena<Uint8>(bytes.length + 1);");
+        writeln!(w, "    data.asTypedList(bytes.length).setAll(0, bytes);");
+        writeln!(w, "    return _{}string_new(data, bytes.length);", prefix);
+        writeln!(w, "  }});");
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "String _stringFromNative(_String v) {{");
+        writeln!(w, "  return utf8.decode(v.data.asTypedList(v.len));");
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "_Binary _binaryToNative(Uint8List v) {{");
+        writeln!(w, "  return using((arena) {{");
+        writeln!(w, "    final data = arena<Uint8>(v.length + 1);");
+        writeln!(w, "    data.asTypedList(v.length).setAll(0, v);");
+        writeln!(w, "    return _{}binary_new(data, v.length);", prefix);
+        writeln!(w, "  }});");
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "Uint8List _binaryFromNative(_Binary v) {{");
+        writeln!(w, "  return Uint8List.fromList(v.data.asTypedList(v.len));");
+        writeln!(w, "}}");
+
+        for (i, _, _) in self.interfaces(program) {
+            let ty = m.type_ident(i);
+            let h = m.helper_ident(&DataTypeStatement::Object(i.clone()));
+            writeln!(w, "");
+            writeln!(w, "Pointer<Void> {}ToNative({}? v) {{", h, ty);
+            writeln!(w, "  return v == null ? nullptr : v._retain();");
+            writeln!(w, "}}");
+            writeln!(w, "");
+            writeln!(w, "{}? {}FromNative(Pointer<Void> v) {{", ty, h);
+            writeln!(w, "  return v == nullptr ? null : {}._({}(v));", ty, m.function_ident(&c.function_name(i, "retain")));
+            writeln!(w, "}}");
+        }
+
+        for dts in &types {
+            self.write_conversions(w, &m, program, dts);
+        }
+    }
+}
+
+impl Generate for DartGenerator {
+    // Everything is written by `finish`, the whole API is a single Dart library.
+    fn write_enum(&self, _e: &StatementKind, _spec: &Spec, _output: &mut GeneratedOutput) {
+    }
+
+    fn write_record(&self, _r: &StatementKind, _spec: &Spec, _output: &mut GeneratedOutput) {
+    }
+
+    fn write_interface(&self, _i: &StatementKind, _spec: &Spec, _output: &mut GeneratedOutput) {
+    }
+
+    fn finish(&self, spec: &Spec, program: &Program, output: &mut GeneratedOutput) {
+        let folder = spec.config.dart.out.as_ref().map_or("", |s| s.as_str());
+
+        let mut w = Vec::new();
+        self.write_library(&mut w, spec, program);
+        output.add_file(self.make_path(folder, &self.library(spec), "dart"), w);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::Config;
+    use generator::testing;
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.cpp.namespace = "demo".into();
+        config.c.out = Some("c".into());
+        config.dart.out = Some("dart".into());
+        config.dart.library = Some("core".into());
+
+        config
+    }
+
+    #[test]
+    fn test_write_library() {
+        let output = testing::compile(config(), testing::C_BINDINGS_INPUT);
+        let library = &output.get("dart/core.dart").unwrap().contents;
+
+        assert!(library.contains("    return DynamicLibrary.open('libcore.dylib');"), "{}", library);
+        assert!(library.contains("enum Color {\n  red,\n  darkBlue,\n}"), "{}", library);
+        assert!(library.contains("class Key {\n  Key({required this.id, required this.name, required this.col, this.from});\n\n  \
+                                  static const int maxId = 99;\n\n  int id;\n  String name;\n  Color col;\n  DateTime? from;\n}"), "{}", library);
+        assert!(library.contains("final _keyStoreFinalizer = NativeFinalizer(_lib.lookup<NativeFinalizerFunction>('lamp_key_store_release'));"), "{}", library);
+        assert!(library.contains("class KeyStore implements Finalizable {\n  KeyStore._(this._handle) {\n    _keyStoreFinalizer.attach(this, _handle);\n  }"), "{}", library);
+
+        // Arguments are released after the call, the result once it has been copied.
+        assert!(library.contains("  Map<String, Key> lookup(List<String> names, bool fuzzy) {\n    return using((arena) {\n      \
+                                  final namesNative = arena<_ListString>()..ref = _listStringToNative(names);\n      try {\n        \
+                                  final result = arena<_MapStringKey>()..ref = _lamp_key_store_lookup(_handle, namesNative, fuzzy);\n        \
+                                  _throwIfError();\n        try {\n          return _mapStringKeyFromNative(result.ref);\n        } finally {\n          \
+                                  _lamp_map_string_key_release(result);\n        }\n      } finally {\n        \
+                                  _lamp_list_string_release(namesNative);\n      }\n    });\n  }"), "{}", library);
+        assert!(library.contains("      return _keyStoreFromNative(result)!;"), "{}", library);
+    }
+
+    #[test]
+    fn test_write_structs() {
+        let output = testing::compile(config(), testing::C_BINDINGS_INPUT);
+        let library = &output.get("dart/core.dart").unwrap().contents;
+
+        assert!(library.contains("final class _Key extends Struct {\n  @Int64()\n  external int id;\n\n  external _String name;\n\n  \
+                                  @Int32()\n  external int col;\n\n  external _OptDate from;\n}"), "{}", library);
+        assert!(library.contains("final class _ListString extends Struct {\n  external Pointer<_String> data;\n\n  @Size()\n  external int len;\n}"), "{}", library);
+        assert!(library.contains("final _lamp_key_store_lookup = _lib.lookupFunction<_MapStringKey Function(Pointer<Void>, Pointer<_ListString>, Bool), \
+                                  _MapStringKey Function(Pointer<Void>, Pointer<_ListString>, bool)>('lamp_key_store_lookup');"), "{}", library);
+        assert!(library.contains("  return Key(id: v.id, name: _stringFromNative(v.name), col: Color.values[v.col], from: _optDateFromNative(v.from));"), "{}", library);
+        assert!(library.contains("    r.value = v.millisecondsSinceEpoch;"), "{}", library);
+    }
+}
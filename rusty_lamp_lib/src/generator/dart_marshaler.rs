@@ -0,0 +1,226 @@
+/*
+ * Copyright © 2002-2017 Bluebeam Software, Inc. All Rights Reserved.
+ * Creator: Jonathan Rothberg
+ */
+
+use parser::ast::{DataTypeStatement, Identifier};
+use generator::spec::Spec;
+use generator::c_marshaler::CMarshaler;
+use generator::ident_style::IdentStyle;
+use generator::typer::UserObjectKind;
+
+const KEYWORDS: &'static [&'static str] = &[
+    "assert", "break", "case", "catch", "class", "const", "continue", "default", "do", "else", "enum",
+    "extends", "false", "final", "finally", "for", "if", "in", "is", "new", "null", "rethrow", "return",
+    "super", "switch", "this", "throw", "true", "try", "var", "void", "while", "with"
+];
+
+/// Maps types to Dart and to the `dart:ffi` structs mirroring the C API.  Every type that needs
+/// converting gets `_xToNative` and `_xFromNative` functions named after its C mangling;
+/// `_xToNative` returns a value owned by the caller, `_xFromNative` only copies.
+pub struct DartMarshaler<'a> {
+    spec: &'a Spec,
+    c: CMarshaler<'a>
+}
+
+impl<'a> DartMarshaler<'a> {
+    pub fn new(spec: &'a Spec) -> DartMarshaler<'a> {
+        DartMarshaler {
+            spec: spec,
+            c: CMarshaler::new(spec)
+        }
+    }
+
+    /// Reserved words get a trailing underscore, a leading one would make them private.
+    pub fn escape(name: String) -> String {
+        if KEYWORDS.contains(&name.as_str()) {
+            format!("{}_", name)
+        }
+        else {
+            name
+        }
+    }
+
+    pub fn c(&self) -> &CMarshaler<'a> {
+        &self.c
+    }
+
+    pub fn type_ident(&self, i: &Identifier) -> String {
+        (self.spec.dart_ident_style.ty)(i.value.clone())
+    }
+
+    pub fn method_ident(&self, i: &Identifier) -> String {
+        DartMarshaler::escape((self.spec.dart_ident_style.method)(i.value.clone()))
+    }
+
+    pub fn field_ident(&self, i: &Identifier) -> String {
+        DartMarshaler::escape((self.spec.dart_ident_style.field)(i.value.clone()))
+    }
+
+    pub fn local_ident(&self, i: &Identifier) -> String {
+        DartMarshaler::escape((self.spec.dart_ident_style.local)(i.value.clone()))
+    }
+
+    pub fn enum_member(&self, i: &Identifier) -> String {
+        DartMarshaler::escape((self.spec.dart_ident_style.enm)(i.value.clone()))
+    }
+
+    pub fn const_ident(&self, i: &Identifier) -> String {
+        DartMarshaler::escape((self.spec.dart_ident_style.cnst)(i.value.clone()))
+    }
+
+    /// The local holding the native value of a parameter.
+    pub fn native_local_ident(&self, i: &Identifier) -> String {
+        format!("{}Native", (self.spec.dart_ident_style.local)(i.value.clone()))
+    }
+
+    /// The private struct mirroring the C struct of `dts`, e.g. `_MapStringListF64`.
+    pub fn struct_ident(&self, dts: &DataTypeStatement) -> String {
+        format!("_{}", IdentStyle::camel_upper(self.c.mangle(dts)))
+    }
+
+    /// The prefix of the conversion functions for `dts`, e.g. `_mapStringListF64`.
+    pub fn helper_ident(&self, dts: &DataTypeStatement) -> String {
+        format!("_{}", IdentStyle::camel_lower(self.c.mangle(dts)))
+    }
+
+    /// The lookup of a C function, `_` followed by its C name.
+    pub fn function_ident(&self, name: &str) -> String {
+        format!("_{}", name)
+    }
+
+    pub fn type_name(&self, dts: &DataTypeStatement) -> String {
+        match *dts {
+            DataTypeStatement::None => "void".into(),
+            DataTypeStatement::Float32 | DataTypeStatement::Float64 => "double".into(),
+            DataTypeStatement::Bool => "bool".into(),
+            DataTypeStatement::String => "String".into(),
+            DataTypeStatement::Binary => "Uint8List".into(),
+            DataTypeStatement::Date => "DateTime".into(),
+            DataTypeStatement::List(ref t) => format!("List<{}>", self.type_name(t)),
+            DataTypeStatement::Array(ref t) => format!("{}List", self.typed_data_element(t)),
+            DataTypeStatement::Set(ref t) => format!("Set<{}>", self.type_name(t)),
+            DataTypeStatement::Map(ref k, ref v) => format!("Map<{}, {}>", self.type_name(k), self.type_name(v)),
+            DataTypeStatement::Optional(ref t) => format!("{}?", self.type_name(t)),
+            DataTypeStatement::Object(ref i) => self.type_ident(i),
+            _ => "int".into()
+        }
+    }
+
+    /// `array<T>` is the `dart:typed_data` list of its element, `Float32List` for `f32`.
+    pub fn typed_data_element(&self, dts: &DataTypeStatement) -> String {
+        match *dts {
+            DataTypeStatement::Float32 => "Float32".into(),
+            DataTypeStatement::Float64 => "Float64".into(),
+            _ => self.ffi_type_name(dts)
+        }
+    }
+
+    fn is_enum(&self, dts: &DataTypeStatement) -> bool {
+        match *dts {
+            DataTypeStatement::Object(ref i) => self.c.user_object_kind(i) == Some(UserObjectKind::Enum),
+            _ => false
+        }
+    }
+
+    /// The `dart:ffi` native type of `dts` in the C API.
+    pub fn ffi_type_name(&self, dts: &DataTypeStatement) -> String {
+        let dts = self.c.strip_optional(dts);
+        match *dts {
+            DataTypeStatement::None => "Void".into(),
+            DataTypeStatement::Integer8 => "Int8".into(),
+            DataTypeStatement::Integer16 => "Int16".into(),
+            DataTypeStatement::Integer32 => "Int32".into(),
+            DataTypeStatement::Integer64 | DataTypeStatement::Date => "Int64".into(),
+            DataTypeStatement::Float32 => "Float".into(),
+            DataTypeStatement::Float64 => "Double".into(),
+            DataTypeStatement::Bool => "Bool".into(),
+            DataTypeStatement::Object(ref i) if self.c.is_interface(i) => "Pointer<Void>".into(),
+            _ if self.is_enum(dts) => "Int32".into(),
+            _ => self.struct_ident(dts)
+        }
+    }
+
+    /// The Dart type a native value of `dts` is read as.
+    pub fn ffi_dart_type_name(&self, dts: &DataTypeStatement) -> String {
+        let dts = self.c.strip_optional(dts);
+        match *dts {
+            DataTypeStatement::None => "void".into(),
+            DataTypeStatement::Float32 | DataTypeStatement::Float64 => "double".into(),
+            DataTypeStatement::Bool => "bool".into(),
+            DataTypeStatement::Integer8 | DataTypeStatement::Integer16 | DataTypeStatement::Integer32 |
+            DataTypeStatement::Integer64 | DataTypeStatement::Date => "int".into(),
+            _ if self.is_enum(dts) => "int".into(),
+            _ => self.ffi_type_name(dts)
+        }
+    }
+
+    /// A struct field, primitives need their native type as an annotation.
+    pub fn ffi_field(&self, dts: &DataTypeStatement, name: &str) -> String {
+        let ffi = self.ffi_type_name(dts);
+        let dart = self.ffi_dart_type_name(dts);
+        if ffi == dart {
+            format!("external {} {};", ffi, name)
+        }
+        else {
+            format!("@{}()\n  external {} {};", ffi, dart, name)
+        }
+    }
+
+    /// Parameters the C API borrows by pointer are `Pointer`s to the struct.
+    pub fn ffi_param_type(&self, dts: &DataTypeStatement, dart: bool) -> String {
+        if !self.c.by_value(dts) {
+            format!("Pointer<{}>", self.ffi_type_name(dts))
+        }
+        else if dart {
+            self.ffi_dart_type_name(dts)
+        }
+        else {
+            self.ffi_type_name(dts)
+        }
+    }
+
+    /// Converts the Dart value `expr` to its native type.
+    pub fn to_native(&self, dts: &DataTypeStatement, expr: &str) -> String {
+        let dts = self.c.strip_optional(dts);
+        match *dts {
+            DataTypeStatement::Date => format!("{}.millisecondsSinceEpoch", expr),
+            _ if self.is_enum(dts) => format!("{}.index", expr),
+            DataTypeStatement::Object(..) | DataTypeStatement::String | DataTypeStatement::Binary | DataTypeStatement::List(..) |
+            DataTypeStatement::Array(..) | DataTypeStatement::Set(..) | DataTypeStatement::Map(..) | DataTypeStatement::Optional(..) => {
+                format!("{}ToNative({})", self.helper_ident(dts), expr)
+            },
+            _ => expr.into()
+        }
+    }
+
+    /// Converts the native value `expr` to Dart, copying anything it points to and retaining
+    /// interfaces.  Interface handles may be null, so only optional ones stay nullable.
+    pub fn from_native(&self, dts: &DataTypeStatement, expr: &str) -> String {
+        let stripped = self.c.strip_optional(dts);
+        match *stripped {
+            DataTypeStatement::Date => format!("DateTime.fromMillisecondsSinceEpoch({})", expr),
+            DataTypeStatement::Object(ref i) if self.is_enum(stripped) => format!("{}.values[{}]", self.type_ident(i), expr),
+            DataTypeStatement::Object(ref i) if self.c.is_interface(i) && stripped == dts => {
+                format!("{}FromNative({})!", self.helper_ident(stripped), expr)
+            },
+            DataTypeStatement::Object(..) | DataTypeStatement::String | DataTypeStatement::Binary | DataTypeStatement::List(..) |
+            DataTypeStatement::Array(..) | DataTypeStatement::Set(..) | DataTypeStatement::Map(..) | DataTypeStatement::Optional(..) => {
+                format!("{}FromNative({})", self.helper_ident(stripped), expr)
+            },
+            _ => expr.into()
+        }
+    }
+
+    /// Releases the native value `expr`: an interface handle, or a pointer to anything else.
+    pub fn release(&self, dts: &DataTypeStatement, expr: &str) -> Option<String> {
+        let dts = self.c.strip_optional(dts);
+        match *dts {
+            DataTypeStatement::Object(ref i) if self.c.is_interface(i) => {
+                Some(format!("{}({});", self.function_ident(&self.c.function_name(i, "release")), expr))
+            },
+            _ if self.c.is_owned(dts) => Some(format!("{}({});", self.function_ident(&format!("{}_release", self.c.type_name(dts))), expr)),
+            _ => None
+        }
+    }
+}
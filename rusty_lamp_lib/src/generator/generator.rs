@@ -3,55 +3,50 @@
  * Creator: Jonathan Rothberg
  */
 
-use std::fs;
-use std::fs::{File};
-use std::io::{Write, BufWriter};
-use std::error::Error;
-use std::sync::Arc;
-use std::convert::AsRef;
+use std::io::{Write};
+use std::path::{PathBuf};
 use parser::program::Program;
 use parser::ast::{StatementKind};
 use generator::spec::Spec;
+use generator::output::GeneratedOutput;
 
 pub trait Generate {
     // fn new() -> Arc<Generate> where Self:Sized;
-    fn generate(&self, spec: &Spec, program: &Program) {
+    fn generate(&self, spec: &Spec, program: &Program, output: &mut GeneratedOutput) {
         for stmt in &program.statements {
             match stmt.stmtKind {
                 StatementKind::Enum(..) => {
-                    // let mut w = self.make_writer(spec, &i.value);
-                    // write!(w, "before write_enum call...");
-                    // w.flush();
-                    // self.testW(&mut w);
-                    self.write_enum(&stmt.stmtKind, &spec);
-                    // writeln!(w, "after write_enum call...");
-                    // w.flush();
+                    self.write_enum(&stmt.stmtKind, &spec, output);
                 },
                 StatementKind::Record(..) => {
-                    self.write_record(&stmt.stmtKind, &spec);
+                    self.write_record(&stmt.stmtKind, &spec, output);
+                },
+                StatementKind::Interface(..) => {
+                    self.write_interface(&stmt.stmtKind, &spec, output);
+                },
+                StatementKind::Alias(..) => {
+                    self.write_alias(&stmt.stmtKind, &spec, output);
                 }
                 _ => {}
             }
         }
+
+        self.finish(spec, program, output);
+    }
+    // Called once every type has been written, for files that cover the whole program.
+    fn finish(&self, spec: &Spec, program: &Program, output: &mut GeneratedOutput) {
     }
     fn testW(&self, w: &mut Write) {
         writeln!(w, "Test function...");
     }
-    fn write_enum(&self, e: &StatementKind, spec: &Spec);
-    fn write_record(&self, r: &StatementKind, spec: &Spec);
-    fn write_interface(&self, i: &StatementKind);
-    fn make_writer(&self, spec: &Spec, file_name: &String) -> BufWriter<File> {
-        // Open a file in write-only mode, returns `io::Result<File>`
-        let path = format!("{}/{}.hpp", spec.cpp_out_folder.clone().unwrap(), file_name);
-        let mut file = match File::create(&path) {
-            Err(why) => panic!("couldn't create {}: {}",
-                            path,
-                            why.description()),
-            Ok(file) => file,
-        };
-
-        BufWriter::new(file)
-
+    fn write_enum(&self, e: &StatementKind, spec: &Spec, output: &mut GeneratedOutput);
+    fn write_record(&self, r: &StatementKind, spec: &Spec, output: &mut GeneratedOutput);
+    fn write_interface(&self, i: &StatementKind, spec: &Spec, output: &mut GeneratedOutput);
+    // Uses of an alias are already expanded, so only generators that can name it override this.
+    fn write_alias(&self, a: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
+    }
+    fn make_path(&self, folder: &str, file_name: &String, ext: &str) -> PathBuf {
+        PathBuf::from(folder).join(format!("{}.{}", file_name, ext))
     }
     fn test(&self) {
     }
@@ -68,13 +63,7 @@ impl<G: Generate> Generator<G> {
         }
     }
 
-    pub fn generate(&self, spec: &Spec, program: &Program) {
-        self.gen.generate(spec, program);
-        
+    pub fn generate(&self, spec: &Spec, program: &Program, output: &mut GeneratedOutput) {
+        self.gen.generate(spec, program, output);
     }
-
-    
 }
-
-
-
@@ -3,9 +3,8 @@
  * Creator: Jonathan Rothberg
  */
 
-#![feature(conservative_impl_trait)]
+use std::fmt;
 use std::sync::Arc;
-use std::collections::HashMap;
 
 pub type IdentConverter = Fn(String) -> String;
 
@@ -44,8 +43,96 @@ impl IdentStyleDefault {
 pub struct IdentStyle {
     pub cpp_style_default: IdentStyleDefault,
     pub java_style_default: IdentStyleDefault,
+    pub kotlin_style_default: IdentStyleDefault,
     pub objc_style_default: IdentStyleDefault,
-    pub styles: HashMap<&'static str, Arc<IdentConverter>>
+    pub rust_style_default: IdentStyleDefault,
+    pub c_style_default: IdentStyleDefault,
+    pub swift_style_default: IdentStyleDefault,
+    pub wasm_style_default: IdentStyleDefault,
+    pub python_style_default: IdentStyleDefault,
+    pub csharp_style_default: IdentStyleDefault,
+    pub dart_style_default: IdentStyleDefault,
+    pub node_style_default: IdentStyleDefault
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum IdentCase {
+    CamelUpper,
+    CamelLower,
+    UnderLower,
+    UnderUpper,
+    UnderCaps
+}
+
+impl IdentCase {
+    // Checked in this order so that parsing a spec never depends on hash order.
+    pub fn all() -> Vec<IdentCase> {
+        vec![IdentCase::CamelUpper, IdentCase::CamelLower, IdentCase::UnderLower,
+             IdentCase::UnderUpper, IdentCase::UnderCaps]
+    }
+
+    pub fn pattern(&self) -> &'static str {
+        match *self {
+            IdentCase::CamelUpper => "FooBar",
+            IdentCase::CamelLower => "fooBar",
+            IdentCase::UnderLower => "foo_bar",
+            IdentCase::UnderUpper => "Foo_Bar",
+            IdentCase::UnderCaps => "FOO_BAR"
+        }
+    }
+
+    pub fn apply(&self, s: String) -> String {
+        match *self {
+            IdentCase::CamelUpper => IdentStyle::camel_upper(s),
+            IdentCase::CamelLower => IdentStyle::camel_lower(s),
+            IdentCase::UnderLower => IdentStyle::under_lower(s),
+            IdentCase::UnderUpper => IdentStyle::under_upper(s),
+            IdentCase::UnderCaps => IdentStyle::under_caps(s)
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct IdentSpec {
+    pub prefix: String,
+    pub case: IdentCase,
+    pub suffix: String
+}
+
+impl IdentSpec {
+    pub fn convert(&self, s: String) -> String {
+        format!("{}{}{}", self.prefix, self.case.apply(s), self.suffix)
+    }
+
+    pub fn to_converter(&self) -> Arc<IdentConverter> {
+        let spec = self.clone();
+        Arc::new(move |s| spec.convert(s))
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum IdentStyleError {
+    Empty,
+    NoStyle(String),
+    Ambiguous(String, Vec<&'static str>),
+    InvalidCharacter(String, char)
+}
+
+impl fmt::Display for IdentStyleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IdentStyleError::Empty => write!(f, "ident spec is empty"),
+            IdentStyleError::NoStyle(ref s) => {
+                write!(f, "\"{}\" does not contain one of FooBar, fooBar, foo_bar, Foo_Bar or FOO_BAR", s)
+            },
+            IdentStyleError::Ambiguous(ref s, ref styles) => {
+                write!(f, "\"{}\" is ambiguous, it contains {}", s, styles.join(" and "))
+            },
+            IdentStyleError::InvalidCharacter(ref s, c) => {
+                write!(f, "\"{}\" contains invalid character '{}'", s, c)
+            }
+        }
+    }
 }
 
 
@@ -56,46 +143,68 @@ pub enum IdentStyleType {
 
 impl IdentStyle {
     pub fn new() -> IdentStyle {
-        let mut styles_map: HashMap<&'static str, Arc<IdentConverter>> = HashMap::new();
-        styles_map.insert("FooBar", Arc::new(IdentStyle::camel_upper));
-        styles_map.insert("fooBar", Arc::new(IdentStyle::camel_lower));
-        styles_map.insert("foo_bar", Arc::new(IdentStyle::under_lower));
-        styles_map.insert("Foo_Bar", Arc::new(IdentStyle::under_upper));
-        styles_map.insert("FOO_BAR", Arc::new(IdentStyle::under_caps));
-        
         IdentStyle {
-            cpp_style_default: IdentStyleDefault {
-                ty: Arc::new(IdentStyle::camel_upper),
-                enum_type: Arc::new(IdentStyle::camel_upper),
-                type_param: Arc::new(IdentStyle::camel_upper),
-                method: Arc::new(IdentStyle::camel_lower),
-                field: Arc::new(IdentStyle::camel_lower),
-                local: Arc::new(IdentStyle::camel_lower),
-                enm: Arc::new(IdentStyle::under_caps),
-                cnst: Arc::new(IdentStyle::under_caps),
+            cpp_style_default: IdentStyle::default_for("cpp"),
+            java_style_default: IdentStyle::default_for("java"),
+            kotlin_style_default: IdentStyle::default_for("kotlin"),
+            rust_style_default: IdentStyle::default_for("rust"),
+            objc_style_default: IdentStyle::default_for("objc"),
+            c_style_default: IdentStyle::default_for("c"),
+            swift_style_default: IdentStyle::default_for("swift"),
+            wasm_style_default: IdentStyle::default_for("wasm"),
+            python_style_default: IdentStyle::default_for("python"),
+            csharp_style_default: IdentStyle::default_for("csharp"),
+            dart_style_default: IdentStyle::default_for("dart"),
+            node_style_default: IdentStyle::default_for("node")
+        }
+    }
+
+    // Every generator starts from FooBar types, fooBar members and FOO_BAR enums and
+    // constants; only the languages whose conventions differ override anything.
+    pub fn default_for(lang: &str) -> IdentStyleDefault {
+        let mut style = IdentStyleDefault::new(
+            Arc::new(IdentStyle::camel_upper), Arc::new(IdentStyle::camel_upper), Arc::new(IdentStyle::camel_upper),
+            Arc::new(IdentStyle::camel_lower), Arc::new(IdentStyle::camel_lower), Arc::new(IdentStyle::camel_lower),
+            Arc::new(IdentStyle::under_caps), Arc::new(IdentStyle::under_caps));
+
+        match lang {
+            "rust" => {
+                style.method = Arc::new(IdentStyle::under_lower);
+                style.field = Arc::new(IdentStyle::under_lower);
+                style.local = Arc::new(IdentStyle::under_lower);
+                style.enm = Arc::new(IdentStyle::camel_upper);
             },
-            java_style_default: IdentStyleDefault {
-                ty: Arc::new(IdentStyle::camel_upper),
-                enum_type: Arc::new(IdentStyle::camel_upper),
-                type_param: Arc::new(IdentStyle::camel_upper),
-                method: Arc::new(IdentStyle::camel_lower),
-                field: Arc::new(IdentStyle::camel_lower),
-                local: Arc::new(IdentStyle::camel_lower),
-                enm: Arc::new(IdentStyle::under_caps),
-                cnst: Arc::new(IdentStyle::under_caps),
+            "objc" => {
+                style.enm = Arc::new(IdentStyle::camel_upper);
+                style.cnst = Arc::new(IdentStyle::camel_upper);
             },
-            objc_style_default: IdentStyleDefault {
-                ty: Arc::new(IdentStyle::camel_upper),
-                enum_type: Arc::new(IdentStyle::camel_upper),
-                type_param: Arc::new(IdentStyle::camel_upper),
-                method: Arc::new(IdentStyle::camel_lower),
-                field: Arc::new(IdentStyle::camel_lower),
-                local: Arc::new(IdentStyle::camel_lower),
-                enm: Arc::new(IdentStyle::camel_upper),
-                cnst: Arc::new(IdentStyle::camel_upper),
+            "c" => {
+                style.ty = Arc::new(IdentStyle::under_lower);
+                style.enum_type = Arc::new(IdentStyle::under_lower);
+                style.type_param = Arc::new(IdentStyle::under_lower);
+                style.method = Arc::new(IdentStyle::under_lower);
+                style.field = Arc::new(IdentStyle::under_lower);
+                style.local = Arc::new(IdentStyle::under_lower);
             },
-            styles: styles_map
+            "swift" | "dart" => {
+                style.enm = Arc::new(IdentStyle::camel_lower);
+                style.cnst = Arc::new(IdentStyle::camel_lower);
+            },
+            "python" => {
+                style.method = Arc::new(IdentStyle::under_lower);
+                style.field = Arc::new(IdentStyle::under_lower);
+                style.local = Arc::new(IdentStyle::under_lower);
+            },
+            "csharp" => {
+                style.method = Arc::new(IdentStyle::camel_upper);
+                style.field = Arc::new(IdentStyle::camel_upper);
+                style.enm = Arc::new(IdentStyle::camel_upper);
+                style.cnst = Arc::new(IdentStyle::camel_upper);
+            },
+            _ => {}
         }
+
+        style
     }
 
     pub fn first_upper(token: String) -> String {
@@ -110,32 +219,64 @@ impl IdentStyle {
         }
     }
 
+    // Splits an identifier into lower case words.  Word boundaries are underscores,
+    // a lower case letter or digit followed by an upper case letter, and the last
+    // letter of an upper case run followed by a lower case letter, so "HTTPServer"
+    // becomes ["http", "server"].
+    pub fn split_words(s: &str) -> Vec<String> {
+        let chars = s.chars().collect::<Vec<char>>();
+        let mut words = Vec::new();
+        let mut word = String::new();
+
+        for (index, &c) in chars.iter().enumerate() {
+            if c == '_' {
+                if word.len() > 0 {
+                    words.push(word.clone());
+                    word.clear();
+                }
+                continue;
+            }
+
+            if c.is_uppercase() && word.len() > 0 {
+                let prev = chars[index - 1];
+                let next_is_lower = chars.get(index + 1).map_or(false, |n| n.is_lowercase());
+                if prev.is_lowercase() || prev.is_numeric() || (prev.is_uppercase() && next_is_lower) {
+                    words.push(word.clone());
+                    word.clear();
+                }
+            }
+
+            word.extend(c.to_lowercase());
+        }
+
+        if word.len() > 0 {
+            words.push(word);
+        }
+
+        words
+    }
+
     pub fn camel_upper(s: String) -> String {
-        let vec = s.split("_").map(|i| i.to_string()).collect::<Vec<String>>();
-        let result = vec.into_iter().map(IdentStyle::first_upper).collect::<String>();
-        return result;
+        IdentStyle::split_words(&s).into_iter().map(IdentStyle::first_upper).collect::<String>()
     }
 
     pub fn camel_lower(s: String) -> String {
-        let mut parts = s.split("_");
-        let mut result = String::new();
-        result.push_str(parts.nth(0).unwrap());
-        result.push_str(parts.skip(1).map(|i| i.to_string()).into_iter().map(IdentStyle::first_upper).collect::<String>().as_str());
+        let mut words = IdentStyle::split_words(&s).into_iter();
+        let mut result = words.next().unwrap_or_default();
+        result.push_str(words.map(IdentStyle::first_upper).collect::<String>().as_str());
         return result;
     }
 
     pub fn under_lower(s: String) -> String {
-        s
+        IdentStyle::split_words(&s).join("_")
     }
 
     pub fn under_upper(s: String) -> String {
-        let vec = s.split("_").map(|i| i.to_string()).collect::<Vec<String>>();
-        let result = vec.into_iter().map(IdentStyle::first_upper).collect::<Vec<String>>().join("_");
-        return result;
+        IdentStyle::split_words(&s).into_iter().map(IdentStyle::first_upper).collect::<Vec<String>>().join("_")
     }
 
     pub fn under_caps(s: String) -> String {
-        s.to_uppercase()
+        IdentStyle::under_lower(s).to_uppercase()
     }
 
     pub fn prefix(pre: String, suffix: Arc<IdentConverter>) -> Arc<IdentConverter> {
@@ -147,36 +288,36 @@ impl IdentStyle {
         });
     }
 
-    pub fn infer(styles: &HashMap<&'static str, Arc<IdentConverter>>, input: &String) -> Option<Arc<IdentConverter>> {
-        for (s, func) in styles {
-            if(input.ends_with(s)) {
-                let diff = input.len() - s.len();
-                if (diff > 0) {
-                    let before = input[0..diff].into();
-                    return Some(IdentStyle::prefix(before, func.clone()))
-                }
-                else {
-                    return Some(func.clone())
-                }
-            }
+    pub fn parse(spec: &str) -> Result<IdentSpec, IdentStyleError> {
+        if spec.len() == 0 {
+            return Err(IdentStyleError::Empty);
+        }
+
+        if let Some(c) = spec.chars().find(|c| !(c.is_alphanumeric() || *c == '_')) {
+            return Err(IdentStyleError::InvalidCharacter(spec.into(), c));
         }
-        None
-    }
 
-    pub fn build_ident_style(name: &String) -> Option<Arc<IdentConverter>> {
-        let ident_style = IdentStyle::new();
+        let matches = IdentCase::all().into_iter()
+            .filter_map(|case| spec.find(case.pattern()).map(|index| (case, index)))
+            .collect::<Vec<_>>();
 
-        let infer = IdentStyle::infer(&ident_style.styles, name);
-        match infer {
-            Some(i) => {
-                Some(i)
+        match matches.len() {
+            0 => Err(IdentStyleError::NoStyle(spec.into())),
+            1 => {
+                let (case, index) = matches[0];
+                Ok(IdentSpec {
+                    prefix: spec[..index].into(),
+                    case: case,
+                    suffix: spec[index + case.pattern().len()..].into()
+                })
             },
-            None => {
-                // panic!("invalid ident spec: {}", name)
-                None
-            }
+            _ => Err(IdentStyleError::Ambiguous(spec.into(), matches.iter().map(|&(c, _)| c.pattern()).collect()))
         }
     }
+
+    pub fn build_ident_style(name: &String) -> Result<Arc<IdentConverter>, IdentStyleError> {
+        IdentStyle::parse(name).map(|spec| spec.to_converter())
+    }
 }
 
 #[cfg(test)]
@@ -188,21 +329,86 @@ mod tests {
         let token = String::from("fooBar");
 
         let result = IdentStyle::first_upper(token);
-        println!("First Upper: {}", result);
+        assert!(result == "FooBar", "{} != FooBar", result);
+    }
+
+    #[test]
+    fn test_split_words() {
+        let test_cases = vec![
+            ("my_record", vec!["my", "record"]),
+            ("myRecord", vec!["my", "record"]),
+            ("MyRecord", vec!["my", "record"]),
+            ("MY_RECORD", vec!["my", "record"]),
+            ("HTTPServer", vec!["http", "server"]),
+            ("parseHTTPResponse", vec!["parse", "http", "response"]),
+            ("utf8String", vec!["utf8", "string"]),
+            ("__leading", vec!["leading"]),
+        ];
+
+        for (input, expected) in test_cases {
+            let words = IdentStyle::split_words(input);
+            assert!(words == expected, "{}: {:?} != {:?}", input, words, expected);
+        }
+    }
+
+    #[test]
+    fn test_cases() {
+        assert!(IdentStyle::under_lower("HTTPServer".into()) == "http_server");
+        assert!(IdentStyle::camel_upper("HTTPServer".into()) == "HttpServer");
+        assert!(IdentStyle::camel_upper("my_record".into()) == "MyRecord");
+        assert!(IdentStyle::camel_lower("MyRecord".into()) == "myRecord");
+        assert!(IdentStyle::under_upper("myRecord".into()) == "My_Record");
+        assert!(IdentStyle::under_caps("myRecord".into()) == "MY_RECORD");
+    }
+
+    #[test]
+    fn test_language_defaults() {
+        let cpp = IdentStyle::default_for("cpp");
+        assert!((cpp.method)("my_method".into()) == "myMethod");
+        assert!((cpp.enm)("first_value".into()) == "FIRST_VALUE");
+
+        let rust = IdentStyle::default_for("rust");
+        assert!((rust.method)("myMethod".into()) == "my_method");
+        assert!((rust.enm)("first_value".into()) == "FirstValue");
+        assert!((rust.cnst)("max_size".into()) == "MAX_SIZE");
+
+        let c = IdentStyle::default_for("c");
+        assert!((c.ty)("MyRecord".into()) == "my_record");
+        assert!((c.enm)("first_value".into()) == "FIRST_VALUE");
+
+        let csharp = IdentStyle::default_for("csharp");
+        assert!((csharp.field)("my_field".into()) == "MyField");
+        assert!((csharp.local)("my_local".into()) == "myLocal");
+
+        let swift = IdentStyle::default_for("swift");
+        assert!((swift.cnst)("max_size".into()) == "maxSize");
     }
 
     #[test]
     fn test_infer() {
-        let ident_style = IdentStyle::new();
+        let infer = IdentStyle::build_ident_style(&"foo_bar".into()).unwrap();
+        assert!(infer("myRecord".into()) == "my_record");
 
-        let infer = IdentStyle::infer(&ident_style.styles, "foo_bar".into());
-        match infer {
-            Some(i) => {
-                println!("{}", i("my_record".into()));
-            },
-            None => {
-                println!("None...");
-            }
+        let spec = IdentStyle::parse("mFooBar").unwrap();
+        assert!(spec == IdentSpec { prefix: "m".into(), case: IdentCase::CamelUpper, suffix: "".into() }, "{:?}", spec);
+        assert!(spec.convert("some_field".into()) == "mSomeField");
+
+        let spec = IdentStyle::parse("NativeFooBar").unwrap();
+        assert!(spec.convert("my_record".into()) == "NativeMyRecord");
+
+        let spec = IdentStyle::parse("foo_bar_t").unwrap();
+        assert!(spec.suffix == "_t", "{:?}", spec);
+        assert!(spec.convert("MyRecord".into()) == "my_record_t");
+    }
+
+    #[test]
+    fn test_invalid_specs() {
+        assert!(IdentStyle::parse("") == Err(IdentStyleError::Empty));
+        assert!(IdentStyle::parse("bogus") == Err(IdentStyleError::NoStyle("bogus".into())));
+        assert!(IdentStyle::parse("foo-bar") == Err(IdentStyleError::InvalidCharacter("foo-bar".into(), '-')));
+        match IdentStyle::parse("FooBarfoo_bar") {
+            Err(IdentStyleError::Ambiguous(_, styles)) => assert!(styles == vec!["FooBar", "foo_bar"], "{:?}", styles),
+            r => assert!(false, "expected an ambiguous spec, got={:?}", r)
         }
     }
 }
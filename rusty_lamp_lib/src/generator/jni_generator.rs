@@ -10,6 +10,7 @@ use parser::program::Program;
 use parser::ast::{StatementKind};
 use generator::generator::{ Generate };
 use generator::spec::Spec;
+use generator::output::GeneratedOutput;
 
 pub struct JniGenerator {
     
@@ -28,15 +29,15 @@ impl Generate for JniGenerator {
     //     println!("Generating JNI files...");
     // }
 
-    fn write_enum(&self, e: &StatementKind, spec: &Spec) {
+    fn write_enum(&self, e: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
         
     }
 
-    fn write_record(&self, r: &StatementKind, spec: &Spec) {
+    fn write_record(&self, r: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
         
     }
 
-    fn write_interface(&self, i: &StatementKind) {
+    fn write_interface(&self, i: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
         
     }
 
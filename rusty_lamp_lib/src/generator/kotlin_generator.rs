@@ -0,0 +1,625 @@
+/*
+ * Copyright © 2002-2017 Bluebeam Software, Inc. All Rights Reserved.
+ * Creator: Jonathan Rothberg
+ */
+
+use std::io::{Write};
+use generator::generator::{Generate};
+use generator::spec::Spec;
+use generator::output::GeneratedOutput;
+use generator::kotlin_marshaler::KotlinMarshaler;
+use generator::resolver::program_errors;
+use generator::typer::module_name;
+use parser::program::Program;
+use parser::ast::{Annotation, Statement, StatementKind, DataTypeStatement, DeriveType, FunctionModifier, Identifier, Parameter, find_annotation};
+
+pub struct KotlinGenerator {
+
+}
+
+impl KotlinGenerator {
+    pub fn new() -> KotlinGenerator {
+        KotlinGenerator {
+
+        }
+    }
+
+    /// The header of the file declaring the user type `name`.  Files in a module's subpackage
+    /// import the top level package, whose types they use unqualified.
+    fn write_header(&self, w: &mut Write, spec: &Spec, name: &String) {
+        let marshaler = KotlinMarshaler::new(spec);
+        writeln!(w, "// AUTOGENERATED FILE - DO NOT MODIFY!");
+        writeln!(w, "// This file was generated by rusty_lamp");
+        writeln!(w, "");
+        if let Some(package) = marshaler.package(name) {
+            writeln!(w, "package {}", package);
+            writeln!(w, "");
+        }
+        if let (Some(_), Some(ref package)) = (module_name(name), spec.config.java.package.as_ref()) {
+            writeln!(w, "import {}.*", package);
+            writeln!(w, "");
+        }
+    }
+
+    fn add_file(&self, spec: &Spec, i: &Identifier, w: Vec<u8>, output: &mut GeneratedOutput) {
+        let marshaler = KotlinMarshaler::new(spec);
+        output.add_file(self.file_path(spec, &i.value, &marshaler.type_ident(i)), w);
+    }
+
+    /// Files of a module go into its subpackage's folder.
+    fn file_path(&self, spec: &Spec, name: &String, file_name: &String) -> ::std::path::PathBuf {
+        let out = ::std::path::PathBuf::from(spec.config.kotlin.out.as_ref().map_or("", |s| s.as_str()));
+        let folder = match module_name(name) {
+            Some(m) => m.split('.').fold(out, |f, s| f.join(s)),
+            None => out
+        };
+        self.make_path(folder.to_str().unwrap_or(""), file_name, "kt")
+    }
+
+    fn write_comment(&self, w: &mut Write, indent: &str, comment: &String) {
+        writeln!(w, "{}//{}", indent, comment);
+    }
+
+    /// The resolver only lets numbers, strings and bools through as field defaults.  Kotlin won't
+    /// widen an integer literal to a `Double`, so those get a fraction.
+    fn default_value(&self, dts: &DataTypeStatement, value: &StatementKind) -> String {
+        match (dts, value) {
+            (&DataTypeStatement::Optional(ref t), _) => self.default_value(t, value),
+            (_, &StatementKind::StringLiteral(_, ref s)) => format!("{:?}", s),
+            (_, &StatementKind::Boolean(_, b)) => format!("{}", b),
+            (&DataTypeStatement::Float32, &StatementKind::NumberLiteral(_, ref n)) => format!("{}f", n),
+            (&DataTypeStatement::Float64, &StatementKind::NumberLiteral(_, ref n)) if !n.contains('.') => format!("{}.0", n),
+            (_, &StatementKind::NumberLiteral(_, ref n)) => n.clone(),
+            _ => value.to_string()
+        }
+    }
+
+    /// Consts live in the companion object, as `const val` where Kotlin allows it.
+    /// An `Int` ordering `a` before or after `b`, both of type `dts`.  Lists are compared element
+    /// by element with `compareLists`, and nulls come first like `compareValues` orders them.
+    fn compare_values(&self, marshaler: &KotlinMarshaler, dts: &DataTypeStatement, a: &str, b: &str, depth: usize) -> String {
+        match *dts {
+            _ if marshaler.is_comparable(dts) => format!("compareValues({}, {})", a, b),
+            DataTypeStatement::List(ref t) => {
+                let (l, r) = (format!("l{}", depth), format!("r{}", depth));
+                format!("compareLists({}, {}) {{ {}, {} -> {} }}", a, b, l, r, self.compare_values(marshaler, t, &l, &r, depth + 1))
+            },
+            DataTypeStatement::Optional(ref t) => {
+                format!("(if ({} == null || {} == null) compareValues({} != null, {} != null) else {})",
+                        a, b, a, b, self.compare_values(marshaler, t, a, b, depth))
+            },
+            _ => format!("compareValues({}, {})", a, b)
+        }
+    }
+
+    /// Whether `a` and `b`, both of type `dts`, are equal.  Arrays don't override `equals`, so they
+    /// and the containers holding them are compared by content.
+    fn equal_values(&self, marshaler: &KotlinMarshaler, dts: &DataTypeStatement, a: &str, b: &str, depth: usize) -> String {
+        match *dts {
+            _ if !marshaler.compares_by_reference(dts) => format!("{} == {}", a, b),
+            _ if marshaler.is_array(dts) => format!("{}.contentEquals({})", a, b),
+            DataTypeStatement::Optional(ref t) if marshaler.is_array(t) => format!("{}.contentEquals({})", a, b),
+            DataTypeStatement::Optional(ref t) => {
+                format!("(if ({} == null || {} == null) {} == {} else {})", a, b, a, b, self.equal_values(marshaler, t, a, b, depth))
+            },
+            DataTypeStatement::List(ref t) => {
+                let i = format!("i{}", depth);
+                format!("({}.size == {}.size && {}.indices.all {{ {} -> {} }})", a, b, a, i,
+                        self.equal_values(marshaler, t, &format!("{}[{}]", a, i), &format!("{}[{}]", b, i), depth + 1))
+            },
+            DataTypeStatement::Set(ref t) => {
+                let (l, r) = (format!("l{}", depth), format!("r{}", depth));
+                format!("({}.size == {}.size && {}.all {{ {} -> {}.any {{ {} -> {} }} }})", a, b, a, l, b, r,
+                        self.equal_values(marshaler, t, &l, &r, depth + 1))
+            },
+            DataTypeStatement::Map(ref k, ref v) if marshaler.compares_by_reference(k) => {
+                let (lk, l, rk, r) = (format!("lk{}", depth), format!("l{}", depth), format!("rk{}", depth), format!("r{}", depth));
+                format!("({}.size == {}.size && {}.all {{ ({}, {}) -> {}.any {{ ({}, {}) -> {} && {} }} }})", a, b, a, lk, l, b, rk, r,
+                        self.equal_values(marshaler, k, &lk, &rk, depth + 1), self.equal_values(marshaler, v, &l, &r, depth + 1))
+            },
+            DataTypeStatement::Map(_, ref v) => {
+                let (k, l) = (format!("k{}", depth), format!("l{}", depth));
+                format!("({}.keys == {}.keys && {}.all {{ ({}, {}) -> {} }})", a, b, a, k, l,
+                        self.equal_values(marshaler, v, &l, &format!("{}.getValue({})", b, k), depth + 1))
+            },
+            _ => format!("{} == {}", a, b)
+        }
+    }
+
+    /// The hash of `v`, of type `dts`, agreeing with `equal_values`.
+    fn hash_value(&self, marshaler: &KotlinMarshaler, dts: &DataTypeStatement, v: &str, depth: usize) -> String {
+        let x = format!("x{}", depth);
+        match *dts {
+            _ if !marshaler.compares_by_reference(dts) => format!("{}.hashCode()", v),
+            _ if marshaler.is_array(dts) => format!("{}.contentHashCode()", v),
+            DataTypeStatement::Optional(ref t) if marshaler.is_array(t) => format!("{}.contentHashCode()", v),
+            DataTypeStatement::Optional(ref t) => {
+                format!("({}?.let {{ {} -> {} }} ?: 0)", v, x, self.hash_value(marshaler, t, &x, depth + 1))
+            },
+            DataTypeStatement::List(ref t) => {
+                let h = format!("h{}", depth);
+                format!("{}.fold(1) {{ {}, {} -> 31 * {} + {} }}", v, h, x, h, self.hash_value(marshaler, t, &x, depth + 1))
+            },
+            DataTypeStatement::Set(ref t) => {
+                format!("{}.sumOf {{ {} -> {} }}", v, x, self.hash_value(marshaler, t, &x, depth + 1))
+            },
+            DataTypeStatement::Map(ref k, ref t) => {
+                let key = format!("k{}", depth);
+                format!("{}.entries.sumOf {{ ({}, {}) -> {} xor {} }}", v, key, x,
+                        self.hash_value(marshaler, k, &key, depth + 1), self.hash_value(marshaler, t, &x, depth + 1))
+            },
+            _ => format!("{}.hashCode()", v)
+        }
+    }
+
+    fn consts(&self, marshaler: &KotlinMarshaler, statements: &Vec<Statement>) -> Vec<String> {
+        let mut result = Vec::new();
+        for s in statements {
+            if let StatementKind::Const(_, ref i, ref dts, ref v) = s.stmtKind {
+                let value = match v.stmtKind {
+                    StatementKind::StringLiteral(_, ref s) => format!("{:?}", s),
+                    StatementKind::Boolean(_, b) => format!("{}", b),
+                    StatementKind::NumberLiteral(_, ref n) if *dts == DataTypeStatement::Float32 => format!("{}f", n),
+                    StatementKind::NumberLiteral(_, ref n) => n.clone(),
+                    _ => {
+                        result.push(format!("// const {}: {} is not representable as a Kotlin constant", i.value, dts));
+                        continue;
+                    }
+                };
+                result.push(format!("const val {}: {} = {}", marshaler.const_ident(i), marshaler.type_name(dts), value));
+            }
+        }
+        result
+    }
+
+    fn params(&self, marshaler: &KotlinMarshaler, params: &Vec<Parameter>) -> String {
+        params.iter()
+            .map(|p| format!("{}: {}", self.param_ident(marshaler, p), marshaler.type_name(&p.data_type)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn param_ident(&self, marshaler: &KotlinMarshaler, p: &Parameter) -> String {
+        marshaler.java_name(&p.annotations, marshaler.local_ident(&p.ident))
+    }
+
+    /// The KDoc `@since` and `@Deprecated` lines in front of a declaration.
+    fn write_annotations(&self, w: &mut Write, marshaler: &KotlinMarshaler, indent: &str, since: Option<&Annotation>, deprecated: Option<&Annotation>) {
+        if let Some(v) = since.and_then(|a| a.value.as_ref()) {
+            writeln!(w, "{}/** @since {} */", indent, v);
+        }
+        if let Some(d) = marshaler.deprecated(deprecated) {
+            writeln!(w, "{}{}", indent, d);
+        }
+    }
+
+    /// Lets Java callers catch the typed exception.  Async methods complete their future
+    /// exceptionally instead.
+    fn write_throws(&self, w: &mut Write, marshaler: &KotlinMarshaler, indent: &str, ret: &DataTypeStatement, throws: &Option<Identifier>) {
+        match (ret, throws) {
+            (&DataTypeStatement::Future(..), _) => {},
+            (_, &Some(ref e)) => {
+                writeln!(w, "{}@Throws({}::class)", indent, marshaler.exception_ident(e));
+            },
+            _ => {}
+        }
+    }
+
+    fn returns(&self, marshaler: &KotlinMarshaler, ret: &DataTypeStatement) -> String {
+        match *ret {
+            DataTypeStatement::None => "".into(),
+            _ => format!(": {}", marshaler.type_name(ret))
+        }
+    }
+}
+
+impl Generate for KotlinGenerator {
+    fn write_enum(&self, e: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
+        if let StatementKind::Enum(_, ref i, ref b) = *e {
+            let marshaler = KotlinMarshaler::new(spec);
+            let mut w = Vec::new();
+            self.write_header(&mut w, spec, &i.value);
+
+            let members = b.statements.iter()
+                .filter(|s| match s.stmtKind { StatementKind::EnumMember(..) => true, _ => false })
+                .count();
+            self.write_annotations(&mut w, &marshaler, "", spec.typer.get_annotation(&i.value, "since"), spec.typer.get_annotation(&i.value, "deprecated"));
+            writeln!(w, "enum class {} {{", marshaler.type_ident(i));
+            let mut written = 0;
+            for o in &b.statements {
+                match o.stmtKind {
+                    StatementKind::EnumMember(_, ref oi) => {
+                        written += 1;
+                        self.write_annotations(&mut w, &marshaler, "    ", o.annotation("since"), o.annotation("deprecated"));
+                        writeln!(w, "    {}{}", marshaler.java_name(&o.annotations, marshaler.enum_member(oi)), if written == members { ";" } else { "," });
+                    },
+                    StatementKind::Comment(_, ref c) => self.write_comment(&mut w, "    ", c),
+                    _ => {}
+                }
+            }
+            writeln!(w, "}}");
+
+            self.add_file(spec, i, w, output);
+        }
+    }
+
+    fn write_record(&self, r: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
+        if let StatementKind::Record(_, ref i, ref bs, ref dt) = *r {
+            let marshaler = KotlinMarshaler::new(spec);
+            let name = marshaler.type_ident(i);
+            let mut w = Vec::new();
+            self.write_header(&mut w, spec, &i.value);
+
+            let fields = bs.statements.iter()
+                .filter_map(|s| match s.stmtKind {
+                    StatementKind::RecordMember(_, ref fi, ref dts, _) => Some((marshaler.java_name(&s.annotations, marshaler.field_ident(fi)), dts)),
+                    _ => None
+                })
+                .collect::<Vec<_>>();
+            // A data class already derives equality, except that it compares arrays by reference.
+            // Ordering needs every field to be orderable.
+            let by_content = fields.iter().any(|&(_, dts)| marshaler.compares_by_reference(dts));
+            let comparable = dt.contains(&DeriveType::Ord) && fields.iter().all(|&(_, dts)| marshaler.is_orderable(dts));
+            let compares_lists = comparable && !fields.iter().all(|&(_, dts)| marshaler.is_comparable(dts));
+
+            self.write_annotations(&mut w, &marshaler, "", spec.typer.get_annotation(&i.value, "since"), spec.typer.get_annotation(&i.value, "deprecated"));
+            // Java gets an overload per trailing defaulted field.
+            let has_defaults = bs.statements.iter().any(|s| match s.stmtKind {
+                StatementKind::RecordMember(_, _, _, Some(_)) => true,
+                _ => false
+            });
+            // kotlinx.serialization, keyed by the idl field names like the C++ output.
+            let json = dt.contains(&DeriveType::Json);
+            if json {
+                writeln!(w, "@kotlinx.serialization.Serializable");
+            }
+            writeln!(w, "data class {}{}(", name, if has_defaults { " @JvmOverloads constructor" } else { "" });
+            let mut written = 0;
+            for f in &bs.statements {
+                match f.stmtKind {
+                    StatementKind::RecordMember(_, ref fi, ref dts, ref default) => {
+                        written += 1;
+                        let default = match *default {
+                            Some(ref v) => format!(" = {}", self.default_value(dts, &v.stmtKind)),
+                            None => String::new()
+                        };
+                        self.write_annotations(&mut w, &marshaler, "    ", f.annotation("since"), f.annotation("deprecated"));
+                        let field = marshaler.java_name(&f.annotations, marshaler.field_ident(fi));
+                        if json && field != fi.value {
+                            writeln!(w, "    @kotlinx.serialization.SerialName(\"{}\")", fi.value);
+                        }
+                        writeln!(w, "    val {}: {}{}{}", field, marshaler.type_name(dts), default, if written == fields.len() { "" } else { "," });
+                    },
+                    StatementKind::Comment(_, ref c) => self.write_comment(&mut w, "    ", c),
+                    _ => {}
+                }
+            }
+
+            let consts = self.consts(&marshaler, &bs.statements);
+            if !comparable && !by_content && consts.len() == 0 {
+                writeln!(w, ")");
+                self.add_file(spec, i, w, output);
+                return;
+            }
+
+            if comparable {
+                writeln!(w, ") : Comparable<{}> {{", name);
+                writeln!(w, "    override fun compareTo(other: {}): Int {{", name);
+                if compares_lists {
+                    // compareValuesBy only takes Comparable selectors.
+                    for (n, &(ref field, dts)) in fields.iter().enumerate() {
+                        let compare = self.compare_values(&marshaler, dts, &format!("this.{}", field), &format!("other.{}", field), 0);
+                        if n + 1 == fields.len() {
+                            writeln!(w, "        return {}", compare);
+                        }
+                        else {
+                            writeln!(w, "        {}.let {{ if (it != 0) return it }}", compare);
+                        }
+                    }
+                }
+                else {
+                    let selectors = fields.iter()
+                        .map(|&(ref field, _)| format!("{{ it.{} }}", field))
+                        .collect::<Vec<_>>();
+                    writeln!(w, "        return compareValuesBy(this, other, {})", selectors.join(", "));
+                }
+                writeln!(w, "    }}");
+            }
+            else {
+                writeln!(w, ") {{");
+            }
+            if by_content {
+                if comparable {
+                    writeln!(w, "");
+                }
+                let equals = fields.iter()
+                    .map(|&(ref field, dts)| self.equal_values(&marshaler, dts, &format!("this.{}", field), &format!("other.{}", field), 0))
+                    .collect::<Vec<_>>();
+                writeln!(w, "    override fun equals(other: Any?): Boolean {{");
+                writeln!(w, "        if (this === other) return true");
+                writeln!(w, "        if (other !is {}) return false", name);
+                writeln!(w, "        return {}", equals.join(" &&\n            "));
+                writeln!(w, "    }}");
+                writeln!(w, "");
+                writeln!(w, "    override fun hashCode(): Int {{");
+                for (n, &(ref field, dts)) in fields.iter().enumerate() {
+                    let hash = self.hash_value(&marshaler, dts, &format!("this.{}", field), 0);
+                    if n == 0 {
+                        writeln!(w, "        var result = {}", hash);
+                    }
+                    else {
+                        writeln!(w, "        result = 31 * result + {}", hash);
+                    }
+                }
+                writeln!(w, "        return result");
+                writeln!(w, "    }}");
+            }
+            if consts.len() > 0 {
+                if comparable || by_content {
+                    writeln!(w, "");
+                }
+                writeln!(w, "    companion object {{");
+                for c in &consts {
+                    writeln!(w, "        {}", c);
+                }
+                writeln!(w, "    }}");
+            }
+            writeln!(w, "}}");
+            if compares_lists {
+                writeln!(w, "");
+                writeln!(w, "private fun <T> compareLists(a: List<T>, b: List<T>, compare: (T, T) -> Int): Int {{");
+                writeln!(w, "    for (i in 0 until minOf(a.size, b.size)) {{");
+                writeln!(w, "        val c = compare(a[i], b[i])");
+                writeln!(w, "        if (c != 0) return c");
+                writeln!(w, "    }}");
+                writeln!(w, "    return a.size.compareTo(b.size)");
+                writeln!(w, "}}");
+            }
+
+            self.add_file(spec, i, w, output);
+        }
+    }
+
+    fn write_interface(&self, i: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
+        if let StatementKind::Interface(_, ref id, ref it, ref bs, _) = *i {
+            let marshaler = KotlinMarshaler::new(spec);
+            let name = marshaler.type_ident(id);
+            let has_proxy = marshaler.has_cpp_proxy(it);
+            let mut w = Vec::new();
+            self.write_header(&mut w, spec, &id.value);
+            if has_proxy {
+                writeln!(w, "import java.util.concurrent.atomic.AtomicBoolean");
+                writeln!(w, "");
+            }
+
+            let methods = bs.statements.iter()
+                .filter_map(|s| match s.stmtKind {
+                    StatementKind::Function(_, ref m, ref fi, ref params, ref ret, ref throws) => Some((m, fi, params, ret, throws, &s.annotations)),
+                    _ => None
+                })
+                .collect::<Vec<_>>();
+
+            self.write_annotations(&mut w, &marshaler, "", spec.typer.get_annotation(&id.value, "since"), spec.typer.get_annotation(&id.value, "deprecated"));
+            writeln!(w, "abstract class {} {{", name);
+            for s in &bs.statements {
+                match s.stmtKind {
+                    StatementKind::Function(_, FunctionModifier::None, ref fi, ref params, ref ret, ref throws) => {
+                        self.write_annotations(&mut w, &marshaler, "    ", s.annotation("since"), s.annotation("deprecated"));
+                        self.write_throws(&mut w, &marshaler, "    ", ret, throws);
+                        writeln!(w, "    abstract fun {}({}){}", marshaler.java_name(&s.annotations, marshaler.method_ident(fi)),
+                                 self.params(&marshaler, params), self.returns(&marshaler, ret));
+                    },
+                    StatementKind::Comment(_, ref c) => self.write_comment(&mut w, "    ", c),
+                    _ => {}
+                }
+            }
+
+            // Static methods are implemented in C++ and called straight through JNI.
+            let consts = self.consts(&marshaler, &bs.statements);
+            let statics = methods.iter().filter(|&&(m, _, _, _, _, _)| *m == FunctionModifier::Static).collect::<Vec<_>>();
+            if consts.len() > 0 || statics.len() > 0 {
+                writeln!(w, "");
+                writeln!(w, "    companion object {{");
+                for c in &consts {
+                    writeln!(w, "        {}", c);
+                }
+                for &&(_, fi, params, ret, throws, annotations) in &statics {
+                    self.write_annotations(&mut w, &marshaler, "        ", find_annotation(annotations, "since"), find_annotation(annotations, "deprecated"));
+                    writeln!(w, "        @JvmStatic");
+                    self.write_throws(&mut w, &marshaler, "        ", ret, throws);
+                    writeln!(w, "        external fun {}({}){}", marshaler.java_name(annotations, marshaler.method_ident(fi)),
+                             self.params(&marshaler, params), self.returns(&marshaler, ret));
+                }
+                writeln!(w, "    }}");
+            }
+
+            // Same class name, constructor and native methods as the Java CppProxy so the JNI side is shared.
+            if has_proxy {
+                writeln!(w, "");
+                writeln!(w, "    private class CppProxy(private val nativeRef: Long) : {}() {{", name);
+                writeln!(w, "        private val destroyed = AtomicBoolean(false)");
+                writeln!(w, "");
+                writeln!(w, "        init {{");
+                writeln!(w, "            if (nativeRef == 0L) throw RuntimeException(\"nativeRef is zero\")");
+                writeln!(w, "        }}");
+                writeln!(w, "");
+                writeln!(w, "        private external fun nativeDestroy(nativeRef: Long)");
+                writeln!(w, "");
+                writeln!(w, "        fun destroy() {{");
+                writeln!(w, "            if (!destroyed.getAndSet(true)) nativeDestroy(nativeRef)");
+                writeln!(w, "        }}");
+                writeln!(w, "");
+                writeln!(w, "        protected fun finalize() {{");
+                writeln!(w, "            destroy()");
+                writeln!(w, "        }}");
+                for &(m, fi, params, ret, throws, annotations) in &methods {
+                    if *m == FunctionModifier::Static {
+                        continue;
+                    }
+                    let args = params.iter().map(|p| self.param_ident(&marshaler, p)).collect::<Vec<_>>();
+                    let native_params = if params.len() > 0 { format!(", {}", self.params(&marshaler, params)) } else { "".into() };
+                    let native_args = if args.len() > 0 { format!(", {}", args.join(", ")) } else { "".into() };
+                    writeln!(w, "");
+                    self.write_throws(&mut w, &marshaler, "        ", ret, throws);
+                    writeln!(w, "        override fun {}({}){} {{", marshaler.java_name(annotations, marshaler.method_ident(fi)),
+                             self.params(&marshaler, params), self.returns(&marshaler, ret));
+                    writeln!(w, "            assert(!destroyed.get()) {{ \"trying to use a destroyed object\" }}");
+                    writeln!(w, "            {}{}(nativeRef{})", if *ret == DataTypeStatement::None { "" } else { "return " },
+                             marshaler.native_method_ident(fi), native_args);
+                    writeln!(w, "        }}");
+                    writeln!(w, "");
+                    writeln!(w, "        private external fun {}(_nativeRef: Long{}){}", marshaler.native_method_ident(fi), native_params,
+                             self.returns(&marshaler, ret));
+                }
+                writeln!(w, "    }}");
+            }
+            writeln!(w, "}}");
+
+            self.add_file(spec, id, w, output);
+        }
+    }
+    // Every error type thrown by a method gets an exception carrying it.
+    fn finish(&self, spec: &Spec, program: &Program, output: &mut GeneratedOutput) {
+        let marshaler = KotlinMarshaler::new(spec);
+        for e in program_errors(program) {
+            let name = format!("{}Exception", marshaler.type_ident(&e));
+            let mut w = Vec::new();
+            self.write_header(&mut w, spec, &e.value);
+            writeln!(w, "class {}(val error: {}) : Exception(error.toString())", name, marshaler.type_ident(&e));
+            output.add_file(self.file_path(spec, &e.value, &name), w);
+        }
+    }
+
+    fn write_alias(&self, a: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
+        if !spec.config.kotlin.aliases {
+            return;
+        }
+
+        if let StatementKind::Alias(_, ref i, ref dts) = *a {
+            let marshaler = KotlinMarshaler::new(spec);
+            let mut w = Vec::new();
+            self.write_header(&mut w, spec, &i.value);
+
+            self.write_annotations(&mut w, &marshaler, "", spec.typer.get_annotation(&i.value, "since"), spec.typer.get_annotation(&i.value, "deprecated"));
+            writeln!(w, "typealias {} = {}", marshaler.type_ident(i), marshaler.type_name(dts));
+
+            self.add_file(spec, i, w, output);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::Config;
+    use generator::testing;
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.java.package = Some("com.bluebeam.lamp".into());
+        config.kotlin.out = Some("kotlin".into());
+
+        config
+    }
+
+    #[test]
+    fn test_write_record() {
+        let output = testing::compile(config(), "color = enum { red; dark_blue; }\n\
+                                                 point = record { x: i32; col: color; label: optional<string>; const max_x: i32 = 99; } deriving(ord)\n\
+                                                 key = record { id: i64; tags: list<color>; groups: optional<list<list<color>>>; } deriving(ord)\n");
+
+        let point = &output.get("kotlin/Point.kt").unwrap().contents;
+        assert!(point.contains("package com.bluebeam.lamp\n"), "{}", point);
+        assert!(point.contains("data class Point(\n    val x: Int,\n    val col: Color,\n    val label: String?\n) : Comparable<Point> {"), "{}", point);
+        assert!(point.contains("return compareValuesBy(this, other, { it.x }, { it.col }, { it.label })"), "{}", point);
+        assert!(point.contains("    companion object {\n        const val MAX_X: Int = 99\n    }"), "{}", point);
+
+        // Lists aren't Comparable, so they are compared element by element.
+        let key = &output.get("kotlin/Key.kt").unwrap().contents;
+        assert!(key.contains("data class Key(\n    val id: Long,\n    val tags: ArrayList<Color>,\n    val groups: ArrayList<ArrayList<Color>>?\n\
+                              ) : Comparable<Key> {"), "{}", key);
+        assert!(key.contains("        compareValues(this.id, other.id).let { if (it != 0) return it }\n\
+                              \x20       compareLists(this.tags, other.tags) { l0, r0 -> compareValues(l0, r0) }.let { if (it != 0) return it }\n\
+                              \x20       return (if (this.groups == null || other.groups == null) compareValues(this.groups != null, other.groups != null) \
+                              else compareLists(this.groups, other.groups) { l0, r0 -> compareLists(l0, r0) { l1, r1 -> compareValues(l1, r1) } })\n"), "{}", key);
+        assert!(key.contains("private fun <T> compareLists(a: List<T>, b: List<T>, compare: (T, T) -> Int): Int {"), "{}", key);
+        assert!(!point.contains("compareLists"), "{}", point);
+
+        let color = &output.get("kotlin/Color.kt").unwrap().contents;
+        assert!(color.contains("enum class Color {\n    RED,\n    DARK_BLUE;\n}"), "{}", color);
+    }
+
+    #[test]
+    fn test_write_record_binary() {
+        let output = testing::compile(config(), "blob = record { id: i64; data: binary; tags: list<binary>; note: optional<binary>; } deriving(eq)\n\
+                                                 plain = record { id: i64; } deriving(eq)\n");
+
+        // A data class compares and hashes a ByteArray by reference.
+        let blob = &output.get("kotlin/Blob.kt").unwrap().contents;
+        assert!(blob.contains("        if (other !is Blob) return false\n\
+                               \x20       return this.id == other.id &&\n\
+                               \x20           this.data.contentEquals(other.data) &&\n\
+                               \x20           (this.tags.size == other.tags.size && this.tags.indices.all { i0 -> this.tags[i0].contentEquals(other.tags[i0]) }) &&\n\
+                               \x20           this.note.contentEquals(other.note)\n"), "{}", blob);
+        assert!(blob.contains("        var result = this.id.hashCode()\n\
+                               \x20       result = 31 * result + this.data.contentHashCode()\n\
+                               \x20       result = 31 * result + this.tags.fold(1) { h0, x0 -> 31 * h0 + x0.contentHashCode() }\n\
+                               \x20       result = 31 * result + this.note.contentHashCode()\n"), "{}", blob);
+
+        let plain = &output.get("kotlin/Plain.kt").unwrap().contents;
+        assert!(plain.contains("data class Plain(\n    val id: Long\n)\n"), "{}", plain);
+        assert!(!plain.contains("equals"), "{}", plain);
+    }
+
+    #[test]
+    fn test_write_record_array() {
+        let output = testing::compile(config(), "samples = record { rate: i32; values: array<f32>; channels: map<string, array<i16>>; \
+                                                 peaks: optional<list<array<f64>>>; } deriving(eq)\n");
+
+        let samples = &output.get("kotlin/Samples.kt").unwrap().contents;
+        assert!(samples.contains("    val values: FloatArray,\n"), "{}", samples);
+        assert!(samples.contains("        return this.rate == other.rate &&\n\
+                                  \x20           this.values.contentEquals(other.values) &&\n\
+                                  \x20           (this.channels.keys == other.channels.keys && \
+                                  this.channels.all { (k0, l0) -> l0.contentEquals(other.channels.getValue(k0)) }) &&\n\
+                                  \x20           (if (this.peaks == null || other.peaks == null) this.peaks == other.peaks else \
+                                  (this.peaks.size == other.peaks.size && this.peaks.indices.all { i0 -> this.peaks[i0].contentEquals(other.peaks[i0]) }))\n"),
+                "{}", samples);
+        assert!(samples.contains("        result = 31 * result + this.values.contentHashCode()\n\
+                                  \x20       result = 31 * result + this.channels.entries.sumOf { (k0, x0) -> k0.hashCode() xor x0.contentHashCode() }\n\
+                                  \x20       result = 31 * result + (this.peaks?.let { x0 -> x0.fold(1) { h1, x1 -> 31 * h1 + x1.contentHashCode() } } ?: 0)\n"),
+                "{}", samples);
+    }
+
+    #[test]
+    fn test_write_interface() {
+        let output = testing::compile(config(), "key = record { id: i64; }\n\
+                                                 listener = interface +j { on_key(key: optional<key>); }\n\
+                                                 key_store = interface +c { static create(): key_store; lookup(id: i64, l: listener): key; close(); }\n");
+
+        let store = &output.get("kotlin/KeyStore.kt").unwrap().contents;
+        assert!(store.contains("abstract class KeyStore {\n    abstract fun lookup(id: Long, l: Listener): Key\n    abstract fun close()\n"), "{}", store);
+        assert!(store.contains("        @JvmStatic\n        external fun create(): KeyStore\n"), "{}", store);
+        assert!(store.contains("private class CppProxy(private val nativeRef: Long) : KeyStore() {"), "{}", store);
+        assert!(store.contains("return native_lookup(nativeRef, id, l)"), "{}", store);
+        assert!(store.contains("private external fun native_lookup(_nativeRef: Long, id: Long, l: Listener): Key"), "{}", store);
+        assert!(store.contains("private external fun native_close(_nativeRef: Long)\n"), "{}", store);
+
+        let listener = &output.get("kotlin/Listener.kt").unwrap().contents;
+        assert!(listener.contains("abstract fun onKey(key: Key?)\n"), "{}", listener);
+        assert!(!listener.contains("CppProxy"), "{}", listener);
+    }
+
+    #[test]
+    fn test_write_throws() {
+        let output = testing::compile(config(), "store_error = record { reason: string; }\n\
+                                                 key_store = interface +c { static open(path: string): key_store throws store_error; close() throws store_error; }\n");
+
+        let exception = &output.get("kotlin/StoreErrorException.kt").unwrap().contents;
+        assert!(exception.contains("class StoreErrorException(val error: StoreError) : Exception(error.toString())"), "{}", exception);
+
+        let store = &output.get("kotlin/KeyStore.kt").unwrap().contents;
+        assert!(store.contains("    @Throws(StoreErrorException::class)\n    abstract fun close()\n"), "{}", store);
+        assert!(store.contains("        @JvmStatic\n        @Throws(StoreErrorException::class)\n        external fun open(path: String): KeyStore\n"), "{}", store);
+        assert!(store.contains("        @Throws(StoreErrorException::class)\n        override fun close() {"), "{}", store);
+    }
+}
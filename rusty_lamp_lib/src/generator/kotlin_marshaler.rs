@@ -0,0 +1,214 @@
+/*
+ * Copyright © 2002-2017 Bluebeam Software, Inc. All Rights Reserved.
+ * Creator: Jonathan Rothberg
+ */
+
+use parser::ast::{Annotation, DataTypeStatement, DeriveType, Identifier, InterfaceType, find_annotation};
+use generator::spec::Spec;
+use generator::typer::{TypeDefinitionKind, UserObjectKind, local_name, module_name};
+use generator::resolver::nested_types;
+
+const KEYWORDS: &'static [&'static str] = &[
+    "as", "break", "class", "continue", "do", "else", "false", "for", "fun", "if", "in", "interface",
+    "is", "null", "object", "package", "return", "super", "this", "throw", "true", "try", "typealias",
+    "typeof", "val", "var", "when", "while"
+];
+
+// (java, kotlin) for every JVM primitive.
+const KOTLIN_PRIMITIVES: &'static [(&'static str, &'static str)] = &[
+    ("byte", "Byte"),
+    ("short", "Short"),
+    ("int", "Int"),
+    ("long", "Long"),
+    ("float", "Float"),
+    ("double", "Double"),
+    ("boolean", "Boolean"),
+    ("char", "Char")
+];
+
+pub struct KotlinMarshaler<'a> {
+    spec: &'a Spec
+}
+
+impl<'a> KotlinMarshaler<'a> {
+    pub fn new(spec: &'a Spec) -> KotlinMarshaler<'a> {
+        KotlinMarshaler {
+            spec: spec
+        }
+    }
+
+    pub fn escape(name: String) -> String {
+        if KEYWORDS.contains(&name.as_str()) {
+            format!("`{}`", name)
+        }
+        else {
+            name
+        }
+    }
+
+    /// `@java_name` on the declaration replaces the styled name.
+    pub fn type_ident(&self, i: &Identifier) -> String {
+        match self.spec.typer.get_annotation(&i.value, "java_name").and_then(|a| a.value.clone()) {
+            Some(name) => name,
+            None => (self.spec.kotlin_ident_style.ty)(local_name(&i.value).to_string())
+        }
+    }
+
+    /// The name to refer to `i` by from any file.  A type in a module lives in a subpackage, so
+    /// it is fully qualified.
+    pub fn qualified_ident(&self, i: &Identifier) -> String {
+        match module_name(&i.value) {
+            Some(_) => format!("{}.{}", self.package(&i.value).unwrap_or_default(), self.type_ident(i)),
+            None => self.type_ident(i)
+        }
+    }
+
+    /// The package of the user type `name`: the configured one, with the type's module as a
+    /// subpackage.
+    pub fn package(&self, name: &String) -> Option<String> {
+        match (self.spec.config.java.package.as_ref(), module_name(name)) {
+            (Some(p), Some(m)) => Some(format!("{}.{}", p, m)),
+            (None, Some(m)) => Some(m.into()),
+            (p, None) => p.cloned()
+        }
+    }
+
+    /// The name of a member or parameter: `styled` unless `@java_name` replaces it.
+    pub fn java_name(&self, annotations: &Vec<Annotation>, styled: String) -> String {
+        find_annotation(annotations, "java_name")
+            .and_then(|a| a.value.clone())
+            .map_or(styled, KotlinMarshaler::escape)
+    }
+
+    /// `@Deprecated("reason")`; Kotlin requires the message, so it may be empty.
+    pub fn deprecated(&self, deprecated: Option<&Annotation>) -> Option<String> {
+        deprecated.map(|a| format!("@Deprecated({:?})", a.value.clone().unwrap_or_default()))
+    }
+
+    pub fn method_ident(&self, i: &Identifier) -> String {
+        KotlinMarshaler::escape((self.spec.kotlin_ident_style.method)(i.value.clone()))
+    }
+
+    pub fn field_ident(&self, i: &Identifier) -> String {
+        KotlinMarshaler::escape((self.spec.kotlin_ident_style.field)(i.value.clone()))
+    }
+
+    pub fn local_ident(&self, i: &Identifier) -> String {
+        KotlinMarshaler::escape((self.spec.kotlin_ident_style.local)(i.value.clone()))
+    }
+
+    pub fn enum_member(&self, i: &Identifier) -> String {
+        KotlinMarshaler::escape((self.spec.kotlin_ident_style.enm)(i.value.clone()))
+    }
+
+    pub fn const_ident(&self, i: &Identifier) -> String {
+        KotlinMarshaler::escape((self.spec.kotlin_ident_style.cnst)(i.value.clone()))
+    }
+
+    /// The exception thrown for the error type `i`, declared next to it.
+    pub fn exception_ident(&self, i: &Identifier) -> String {
+        format!("{}Exception", self.qualified_ident(i))
+    }
+
+    /// The native method backing `i` on the `CppProxy`, named the way the JNI side expects.
+    pub fn native_method_ident(&self, i: &Identifier) -> String {
+        format!("native_{}", (self.spec.kotlin_ident_style.method)(i.value.clone()))
+    }
+
+    /// Interfaces implemented in C++ get a `CppProxy` calling into native code.
+    pub fn has_cpp_proxy(&self, types: &Vec<InterfaceType>) -> bool {
+        types.contains(&InterfaceType::Cpp)
+    }
+
+    /// Collections use the `java.util` classes so the erased types match the JNI signatures of the
+    /// Java output.  Only `optional<T>` is nullable, and functions are Kotlin function types, which
+    /// Java sees as the `kotlin.jvm.functions` interfaces.  `array<T>` is the primitive array of the
+    /// element's JVM type, `FloatArray` for `f32`, which Java sees as `float[]` and JNI as
+    /// `jfloatArray`.
+    pub fn type_name(&self, dts: &DataTypeStatement) -> String {
+        match *dts {
+            DataTypeStatement::None => "Unit".into(),
+            DataTypeStatement::List(ref t) => format!("ArrayList<{}>", self.type_name(t)),
+            DataTypeStatement::Array(ref t) => format!("{}Array", self.type_name(t)),
+            DataTypeStatement::Set(ref t) => format!("HashSet<{}>", self.type_name(t)),
+            DataTypeStatement::Map(ref k, ref v) => format!("HashMap<{}, {}>", self.type_name(k), self.type_name(v)),
+            DataTypeStatement::Optional(ref t) => {
+                match **t {
+                    DataTypeStatement::Function(..) => format!("({})?", self.type_name(t)),
+                    _ => format!("{}?", self.type_name(t))
+                }
+            },
+            DataTypeStatement::Future(ref t) => format!("java.util.concurrent.CompletableFuture<{}>", self.type_name(t)),
+            DataTypeStatement::Function(ref p, ref r) => {
+                let params: Vec<String> = p.iter().map(|t| self.type_name(t)).collect();
+                format!("({}) -> {}", params.join(", "), self.type_name(r))
+            },
+            DataTypeStatement::Object(ref i) => self.qualified_ident(i),
+            _ => {
+                match self.spec.typer.get_from_data_type(dts) {
+                    TypeDefinitionKind::Primitive(ref p) => {
+                        KOTLIN_PRIMITIVES.iter()
+                            .find(|&&(java, _)| java == p.java)
+                            .map_or(p.java.clone(), |&(_, kotlin)| kotlin.into())
+                    },
+                    TypeDefinitionKind::String => self.override_type("string").unwrap_or("String".into()),
+                    TypeDefinitionKind::Binary => self.override_type("binary").unwrap_or("ByteArray".into()),
+                    TypeDefinitionKind::Date => self.override_type("date").unwrap_or("java.util.Date".into()),
+                    _ => "".into()
+                }
+            }
+        }
+    }
+
+    /// Whether values of `dts` can be compared with `compareValuesBy`.
+    pub fn is_comparable(&self, dts: &DataTypeStatement) -> bool {
+        match *dts {
+            DataTypeStatement::List(..) | DataTypeStatement::Array(..) | DataTypeStatement::Set(..) | DataTypeStatement::Map(..) |
+            DataTypeStatement::Function(..) | DataTypeStatement::Binary | DataTypeStatement::None => false,
+            DataTypeStatement::Optional(ref t) => self.is_comparable(t),
+            DataTypeStatement::Object(ref i) => {
+                match self.user_object_kind(i) {
+                    Some(UserObjectKind::Enum) => true,
+                    Some(UserObjectKind::Record(ref d)) => d.contains(&DeriveType::Ord),
+                    _ => false
+                }
+            },
+            _ => true
+        }
+    }
+
+    /// Whether a record field of type `dts` can be ordered, lists element by element since they
+    /// aren't Comparable.
+    pub fn is_orderable(&self, dts: &DataTypeStatement) -> bool {
+        match *dts {
+            DataTypeStatement::List(ref t) | DataTypeStatement::Optional(ref t) => self.is_orderable(t),
+            _ => self.is_comparable(dts)
+        }
+    }
+
+    /// Whether `dts` is a Kotlin array, whose `equals` and `hashCode` go by reference: `array<T>`,
+    /// and `binary` unless its type is overridden.
+    pub fn is_array(&self, dts: &DataTypeStatement) -> bool {
+        match *dts {
+            DataTypeStatement::Array(..) => true,
+            DataTypeStatement::Binary => self.override_type("binary").is_none(),
+            _ => false
+        }
+    }
+
+    /// Whether a data class would compare some part of a field of type `dts` by reference.
+    pub fn compares_by_reference(&self, dts: &DataTypeStatement) -> bool {
+        nested_types(dts).iter().any(|t| self.is_array(t))
+    }
+
+    pub fn user_object_kind(&self, i: &Identifier) -> Option<UserObjectKind> {
+        match self.spec.typer.get(&i.value) {
+            TypeDefinitionKind::UserObject(_, k) => Some(k),
+            _ => None
+        }
+    }
+
+    fn override_type(&self, name: &str) -> Option<String> {
+        self.spec.typer.get_override(name).and_then(|o| o.java.clone())
+    }
+}
@@ -3,8 +3,31 @@ pub mod resolver;
 pub mod typer;
 pub mod cpp_generator;
 pub mod java_generator;
+pub mod kotlin_marshaler;
+pub mod kotlin_generator;
 pub mod jni_generator;
 pub mod objc_generator;
+pub mod objc_marshaler;
 pub mod spec;
 pub mod cpp_marshaler;
 pub mod ident_style;
+pub mod output;
+pub mod rust_marshaler;
+pub mod rust_generator;
+pub mod rust_bridge_generator;
+pub mod c_marshaler;
+pub mod c_generator;
+pub mod swift_marshaler;
+pub mod swift_generator;
+pub mod wasm_marshaler;
+pub mod wasm_generator;
+pub mod python_marshaler;
+pub mod python_generator;
+pub mod csharp_marshaler;
+pub mod csharp_generator;
+pub mod dart_marshaler;
+pub mod dart_generator;
+pub mod node_marshaler;
+pub mod node_generator;
+#[cfg(test)]
+pub mod testing;
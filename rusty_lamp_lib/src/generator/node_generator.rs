@@ -0,0 +1,761 @@
+/*
+ * Copyright © 2002-2017 Bluebeam Software, Inc. All Rights Reserved.
+ * Creator: Jonathan Rothberg
+ */
+
+use std::io::{Write};
+use generator::generator::{Generate};
+use generator::spec::Spec;
+use generator::output::GeneratedOutput;
+use generator::cpp_marshaler::CppMarshaler;
+use generator::node_marshaler::NodeMarshaler;
+use generator::resolver::{program_uses_optional, program_uses_future};
+use parser::program::Program;
+use parser::ast::{Statement, StatementKind, DataTypeStatement, FunctionModifier, Identifier, InterfaceType, BlockStatement, Parameter};
+
+/// Exposes the generated C++ classes to Node.js as an N-API addon built on node-addon-api,
+/// written as a single source plus `index.d.ts`.  Records are converted to and from plain
+/// objects, enums are objects of numbers and interfaces are `Napi::ObjectWrap` classes holding
+/// a `std::shared_ptr`.  Every conversion goes through the `Convert<T>` templates at the top of
+/// the source, which need `NAPI_CPP_EXCEPTIONS`.
+pub struct NodeGenerator {
+
+}
+
+type Method<'p> = (&'p FunctionModifier, &'p Identifier, &'p Vec<Parameter>, &'p DataTypeStatement);
+
+impl NodeGenerator {
+    pub fn new() -> NodeGenerator {
+        NodeGenerator {
+
+        }
+    }
+
+    fn write_header(&self, w: &mut Write) {
+        writeln!(w, "// AUTOGENERATED FILE - DO NOT MODIFY!");
+        writeln!(w, "// This file was generated by rusty_lamp");
+    }
+
+    fn methods<'p>(&self, bs: &'p BlockStatement) -> Vec<Method<'p>> {
+        bs.statements.iter()
+            .filter_map(|s| match s.stmtKind {
+                StatementKind::Function(_, ref m, ref fi, ref params, ref ret, _) => Some((m, fi, params, ret)),
+                _ => None
+            })
+            .collect()
+    }
+
+    fn fields<'b>(&self, bs: &'b BlockStatement) -> Vec<(&'b Identifier, &'b DataTypeStatement)> {
+        bs.statements.iter()
+            .filter_map(|s| match s.stmtKind { StatementKind::RecordMember(_, ref i, ref dts, _) => Some((i, dts)), _ => None })
+            .collect()
+    }
+
+    fn to_js(&self, cpp: &CppMarshaler, dts: &DataTypeStatement, expr: &str) -> String {
+        format!("Convert<{}>::ToJs(env, {})", cpp.fq_type_name(dts), expr)
+    }
+
+    fn from_js(&self, cpp: &CppMarshaler, dts: &DataTypeStatement, expr: &str) -> String {
+        format!("Convert<{}>::FromJs({})", cpp.fq_type_name(dts), expr)
+    }
+
+    /// `(name, value)` for the literal consts of a type, converted like any other value.
+    fn consts(&self, m: &NodeMarshaler, cpp: &CppMarshaler, statements: &Vec<Statement>) -> Vec<(String, String)> {
+        statements.iter()
+            .filter_map(|s| match s.stmtKind {
+                StatementKind::Const(_, ref i, ref dts, ref v) => {
+                    let value = match v.stmtKind {
+                        StatementKind::StringLiteral(_, ref s) => format!("{:?}", s),
+                        StatementKind::Boolean(_, b) => format!("{}", b),
+                        StatementKind::NumberLiteral(_, ref n) => n.clone(),
+                        _ => return None
+                    };
+                    Some((m.const_ident(i), self.to_js(cpp, dts, &format!("{}({})", cpp.fq_type_name(dts), value))))
+                },
+                _ => None
+            })
+            .collect()
+    }
+
+    fn ts_consts(&self, m: &NodeMarshaler, statements: &Vec<Statement>) -> Vec<String> {
+        statements.iter()
+            .filter_map(|s| match s.stmtKind {
+                StatementKind::Const(_, ref i, ref dts, ref v) => match v.stmtKind {
+                    StatementKind::StringLiteral(..) | StatementKind::Boolean(..) | StatementKind::NumberLiteral(..) => {
+                        Some(format!("readonly {}: {};", m.const_ident(i), m.ts_type_name(dts)))
+                    },
+                    _ => None
+                },
+                _ => None
+            })
+            .collect()
+    }
+
+    fn ts_params(&self, m: &NodeMarshaler, params: &Vec<Parameter>) -> String {
+        params.iter()
+            .map(|p| format!("{}: {}", m.local_ident(&p.ident), m.ts_type_name(&p.data_type)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn write_numbers(&self, w: &mut Write, cpp: &CppMarshaler) {
+        let mut names: Vec<String> = Vec::new();
+        for dts in &[DataTypeStatement::Integer8, DataTypeStatement::Integer16, DataTypeStatement::Integer32,
+                     DataTypeStatement::Float32, DataTypeStatement::Float64] {
+            let name = cpp.fq_type_name(dts);
+            if !names.contains(&name) {
+                writeln!(w, "template <> struct Convert<{}> : ConvertNumber<{}> {{}};", name, name);
+                names.push(name);
+            }
+        }
+    }
+
+    fn write_convert_support(&self, w: &mut Write, spec: &Spec, cpp: &CppMarshaler, uses_optional: bool) {
+        let i64_name = cpp.fq_type_name(&DataTypeStatement::Integer64);
+        let bool_name = cpp.fq_type_name(&DataTypeStatement::Bool);
+        let string = cpp.fq_type_name(&DataTypeStatement::String);
+        let binary = cpp.fq_type_name(&DataTypeStatement::Binary);
+        let date = cpp.fq_type_name(&DataTypeStatement::Date);
+
+        writeln!(w, "// Turns a C++ exception into a JavaScript one.");
+        writeln!(w, "template <typename F>");
+        writeln!(w, "Napi::Value guard(Napi::Env env, F && f) {{");
+        writeln!(w, "    try {{");
+        writeln!(w, "        return f();");
+        writeln!(w, "    }} catch (const Napi::Error &) {{");
+        writeln!(w, "        throw;");
+        writeln!(w, "    }} catch (const std::exception & e) {{");
+        writeln!(w, "        throw Napi::Error::New(env, e.what());");
+        writeln!(w, "    }}");
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "void check_arguments(const Napi::CallbackInfo & info, size_t count) {{");
+        writeln!(w, "    if (info.Length() < count) {{");
+        writeln!(w, "        throw Napi::TypeError::New(info.Env(), \"expected \" + std::to_string(count) + \" arguments\");");
+        writeln!(w, "    }}");
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "// `Array.from(v)`, the values of a Set or the entries of a Map.");
+        writeln!(w, "Napi::Array array_from(const Napi::Value & v) {{");
+        writeln!(w, "    Napi::Object array = v.Env().Global().Get(\"Array\").As<Napi::Object>();");
+        writeln!(w, "    return array.Get(\"from\").As<Napi::Function>().Call(array, {{ v }}).As<Napi::Array>();");
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "template <typename T>");
+        writeln!(w, "struct Convert;");
+        writeln!(w, "");
+        writeln!(w, "template <typename T>");
+        writeln!(w, "struct ConvertNumber {{");
+        writeln!(w, "    static Napi::Value ToJs(Napi::Env env, T v) {{");
+        writeln!(w, "        return Napi::Number::New(env, static_cast<double>(v));");
+        writeln!(w, "    }}");
+        writeln!(w, "    static T FromJs(const Napi::Value & v) {{");
+        writeln!(w, "        return static_cast<T>(v.ToNumber().DoubleValue());");
+        writeln!(w, "    }}");
+        writeln!(w, "}};");
+        writeln!(w, "");
+        self.write_numbers(w, cpp);
+        writeln!(w, "");
+        writeln!(w, "template <>");
+        writeln!(w, "struct Convert<{}> {{", i64_name);
+        writeln!(w, "    static Napi::Value ToJs(Napi::Env env, {} v) {{", i64_name);
+        writeln!(w, "        return Napi::BigInt::New(env, static_cast<int64_t>(v));");
+        writeln!(w, "    }}");
+        writeln!(w, "    static {} FromJs(const Napi::Value & v) {{", i64_name);
+        writeln!(w, "        if (v.IsBigInt()) {{");
+        writeln!(w, "            bool lossless;");
+        writeln!(w, "            return v.As<Napi::BigInt>().Int64Value(&lossless);");
+        writeln!(w, "        }}");
+        writeln!(w, "        return v.ToNumber().Int64Value();");
+        writeln!(w, "    }}");
+        writeln!(w, "}};");
+        writeln!(w, "");
+        writeln!(w, "template <>");
+        writeln!(w, "struct Convert<{}> {{", bool_name);
+        writeln!(w, "    static Napi::Value ToJs(Napi::Env env, {} v) {{", bool_name);
+        writeln!(w, "        return Napi::Boolean::New(env, v);");
+        writeln!(w, "    }}");
+        writeln!(w, "    static {} FromJs(const Napi::Value & v) {{", bool_name);
+        writeln!(w, "        return v.ToBoolean().Value();");
+        writeln!(w, "    }}");
+        writeln!(w, "}};");
+        writeln!(w, "");
+        writeln!(w, "template <>");
+        writeln!(w, "struct Convert<{}> {{", string);
+        writeln!(w, "    static Napi::Value ToJs(Napi::Env env, const {} & v) {{", string);
+        writeln!(w, "        return Napi::String::New(env, v);");
+        writeln!(w, "    }}");
+        writeln!(w, "    static {} FromJs(const Napi::Value & v) {{", string);
+        writeln!(w, "        return v.ToString().Utf8Value();");
+        writeln!(w, "    }}");
+        writeln!(w, "}};");
+        writeln!(w, "");
+        writeln!(w, "template <>");
+        writeln!(w, "struct Convert<{}> {{", binary);
+        writeln!(w, "    static Napi::Value ToJs(Napi::Env env, const {} & v) {{", binary);
+        writeln!(w, "        return Napi::Buffer<uint8_t>::Copy(env, v.data(), v.size());");
+        writeln!(w, "    }}");
+        writeln!(w, "    static {} FromJs(const Napi::Value & v) {{", binary);
+        writeln!(w, "        if (!v.IsBuffer()) {{");
+        writeln!(w, "            throw Napi::TypeError::New(v.Env(), \"expected a Buffer\");");
+        writeln!(w, "        }}");
+        writeln!(w, "        const auto buffer = v.As<Napi::Buffer<uint8_t>>();");
+        writeln!(w, "        return {}(buffer.Data(), buffer.Data() + buffer.Length());", binary);
+        writeln!(w, "    }}");
+        writeln!(w, "}};");
+        writeln!(w, "");
+        writeln!(w, "template <>");
+        writeln!(w, "struct Convert<{}> {{", date);
+        writeln!(w, "    static Napi::Value ToJs(Napi::Env env, const {} & v) {{", date);
+        writeln!(w, "        return Napi::Date::New(env, static_cast<double>(std::chrono::duration_cast<std::chrono::milliseconds>(v.time_since_epoch()).count()));");
+        writeln!(w, "    }}");
+        writeln!(w, "    static {} FromJs(const Napi::Value & v) {{", date);
+        writeln!(w, "        if (!v.IsDate()) {{");
+        writeln!(w, "            throw Napi::TypeError::New(v.Env(), \"expected a Date\");");
+        writeln!(w, "        }}");
+        writeln!(w, "        return {}(std::chrono::milliseconds(static_cast<int64_t>(v.As<Napi::Date>().ValueOf())));", date);
+        writeln!(w, "    }}");
+        writeln!(w, "}};");
+        writeln!(w, "");
+        writeln!(w, "template <typename T>");
+        writeln!(w, "struct Convert<std::vector<T>> {{");
+        writeln!(w, "    static Napi::Value ToJs(Napi::Env env, const std::vector<T> & v) {{");
+        writeln!(w, "        Napi::Array r = Napi::Array::New(env, v.size());");
+        writeln!(w, "        for (size_t i = 0; i < v.size(); ++i) {{");
+        writeln!(w, "            r.Set(static_cast<uint32_t>(i), Convert<T>::ToJs(env, v[i]));");
+        writeln!(w, "        }}");
+        writeln!(w, "        return r;");
+        writeln!(w, "    }}");
+        writeln!(w, "    static std::vector<T> FromJs(const Napi::Value & v) {{");
+        writeln!(w, "        if (!v.IsArray()) {{");
+        writeln!(w, "            throw Napi::TypeError::New(v.Env(), \"expected an Array\");");
+        writeln!(w, "        }}");
+        writeln!(w, "        const auto values = v.As<Napi::Array>();");
+        writeln!(w, "        std::vector<T> r;");
+        writeln!(w, "        r.reserve(values.Length());");
+        writeln!(w, "        for (uint32_t i = 0; i < values.Length(); ++i) {{");
+        writeln!(w, "            r.push_back(Convert<T>::FromJs(values.Get(i)));");
+        writeln!(w, "        }}");
+        writeln!(w, "        return r;");
+        writeln!(w, "    }}");
+        writeln!(w, "}};");
+        writeln!(w, "");
+        writeln!(w, "template <typename T>");
+        writeln!(w, "struct Convert<std::unordered_set<T>> {{");
+        writeln!(w, "    static Napi::Value ToJs(Napi::Env env, const std::unordered_set<T> & v) {{");
+        writeln!(w, "        Napi::Object r = env.Global().Get(\"Set\").As<Napi::Function>().New({{}});");
+        writeln!(w, "        Napi::Function add = r.Get(\"add\").As<Napi::Function>();");
+        writeln!(w, "        for (const auto & e : v) {{");
+        writeln!(w, "            add.Call(r, {{ Convert<T>::ToJs(env, e) }});");
+        writeln!(w, "        }}");
+        writeln!(w, "        return r;");
+        writeln!(w, "    }}");
+        writeln!(w, "    static std::unordered_set<T> FromJs(const Napi::Value & v) {{");
+        writeln!(w, "        const auto values = array_from(v);");
+        writeln!(w, "        std::unordered_set<T> r;");
+        writeln!(w, "        for (uint32_t i = 0; i < values.Length(); ++i) {{");
+        writeln!(w, "            r.insert(Convert<T>::FromJs(values.Get(i)));");
+        writeln!(w, "        }}");
+        writeln!(w, "        return r;");
+        writeln!(w, "    }}");
+        writeln!(w, "}};");
+        writeln!(w, "");
+        writeln!(w, "template <typename K, typename V>");
+        writeln!(w, "struct Convert<std::unordered_map<K, V>> {{");
+        writeln!(w, "    static Napi::Value ToJs(Napi::Env env, const std::unordered_map<K, V> & v) {{");
+        writeln!(w, "        Napi::Object r = env.Global().Get(\"Map\").As<Napi::Function>().New({{}});");
+        writeln!(w, "        Napi::Function set = r.Get(\"set\").As<Napi::Function>();");
+        writeln!(w, "        for (const auto & e : v) {{");
+        writeln!(w, "            set.Call(r, {{ Convert<K>::ToJs(env, e.first), Convert<V>::ToJs(env, e.second) }});");
+        writeln!(w, "        }}");
+        writeln!(w, "        return r;");
+        writeln!(w, "    }}");
+        writeln!(w, "    static std::unordered_map<K, V> FromJs(const Napi::Value & v) {{");
+        writeln!(w, "        const auto entries = array_from(v);");
+        writeln!(w, "        std::unordered_map<K, V> r;");
+        writeln!(w, "        for (uint32_t i = 0; i < entries.Length(); ++i) {{");
+        writeln!(w, "            const auto e = entries.Get(i).As<Napi::Array>();");
+        writeln!(w, "            r.emplace(Convert<K>::FromJs(e.Get(0u)), Convert<V>::FromJs(e.Get(1u)));");
+        writeln!(w, "        }}");
+        writeln!(w, "        return r;");
+        writeln!(w, "    }}");
+        writeln!(w, "}};");
+
+        if uses_optional {
+            let optional = &spec.config.cpp.optional_template;
+            writeln!(w, "");
+            writeln!(w, "template <typename T>");
+            writeln!(w, "struct Convert<{}<T>> {{", optional);
+            writeln!(w, "    static Napi::Value ToJs(Napi::Env env, const {}<T> & v) {{", optional);
+            writeln!(w, "        return v ? Convert<T>::ToJs(env, *v) : env.Undefined();");
+            writeln!(w, "    }}");
+            writeln!(w, "    static {}<T> FromJs(const Napi::Value & v) {{", optional);
+            writeln!(w, "        if (v.IsUndefined() || v.IsNull()) {{");
+            writeln!(w, "            return {{}};");
+            writeln!(w, "        }}");
+            writeln!(w, "        return Convert<T>::FromJs(v);");
+            writeln!(w, "    }}");
+            writeln!(w, "}};");
+        }
+    }
+
+    /// Async results are waited for on a thread of their own and settle a `Promise` back on the
+    /// JavaScript thread through a `Napi::ThreadSafeFunction`.  JavaScript promises can't be
+    /// cancelled, so nothing is propagated back to the future.
+    fn write_future_convert(&self, w: &mut Write, spec: &Spec) {
+        let future = &spec.config.cpp.future_template;
+        writeln!(w, "");
+        writeln!(w, "template <typename T>");
+        writeln!(w, "struct Convert<{}<T>> {{", future);
+        writeln!(w, "    static Napi::Value ToJs(Napi::Env env, {}<T> v) {{", future);
+        writeln!(w, "        auto deferred = std::make_shared<Napi::Promise::Deferred>(env);");
+        writeln!(w, "        auto settle = Napi::ThreadSafeFunction::New(env, Napi::Function::New(env, [](const Napi::CallbackInfo &) {{}}), \"future\", 0, 1);");
+        writeln!(w, "        auto pending = std::make_shared<{}<T>>(std::move(v));", future);
+        writeln!(w, "        std::thread([settle, deferred, pending]() mutable {{");
+        writeln!(w, "            std::shared_ptr<T> result;");
+        writeln!(w, "            std::string error;");
+        writeln!(w, "            try {{");
+        writeln!(w, "                result = std::make_shared<T>(pending->get());");
+        writeln!(w, "            }} catch (const std::exception & e) {{");
+        writeln!(w, "                error = e.what();");
+        writeln!(w, "            }} catch (...) {{");
+        writeln!(w, "                error = \"unknown error\";");
+        writeln!(w, "            }}");
+        writeln!(w, "            settle.BlockingCall([deferred, result, error](Napi::Env env, Napi::Function) {{");
+        writeln!(w, "                try {{");
+        writeln!(w, "                    if (!result) {{");
+        writeln!(w, "                        throw Napi::Error::New(env, error);");
+        writeln!(w, "                    }}");
+        writeln!(w, "                    deferred->Resolve(Convert<T>::ToJs(env, *result));");
+        writeln!(w, "                }} catch (const Napi::Error & e) {{");
+        writeln!(w, "                    deferred->Reject(e.Value());");
+        writeln!(w, "                }}");
+        writeln!(w, "            }});");
+        writeln!(w, "            settle.Release();");
+        writeln!(w, "        }}).detach();");
+        writeln!(w, "        return deferred->Promise();");
+        writeln!(w, "    }}");
+        writeln!(w, "}};");
+    }
+
+    /// Records and interfaces are only declared here, their conversions are defined once every
+    /// type they may contain has been declared.
+    fn write_convert_declaration(&self, w: &mut Write, cpp: &CppMarshaler, i: &Identifier) {
+        let ty = cpp.fq_type_name(&DataTypeStatement::Object(i.clone()));
+        writeln!(w, "");
+        writeln!(w, "template <>");
+        writeln!(w, "struct Convert<{}> {{", ty);
+        writeln!(w, "    static Napi::Value ToJs(Napi::Env env, const {} & v);", ty);
+        writeln!(w, "    static {} FromJs(const Napi::Value & v);", ty);
+        writeln!(w, "}};");
+    }
+
+    fn write_enum_convert(&self, w: &mut Write, cpp: &CppMarshaler, i: &Identifier) {
+        let ty = cpp.fq_name(&i.value);
+        writeln!(w, "");
+        writeln!(w, "template <>");
+        writeln!(w, "struct Convert<{}> {{", ty);
+        writeln!(w, "    static Napi::Value ToJs(Napi::Env env, {} v) {{", ty);
+        writeln!(w, "        return Napi::Number::New(env, static_cast<int>(v));");
+        writeln!(w, "    }}");
+        writeln!(w, "    static {} FromJs(const Napi::Value & v) {{", ty);
+        writeln!(w, "        return static_cast<{}>(v.ToNumber().Int32Value());", ty);
+        writeln!(w, "    }}");
+        writeln!(w, "}};");
+    }
+
+    fn write_wrapper_declaration(&self, w: &mut Write, m: &NodeMarshaler, cpp: &CppMarshaler, i: &Identifier, methods: &Vec<Method>) {
+        let wrapper = m.wrapper_ident(i);
+        writeln!(w, "");
+        writeln!(w, "class {} : public Napi::ObjectWrap<{}> {{", wrapper, wrapper);
+        writeln!(w, "public:");
+        writeln!(w, "    static Napi::FunctionReference constructor;");
+        writeln!(w, "");
+        writeln!(w, "    static Napi::Function Init(Napi::Env env);");
+        writeln!(w, "");
+        writeln!(w, "    {}(const Napi::CallbackInfo & info);", wrapper);
+        writeln!(w, "");
+        writeln!(w, "    std::shared_ptr<{}> ptr;", cpp.fq_name(&i.value));
+        if methods.len() > 0 {
+            writeln!(w, "");
+            writeln!(w, "private:");
+            for &(modifier, fi, _, _) in methods {
+                let stat = if *modifier == FunctionModifier::Static { "static " } else { "" };
+                writeln!(w, "    {}Napi::Value {}(const Napi::CallbackInfo & info);", stat, fi.value);
+            }
+        }
+        writeln!(w, "}};");
+        writeln!(w, "");
+        writeln!(w, "Napi::FunctionReference {}::constructor;", wrapper);
+    }
+
+    fn write_record_convert(&self, w: &mut Write, m: &NodeMarshaler, cpp: &CppMarshaler, i: &Identifier, bs: &BlockStatement) {
+        let ty = cpp.fq_name(&i.value);
+        let fields = self.fields(bs);
+        writeln!(w, "");
+        writeln!(w, "Napi::Value Convert<{}>::ToJs(Napi::Env env, const {} & v) {{", ty, ty);
+        writeln!(w, "    Napi::Object r = Napi::Object::New(env);");
+        for &(fi, dts) in &fields {
+            writeln!(w, "    r.Set(\"{}\", {});", m.field_ident(fi), self.to_js(cpp, dts, &format!("v.{}", fi.value)));
+        }
+        writeln!(w, "    return r;");
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "{} Convert<{}>::FromJs(const Napi::Value & v) {{", ty, ty);
+        writeln!(w, "    if (!v.IsObject()) {{");
+        writeln!(w, "        throw Napi::TypeError::New(v.Env(), \"expected an object for {}\");", m.type_ident(i));
+        writeln!(w, "    }}");
+        writeln!(w, "    Napi::Object o = v.As<Napi::Object>();");
+        writeln!(w, "    {} r;", ty);
+        for &(fi, dts) in &fields {
+            writeln!(w, "    r.{} = {};", fi.value, self.from_js(cpp, dts, &format!("o.Get(\"{}\")", m.field_ident(fi))));
+        }
+        writeln!(w, "    return r;");
+        writeln!(w, "}}");
+    }
+
+    fn write_interface_convert(&self, w: &mut Write, m: &NodeMarshaler, cpp: &CppMarshaler, i: &Identifier) {
+        let ty = cpp.fq_type_name(&DataTypeStatement::Object(i.clone()));
+        let wrapper = m.wrapper_ident(i);
+        writeln!(w, "");
+        writeln!(w, "Napi::Value Convert<{}>::ToJs(Napi::Env env, const {} & v) {{", ty, ty);
+        writeln!(w, "    if (!v) {{");
+        writeln!(w, "        return env.Null();");
+        writeln!(w, "    }}");
+        writeln!(w, "    {} ptr = v;", ty);
+        writeln!(w, "    return {}::constructor.New({{ Napi::External<{}>::New(env, &ptr) }});", wrapper, ty);
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "{} Convert<{}>::FromJs(const Napi::Value & v) {{", ty, ty);
+        writeln!(w, "    if (v.IsUndefined() || v.IsNull()) {{");
+        writeln!(w, "        return nullptr;");
+        writeln!(w, "    }}");
+        writeln!(w, "    if (!v.IsObject() || !v.As<Napi::Object>().InstanceOf({}::constructor.Value())) {{", wrapper);
+        writeln!(w, "        throw Napi::TypeError::New(v.Env(), \"expected a {}\");", m.type_ident(i));
+        writeln!(w, "    }}");
+        writeln!(w, "    return {}::Unwrap(v.As<Napi::Object>())->ptr;", wrapper);
+        writeln!(w, "}}");
+    }
+
+    fn write_wrapper(&self, w: &mut Write, m: &NodeMarshaler, cpp: &CppMarshaler, i: &Identifier, methods: &Vec<Method>, consts: Vec<(String, String)>) {
+        let wrapper = m.wrapper_ident(i);
+        let ty = cpp.fq_type_name(&DataTypeStatement::Object(i.clone()));
+
+        let mut properties = Vec::new();
+        for &(modifier, fi, _, _) in methods {
+            let register = if *modifier == FunctionModifier::Static { "StaticMethod" } else { "InstanceMethod" };
+            properties.push(format!("{}(\"{}\", &{}::{}),", register, m.method_ident(fi), wrapper, fi.value));
+        }
+        for (name, value) in consts {
+            properties.push(format!("StaticValue(\"{}\", {}, napi_enumerable),", name, value));
+        }
+
+        writeln!(w, "");
+        writeln!(w, "Napi::Function {}::Init(Napi::Env env) {{", wrapper);
+        if properties.len() > 0 {
+            writeln!(w, "    Napi::Function f = DefineClass(env, \"{}\", {{", m.type_ident(i));
+            for p in properties {
+                writeln!(w, "        {}", p);
+            }
+            writeln!(w, "    }});");
+        }
+        else {
+            writeln!(w, "    Napi::Function f = DefineClass(env, \"{}\", std::vector<PropertyDescriptor>());", m.type_ident(i));
+        }
+        writeln!(w, "    constructor = Napi::Persistent(f);");
+        writeln!(w, "    constructor.SuppressDestruct();");
+        writeln!(w, "    return f;");
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "{}::{}(const Napi::CallbackInfo & info) : Napi::ObjectWrap<{}>(info) {{", wrapper, wrapper, wrapper);
+        writeln!(w, "    if (info.Length() != 1 || !info[0].IsExternal()) {{");
+        writeln!(w, "        throw Napi::TypeError::New(info.Env(), \"{} can't be constructed from JavaScript\");", m.type_ident(i));
+        writeln!(w, "    }}");
+        writeln!(w, "    ptr = *info[0].As<Napi::External<{}>>().Data();", ty);
+        writeln!(w, "}}");
+
+        for &(modifier, fi, params, ret) in methods {
+            let target = if *modifier == FunctionModifier::Static { format!("{}::", cpp.fq_name(&i.value)) } else { "ptr->".to_string() };
+            let args = params.iter().enumerate()
+                .map(|(n, p)| self.from_js(cpp, &p.data_type, &format!("info[{}]", n)))
+                .collect::<Vec<_>>();
+            let call = format!("{}{}({})", target, fi.value, args.join(", "));
+
+            writeln!(w, "");
+            writeln!(w, "Napi::Value {}::{}(const Napi::CallbackInfo & info) {{", wrapper, fi.value);
+            writeln!(w, "    Napi::Env env = info.Env();");
+            writeln!(w, "    return guard(env, [&]() -> Napi::Value {{");
+            if params.len() > 0 {
+                writeln!(w, "        check_arguments(info, {});", params.len());
+            }
+            match *ret {
+                DataTypeStatement::None => {
+                    writeln!(w, "        {};", call);
+                    writeln!(w, "        return env.Undefined();");
+                },
+                _ => {
+                    writeln!(w, "        return {};", self.to_js(cpp, ret, &call));
+                }
+            }
+            writeln!(w, "    }});");
+            writeln!(w, "}}");
+        }
+    }
+
+    fn write_source(&self, w: &mut Write, spec: &Spec, program: &Program) {
+        let m = NodeMarshaler::new(spec);
+        let cpp = CppMarshaler::new(spec);
+        let uses_optional = program_uses_optional(program);
+        let uses_future = program_uses_future(program);
+        let interfaces = program.statements.iter()
+            .filter_map(|s| match s.stmtKind {
+                StatementKind::Interface(_, ref i, ref it, ref bs, _) => {
+                    // Only interfaces implemented in C++ can be called from JavaScript.
+                    let methods = if it.contains(&InterfaceType::Cpp) { self.methods(bs) } else { Vec::new() };
+                    Some((i, bs, methods))
+                },
+                _ => None
+            })
+            .collect::<Vec<_>>();
+
+        self.write_header(w);
+        for s in &program.statements {
+            match s.stmtKind {
+                StatementKind::Enum(_, ref i, _) | StatementKind::Record(_, ref i, _, _) | StatementKind::Interface(_, ref i, _, _, _) => {
+                    writeln!(w, "#include {}", cpp.header_include(&i.value));
+                },
+                _ => {}
+            }
+        }
+        let mut includes = vec!["<chrono>", "<cstdint>", "<exception>", "<memory>", "<string>", "<unordered_map>", "<unordered_set>", "<vector>",
+                                "<napi.h>"];
+        if uses_optional {
+            includes.push(spec.config.cpp.optional_header.as_str());
+        }
+        if uses_future {
+            includes.push("<thread>");
+            includes.push(spec.config.cpp.future_header.as_str());
+        }
+        for include in includes {
+            writeln!(w, "#include {}", include);
+        }
+        writeln!(w, "");
+        writeln!(w, "namespace {{");
+        writeln!(w, "");
+        self.write_convert_support(w, spec, &cpp, uses_optional);
+        if uses_future {
+            self.write_future_convert(w, spec);
+        }
+
+        for s in &program.statements {
+            match s.stmtKind {
+                StatementKind::Enum(_, ref i, _) => self.write_enum_convert(w, &cpp, i),
+                StatementKind::Record(_, ref i, _, _) | StatementKind::Interface(_, ref i, _, _, _) => {
+                    self.write_convert_declaration(w, &cpp, i);
+                },
+                _ => {}
+            }
+        }
+        for &(i, _, ref methods) in &interfaces {
+            self.write_wrapper_declaration(w, &m, &cpp, i, methods);
+        }
+        for s in &program.statements {
+            if let StatementKind::Record(_, ref i, ref bs, _) = s.stmtKind {
+                self.write_record_convert(w, &m, &cpp, i, bs);
+            }
+        }
+        for &(i, bs, ref methods) in &interfaces {
+            self.write_interface_convert(w, &m, &cpp, i);
+            self.write_wrapper(w, &m, &cpp, i, methods, self.consts(&m, &cpp, &bs.statements));
+        }
+        writeln!(w, "");
+        writeln!(w, "}} // namespace");
+        writeln!(w, "");
+
+        writeln!(w, "Napi::Object Init(Napi::Env env, Napi::Object exports) {{");
+        for s in &program.statements {
+            match s.stmtKind {
+                StatementKind::Enum(_, ref i, ref bs) => {
+                    writeln!(w, "    {{");
+                    writeln!(w, "        Napi::Object values = Napi::Object::New(env);");
+                    let members = bs.statements.iter()
+                        .filter_map(|o| match o.stmtKind { StatementKind::EnumMember(_, ref oi) => Some(oi), _ => None });
+                    for (n, oi) in members.enumerate() {
+                        writeln!(w, "        values.Set(\"{}\", Napi::Number::New(env, {}));", m.enum_member(oi), n);
+                    }
+                    writeln!(w, "        exports.Set(\"{}\", values);", m.type_ident(i));
+                    writeln!(w, "    }}");
+                },
+                StatementKind::Record(_, ref i, ref bs, _) => {
+                    // Records are plain objects, their consts are exported on an object of the same name.
+                    let consts = self.consts(&m, &cpp, &bs.statements);
+                    if consts.len() > 0 {
+                        writeln!(w, "    {{");
+                        writeln!(w, "        Napi::Object consts = Napi::Object::New(env);");
+                        for (name, value) in consts {
+                            writeln!(w, "        consts.Set(\"{}\", {});", name, value);
+                        }
+                        writeln!(w, "        exports.Set(\"{}\", consts);", m.type_ident(i));
+                        writeln!(w, "    }}");
+                    }
+                },
+                StatementKind::Interface(_, ref i, _, _, _) => {
+                    writeln!(w, "    exports.Set(\"{}\", {}::Init(env));", m.type_ident(i), m.wrapper_ident(i));
+                },
+                _ => {}
+            }
+        }
+        writeln!(w, "    return exports;");
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "NODE_API_MODULE({}, Init)", spec.config.node.module);
+    }
+
+    fn write_ts_comments(&self, w: &mut Write, statements: &Vec<Statement>) {
+        for s in statements {
+            if let StatementKind::Comment(_, ref c) = s.stmtKind {
+                writeln!(w, "    //{}", c);
+            }
+        }
+    }
+
+    fn write_declarations(&self, w: &mut Write, spec: &Spec, program: &Program) {
+        let m = NodeMarshaler::new(spec);
+
+        self.write_header(w);
+        for s in &program.statements {
+            match s.stmtKind {
+                StatementKind::Enum(_, ref i, ref bs) => {
+                    writeln!(w, "");
+                    writeln!(w, "export declare enum {} {{", m.type_ident(i));
+                    let mut n = 0;
+                    for o in &bs.statements {
+                        match o.stmtKind {
+                            StatementKind::EnumMember(_, ref oi) => {
+                                writeln!(w, "    {} = {},", m.enum_member(oi), n);
+                                n += 1;
+                            },
+                            StatementKind::Comment(_, ref c) => {
+                                writeln!(w, "    //{}", c);
+                            },
+                            _ => {}
+                        }
+                    }
+                    writeln!(w, "}}");
+                },
+                StatementKind::Record(_, ref i, ref bs, _) => {
+                    writeln!(w, "");
+                    writeln!(w, "export interface {} {{", m.type_ident(i));
+                    self.write_ts_comments(w, &bs.statements);
+                    for (fi, dts) in self.fields(bs) {
+                        writeln!(w, "    {}: {};", m.field_ident(fi), m.ts_type_name(dts));
+                    }
+                    writeln!(w, "}}");
+                    let consts = self.ts_consts(&m, &bs.statements);
+                    if consts.len() > 0 {
+                        writeln!(w, "export declare const {}: {{", m.type_ident(i));
+                        for c in consts {
+                            writeln!(w, "    {}", c);
+                        }
+                        writeln!(w, "}};");
+                    }
+                },
+                StatementKind::Interface(_, ref i, ref it, ref bs, _) => {
+                    writeln!(w, "");
+                    writeln!(w, "export declare class {} {{", m.type_ident(i));
+                    self.write_ts_comments(w, &bs.statements);
+                    writeln!(w, "    private constructor();");
+                    for c in self.ts_consts(&m, &bs.statements) {
+                        writeln!(w, "    static {}", c);
+                    }
+                    if it.contains(&InterfaceType::Cpp) {
+                        for (modifier, fi, params, ret) in self.methods(bs) {
+                            let stat = if *modifier == FunctionModifier::Static { "static " } else { "" };
+                            writeln!(w, "    {}{}({}): {};", stat, m.method_ident(fi), self.ts_params(&m, params), m.ts_type_name(ret));
+                        }
+                    }
+                    writeln!(w, "}}");
+                },
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Generate for NodeGenerator {
+    // Everything is written by `finish`, the addon registers the whole program in one place.
+    fn write_enum(&self, _e: &StatementKind, _spec: &Spec, _output: &mut GeneratedOutput) {
+    }
+
+    fn write_record(&self, _r: &StatementKind, _spec: &Spec, _output: &mut GeneratedOutput) {
+    }
+
+    fn write_interface(&self, _i: &StatementKind, _spec: &Spec, _output: &mut GeneratedOutput) {
+    }
+
+    fn finish(&self, spec: &Spec, program: &Program, output: &mut GeneratedOutput) {
+        let mut w = Vec::new();
+        self.write_source(&mut w, spec, program);
+        output.add_file(self.make_path(spec.config.node.out.as_ref().map_or("", |s| s.as_str()), &spec.config.node.module, &spec.config.cpp.ext), w);
+
+        let mut w = Vec::new();
+        self.write_declarations(&mut w, spec, program);
+        output.add_file(self.make_path(spec.config.node.ts_out().map_or("", |s| s.as_str()), &"index".to_string(), "d.ts"), w);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::Config;
+    use generator::testing;
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.cpp.namespace = "demo".into();
+        config.node.out = Some("node".into());
+
+        config
+    }
+
+    const INPUT: &'static str = "color = enum { red; dark_blue; }\n\
+                                 key = record { id: i64; tags: list<color>; data: binary; seen: optional<date>; const max_id: i64 = 99; }\n\
+                                 key_store = interface +c { static create(): key_store; lookup(id: i64): key; clear(); const limit: i32 = 10; }\n";
+
+    #[test]
+    fn test_write_addon() {
+        let output = testing::compile(config(), INPUT);
+        let source = &output.get("node/lamp.cpp").unwrap().contents;
+        assert!(source.contains("#include <napi.h>"), "{}", source);
+        assert!(source.contains("struct Convert<std::vector<uint8_t>> {"), "{}", source);
+        assert!(source.contains("r.Set(\"seen\", Convert<std::optional<std::chrono::system_clock::time_point>>::ToJs(env, v.seen));"), "{}", source);
+        assert!(source.contains("class KeyStoreNode : public Napi::ObjectWrap<KeyStoreNode> {"), "{}", source);
+        assert!(source.contains("StaticMethod(\"create\", &KeyStoreNode::create),"), "{}", source);
+        assert!(source.contains("return Convert<::demo::key>::ToJs(env, ptr->lookup(Convert<int64_t>::FromJs(info[0])));"), "{}", source);
+        assert!(source.contains("values.Set(\"DARK_BLUE\", Napi::Number::New(env, 1));"), "{}", source);
+        assert!(source.contains("NODE_API_MODULE(lamp, Init)"), "{}", source);
+    }
+
+    #[test]
+    fn test_write_declarations() {
+        let output = testing::compile(config(), INPUT);
+        let dts = &output.get("node/index.d.ts").unwrap().contents;
+        assert!(dts.contains("export declare enum Color {\n    RED = 0,\n    DARK_BLUE = 1,\n}"), "{}", dts);
+        assert!(dts.contains("export interface Key {\n    id: bigint;\n    tags: Color[];\n    data: Buffer;\n    seen: Date | undefined;\n}"), "{}", dts);
+        assert!(dts.contains("export declare const Key: {\n    readonly MAX_ID: bigint;\n};"), "{}", dts);
+        assert!(dts.contains("    static create(): KeyStore;\n    lookup(id: bigint): Key;\n    clear(): void;\n"), "{}", dts);
+    }
+
+    #[test]
+    fn test_write_async() {
+        let output = testing::compile(config(), "key = record { id: i64; }\n\
+                                                 key_store = interface +c { fetch(id: i64): future<key>; }\n");
+
+        let source = &output.get("node/lamp.cpp").unwrap().contents;
+        assert!(source.contains("#include <future>"), "{}", source);
+        assert!(source.contains("struct Convert<std::future<T>> {"), "{}", source);
+        assert!(source.contains("return Convert<std::future<::demo::key>>::ToJs(env, ptr->fetch(Convert<int64_t>::FromJs(info[0])));"), "{}", source);
+
+        let dts = &output.get("node/index.d.ts").unwrap().contents;
+        assert!(dts.contains("    fetch(id: bigint): Promise<Key>;\n"), "{}", dts);
+    }
+}
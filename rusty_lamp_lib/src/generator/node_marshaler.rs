@@ -0,0 +1,83 @@
+/*
+ * Copyright © 2002-2017 Bluebeam Software, Inc. All Rights Reserved.
+ * Creator: Jonathan Rothberg
+ */
+
+use parser::ast::{DataTypeStatement, Identifier};
+use generator::spec::Spec;
+use generator::typer::{TypeDefinitionKind, UserObjectKind};
+
+pub struct NodeMarshaler<'a> {
+    spec: &'a Spec
+}
+
+impl<'a> NodeMarshaler<'a> {
+    pub fn new(spec: &'a Spec) -> NodeMarshaler<'a> {
+        NodeMarshaler {
+            spec: spec
+        }
+    }
+
+    pub fn type_ident(&self, i: &Identifier) -> String {
+        (self.spec.node_ident_style.ty)(i.value.clone())
+    }
+
+    pub fn method_ident(&self, i: &Identifier) -> String {
+        (self.spec.node_ident_style.method)(i.value.clone())
+    }
+
+    pub fn field_ident(&self, i: &Identifier) -> String {
+        (self.spec.node_ident_style.field)(i.value.clone())
+    }
+
+    pub fn local_ident(&self, i: &Identifier) -> String {
+        (self.spec.node_ident_style.local)(i.value.clone())
+    }
+
+    pub fn enum_member(&self, i: &Identifier) -> String {
+        (self.spec.node_ident_style.enm)(i.value.clone())
+    }
+
+    pub fn const_ident(&self, i: &Identifier) -> String {
+        (self.spec.node_ident_style.cnst)(i.value.clone())
+    }
+
+    /// The `Napi::ObjectWrap` class exposing an interface to JavaScript.
+    pub fn wrapper_ident(&self, i: &Identifier) -> String {
+        format!("{}Node", self.type_ident(i))
+    }
+
+    /// The TypeScript spelling of `dts` in `index.d.ts`.  Interfaces come back as `null` when
+    /// empty, every other optional as `undefined`, and async results are `Promise`s.
+    pub fn ts_type_name(&self, dts: &DataTypeStatement) -> String {
+        match *dts {
+            DataTypeStatement::None => "void".into(),
+            DataTypeStatement::List(ref t) => match **t {
+                DataTypeStatement::Optional(..) => format!("({})[]", self.ts_type_name(t)),
+                _ => format!("{}[]", self.ts_type_name(t))
+            },
+            DataTypeStatement::Array(ref t) => format!("{}[]", self.ts_type_name(t)),
+            DataTypeStatement::Set(ref t) => format!("Set<{}>", self.ts_type_name(t)),
+            DataTypeStatement::Map(ref k, ref v) => format!("Map<{}, {}>", self.ts_type_name(k), self.ts_type_name(v)),
+            DataTypeStatement::Optional(ref t) => match **t {
+                DataTypeStatement::Object(ref i) if self.is_interface(i) => format!("{} | null", self.ts_type_name(t)),
+                _ => format!("{} | undefined", self.ts_type_name(t))
+            },
+            DataTypeStatement::Future(ref t) => format!("Promise<{}>", self.ts_type_name(t)),
+            DataTypeStatement::Object(ref i) => self.type_ident(i),
+            DataTypeStatement::Integer64 => "bigint".into(),
+            DataTypeStatement::Bool => "boolean".into(),
+            DataTypeStatement::String => "string".into(),
+            DataTypeStatement::Binary => "Buffer".into(),
+            DataTypeStatement::Date => "Date".into(),
+            _ => "number".into()
+        }
+    }
+
+    pub fn is_interface(&self, i: &Identifier) -> bool {
+        match self.spec.typer.get(&i.value) {
+            TypeDefinitionKind::UserObject(_, UserObjectKind::Interface(..)) => true,
+            _ => false
+        }
+    }
+}
@@ -3,40 +3,758 @@
  * Creator: Jonathan Rothberg
  */
 
-use std::sync::Arc;
-use std::io::{Write, BufWriter};
-use std::fs::{File};
-use parser::ast::{StatementKind};
+use std::io::{Write};
+use std::collections::{BTreeSet};
+use parser::ast::{Annotation, Statement, StatementKind, DataTypeStatement, DeriveType, FunctionModifier, Identifier, Parameter};
 use generator::generator::{ Generate };
-use parser::program::Program;
 use generator::spec::Spec;
+use generator::output::GeneratedOutput;
+use generator::objc_marshaler::ObjcMarshaler;
+use generator::resolver::program_errors;
+use parser::program::Program;
+use generator::typer::UserObjectKind;
 
 pub struct ObjcGenerator {
-    
+
+}
+
+struct ObjcRefs {
+    imports: BTreeSet<String>,
+    fwds: BTreeSet<String>,
+    impl_imports: BTreeSet<String>
+}
+
+impl ObjcRefs {
+    fn new() -> ObjcRefs {
+        ObjcRefs {
+            imports: BTreeSet::new(),
+            fwds: BTreeSet::new(),
+            impl_imports: BTreeSet::new()
+        }
+    }
+
+    // Enums are imported since their size is needed, everything else is forward declared
+    // in the header so that types can refer to each other.
+    fn add(&mut self, marshaler: &ObjcMarshaler, self_name: &Identifier, dts: &DataTypeStatement) {
+        for i in marshaler.references(dts) {
+            if i.value == self_name.value {
+                continue;
+            }
+
+            let import = marshaler.header_import(&i);
+            match marshaler.user_object_kind(&i) {
+                Some(UserObjectKind::Enum) => {
+                    self.imports.insert(import);
+                },
+                Some(..) if marshaler.is_protocol(&i) => {
+                    self.fwds.insert(format!("@protocol {};", marshaler.type_ident(&i)));
+                    self.impl_imports.insert(import);
+                },
+                Some(..) => {
+                    self.fwds.insert(format!("@class {};", marshaler.type_ident(&i)));
+                    self.impl_imports.insert(import);
+                },
+                None => {}
+            }
+        }
+    }
 }
 
 impl ObjcGenerator {
     pub fn new() -> ObjcGenerator {
         ObjcGenerator {
-            
+
+        }
+    }
+
+    fn write_header(&self, w: &mut Write) {
+        writeln!(w, "// AUTOGENERATED FILE - DO NOT MODIFY!");
+        writeln!(w, "// This file was generated by rusty_lamp");
+        writeln!(w, "");
+    }
+
+    fn write_refs(&self, w: &mut Write, refs: &ObjcRefs) {
+        writeln!(w, "#import <Foundation/Foundation.h>");
+        for i in &refs.imports {
+            writeln!(w, "{}", i);
+        }
+        if refs.fwds.len() > 0 {
+            writeln!(w, "");
+            for f in &refs.fwds {
+                writeln!(w, "{}", f);
+            }
+        }
+        writeln!(w, "");
+    }
+
+    fn add_file(&self, spec: &Spec, i: &Identifier, ext: &str, w: Vec<u8>, output: &mut GeneratedOutput) {
+        let marshaler = ObjcMarshaler::new(spec);
+        output.add_file(self.make_path(spec.config.objc.out.as_ref().map_or("", |s| s.as_str()), &marshaler.file_name(i), ext), w);
+    }
+
+    fn consts<'b>(&self, statements: &'b Vec<Statement>) -> Vec<(&'b Identifier, &'b DataTypeStatement, &'b StatementKind)> {
+        statements.iter()
+            .filter_map(|s| match s.stmtKind {
+                StatementKind::Const(_, ref i, ref dts, ref v) => Some((i, dts, &v.stmtKind)),
+                _ => None
+            })
+            .collect()
+    }
+
+    fn const_value(&self, dts: &DataTypeStatement, value: &StatementKind) -> Option<String> {
+        match (dts, value) {
+            (_, &StatementKind::StringLiteral(_, ref s)) => Some(format!("@{:?}", s)),
+            (_, &StatementKind::Boolean(_, b)) => Some(if b { "YES".into() } else { "NO".into() }),
+            (_, &StatementKind::NumberLiteral(_, ref n)) => Some(n.clone()),
+            _ => None
+        }
+    }
+
+    fn write_const_declarations(&self, w: &mut Write, marshaler: &ObjcMarshaler, ty: &Identifier, statements: &Vec<Statement>) {
+        let consts = self.consts(statements);
+        if consts.len() == 0 {
+            return;
+        }
+
+        writeln!(w, "");
+        for (i, dts, v) in consts {
+            if self.const_value(dts, v).is_none() {
+                writeln!(w, "// const {}: {} is not representable as an Objective-C constant", i.value, dts);
+                continue;
+            }
+            writeln!(w, "extern {} const {};", marshaler.type_name(dts), marshaler.const_name(ty, i));
+        }
+    }
+
+    fn write_const_definitions(&self, w: &mut Write, marshaler: &ObjcMarshaler, ty: &Identifier, statements: &Vec<Statement>) {
+        for (i, dts, v) in self.consts(statements) {
+            if let Some(value) = self.const_value(dts, v) {
+                writeln!(w, "{} const {} = {};", marshaler.type_name(dts), marshaler.const_name(ty, i), value);
+            }
+        }
+    }
+
+    /// `lookup:(int64_t)id fallback:(nonnull NSString *)fallback`
+    fn selector(&self, marshaler: &ObjcMarshaler, first: String, params: &Vec<Parameter>) -> String {
+        if params.len() == 0 {
+            return first;
+        }
+
+        params.iter()
+            .enumerate()
+            .map(|(n, p)| {
+                let label = if n == 0 { first.clone() } else { marshaler.local_ident(&p.ident) };
+                format!("{}:({}){}", label, marshaler.annotated_type_for(&p.data_type, &p.annotations), marshaler.local_ident(&p.ident))
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// `id:fallback:`
+    fn swift_labels(&self, spec: &Spec, params: &Vec<Parameter>) -> String {
+        params.iter()
+            .map(|p| format!("{}:", (spec.swift_ident_style.local)(p.ident.value.clone())))
+            .collect::<Vec<_>>()
+            .join("")
+    }
+
+    /// `NS_SWIFT_NAME(lookup(id:fallback:))`
+    fn swift_selector(&self, spec: &Spec, name: String, params: &Vec<Parameter>) -> String {
+        format!("NS_SWIFT_NAME({}({}))", name, self.swift_labels(spec, params))
+    }
+
+    /// The doc comment for `@since`, which has no Objective-C attribute.
+    fn write_since(&self, w: &mut Write, since: Option<&Annotation>) {
+        if let Some(v) = since.and_then(|a| a.value.as_ref()) {
+            writeln!(w, "/** @since {} */", v);
+        }
+    }
+
+    /// ` __attribute__((deprecated))` at the end of a declaration, or nothing.
+    fn deprecated_suffix(&self, marshaler: &ObjcMarshaler, deprecated: Option<&Annotation>) -> String {
+        marshaler.deprecated(deprecated).map_or("".into(), |d| format!(" {}", d))
+    }
+
+    fn record_fields<'b>(&self, statements: &'b Vec<Statement>) -> Vec<(&'b Identifier, &'b DataTypeStatement)> {
+        statements.iter()
+            .filter_map(|s| match s.stmtKind { StatementKind::RecordMember(_, ref i, ref dts, _) => Some((i, dts)), _ => None })
+            .collect()
+    }
+
+    /// Optional fields hold boxed values, so their defaults are boxed too.
+    fn default_value(&self, dts: &DataTypeStatement, value: &StatementKind) -> String {
+        match (dts, value) {
+            (&DataTypeStatement::Optional(ref t), &StatementKind::StringLiteral(..)) => self.default_value(t, value),
+            (&DataTypeStatement::Optional(ref t), _) => format!("@({})", self.default_value(t, value)),
+            _ => self.const_value(dts, value).unwrap_or_else(|| value.to_string())
+        }
+    }
+
+    /// The initializer taking only the fields without a default, and the call to the designated
+    /// initializer it makes, or `None` when no field has a default.
+    fn convenience_init(&self, marshaler: &ObjcMarshaler, statements: &Vec<Statement>) -> Option<(String, Vec<Parameter>, String)> {
+        let fields = statements.iter()
+            .filter_map(|s| match s.stmtKind {
+                StatementKind::RecordMember(_, ref i, ref dts, ref default) => Some((s, i, dts, default)),
+                _ => None
+            })
+            .collect::<Vec<_>>();
+        if !fields.iter().any(|&(_, _, _, default)| default.is_some()) {
+            return None;
+        }
+
+        let params = fields.iter()
+            .filter(|&&(_, _, _, default)| default.is_none())
+            .map(|&(s, i, dts, _)| Parameter { ident: i.clone(), data_type: dts.clone(), annotations: s.annotations.clone() })
+            .collect::<Vec<_>>();
+        let name = match params.first() {
+            Some(p) => format!("initWith{}", ::generator::ident_style::IdentStyle::first_upper(marshaler.local_ident(&p.ident))),
+            None => "init".into()
+        };
+        let call = fields.iter()
+            .enumerate()
+            .map(|(n, &(_, i, dts, default))| {
+                let label = if n == 0 {
+                    format!("initWith{}", ::generator::ident_style::IdentStyle::first_upper(marshaler.local_ident(i)))
+                } else {
+                    marshaler.local_ident(i)
+                };
+                let value = match *default {
+                    Some(ref v) => self.default_value(dts, &v.stmtKind),
+                    None => marshaler.local_ident(i)
+                };
+                format!("{}:{}", label, value)
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Some((name, params, format!("[self {}]", call)))
+    }
+
+    // The fields' annotations carry over, so `@nonnull` fields take nonnull arguments.
+    fn init_params(&self, statements: &Vec<Statement>) -> Vec<Parameter> {
+        statements.iter()
+            .filter_map(|s| match s.stmtKind {
+                StatementKind::RecordMember(_, ref i, ref dts, _) => {
+                    Some(Parameter { ident: i.clone(), data_type: dts.clone(), annotations: s.annotations.clone() })
+                },
+                _ => None
+            })
+            .collect()
+    }
+
+    fn write_record_implementation(&self, w: &mut Write, marshaler: &ObjcMarshaler, i: &Identifier,
+                                   statements: &Vec<Statement>, derives: &Vec<DeriveType>, refs: &ObjcRefs) {
+        let name = marshaler.type_ident(i);
+        let fields = self.record_fields(statements);
+
+        self.write_header(w);
+        writeln!(w, "{}", marshaler.header_import(i));
+        for import in &refs.impl_imports {
+            writeln!(w, "{}", import);
+        }
+        writeln!(w, "");
+
+        if self.consts(statements).len() > 0 {
+            self.write_const_definitions(w, marshaler, i, statements);
+            writeln!(w, "");
+        }
+
+        writeln!(w, "@implementation {}", name);
+        writeln!(w, "");
+        if fields.len() > 0 {
+            let init = format!("initWith{}", ::generator::ident_style::IdentStyle::first_upper(marshaler.local_ident(fields[0].0)));
+            writeln!(w, "- (nonnull instancetype){}", self.selector(marshaler, init, &self.init_params(statements)));
+            writeln!(w, "{{");
+            writeln!(w, "    if ((self = [super init])) {{");
+            for &(fi, dts) in &fields {
+                let local = marshaler.local_ident(fi);
+                let value = if self.copies(dts) { format!("[{} copy]", local) } else { local };
+                writeln!(w, "        _{} = {};", marshaler.field_ident(fi), value);
+            }
+            writeln!(w, "    }}");
+            writeln!(w, "    return self;");
+            writeln!(w, "}}");
+            writeln!(w, "");
+
+            if let Some((init, params, call)) = self.convenience_init(marshaler, statements) {
+                writeln!(w, "- (nonnull instancetype){}", self.selector(marshaler, init, &params));
+                writeln!(w, "{{");
+                writeln!(w, "    return {};", call);
+                writeln!(w, "}}");
+                writeln!(w, "");
+            }
+        }
+
+        if derives.contains(&DeriveType::Eq) || derives.contains(&DeriveType::Ord) {
+            writeln!(w, "- (BOOL)isEqual:(id)other");
+            writeln!(w, "{{");
+            writeln!(w, "    if (![other isKindOfClass:[{} class]]) {{", name);
+            writeln!(w, "        return NO;");
+            writeln!(w, "    }}");
+            writeln!(w, "    {} *typedOther = ({} *)other;", name, name);
+            let checks = fields.iter()
+                .map(|&(fi, dts)| self.equality(marshaler, dts, &format!("self.{}", marshaler.field_ident(fi)), &format!("typedOther.{}", marshaler.field_ident(fi))))
+                .collect::<Vec<_>>();
+            if checks.len() == 0 {
+                writeln!(w, "    return YES;");
+            }
+            else {
+                writeln!(w, "    return {};", checks.join(" &&\n            "));
+            }
+            writeln!(w, "}}");
+            writeln!(w, "");
+
+            writeln!(w, "- (NSUInteger)hash");
+            writeln!(w, "{{");
+            let hashes = fields.iter()
+                .map(|&(fi, dts)| {
+                    let field = format!("self.{}", marshaler.field_ident(fi));
+                    if marshaler.is_object(dts) { format!("{}.hash", field) } else { format!("(NSUInteger){}", field) }
+                })
+                .collect::<Vec<_>>();
+            writeln!(w, "    return NSStringFromClass([self class]).hash{};", hashes.iter().map(|h| format!(" ^\n            {}", h)).collect::<String>());
+            writeln!(w, "}}");
+            writeln!(w, "");
+        }
+
+        if derives.contains(&DeriveType::Ord) {
+            writeln!(w, "- (NSComparisonResult)compare:(nonnull {} *)other", name);
+            writeln!(w, "{{");
+            writeln!(w, "    NSComparisonResult tempResult;");
+            for &(fi, dts) in &fields {
+                let field = marshaler.field_ident(fi);
+                match self.comparison(marshaler, dts, &format!("self.{}", field), &format!("other.{}", field)) {
+                    Some(c) => {
+                        writeln!(w, "    tempResult = {};", c);
+                        writeln!(w, "    if (tempResult != NSOrderedSame) {{");
+                        writeln!(w, "        return tempResult;");
+                        writeln!(w, "    }}");
+                    },
+                    None => {
+                        writeln!(w, "    // {} is not comparable", fi.value);
+                    }
+                }
+            }
+            writeln!(w, "    return NSOrderedSame;");
+            writeln!(w, "}}");
+            writeln!(w, "");
+        }
+
+        let format = fields.iter()
+            .map(|&(fi, _)| format!(" {}:%@", marshaler.field_ident(fi)))
+            .collect::<String>();
+        let args = fields.iter()
+            .map(|&(fi, dts)| {
+                let field = format!("self.{}", marshaler.field_ident(fi));
+                if marshaler.is_object(dts) { format!(", {}", field) } else { format!(", @({})", field) }
+            })
+            .collect::<String>();
+        writeln!(w, "- (NSString *)description");
+        writeln!(w, "{{");
+        writeln!(w, "    return [NSString stringWithFormat:@\"<%@ %p{}>\", self.class, (void *)self{}];", format, args);
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "@end");
+    }
+
+    // Immutable values are copied in so a mutable subclass passed by the caller can't change them.
+    fn copies(&self, dts: &DataTypeStatement) -> bool {
+        match *dts {
+            DataTypeStatement::Optional(ref t) => self.copies(t),
+            DataTypeStatement::String | DataTypeStatement::Binary | DataTypeStatement::Array(..) | DataTypeStatement::Date |
+            DataTypeStatement::List(..) | DataTypeStatement::Set(..) | DataTypeStatement::Map(..) => true,
+            _ => false
+        }
+    }
+
+    fn equality(&self, marshaler: &ObjcMarshaler, dts: &DataTypeStatement, a: &str, b: &str) -> String {
+        let method = match *dts {
+            DataTypeStatement::Optional(..) => return format!("(({} == nil && {} == nil) || [{} isEqual:{}])", a, b, a, b),
+            DataTypeStatement::String => "isEqualToString",
+            DataTypeStatement::Binary | DataTypeStatement::Array(..) => "isEqualToData",
+            DataTypeStatement::Date => "isEqualToDate",
+            DataTypeStatement::List(..) => "isEqualToArray",
+            DataTypeStatement::Set(..) => "isEqualToSet",
+            DataTypeStatement::Map(..) => "isEqualToDictionary",
+            DataTypeStatement::Object(ref i) if !marshaler.is_interface(i) && marshaler.is_object(dts) => "isEqual",
+            _ => return format!("{} == {}", a, b)
+        };
+        format!("[{} {}:{}]", a, method, b)
+    }
+
+    fn comparison(&self, marshaler: &ObjcMarshaler, dts: &DataTypeStatement, a: &str, b: &str) -> Option<String> {
+        match *dts {
+            DataTypeStatement::String | DataTypeStatement::Date => Some(format!("[{} compare:{}]", a, b)),
+            DataTypeStatement::Object(ref i) => {
+                match marshaler.user_object_kind(i) {
+                    Some(UserObjectKind::Enum) => Some(format!("{} < {} ? NSOrderedAscending : {} > {} ? NSOrderedDescending : NSOrderedSame", a, b, a, b)),
+                    Some(UserObjectKind::Record(..)) => Some(format!("[{} compare:{}]", a, b)),
+                    _ => None
+                }
+            },
+            DataTypeStatement::Binary | DataTypeStatement::Array(..) | DataTypeStatement::List(..) | DataTypeStatement::Set(..) |
+            DataTypeStatement::Map(..) | DataTypeStatement::Optional(..) => None,
+            _ => Some(format!("{} < {} ? NSOrderedAscending : {} > {} ? NSOrderedDescending : NSOrderedSame", a, b, a, b))
         }
     }
 }
 
 impl Generate for ObjcGenerator {
-    // fn generate(&self, program: &Program) {
-    //     println!("Generating Objective-C files...");
-    // }
+    fn write_enum(&self, e: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
+        if let StatementKind::Enum(_, ref i, ref b) = *e {
+            let marshaler = ObjcMarshaler::new(spec);
+            let mut w = Vec::new();
+            self.write_header(&mut w);
+
+            writeln!(w, "#import <Foundation/Foundation.h>");
+            writeln!(w, "");
+            self.write_since(&mut w, spec.typer.get_annotation(&i.value, "since"));
+            writeln!(w, "typedef NS_ENUM(NSInteger, {})", marshaler.type_ident(i));
+            writeln!(w, "{{");
+            for o in &b.statements {
+                match o.stmtKind {
+                    StatementKind::EnumMember(_, ref oi) => {
+                        writeln!(w, "    {}{},", marshaler.enum_member(i, oi), self.deprecated_suffix(&marshaler, o.annotation("deprecated")));
+                    },
+                    StatementKind::Comment(_, ref c) => {
+                        writeln!(w, "    //{}", c);
+                    },
+                    _ => {}
+                }
+            }
+            writeln!(w, "}} NS_SWIFT_NAME({}){};", marshaler.swift_name(i), self.deprecated_suffix(&marshaler, spec.typer.get_annotation(&i.value, "deprecated")));
+
+            self.add_file(spec, i, &spec.config.objc.h_ext, w, output);
+        }
+    }
+
+    fn write_record(&self, r: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
+        if let StatementKind::Record(_, ref i, ref bs, ref dt) = *r {
+            let marshaler = ObjcMarshaler::new(spec);
+            let name = marshaler.type_ident(i);
+            let fields = self.record_fields(&bs.statements);
+
+            let mut refs = ObjcRefs::new();
+            for &(_, dts) in &fields {
+                refs.add(&marshaler, i, dts);
+            }
+
+            let mut w = Vec::new();
+            self.write_header(&mut w);
+            self.write_refs(&mut w, &refs);
+            writeln!(w, "NS_ASSUME_NONNULL_BEGIN");
+            writeln!(w, "");
+            self.write_since(&mut w, spec.typer.get_annotation(&i.value, "since"));
+            writeln!(w, "NS_SWIFT_NAME({}){}", marshaler.swift_name(i), self.deprecated_suffix(&marshaler, spec.typer.get_annotation(&i.value, "deprecated")));
+            writeln!(w, "@interface {} : NSObject", name);
+            writeln!(w, "");
+            if fields.len() > 0 {
+                let init = format!("initWith{}", ::generator::ident_style::IdentStyle::first_upper(marshaler.local_ident(fields[0].0)));
+                let params = self.init_params(&bs.statements);
+                writeln!(w, "- (nonnull instancetype){}", self.selector(&marshaler, init, &params));
+                writeln!(w, "    {};", self.swift_selector(spec, "init".into(), &params));
+                match self.convenience_init(&marshaler, &bs.statements) {
+                    Some((ref init, ref params, _)) if params.len() > 0 => {
+                        writeln!(w, "- (nonnull instancetype){}", self.selector(&marshaler, init.clone(), params));
+                        writeln!(w, "    {};", self.swift_selector(spec, "init".into(), params));
+                        writeln!(w, "- (nonnull instancetype)init NS_UNAVAILABLE;");
+                        writeln!(w, "+ (nonnull instancetype)new NS_UNAVAILABLE;");
+                    },
+                    Some(_) => {
+                        writeln!(w, "- (nonnull instancetype)init;");
+                    },
+                    None => {
+                        writeln!(w, "- (nonnull instancetype)init NS_UNAVAILABLE;");
+                        writeln!(w, "+ (nonnull instancetype)new NS_UNAVAILABLE;");
+                    }
+                }
+                writeln!(w, "");
+            }
+            for f in &bs.statements {
+                match f.stmtKind {
+                    StatementKind::RecordMember(_, ref fi, ref dts, _) => {
+                        let attributes = match marshaler.annotated_nullability(dts, &f.annotations) {
+                            Some(n) => format!("nonatomic, readonly, {}", n),
+                            None => "nonatomic, readonly".into()
+                        };
+                        let field = marshaler.field_ident(fi);
+                        let swift_field = (spec.swift_ident_style.field)(fi.value.clone());
+                        let swift_name = if swift_field != field { format!(" NS_SWIFT_NAME({})", swift_field) } else { "".into() };
+                        let ty = marshaler.type_name(dts);
+                        let separator = if ty.ends_with('*') { "" } else { " " };
+                        self.write_since(&mut w, f.annotation("since"));
+                        writeln!(w, "@property ({}) {}{}{}{}{};", attributes, ty, separator, field, swift_name,
+                                 self.deprecated_suffix(&marshaler, f.annotation("deprecated")));
+                    },
+                    StatementKind::Comment(_, ref c) => {
+                        writeln!(w, "//{}", c);
+                    },
+                    _ => {}
+                }
+            }
+            if dt.contains(&DeriveType::Ord) {
+                writeln!(w, "");
+                writeln!(w, "- (NSComparisonResult)compare:(nonnull {} *)other;", name);
+            }
+            writeln!(w, "");
+            writeln!(w, "@end");
+            self.write_const_declarations(&mut w, &marshaler, i, &bs.statements);
+            writeln!(w, "");
+            writeln!(w, "NS_ASSUME_NONNULL_END");
+            self.add_file(spec, i, &spec.config.objc.h_ext, w, output);
+
+            let mut w = Vec::new();
+            self.write_record_implementation(&mut w, &marshaler, i, &bs.statements, dt, &refs);
+            self.add_file(spec, i, "m", w, output);
+        }
+    }
+
+    fn write_interface(&self, i: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
+        if let StatementKind::Interface(_, ref id, _, ref bs, _) = *i {
+            let marshaler = ObjcMarshaler::new(spec);
+            let name = marshaler.type_ident(id);
 
-    fn write_enum(&self, e: &StatementKind, spec: &Spec) {
-        
+            let mut refs = ObjcRefs::new();
+            for f in &bs.statements {
+                if let StatementKind::Function(_, _, _, ref params, ref ret, ref throws) = f.stmtKind {
+                    if throws.is_some() {
+                        refs.imports.insert(marshaler.header_import(&marshaler.errors_ident()));
+                    }
+                    refs.add(&marshaler, id, ret);
+                    for p in params {
+                        refs.add(&marshaler, id, &p.data_type);
+                    }
+                }
+            }
+
+            let mut w = Vec::new();
+            self.write_header(&mut w);
+            self.write_refs(&mut w, &refs);
+            writeln!(w, "NS_ASSUME_NONNULL_BEGIN");
+            writeln!(w, "");
+            self.write_since(&mut w, spec.typer.get_annotation(&id.value, "since"));
+            writeln!(w, "NS_SWIFT_NAME({}){}", marshaler.swift_name(id), self.deprecated_suffix(&marshaler, spec.typer.get_annotation(&id.value, "deprecated")));
+            if marshaler.is_protocol(id) {
+                writeln!(w, "@protocol {} <NSObject>", name);
+            }
+            else {
+                writeln!(w, "@interface {} : NSObject", name);
+            }
+            writeln!(w, "");
+            for f in &bs.statements {
+                match f.stmtKind {
+                    StatementKind::Function(_, ref m, ref fi, ref params, ref ret, ref throws) => {
+                        let kind = if *m == FunctionModifier::Static { "+" } else { "-" };
+                        let swift_name = (spec.swift_ident_style.method)(fi.value.clone());
+                        let deprecated = self.deprecated_suffix(&marshaler, f.annotation("deprecated"));
+                        self.write_since(&mut w, f.annotation("since"));
+                        match *ret {
+                            // Async methods take a trailing completion handler, which Swift imports as `async throws`.
+                            DataTypeStatement::Future(ref t) => {
+                                let completion = format!("({})completion", marshaler.completion_type(t));
+                                let selector = if params.len() > 0 {
+                                    format!("{} completion:{}", self.selector(&marshaler, marshaler.method_ident(fi), params), completion)
+                                }
+                                else {
+                                    format!("{}WithCompletion:{}", marshaler.method_ident(fi), completion)
+                                };
+                                writeln!(w, "{} (void){}", kind, selector);
+                                writeln!(w, "    NS_SWIFT_NAME({}({}completion:)){};", swift_name, self.swift_labels(spec, params), deprecated);
+                            },
+                            // Errors come back through an `NSError **`, which Swift imports as `throws` when the
+                            // result is a BOOL or an object that is nil on failure.
+                            _ if throws.is_some() => {
+                                let (result, error_label) = match *ret {
+                                    DataTypeStatement::None => ("BOOL".to_string(), ""),
+                                    _ if marshaler.is_object(ret) => (format!("nullable {}", marshaler.type_name(ret)), ""),
+                                    _ => (marshaler.type_name(ret), "error:")
+                                };
+                                let error = "(NSError * _Nullable * _Nullable)error";
+                                let selector = if params.len() > 0 {
+                                    format!("{} error:{}", self.selector(&marshaler, marshaler.method_ident(fi), params), error)
+                                }
+                                else {
+                                    format!("{}AndReturnError:{}", marshaler.method_ident(fi), error)
+                                };
+                                writeln!(w, "{} ({}){}", kind, result, selector);
+                                writeln!(w, "    NS_SWIFT_NAME({}({}{})){};", swift_name, self.swift_labels(spec, params), error_label, deprecated);
+                            },
+                            _ => {
+                                writeln!(w, "{} ({}){}", kind, marshaler.annotated_type_for(ret, &f.annotations), self.selector(&marshaler, marshaler.method_ident(fi), params));
+                                writeln!(w, "    {}{};", self.swift_selector(spec, swift_name, params), deprecated);
+                            }
+                        }
+                        writeln!(w, "");
+                    },
+                    StatementKind::Comment(_, ref c) => {
+                        writeln!(w, "//{}", c);
+                    },
+                    _ => {}
+                }
+            }
+            writeln!(w, "@end");
+            self.write_const_declarations(&mut w, &marshaler, id, &bs.statements);
+            writeln!(w, "");
+            writeln!(w, "NS_ASSUME_NONNULL_END");
+            self.add_file(spec, id, &spec.config.objc.h_ext, w, output);
+
+            // The class itself is implemented by the language bridge, only constants need a definition here.
+            if self.consts(&bs.statements).len() > 0 {
+                let mut w = Vec::new();
+                self.write_header(&mut w);
+                writeln!(w, "{}", marshaler.header_import(id));
+                writeln!(w, "");
+                self.write_const_definitions(&mut w, &marshaler, id, &bs.statements);
+                self.add_file(spec, id, "m", w, output);
+            }
+        }
+    }
+    // The error domains of every thrown type share one file, next to the key holding the error itself.
+    fn finish(&self, spec: &Spec, program: &Program, output: &mut GeneratedOutput) {
+        let errors = program_errors(program);
+        if errors.len() == 0 {
+            return;
+        }
+
+        let marshaler = ObjcMarshaler::new(spec);
+        let file = marshaler.errors_ident();
+        let mut w = Vec::new();
+        self.write_header(&mut w);
+        writeln!(w, "#import <Foundation/Foundation.h>");
+        writeln!(w, "");
+        writeln!(w, "NS_ASSUME_NONNULL_BEGIN");
+        writeln!(w, "");
+        writeln!(w, "// The `userInfo` key holding the error a method threw: the record, or the enum as an `NSNumber`.");
+        writeln!(w, "// The `code` of the `NSError` is the enum value, or 0 for a record.");
+        writeln!(w, "extern NSErrorUserInfoKey const {};", marshaler.error_value_key());
+        writeln!(w, "");
+        for e in &errors {
+            writeln!(w, "extern NSErrorDomain const {};", marshaler.error_domain(e));
+        }
+        writeln!(w, "");
+        writeln!(w, "NS_ASSUME_NONNULL_END");
+        self.add_file(spec, &file, &spec.config.objc.h_ext, w, output);
+
+        let mut w = Vec::new();
+        self.write_header(&mut w);
+        writeln!(w, "{}", marshaler.header_import(&file));
+        writeln!(w, "");
+        writeln!(w, "NSErrorUserInfoKey const {} = @\"{}\";", marshaler.error_value_key(), marshaler.error_value_key());
+        for e in &errors {
+            writeln!(w, "NSErrorDomain const {} = @\"{}\";", marshaler.error_domain(e), marshaler.error_domain(e));
+        }
+        self.add_file(spec, &file, "m", w, output);
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use generator::testing;
+    use config::Config;
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.objc.out = Some("objc".into());
+        config.objc.type_prefix = "BB".into();
 
-    fn write_record(&self, r: &StatementKind, spec: &Spec) {
-        
+        config
     }
 
-    fn write_interface(&self, i: &StatementKind) {
-        
+    #[test]
+    fn test_write_record() {
+        let output = testing::compile(config(), r#"
+            color = enum {
+                dark_blue;
+            }
+
+            key = record {
+                id: i64;
+                name: string;
+                col: color;
+                const max_id: i64 = 99;
+            } deriving(eq)
+        "#);
+
+        let header = &output.get("objc/BBKey.h").expect("missing BBKey.h").contents;
+        assert!(header.contains("#import \"BBColor.h\""), "{}", header);
+        assert!(header.contains("NS_SWIFT_NAME(Key)\n@interface BBKey : NSObject"), "{}", header);
+        assert!(header.contains("- (nonnull instancetype)initWithId:(int64_t)id name:(nonnull NSString *)name col:(BBColor)col\n    NS_SWIFT_NAME(init(id:name:col:));"), "{}", header);
+        assert!(header.contains("@property (nonatomic, readonly, nonnull) NSString *name;"), "{}", header);
+        assert!(header.contains("extern int64_t const BBKeyMaxId;"), "{}", header);
+
+        let source = &output.get("objc/BBKey.m").expect("missing BBKey.m").contents;
+        assert!(source.contains("_name = [name copy];"), "{}", source);
+        assert!(source.contains("[self.name isEqualToString:typedOther.name]"), "{}", source);
+        assert!(!source.contains("compare:"), "{}", source);
+
+        let color = &output.get("objc/BBColor.h").expect("missing BBColor.h").contents;
+        assert!(color.contains("    BBColorDarkBlue,\n} NS_SWIFT_NAME(Color);"), "{}", color);
+    }
+
+    #[test]
+    fn test_write_interface() {
+        let output = testing::compile(config(), r#"
+            listener = interface +o {
+                on_key(id: i64);
+            }
+
+            key_store = interface +c {
+                static create(): key_store;
+                notify(l: listener, names: list<string>): bool;
+            }
+        "#);
+
+        let store = &output.get("objc/BBKeyStore.h").expect("missing BBKeyStore.h").contents;
+        assert!(store.contains("@protocol BBListener;"), "{}", store);
+        assert!(store.contains("+ (nullable BBKeyStore *)create\n    NS_SWIFT_NAME(create());"), "{}", store);
+        assert!(store.contains("- (BOOL)notify:(nullable id<BBListener>)l names:(nonnull NSArray<NSString *> *)names\n    NS_SWIFT_NAME(notify(l:names:));"), "{}", store);
+
+        let listener = &output.get("objc/BBListener.h").expect("missing BBListener.h").contents;
+        assert!(listener.contains("@protocol BBListener <NSObject>"), "{}", listener);
+    }
+
+    #[test]
+    fn test_write_async_interface() {
+        let output = testing::compile(config(), r#"
+            key = record { id: i64; }
+
+            key_store = interface +c {
+                fetch(id: i64): future<key>;
+                count(): future<i32>;
+            }
+        "#);
+
+        let store = &output.get("objc/BBKeyStore.h").expect("missing BBKeyStore.h").contents;
+        assert!(store.contains("- (void)fetch:(int64_t)id completion:(void (^)(BBKey * _Nullable result, NSError * _Nullable error))completion\n    NS_SWIFT_NAME(fetch(id:completion:));"), "{}", store);
+        assert!(store.contains("- (void)countWithCompletion:(void (^)(int32_t result, NSError * _Nullable error))completion\n    NS_SWIFT_NAME(count(completion:));"), "{}", store);
+        assert!(store.contains("@class BBKey;"), "{}", store);
+    }
+
+    #[test]
+    fn test_write_throwing_interface() {
+        let output = testing::compile(config(), r#"
+            store_error = enum { missing; }
+
+            key = record { id: i64; }
+
+            key_store = interface +c {
+                save(k: key) throws store_error;
+                fetch(id: i64): key throws store_error;
+                count(): i32 throws store_error;
+            }
+        "#);
+
+        let store = &output.get("objc/BBKeyStore.h").expect("missing BBKeyStore.h").contents;
+        assert!(store.contains("#import \"BBErrors.h\""), "{}", store);
+        assert!(store.contains("- (BOOL)save:(nonnull BBKey *)k error:(NSError * _Nullable * _Nullable)error\n    NS_SWIFT_NAME(save(k:));"), "{}", store);
+        assert!(store.contains("- (nullable BBKey *)fetch:(int64_t)id error:(NSError * _Nullable * _Nullable)error\n    NS_SWIFT_NAME(fetch(id:));"), "{}", store);
+        assert!(store.contains("- (int32_t)countAndReturnError:(NSError * _Nullable * _Nullable)error\n    NS_SWIFT_NAME(count(error:));"), "{}", store);
+
+        let errors = &output.get("objc/BBErrors.h").expect("missing BBErrors.h").contents;
+        assert!(errors.contains("extern NSErrorUserInfoKey const BBErrorValueKey;"), "{}", errors);
+        assert!(errors.contains("extern NSErrorDomain const BBStoreErrorDomain;"), "{}", errors);
+        let errors = &output.get("objc/BBErrors.m").expect("missing BBErrors.m").contents;
+        assert!(errors.contains("NSErrorDomain const BBStoreErrorDomain = @\"BBStoreErrorDomain\";"), "{}", errors);
     }
 }
@@ -0,0 +1,250 @@
+/*
+ * Copyright © 2002-2017 Bluebeam Software, Inc. All Rights Reserved.
+ * Creator: Jonathan Rothberg
+ */
+
+use parser::ast::{Annotation, DataTypeStatement, Identifier, InterfaceType, find_annotation};
+use parser::token::Token;
+use generator::spec::Spec;
+use generator::typer::{TypeDefinitionKind, UserObjectKind};
+
+pub struct ObjcMarshaler<'a> {
+    spec: &'a Spec
+}
+
+impl<'a> ObjcMarshaler<'a> {
+    pub fn new(spec: &'a Spec) -> ObjcMarshaler<'a> {
+        ObjcMarshaler {
+            spec: spec
+        }
+    }
+
+    /// Objective-C has no namespaces, so the module of a type becomes part of its prefix:
+    /// `net.device` is styled like `net_device`.
+    pub fn type_ident(&self, i: &Identifier) -> String {
+        (self.spec.objc_ident_style.ty)(i.value.replace('.', "_"))
+    }
+
+    pub fn file_name(&self, i: &Identifier) -> String {
+        (self.spec.objc_file_ident_style)(i.value.replace('.', "_"))
+    }
+
+    pub fn header_import(&self, i: &Identifier) -> String {
+        format!("#import \"{}{}.{}\"", self.spec.config.objc.include_prefix, self.file_name(i), self.spec.config.objc.h_ext)
+    }
+
+    pub fn method_ident(&self, i: &Identifier) -> String {
+        (self.spec.objc_ident_style.method)(i.value.clone())
+    }
+
+    pub fn field_ident(&self, i: &Identifier) -> String {
+        (self.spec.objc_ident_style.field)(i.value.clone())
+    }
+
+    pub fn local_ident(&self, i: &Identifier) -> String {
+        (self.spec.objc_ident_style.local)(i.value.clone())
+    }
+
+    pub fn enum_member(&self, ty: &Identifier, member: &Identifier) -> String {
+        format!("{}{}", self.type_ident(ty), (self.spec.objc_ident_style.enm)(member.value.clone()))
+    }
+
+    pub fn const_name(&self, ty: &Identifier, i: &Identifier) -> String {
+        format!("{}{}", self.type_ident(ty), (self.spec.objc_ident_style.cnst)(i.value.clone()))
+    }
+
+    /// The name Swift sees a type under.  When the Swift generator is enabled it owns the plain
+    /// names, so the Objective-C types are imported with an `ObjC` suffix instead.
+    pub fn swift_name(&self, i: &Identifier) -> String {
+        let name = (self.spec.swift_ident_style.ty)(i.value.replace('.', "_"));
+        if self.spec.config.swift.out.as_ref().map_or(false, |o| o.len() > 0) {
+            format!("{}ObjC", name)
+        }
+        else {
+            name
+        }
+    }
+
+    /// The file declaring the error domains, `<prefix>Errors`.
+    pub fn errors_ident(&self) -> Identifier {
+        Identifier {
+            token: Token::Ident("errors".into()),
+            value: "errors".into()
+        }
+    }
+
+    pub fn error_domain(&self, i: &Identifier) -> String {
+        format!("{}Domain", self.type_ident(i))
+    }
+
+    pub fn error_value_key(&self) -> String {
+        format!("{}ErrorValueKey", self.spec.config.objc.type_prefix)
+    }
+
+    /// Interfaces implemented in Objective-C are protocols, every other interface is a class.
+    pub fn is_protocol(&self, i: &Identifier) -> bool {
+        match self.user_object_kind(i) {
+            Some(UserObjectKind::Interface(ref it)) => it.contains(&InterfaceType::ObjectiveC),
+            _ => false
+        }
+    }
+
+    pub fn type_name(&self, dts: &DataTypeStatement) -> String {
+        self.name(dts, false)
+    }
+
+    /// The type used inside collections, where values have to be objects.
+    pub fn boxed_type_name(&self, dts: &DataTypeStatement) -> String {
+        self.name(dts, true)
+    }
+
+    fn name(&self, dts: &DataTypeStatement, boxed: bool) -> String {
+        match *dts {
+            DataTypeStatement::None => "void".into(),
+            DataTypeStatement::List(ref t) => format!("NSArray<{}> *", self.boxed_type_name(t)),
+            DataTypeStatement::Set(ref t) => format!("NSSet<{}> *", self.boxed_type_name(t)),
+            DataTypeStatement::Map(ref k, ref v) => format!("NSDictionary<{}, {}> *", self.boxed_type_name(k), self.boxed_type_name(v)),
+            // The elements are stored unboxed, so `bytes` reads as a `const float *` for `array<f32>`.
+            DataTypeStatement::Array(..) => "NSData *".into(),
+            // Primitives can't be nil, so optional ones are boxed.
+            DataTypeStatement::Optional(ref t) => self.boxed_type_name(t),
+            DataTypeStatement::Function(ref p, ref r) => self.block_type(p, r),
+            DataTypeStatement::Object(ref i) => {
+                match self.user_object_kind(i) {
+                    Some(UserObjectKind::Enum) if boxed => "NSNumber *".into(),
+                    Some(UserObjectKind::Enum) => self.type_ident(i),
+                    _ if self.is_protocol(i) => format!("id<{}>", self.type_ident(i)),
+                    _ => format!("{} *", self.type_ident(i))
+                }
+            },
+            _ => {
+                match self.spec.typer.get_from_data_type(dts) {
+                    TypeDefinitionKind::Primitive(ref p) if boxed => format!("{} *", p.objc_boxed),
+                    TypeDefinitionKind::Primitive(ref p) => p.objc.clone(),
+                    TypeDefinitionKind::String => self.override_type("string").unwrap_or("NSString *".into()),
+                    TypeDefinitionKind::Binary => self.override_type("binary").unwrap_or("NSData *".into()),
+                    TypeDefinitionKind::Date => self.override_type("date").unwrap_or("NSDate *".into()),
+                    _ => "".into()
+                }
+            }
+        }
+    }
+
+    /// `BOOL (^)(int32_t, NSString * _Nonnull)`, the block type of a function.  Its parameters and
+    /// result carry their own nullability since they sit inside another type.
+    fn block_type(&self, params: &Vec<DataTypeStatement>, ret: &DataTypeStatement) -> String {
+        let inner = |t: &DataTypeStatement| {
+            match self.nullability(t) {
+                Some("nullable") => format!("{} _Nullable", self.type_name(t)),
+                Some(_) => format!("{} _Nonnull", self.type_name(t)),
+                None => self.type_name(t)
+            }
+        };
+        let params = match params.len() {
+            0 => "void".into(),
+            _ => params.iter().map(|p| inner(p)).collect::<Vec<_>>().join(", ")
+        };
+        format!("{} (^)({})", inner(ret), params)
+    }
+
+    /// The completion handler an async method takes in place of returning `future<T>`.  The
+    /// result is nil (or zero) whenever `error` is set.
+    pub fn completion_type(&self, result: &DataTypeStatement) -> String {
+        let result = if self.is_object(result) {
+            format!("{} _Nullable result", self.type_name(result))
+        }
+        else {
+            format!("{} result", self.type_name(result))
+        };
+        format!("void (^)({}, NSError * _Nullable error)", result)
+    }
+
+    /// Whether values of `dts` are object pointers that carry a nullability annotation.
+    pub fn is_object(&self, dts: &DataTypeStatement) -> bool {
+        match *dts {
+            DataTypeStatement::None => false,
+            DataTypeStatement::Optional(..) => true,
+            DataTypeStatement::Object(ref i) => self.user_object_kind(i) != Some(UserObjectKind::Enum),
+            _ => match self.spec.typer.get_from_data_type(dts) {
+                TypeDefinitionKind::Primitive(..) => false,
+                _ => true
+            }
+        }
+    }
+
+    /// Interfaces and optionals may be nil; every other object is nonnull.
+    pub fn nullability(&self, dts: &DataTypeStatement) -> Option<&'static str> {
+        match *dts {
+            DataTypeStatement::Optional(..) => Some("nullable"),
+            DataTypeStatement::Object(ref i) if self.is_interface(i) => Some("nullable"),
+            _ if self.is_object(dts) => Some("nonnull"),
+            _ => None
+        }
+    }
+
+    /// `nullability`, except that interfaces annotated `@nonnull` are nonnull.
+    pub fn annotated_nullability(&self, dts: &DataTypeStatement, annotations: &Vec<Annotation>) -> Option<&'static str> {
+        match find_annotation(annotations, "nonnull") {
+            Some(_) => Some("nonnull"),
+            None => self.nullability(dts)
+        }
+    }
+
+    /// `(nonnull NSString *)`, the spelling of a parameter or return type.
+    pub fn annotated_type(&self, dts: &DataTypeStatement) -> String {
+        self.annotated_type_for(dts, &Vec::new())
+    }
+
+    pub fn annotated_type_for(&self, dts: &DataTypeStatement, annotations: &Vec<Annotation>) -> String {
+        match self.annotated_nullability(dts, annotations) {
+            Some(n) => format!("{} {}", n, self.type_name(dts)),
+            None => self.type_name(dts)
+        }
+    }
+
+    /// `__attribute__((deprecated("reason")))` for declarations annotated `@deprecated`.
+    pub fn deprecated(&self, deprecated: Option<&Annotation>) -> Option<String> {
+        deprecated.map(|a| match a.value {
+            Some(ref v) => format!("__attribute__((deprecated(\"{}\")))", v),
+            None => "__attribute__((deprecated))".into()
+        })
+    }
+
+    pub fn is_interface(&self, i: &Identifier) -> bool {
+        match self.user_object_kind(i) {
+            Some(UserObjectKind::Interface(..)) => true,
+            _ => false
+        }
+    }
+
+    pub fn user_object_kind(&self, i: &Identifier) -> Option<UserObjectKind> {
+        match self.spec.typer.get(&i.value) {
+            TypeDefinitionKind::UserObject(_, k) => Some(k),
+            _ => None
+        }
+    }
+
+    /// The user types referenced by `dts`, for imports and forward declarations.
+    pub fn references(&self, dts: &DataTypeStatement) -> Vec<Identifier> {
+        match *dts {
+            DataTypeStatement::List(ref t) | DataTypeStatement::Set(ref t) | DataTypeStatement::Optional(ref t) |
+            DataTypeStatement::Future(ref t) => self.references(t),
+            DataTypeStatement::Map(ref k, ref v) => {
+                let mut result = self.references(k);
+                result.extend(self.references(v));
+                result
+            },
+            DataTypeStatement::Function(ref p, ref r) => {
+                let mut result: Vec<Identifier> = p.iter().flat_map(|t| self.references(t)).collect();
+                result.extend(self.references(r));
+                result
+            },
+            DataTypeStatement::Object(ref i) => vec![i.clone()],
+            _ => vec![]
+        }
+    }
+
+    fn override_type(&self, name: &str) -> Option<String> {
+        self.spec.typer.get_override(name).and_then(|o| o.objc.clone())
+    }
+}
@@ -0,0 +1,52 @@
+/*
+ * Copyright © 2002-2017 Bluebeam Software, Inc. All Rights Reserved.
+ * Creator: Jonathan Rothberg
+ */
+
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct GeneratedFile {
+    pub path: PathBuf,
+    pub contents: String
+}
+
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct GeneratedOutput {
+    pub files: Vec<GeneratedFile>
+}
+
+impl GeneratedOutput {
+    pub fn new() -> GeneratedOutput {
+        GeneratedOutput {
+            files: Vec::new()
+        }
+    }
+
+    pub fn add_file<P: AsRef<Path>>(&mut self, path: P, contents: Vec<u8>) {
+        self.files.push(GeneratedFile {
+            path: path.as_ref().to_path_buf(),
+            contents: String::from_utf8_lossy(&contents).into_owned()
+        });
+    }
+
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<&GeneratedFile> {
+        self.files.iter().find(|f| f.path == path.as_ref())
+    }
+
+    pub fn write_to_disk(&self) -> io::Result<()> {
+        for file in &self.files {
+            if let Some(parent) = file.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            let mut f = fs::File::create(&file.path)?;
+            f.write_all(file.contents.as_bytes())?;
+        }
+
+        Ok(())
+    }
+}
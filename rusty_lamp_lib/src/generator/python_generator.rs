@@ -0,0 +1,460 @@
+/*
+ * Copyright © 2002-2017 Bluebeam Software, Inc. All Rights Reserved.
+ * Creator: Jonathan Rothberg
+ */
+
+use std::io::{Write};
+use generator::generator::{Generate};
+use generator::spec::Spec;
+use generator::output::GeneratedOutput;
+use generator::cpp_marshaler::CppMarshaler;
+use generator::python_marshaler::PythonMarshaler;
+use generator::resolver::program_uses_optional;
+use parser::program::Program;
+use parser::ast::{Statement, StatementKind, DataTypeStatement, DeriveType, FunctionModifier, Identifier, InterfaceType, BlockStatement};
+
+/// Exposes the generated C++ classes to Python as a pybind11 module, written as a single source
+/// plus a `.pyi` stub for type checkers.  Containers, optionals and dates go through pybind11's
+/// stl and chrono casters; binaries are converted to `bytes` by a caster of our own.
+pub struct PythonGenerator {
+
+}
+
+impl PythonGenerator {
+    pub fn new() -> PythonGenerator {
+        PythonGenerator {
+
+        }
+    }
+
+    fn write_header(&self, w: &mut Write, comment: &str) {
+        writeln!(w, "{} AUTOGENERATED FILE - DO NOT MODIFY!", comment);
+        writeln!(w, "{} This file was generated by rusty_lamp", comment);
+    }
+
+    fn fields<'b>(&self, bs: &'b BlockStatement) -> Vec<(&'b Identifier, &'b DataTypeStatement)> {
+        bs.statements.iter()
+            .filter_map(|s| match s.stmtKind { StatementKind::RecordMember(_, ref i, ref dts, _) => Some((i, dts)), _ => None })
+            .collect()
+    }
+
+    /// The records that get `__eq__` and `__lt__`, with whether they are ordered too.
+    fn compared_records<'p>(&self, program: &'p Program) -> Vec<(&'p Identifier, &'p BlockStatement, bool)> {
+        program.statements.iter()
+            .filter_map(|s| match s.stmtKind {
+                StatementKind::Record(_, ref i, ref bs, ref dt) if dt.contains(&DeriveType::Ord) => Some((i, bs, true)),
+                StatementKind::Record(_, ref i, ref bs, ref dt) if dt.contains(&DeriveType::Eq) => Some((i, bs, false)),
+                _ => None
+            })
+            .collect()
+    }
+
+    /// Literal consts as `(python name, C++ value)`.
+    fn consts(&self, m: &PythonMarshaler, cpp: &CppMarshaler, statements: &Vec<Statement>) -> Vec<(String, String)> {
+        statements.iter()
+            .filter_map(|s| match s.stmtKind {
+                StatementKind::Const(_, ref i, ref dts, ref v) => {
+                    let value = match v.stmtKind {
+                        StatementKind::StringLiteral(_, ref s) => format!("{:?}", s),
+                        StatementKind::Boolean(_, b) => format!("{}", b),
+                        StatementKind::NumberLiteral(_, ref n) => n.clone(),
+                        _ => return None
+                    };
+                    Some((m.const_ident(i), format!("{}({})", cpp.fq_type_name(dts), value)))
+                },
+                _ => None
+            })
+            .collect()
+    }
+
+    fn write_chain(&self, w: &mut Write, head: String, links: Vec<String>) {
+        writeln!(w, "");
+        if links.len() == 0 {
+            writeln!(w, "    {};", head);
+            return;
+        }
+        writeln!(w, "    {}", head);
+        for (n, l) in links.iter().enumerate() {
+            writeln!(w, "        {}{}", l, if n + 1 == links.len() { ";" } else { "" });
+        }
+    }
+
+    // The generated C++ records don't define comparison operators, so records deriving eq or ord
+    // are compared field by field, recursing into vectors and optionals of records.
+    fn write_comparisons(&self, w: &mut Write, spec: &Spec, cpp: &CppMarshaler, program: &Program) {
+        let records = self.compared_records(program);
+        if records.len() == 0 {
+            return;
+        }
+
+        writeln!(w, "");
+        for &(i, _, ord) in &records {
+            let class = cpp.fq_name(&i.value);
+            writeln!(w, "bool are_equal(const {} & a, const {} & b);", class, class);
+            if ord {
+                writeln!(w, "bool is_less(const {} & a, const {} & b);", class, class);
+            }
+        }
+        writeln!(w, "");
+        writeln!(w, "template <typename T>");
+        writeln!(w, "bool are_equal(const T & a, const T & b) {{");
+        writeln!(w, "    return a == b;");
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "template <typename T>");
+        writeln!(w, "bool is_less(const T & a, const T & b) {{");
+        writeln!(w, "    return a < b;");
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "template <typename T>");
+        writeln!(w, "bool are_equal(const std::vector<T> & a, const std::vector<T> & b) {{");
+        writeln!(w, "    return std::equal(a.begin(), a.end(), b.begin(), b.end(), [](const T & x, const T & y) {{ return are_equal(x, y); }});");
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "template <typename T>");
+        writeln!(w, "bool is_less(const std::vector<T> & a, const std::vector<T> & b) {{");
+        writeln!(w, "    return std::lexicographical_compare(a.begin(), a.end(), b.begin(), b.end(), [](const T & x, const T & y) {{ return is_less(x, y); }});");
+        writeln!(w, "}}");
+        if program_uses_optional(program) {
+            let optional = &spec.config.cpp.optional_template;
+            writeln!(w, "");
+            writeln!(w, "template <typename T>");
+            writeln!(w, "bool are_equal(const {}<T> & a, const {}<T> & b) {{", optional, optional);
+            writeln!(w, "    return a && b ? are_equal(*a, *b) : !a && !b;");
+            writeln!(w, "}}");
+            writeln!(w, "");
+            writeln!(w, "template <typename T>");
+            writeln!(w, "bool is_less(const {}<T> & a, const {}<T> & b) {{", optional, optional);
+            writeln!(w, "    return b && (!a || is_less(*a, *b));");
+            writeln!(w, "}}");
+        }
+
+        for &(i, bs, ord) in &records {
+            let class = cpp.fq_name(&i.value);
+            let fields = self.fields(bs);
+            writeln!(w, "");
+            writeln!(w, "bool are_equal(const {} & a, const {} & b) {{", class, class);
+            if fields.len() == 0 {
+                writeln!(w, "    return true;");
+            }
+            else {
+                let terms = fields.iter()
+                    .map(|&(fi, _)| format!("are_equal(a.{}, b.{})", fi.value, fi.value))
+                    .collect::<Vec<_>>();
+                writeln!(w, "    return {};", terms.join(" &&\n        "));
+            }
+            writeln!(w, "}}");
+
+            if ord {
+                writeln!(w, "");
+                writeln!(w, "bool is_less(const {} & a, const {} & b) {{", class, class);
+                for &(fi, _) in &fields {
+                    writeln!(w, "    if (is_less(a.{}, b.{})) {{", fi.value, fi.value);
+                    writeln!(w, "        return true;");
+                    writeln!(w, "    }}");
+                    writeln!(w, "    if (is_less(b.{}, a.{})) {{", fi.value, fi.value);
+                    writeln!(w, "        return false;");
+                    writeln!(w, "    }}");
+                }
+                writeln!(w, "    return false;");
+                writeln!(w, "}}");
+            }
+        }
+    }
+
+    fn write_source(&self, w: &mut Write, spec: &Spec, program: &Program) {
+        let m = PythonMarshaler::new(spec);
+        let cpp = CppMarshaler::new(spec);
+        let binary = cpp.fq_type_name(&DataTypeStatement::Binary);
+
+        self.write_header(w, "//");
+        for s in &program.statements {
+            match s.stmtKind {
+                StatementKind::Enum(_, ref i, _) | StatementKind::Record(_, ref i, _, _) | StatementKind::Interface(_, ref i, _, _, _) => {
+                    writeln!(w, "#include {}", cpp.header_include(&i.value));
+                },
+                _ => {}
+            }
+        }
+        for include in &["<algorithm>", "<memory>", "<vector>", "<pybind11/pybind11.h>", "<pybind11/chrono.h>", "<pybind11/stl.h>"] {
+            writeln!(w, "#include {}", include);
+        }
+        writeln!(w, "");
+        writeln!(w, "namespace py = pybind11;");
+        writeln!(w, "");
+
+        // Takes precedence over the stl caster, which would turn binaries into lists of ints.
+        writeln!(w, "namespace pybind11 {{");
+        writeln!(w, "namespace detail {{");
+        writeln!(w, "");
+        writeln!(w, "template <>");
+        writeln!(w, "struct type_caster<{}> {{", binary);
+        writeln!(w, "    PYBIND11_TYPE_CASTER({}, _(\"bytes\"));", binary);
+        writeln!(w, "");
+        writeln!(w, "    bool load(handle src, bool) {{");
+        writeln!(w, "        if (!PyBytes_Check(src.ptr())) {{");
+        writeln!(w, "            return false;");
+        writeln!(w, "        }}");
+        writeln!(w, "        const auto data = reinterpret_cast<const uint8_t *>(PyBytes_AsString(src.ptr()));");
+        writeln!(w, "        value.assign(data, data + PyBytes_Size(src.ptr()));");
+        writeln!(w, "        return true;");
+        writeln!(w, "    }}");
+        writeln!(w, "");
+        writeln!(w, "    static handle cast(const {} & v, return_value_policy, handle) {{", binary);
+        writeln!(w, "        return PyBytes_FromStringAndSize(reinterpret_cast<const char *>(v.data()), v.size());");
+        writeln!(w, "    }}");
+        writeln!(w, "}};");
+        writeln!(w, "");
+        writeln!(w, "}} // namespace detail");
+        writeln!(w, "}} // namespace pybind11");
+        writeln!(w, "");
+        writeln!(w, "namespace {{");
+        self.write_comparisons(w, spec, &cpp, program);
+        writeln!(w, "");
+        writeln!(w, "}} // namespace");
+        writeln!(w, "");
+
+        writeln!(w, "PYBIND11_MODULE({}, m) {{", spec.config.python.module);
+        let mut consts = Vec::new();
+        for s in &program.statements {
+            match s.stmtKind {
+                StatementKind::Enum(_, ref i, ref bs) => {
+                    let values = bs.statements.iter()
+                        .filter_map(|o| match o.stmtKind {
+                            StatementKind::EnumMember(_, ref oi) => {
+                                Some(format!(".value(\"{}\", {}::{})", m.enum_member(oi), cpp.fq_name(&i.value), oi.value))
+                            },
+                            _ => None
+                        })
+                        .collect();
+                    self.write_chain(w, format!("py::enum_<{}>(m, \"{}\")", cpp.fq_name(&i.value), m.type_ident(i)), values);
+                },
+                StatementKind::Record(_, ref i, ref bs, ref dt) => {
+                    let class = cpp.fq_name(&i.value);
+                    let fields = self.fields(bs);
+                    let mut init = vec![format!("py::init<{}>()", fields.iter().map(|&(_, dts)| cpp.fq_type_name(dts)).collect::<Vec<_>>().join(", "))];
+                    init.extend(fields.iter().map(|&(fi, _)| format!("py::arg(\"{}\")", m.local_ident(fi))));
+
+                    let mut links = vec![format!(".def({})", init.join(", "))];
+                    for &(fi, _) in &fields {
+                        links.push(format!(".def_readwrite(\"{}\", &{}::{})", m.field_ident(fi), class, fi.value));
+                    }
+                    if dt.contains(&DeriveType::Eq) || dt.contains(&DeriveType::Ord) {
+                        links.push(format!(".def(\"__eq__\", [](const {} & a, const {} & b) {{ return are_equal(a, b); }}, py::is_operator())", class, class));
+                    }
+                    if dt.contains(&DeriveType::Ord) {
+                        links.push(format!(".def(\"__lt__\", [](const {} & a, const {} & b) {{ return is_less(a, b); }}, py::is_operator())", class, class));
+                    }
+                    self.write_chain(w, format!("py::class_<{}>(m, \"{}\")", class, m.type_ident(i)), links);
+                    consts.extend(self.consts(&m, &cpp, &bs.statements).into_iter().map(|c| (m.type_ident(i), c)));
+                },
+                StatementKind::Interface(_, ref i, ref it, ref bs, _) => {
+                    let class = cpp.fq_name(&i.value);
+                    let mut links = Vec::new();
+                    if it.contains(&InterfaceType::Cpp) {
+                        for f in &bs.statements {
+                            if let StatementKind::Function(_, ref modifier, ref fi, ref params, _, _) = f.stmtKind {
+                                let register = if *modifier == FunctionModifier::Static { "def_static" } else { "def" };
+                                let mut args = vec![format!("\"{}\"", m.method_ident(fi)), format!("&{}::{}", class, fi.value)];
+                                args.extend(params.iter().map(|p| format!("py::arg(\"{}\")", m.local_ident(&p.ident))));
+                                links.push(format!(".{}({})", register, args.join(", ")));
+                            }
+                        }
+                    }
+                    self.write_chain(w, format!("py::class_<{}, std::shared_ptr<{}>>(m, \"{}\")", class, class, m.type_ident(i)), links);
+                    consts.extend(self.consts(&m, &cpp, &bs.statements).into_iter().map(|c| (m.type_ident(i), c)));
+                },
+                _ => {}
+            }
+        }
+        if consts.len() > 0 {
+            writeln!(w, "");
+            for (ty, (name, value)) in consts {
+                writeln!(w, "    m.attr(\"{}\").attr(\"{}\") = {};", ty, name, value);
+            }
+        }
+        writeln!(w, "}}");
+    }
+
+    fn write_stub_comments(&self, w: &mut Write, statements: &Vec<Statement>) {
+        for s in statements {
+            if let StatementKind::Comment(_, ref c) = s.stmtKind {
+                writeln!(w, "    #{}", c);
+            }
+        }
+    }
+
+    fn write_stub_consts(&self, w: &mut Write, m: &PythonMarshaler, statements: &Vec<Statement>) -> usize {
+        let mut written = 0;
+        for s in statements {
+            if let StatementKind::Const(_, ref i, ref dts, ref v) = s.stmtKind {
+                match v.stmtKind {
+                    StatementKind::StringLiteral(..) | StatementKind::Boolean(..) | StatementKind::NumberLiteral(..) => {
+                        writeln!(w, "    {}: ClassVar[{}]", m.const_ident(i), m.type_hint(dts));
+                        written += 1;
+                    },
+                    _ => {}
+                }
+            }
+        }
+        written
+    }
+
+    fn write_stub(&self, w: &mut Write, spec: &Spec, program: &Program) {
+        let m = PythonMarshaler::new(spec);
+
+        self.write_header(w, "#");
+        writeln!(w, "");
+        writeln!(w, "import datetime");
+        writeln!(w, "from typing import ClassVar, Dict, List, Optional, Set");
+        for s in &program.statements {
+            match s.stmtKind {
+                StatementKind::Enum(_, ref i, ref bs) => {
+                    let name = m.type_ident(i);
+                    writeln!(w, "");
+                    writeln!(w, "");
+                    writeln!(w, "class {}:", name);
+                    self.write_stub_comments(w, &bs.statements);
+                    for o in &bs.statements {
+                        if let StatementKind::EnumMember(_, ref oi) = o.stmtKind {
+                            writeln!(w, "    {}: ClassVar[{}]", m.enum_member(oi), name);
+                        }
+                    }
+                    writeln!(w, "    def __init__(self, value: int) -> None: ...");
+                    writeln!(w, "    def __int__(self) -> int: ...");
+                    writeln!(w, "    @property");
+                    writeln!(w, "    def name(self) -> str: ...");
+                    writeln!(w, "    @property");
+                    writeln!(w, "    def value(self) -> int: ...");
+                },
+                StatementKind::Record(_, ref i, ref bs, ref dt) => {
+                    let name = m.type_ident(i);
+                    let fields = self.fields(bs);
+                    writeln!(w, "");
+                    writeln!(w, "");
+                    writeln!(w, "class {}:", name);
+                    self.write_stub_comments(w, &bs.statements);
+                    self.write_stub_consts(w, &m, &bs.statements);
+                    for &(fi, dts) in &fields {
+                        writeln!(w, "    {}: {}", m.field_ident(fi), m.type_hint(dts));
+                    }
+                    let mut params = vec!["self".to_string()];
+                    params.extend(fields.iter().map(|&(fi, dts)| format!("{}: {}", m.local_ident(fi), m.type_hint(dts))));
+                    writeln!(w, "    def __init__({}) -> None: ...", params.join(", "));
+                    if dt.contains(&DeriveType::Eq) || dt.contains(&DeriveType::Ord) {
+                        writeln!(w, "    def __eq__(self, other: object) -> bool: ...");
+                    }
+                    if dt.contains(&DeriveType::Ord) {
+                        writeln!(w, "    def __lt__(self, other: {}) -> bool: ...", name);
+                    }
+                },
+                StatementKind::Interface(_, ref i, ref it, ref bs, _) => {
+                    writeln!(w, "");
+                    writeln!(w, "");
+                    writeln!(w, "class {}:", m.type_ident(i));
+                    self.write_stub_comments(w, &bs.statements);
+                    let mut written = self.write_stub_consts(w, &m, &bs.statements);
+                    if it.contains(&InterfaceType::Cpp) {
+                        for f in &bs.statements {
+                            if let StatementKind::Function(_, ref modifier, ref fi, ref params, ref ret, _) = f.stmtKind {
+                                let mut args = Vec::new();
+                                if *modifier == FunctionModifier::Static {
+                                    writeln!(w, "    @staticmethod");
+                                }
+                                else {
+                                    args.push("self".to_string());
+                                }
+                                args.extend(params.iter().map(|p| format!("{}: {}", m.local_ident(&p.ident), m.type_hint(&p.data_type))));
+                                writeln!(w, "    def {}({}) -> {}: ...", m.method_ident(fi), args.join(", "), m.type_hint(ret));
+                                written += 1;
+                            }
+                        }
+                    }
+                    if written == 0 {
+                        writeln!(w, "    ...");
+                    }
+                },
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Generate for PythonGenerator {
+    // Everything is written by `finish`, pybind11 defines the whole module in one function.
+    fn write_enum(&self, _e: &StatementKind, _spec: &Spec, _output: &mut GeneratedOutput) {
+    }
+
+    fn write_record(&self, _r: &StatementKind, _spec: &Spec, _output: &mut GeneratedOutput) {
+    }
+
+    fn write_interface(&self, _i: &StatementKind, _spec: &Spec, _output: &mut GeneratedOutput) {
+    }
+
+    fn finish(&self, spec: &Spec, program: &Program, output: &mut GeneratedOutput) {
+        let folder = spec.config.python.out.as_ref().map_or("", |s| s.as_str());
+
+        let mut w = Vec::new();
+        self.write_source(&mut w, spec, program);
+        output.add_file(self.make_path(folder, &spec.config.python.module, &spec.config.cpp.ext), w);
+
+        let mut w = Vec::new();
+        self.write_stub(&mut w, spec, program);
+        output.add_file(self.make_path(folder, &spec.config.python.module, "pyi"), w);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::Config;
+    use generator::testing;
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.cpp.namespace = "demo".into();
+        config.python.out = Some("python".into());
+
+        config
+    }
+
+    const INPUT: &'static str = "color = enum { red; dark_blue; }\n\
+                                 point = record { x: i32; col: color; } deriving(ord)\n\
+                                 key = record { id: i64; data: binary; from: optional<date>; const max_id: i64 = 99; } deriving(eq)\n\
+                                 key_store = interface +c { static create(): key_store; lookup(id: i64): list<key>; }\n";
+
+    #[test]
+    fn test_write_module() {
+        let output = testing::compile(config(), INPUT);
+        let source = &output.get("python/lamp.cpp").unwrap().contents;
+
+        assert!(source.contains("PYBIND11_MODULE(lamp, m) {"), "{}", source);
+        assert!(source.contains("py::enum_<::demo::color>(m, \"Color\")\n        .value(\"RED\", ::demo::color::red)\n"), "{}", source);
+        assert!(source.contains("py::class_<::demo::key>(m, \"Key\")\n        \
+                                 .def(py::init<int64_t, std::vector<uint8_t>, std::optional<std::chrono::system_clock::time_point>>(), \
+                                 py::arg(\"id\"), py::arg(\"data\"), py::arg(\"from_\"))\n        \
+                                 .def_readwrite(\"id\", &::demo::key::id)\n"), "{}", source);
+        assert!(source.contains(".def(\"__eq__\", [](const ::demo::key & a, const ::demo::key & b) { return are_equal(a, b); }, py::is_operator());"), "{}", source);
+        assert!(source.contains("m.attr(\"Key\").attr(\"MAX_ID\") = int64_t(99);"), "{}", source);
+        assert!(source.contains("py::class_<::demo::key_store, std::shared_ptr<::demo::key_store>>(m, \"KeyStore\")\n        \
+                                 .def_static(\"create\", &::demo::key_store::create)\n        \
+                                 .def(\"lookup\", &::demo::key_store::lookup, py::arg(\"id\"));"), "{}", source);
+
+        // Only the ordered record gets is_less, and fields compare in declaration order.
+        assert!(source.contains("bool is_less(const ::demo::point & a, const ::demo::point & b) {\n    if (is_less(a.x, b.x)) {"), "{}", source);
+        assert!(!source.contains("bool is_less(const ::demo::key & a"), "{}", source);
+        assert!(source.contains("    return are_equal(a.id, b.id) &&\n        are_equal(a.data, b.data) &&\n        are_equal(a.from, b.from);"), "{}", source);
+    }
+
+    #[test]
+    fn test_write_stub() {
+        let output = testing::compile(config(), INPUT);
+        let stub = &output.get("python/lamp.pyi").unwrap().contents;
+
+        assert!(stub.contains("class Color:\n    RED: ClassVar[Color]\n    DARK_BLUE: ClassVar[Color]\n"), "{}", stub);
+        assert!(stub.contains("class Key:\n    MAX_ID: ClassVar[int]\n    id: int\n    data: bytes\n    from_: Optional[datetime.datetime]\n\
+                               \x20   def __init__(self, id: int, data: bytes, from_: Optional[datetime.datetime]) -> None: ...\n\
+                               \x20   def __eq__(self, other: object) -> bool: ...\n"), "{}", stub);
+        assert!(stub.contains("    def __lt__(self, other: Point) -> bool: ...\n"), "{}", stub);
+        assert!(stub.contains("class KeyStore:\n    @staticmethod\n    def create() -> KeyStore: ...\n    def lookup(self, id: int) -> List[Key]: ...\n"), "{}", stub);
+    }
+}
@@ -0,0 +1,78 @@
+/*
+ * Copyright © 2002-2017 Bluebeam Software, Inc. All Rights Reserved.
+ * Creator: Jonathan Rothberg
+ */
+
+use parser::ast::{DataTypeStatement, Identifier};
+use generator::spec::Spec;
+
+const KEYWORDS: &'static [&'static str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in",
+    "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while", "with", "yield"
+];
+
+pub struct PythonMarshaler<'a> {
+    spec: &'a Spec
+}
+
+impl<'a> PythonMarshaler<'a> {
+    pub fn new(spec: &'a Spec) -> PythonMarshaler<'a> {
+        PythonMarshaler {
+            spec: spec
+        }
+    }
+
+    /// Keywords get a trailing underscore, the usual Python convention.
+    pub fn escape(name: String) -> String {
+        if KEYWORDS.contains(&name.as_str()) {
+            format!("{}_", name)
+        }
+        else {
+            name
+        }
+    }
+
+    pub fn type_ident(&self, i: &Identifier) -> String {
+        (self.spec.python_ident_style.ty)(i.value.clone())
+    }
+
+    pub fn method_ident(&self, i: &Identifier) -> String {
+        PythonMarshaler::escape((self.spec.python_ident_style.method)(i.value.clone()))
+    }
+
+    pub fn field_ident(&self, i: &Identifier) -> String {
+        PythonMarshaler::escape((self.spec.python_ident_style.field)(i.value.clone()))
+    }
+
+    pub fn local_ident(&self, i: &Identifier) -> String {
+        PythonMarshaler::escape((self.spec.python_ident_style.local)(i.value.clone()))
+    }
+
+    pub fn enum_member(&self, i: &Identifier) -> String {
+        PythonMarshaler::escape((self.spec.python_ident_style.enm)(i.value.clone()))
+    }
+
+    pub fn const_ident(&self, i: &Identifier) -> String {
+        PythonMarshaler::escape((self.spec.python_ident_style.cnst)(i.value.clone()))
+    }
+
+    /// The type hint for `dts` in the `.pyi` stub, following what pybind11's stl and chrono
+    /// casters convert to.
+    pub fn type_hint(&self, dts: &DataTypeStatement) -> String {
+        match *dts {
+            DataTypeStatement::None => "None".into(),
+            DataTypeStatement::List(ref t) | DataTypeStatement::Array(ref t) => format!("List[{}]", self.type_hint(t)),
+            DataTypeStatement::Set(ref t) => format!("Set[{}]", self.type_hint(t)),
+            DataTypeStatement::Map(ref k, ref v) => format!("Dict[{}, {}]", self.type_hint(k), self.type_hint(v)),
+            DataTypeStatement::Optional(ref t) => format!("Optional[{}]", self.type_hint(t)),
+            DataTypeStatement::Object(ref i) => self.type_ident(i),
+            DataTypeStatement::Float32 | DataTypeStatement::Float64 => "float".into(),
+            DataTypeStatement::Bool => "bool".into(),
+            DataTypeStatement::String => "str".into(),
+            DataTypeStatement::Binary => "bytes".into(),
+            DataTypeStatement::Date => "datetime.datetime".into(),
+            _ => "int".into()
+        }
+    }
+}
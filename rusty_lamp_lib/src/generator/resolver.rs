@@ -3,12 +3,25 @@
  * Creator: Jonathan Rothberg
  */
 
-use parser::ast::{BlockStatement, Statement, StatementKind};
+use std::fmt;
+use parser::ast::{BlockStatement, Statement, StatementKind, DataTypeStatement, DeriveType, FunctionModifier, Identifier, InterfaceType, Annotation, Parameter};
 use parser::program::Program;
-use generator::typer::{ Typer, DuplicateChecker, TypeDefinitionKind };
+use generator::typer::{ Typer, DuplicateChecker, TypeDefinitionKind, UserObjectKind };
 
 pub struct Resolver {
-    typer: Typer
+    typer: Typer,
+    // The module of the statements being resolved, empty at the top level.
+    module: String,
+    // The statements resolved so far, see `program()`.
+    resolved: Vec<Statement>
+}
+
+/// Where a type is written, which decides what it may hold.
+#[derive(Clone, Copy, PartialEq)]
+enum Position {
+    Field,
+    Parameter,
+    Return
 }
 
 #[derive(Debug)]
@@ -19,37 +32,329 @@ pub enum ResolveError {
     TypeNotFound(String),
 }
 
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ResolveError::Resolve(ref s) => write!(f, "{}", s),
+            ResolveError::Duplicate(ref label, ref s) => write!(f, "{}: {}", label, s),
+            ResolveError::ExpectedEnumOption => write!(f, "expected enum option"),
+            ResolveError::TypeNotFound(ref t) => write!(f, "type not found: {}", t)
+        }
+    }
+}
+
 impl Resolver {
     pub fn new(typer: Typer) -> Resolver {
         Resolver {
             // typer: Typer::new()
-            typer: typer
+            typer: typer,
+            module: String::new(),
+            resolved: Vec::new()
         }
     }
 
     pub fn resolve(&mut self, program: &Program) -> Result<Typer, ResolveError> {
+        self.declare(program)?;
+
         let mut dup_checker = DuplicateChecker::new("Top Level".into());
         for stmt in &program.statements {
-            self.resolve_statement(&stmt, &mut dup_checker)?
+            self.resolve_statement(&stmt, &mut dup_checker)?;
+            // Expanded as soon as the statement is resolved, so aliases are looked up among the
+            // ones declared before it, as they were checked.
+            let resolved = expand_statement(stmt, &self.module, true, &self.typer);
+            self.resolved.push(resolved);
         }
 
         // self.typer.dump();
         return Ok((self.typer.clone()));
     }
 
-    fn type_check(&self, ty: &String) -> Result<(),  ResolveError> {
-        match ty.as_str() {
-            "none" => Ok(()),
-            _ => {
-                match self.typer.get(ty) {
-                    TypeDefinitionKind::None => Err(ResolveError::TypeNotFound(ty.clone())),
-                    _ => Ok(())
+    /// The program as the generators see it: declarations and type references use the qualified
+    /// names the typer knows them by, and every alias is replaced by the type it stands for.  The
+    /// alias declarations are kept, expanded too, for the generators that emit them.
+    pub fn program(&self) -> Program {
+        Program { statements: self.resolved.clone() }
+    }
+
+    /// Enters every enum, record and interface in `program` into the typer before anything is
+    /// resolved, so types can be used ahead of their declaration and the rules about what a type
+    /// may hold see what it refers to.  Aliases are expanded where they are used, so they still
+    /// have to be declared first.  Duplicates and invalid names are left for `resolve_statement`.
+    fn declare(&mut self, program: &Program) -> Result<(), ResolveError> {
+        let mut module = String::new();
+        for stmt in &program.statements {
+            let (i, kind) = match stmt.stmtKind {
+                StatementKind::Module(_, ref m) => {
+                    module = m.clone();
+                    continue;
+                },
+                StatementKind::Enum(_, ref i, _) => (i, UserObjectKind::Enum),
+                StatementKind::Record(_, ref i, _, ref d) => (i, UserObjectKind::Record(d.clone())),
+                StatementKind::Interface(_, ref i, ref it, _, _) => (i, UserObjectKind::Interface(it.clone())),
+                _ => continue
+            };
+            let name = qualified_name(&module, &i.value);
+            self.typer.insert_type(&name, TypeDefinitionKind::UserObject(name.clone(), kind))?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks that `dts` and every type nested in it are declared, and that the generic types
+    /// `label` uses are given their type arguments.
+    fn declared_check(&self, label: &String, dts: &DataTypeStatement) -> Result<(), ResolveError> {
+        for t in nested_types(dts) {
+            if *t == DataTypeStatement::None {
+                continue;
+            }
+            let kind = self.typer.get(&t.get_name());
+            if kind == TypeDefinitionKind::None {
+                return Err(ResolveError::TypeNotFound(t.get_name()));
+            }
+            // The parser only leaves a generic type as a name when its arguments are missing.
+            if let DataTypeStatement::Object(ref i) = *t {
+                if kind.num_params() > 0 {
+                    let (arguments, example) = if kind.num_params() == 1 { ("1 type argument", "<T>") } else { ("2 type arguments", "<K, V>") };
+                    return Err(ResolveError::Resolve(format!("{}: {} needs {}, as in {}{}", label, i.value, arguments, i.value, example)));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the type of `owner.member`, used at `position`, and every type nested in it.
+    /// `future<T>` is only meaningful as the result of a method and fn types are passed to and
+    /// returned from methods, so neither may appear anywhere else.  `array<T>` is copied in bulk
+    /// between languages, so it only holds the numeric primitives every target has a plain array
+    /// of.  Records are values, so a field can't be an interface, which is a reference to an
+    /// object; lists, sets and maps of interfaces are allowed like they are anywhere else.
+    fn type_check(&self, owner: &String, member: &String, dts: &DataTypeStatement, position: Position) -> Result<(), ResolveError> {
+        self.declared_check(&format!("{}.{}", owner, member), dts)?;
+
+        for (n, t) in nested_types(dts).into_iter().enumerate() {
+            match *t {
+                DataTypeStatement::Future(..) if n > 0 || position != Position::Return => {
+                    return Err(ResolveError::Resolve(format!("{}.{}: future<T> can only be the return type of a method", owner, member)));
+                },
+                DataTypeStatement::Function(..) if position == Position::Field => {
+                    return Err(ResolveError::Resolve(format!("{}.{}: fn types can only be used in method parameters and return types", owner, member)));
+                },
+                DataTypeStatement::Array(ref e) => match **e {
+                    DataTypeStatement::Integer8 | DataTypeStatement::Integer16 | DataTypeStatement::Integer32 |
+                    DataTypeStatement::Integer64 | DataTypeStatement::Float32 | DataTypeStatement::Float64 => {},
+                    _ => {
+                        return Err(ResolveError::Resolve(format!("{}.{}: array<T> can only hold i8, i16, i32, i64, f32 or f64, not {}", owner, member, e)));
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        if position == Position::Field {
+            let field = match *dts {
+                DataTypeStatement::Optional(ref t) => &**t,
+                ref t => t
+            };
+            if let DataTypeStatement::Object(ref i) = *field {
+                if let TypeDefinitionKind::UserObject(_, UserObjectKind::Interface(..)) = self.typer.get(&i.value) {
+                    return Err(ResolveError::Resolve(format!("{}.{}: records can't hold interface {} by value, only in a list, set or map", owner, member, i.value)));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Everything a record derives is generated field by field, so every field needs a type that
+    /// each language compares, hashes, prints or serializes the same way.  Hashing only makes sense
+    /// next to equality, which is what every target pairs it with.
+    fn derive_check(&self, owner: &String, derives: &Vec<DeriveType>, fields: &Vec<(&Identifier, DataTypeStatement)>) -> Result<(), ResolveError> {
+        if derives.contains(&DeriveType::Hash) && !derives.contains(&DeriveType::Eq) && !derives.contains(&DeriveType::Ord) {
+            return Err(ResolveError::Resolve(format!("{}: deriving(hash) requires eq as well", owner)));
+        }
+
+        for derive in derives {
+            let supported = match *derive {
+                DeriveType::Eq => "primitives, strings, binary, dates, arrays, enums, records deriving eq or ord, and lists, sets and maps of them",
+                DeriveType::Ord => "primitives, strings, dates, enums, records deriving ord and lists of them",
+                DeriveType::Hash => "primitives, strings, enums and records deriving hash",
+                DeriveType::Show => "primitives, strings, enums and records deriving show",
+                DeriveType::Json => "primitives, strings, arrays, records deriving json, and lists, sets and string keyed maps of them",
+                DeriveType::None => continue
+            };
+            for &(i, ref dts) in fields {
+                if *derive == DeriveType::Ord && self.unordered(dts) {
+                    return Err(ResolveError::Resolve(format!("{}.{}: deriving(ord) can't order {} fields, maps, sets, binary and arrays have no order",
+                                                             owner, i.value, dts)));
+                }
+
+                // Only a field itself can be left out, not an element of a container.
+                let field = match *dts {
+                    DataTypeStatement::Optional(ref t) => &**t,
+                    ref t => t
+                };
+                if !self.derivable(derive, field) {
+                    return Err(ResolveError::Resolve(format!("{}.{}: deriving({}) doesn't support {} fields, only {} or optionals of those",
+                                                             owner, i.value, derive, dts, supported)));
                 }
             }
         }
+
+        Ok(())
+    }
+
+    fn derivable(&self, derive: &DeriveType, dts: &DataTypeStatement) -> bool {
+        let compares = *derive == DeriveType::Eq || *derive == DeriveType::Ord;
+        match *dts {
+            DataTypeStatement::Integer8 | DataTypeStatement::Integer16 | DataTypeStatement::Integer32 | DataTypeStatement::Integer64 |
+            DataTypeStatement::Float32 | DataTypeStatement::Float64 | DataTypeStatement::Bool | DataTypeStatement::String => true,
+            DataTypeStatement::Object(ref i) => match self.typer.get(&i.value) {
+                // JSON has no enum encoding the targets agree on, Kotlin writes names and C++ numbers.
+                TypeDefinitionKind::UserObject(_, UserObjectKind::Enum) => *derive != DeriveType::Json,
+                TypeDefinitionKind::UserObject(_, UserObjectKind::Record(ref d)) => {
+                    d.contains(derive) || (*derive == DeriveType::Eq && d.contains(&DeriveType::Ord))
+                },
+                _ => false
+            },
+            DataTypeStatement::Date => compares,
+            DataTypeStatement::Binary => *derive == DeriveType::Eq,
+            DataTypeStatement::Array(..) => *derive == DeriveType::Eq || *derive == DeriveType::Json,
+            DataTypeStatement::Optional(ref t) => compares && self.derivable(derive, t),
+            DataTypeStatement::List(ref t) => {
+                (compares || *derive == DeriveType::Json) && self.derivable(derive, t)
+            },
+            DataTypeStatement::Set(ref t) => {
+                (*derive == DeriveType::Eq || *derive == DeriveType::Json) && self.derivable(derive, t)
+            },
+            DataTypeStatement::Map(ref k, ref v) => match *derive {
+                DeriveType::Eq => self.derivable(derive, k) && self.derivable(derive, v),
+                DeriveType::Json => **k == DataTypeStatement::String && self.derivable(derive, v),
+                _ => false
+            },
+            _ => false
+        }
+    }
+
+    /// Whether `dts` is or holds a type without an order.
+    fn unordered(&self, dts: &DataTypeStatement) -> bool {
+        nested_types(dts).iter().any(|t| match **t {
+            DataTypeStatement::Map(..) | DataTypeStatement::Set(..) | DataTypeStatement::Binary | DataTypeStatement::Array(..) => true,
+            _ => false
+        })
+    }
+
+    /// Field defaults are limited to the literals every target can spell: numbers that fit the
+    /// field's type, strings and bools.  An `optional<T>` field takes a default for its `T`.
+    fn default_check(&self, owner: &String, member: &String, dts: &DataTypeStatement, value: &Statement) -> Result<(), ResolveError> {
+        let matches = match (dts, &value.stmtKind) {
+            (&DataTypeStatement::Optional(ref t), _) => return self.default_check(owner, member, t, value),
+            (&DataTypeStatement::Integer8, &StatementKind::NumberLiteral(_, ref n)) => n.parse::<i8>().is_ok(),
+            (&DataTypeStatement::Integer16, &StatementKind::NumberLiteral(_, ref n)) => n.parse::<i16>().is_ok(),
+            (&DataTypeStatement::Integer32, &StatementKind::NumberLiteral(_, ref n)) => n.parse::<i32>().is_ok(),
+            (&DataTypeStatement::Integer64, &StatementKind::NumberLiteral(_, ref n)) => n.parse::<i64>().is_ok(),
+            (&DataTypeStatement::Float32, &StatementKind::NumberLiteral(_, ref n)) |
+            (&DataTypeStatement::Float64, &StatementKind::NumberLiteral(_, ref n)) => n.parse::<f64>().is_ok(),
+            (&DataTypeStatement::String, &StatementKind::StringLiteral(..)) => true,
+            (&DataTypeStatement::Bool, &StatementKind::Boolean(..)) => true,
+            (&DataTypeStatement::Integer8, _) | (&DataTypeStatement::Integer16, _) | (&DataTypeStatement::Integer32, _) |
+            (&DataTypeStatement::Integer64, _) | (&DataTypeStatement::Float32, _) | (&DataTypeStatement::Float64, _) |
+            (&DataTypeStatement::String, _) | (&DataTypeStatement::Bool, _) => false,
+            _ => {
+                return Err(ResolveError::Resolve(format!("{}.{}: default values are only supported for numbers, strings and bools, not {}", owner, member, dts)));
+            }
+        };
+
+        if !matches {
+            return Err(ResolveError::Resolve(format!("{}.{}: default value {} does not match type {}", owner, member, value.stmtKind, dts)));
+        }
+        Ok(())
+    }
+
+    /// Errors are IDL values the targets can carry in an exception or `NSError`.
+    fn error_check(&self, owner: &String, member: &String, error: &Identifier) -> Result<(), ResolveError> {
+        let key = self.typer.lookup(&self.module, &error.value).unwrap_or(error.value.clone());
+        match self.typer.get(&key) {
+            TypeDefinitionKind::UserObject(_, UserObjectKind::Enum) | TypeDefinitionKind::UserObject(_, UserObjectKind::Record(..)) => Ok(()),
+            TypeDefinitionKind::None => Err(ResolveError::TypeNotFound(error.value.clone())),
+            _ => Err(ResolveError::Resolve(format!("{}.{}: throws {} must name a record or an enum", owner, member, error.value)))
+        }
+    }
+
+    /// Validates the annotations the generators understand; any others are passed through.
+    /// `dts` is the type of the annotated field, parameter or method result.
+    fn annotation_check(&self, label: &String, annotations: &Vec<Annotation>, dts: Option<&DataTypeStatement>) -> Result<(), ResolveError> {
+        for (n, a) in annotations.iter().enumerate() {
+            let name = a.ident.value.as_str();
+            if annotations[..n].iter().any(|b| b.ident.value == name) {
+                return Err(ResolveError::Resolve(format!("{}: duplicate annotation @{}", label, name)));
+            }
+            match (name, &a.value) {
+                ("since", &None) | ("cpp_name", &None) | ("java_name", &None) => {
+                    return Err(ResolveError::Resolve(format!("{}: @{} needs a value", label, name)));
+                },
+                ("cpp_name", &Some(ref v)) | ("java_name", &Some(ref v)) if !Resolver::is_identifier(v) => {
+                    return Err(ResolveError::Resolve(format!("{}: @{}(\"{}\") is not a valid identifier", label, name, v)));
+                },
+                ("nonnull", &Some(_)) => {
+                    return Err(ResolveError::Resolve(format!("{}: @nonnull takes no value", label)));
+                },
+                ("nonnull", &None) => {
+                    let is_interface = match dts {
+                        Some(&DataTypeStatement::Object(ref i)) => match self.typer.get(&i.value) {
+                            TypeDefinitionKind::UserObject(_, UserObjectKind::Interface(..)) => true,
+                            _ => false
+                        },
+                        _ => false
+                    };
+                    if !is_interface {
+                        return Err(ResolveError::Resolve(format!("{}: @nonnull only applies to interface types", label)));
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_identifier(s: &String) -> bool {
+        let mut chars = s.chars();
+        match chars.next() {
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => chars.all(|c| c.is_ascii_alphanumeric() || c == '_'),
+            _ => false
+        }
+    }
+
+    /// The statement with its declared name qualified by the current module, which is how the
+    /// typer and the generators know it.
+    fn qualify_declaration(&self, stmt: &Statement) -> Result<Statement, ResolveError> {
+        let qualify = |i: &Identifier| -> Result<Identifier, ResolveError> {
+            if i.value.contains('.') {
+                return Err(ResolveError::Resolve(format!("{}: only type references can be qualified, declare the type in its module instead", i.value)));
+            }
+            Ok(Identifier { token: i.token.clone(), value: qualified_name(&self.module, &i.value) })
+        };
+        let kind = match stmt.stmtKind {
+            StatementKind::Enum(ref t, ref i, ref b) => StatementKind::Enum(t.clone(), qualify(i)?, b.clone()),
+            StatementKind::Record(ref t, ref i, ref b, ref d) => StatementKind::Record(t.clone(), qualify(i)?, b.clone(), d.clone()),
+            StatementKind::Interface(ref t, ref i, ref it, ref b, ref d) => {
+                StatementKind::Interface(t.clone(), qualify(i)?, it.clone(), b.clone(), d.clone())
+            },
+            StatementKind::Alias(ref t, ref i, ref dts) => StatementKind::Alias(t.clone(), qualify(i)?, dts.clone()),
+            ref k => k.clone()
+        };
+
+        Ok(Statement { stmtKind: kind, annotations: stmt.annotations.clone() })
     }
 
     fn resolve_statement(&mut self, stmt: &Statement, dup_checker: &mut DuplicateChecker) -> Result<(), ResolveError> {
+        if let StatementKind::Module(_, ref m) = stmt.stmtKind {
+            self.module = m.clone();
+            return Ok(());
+        }
+
+        let stmt = &self.qualify_declaration(stmt)?;
         match stmt.stmtKind {
             StatementKind::Enum(_, ref i, _) => {
                 dup_checker.check(&i.value)?;
@@ -62,6 +367,10 @@ impl Resolver {
             StatementKind::Interface(_, ref i, _, _, _) => {
             dup_checker.check(&i.value)?;
                 self.resolve_interface(&stmt)?
+            },
+            StatementKind::Alias(_, ref i, _) => {
+                dup_checker.check(&i.value)?;
+                self.resolve_alias(&stmt)?
             }
             _ => {
                 
@@ -71,14 +380,31 @@ impl Resolver {
         Ok(())
     }
 
+    /// Aliases are stored expanded, and every type they name is checked since the alias is
+    /// substituted wherever it is used.  A self-referencing alias fails the same way.
+    fn resolve_alias(&mut self, stmt: &Statement) -> Result<(), ResolveError> {
+        if let StatementKind::Alias(_, ref id, ref dts) = stmt.stmtKind {
+            let expanded = self.typer.expand(&self.module, dts);
+            self.declared_check(&id.value, &expanded)?;
+            self.annotation_check(&id.value, &stmt.annotations, None)?;
+            self.typer.insert_annotations(&id.value, &stmt.annotations);
+            self.typer.insert_type(&id.value, TypeDefinitionKind::Alias(expanded))?;
+        }
+
+        Ok(())
+    }
+
     fn resolve_enum(&mut self, stmt: &Statement) -> Result<(), ResolveError> {
         if let StatementKind::Enum(_, ref id, ref b) = stmt.stmtKind {
             let mut dup_checker = DuplicateChecker::new("Enum".into());
-            self.typer.insert_type(&id.value, TypeDefinitionKind::UserObject(id.value.clone()))?;
+            self.typer.insert_type(&id.value, TypeDefinitionKind::UserObject(id.value.clone(), UserObjectKind::Enum))?;
+            self.annotation_check(&id.value, &stmt.annotations, None)?;
+            self.typer.insert_annotations(&id.value, &stmt.annotations);
             for s in &b.statements {
                 match s.stmtKind {
                     StatementKind::EnumMember(_, ref i) => {
-                        dup_checker.check(&i.value)?
+                        dup_checker.check(&i.value)?;
+                        self.annotation_check(&format!("{}.{}", id.value, i.value), &s.annotations, None)?;
                     },
                     _ => {
                         // return Err(ResolveError::ExpectedEnumOption);
@@ -93,18 +419,32 @@ impl Resolver {
     fn resolve_record(&mut self, stmt: &Statement) -> Result<(), ResolveError> {
         if let StatementKind::Record(_, ref id, ref b, ref dt) = stmt.stmtKind {
             let mut dup_checker = DuplicateChecker::new("Record".into());
-            self.typer.insert_type(&id.value, TypeDefinitionKind::UserObject(id.value.clone()))?;
+            self.typer.insert_type(&id.value, TypeDefinitionKind::UserObject(id.value.clone(), UserObjectKind::Record(dt.clone())))?;
+            self.annotation_check(&id.value, &stmt.annotations, None)?;
+            self.typer.insert_annotations(&id.value, &stmt.annotations);
+            let mut fields = Vec::new();
             for s in &b.statements {
                 match s.stmtKind {
-                    StatementKind::RecordMember(_, ref i, ref dts) => {
+                    StatementKind::RecordMember(_, ref i, ref dts, ref default) => {
+                        let dts = &self.typer.expand(&self.module, dts);
+                        fields.push((i, dts.clone()));
                         dup_checker.check(&i.value)?;
-                        self.type_check(&dts.get_name())?;
+                        self.type_check(&id.value, &i.value, dts, Position::Field)?;
+                        if let Some(ref value) = *default {
+                            self.default_check(&id.value, &i.value, dts, value)?;
+                        }
+                        self.annotation_check(&format!("{}.{}", id.value, i.value), &s.annotations, Some(dts))?;
+                    },
+                    StatementKind::Const(_, ref i, _, _) => {
+                        self.annotation_check(&format!("{}.{}", id.value, i.value), &s.annotations, None)?;
                     },
                     _ => {
                         // return Err(ResolveError::ExpectedEnumOption);
                     }
                 }
             }
+            self.derive_check(&id.value, dt, &fields)?;
+            self.typer.insert_fields(&id.value, fields.iter().map(|&(_, ref dts)| dts.clone()).collect());
         }
 
         Ok(())
@@ -113,17 +453,49 @@ impl Resolver {
     fn resolve_interface(&mut self, stmt: &Statement) -> Result<(), ResolveError> {
         if let StatementKind::Interface(_, ref id, ref it, ref b, ref dt) = stmt.stmtKind {
             let mut dup_checker = DuplicateChecker::new("Interface".into());
-            self.typer.insert_type(&id.value, TypeDefinitionKind::UserObject(id.value.clone()))?;
+            if dt.len() > 0 {
+                let derives = dt.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(", ");
+                return Err(ResolveError::Resolve(format!("{}: interfaces can't derive {}, only records can", id.value, derives)));
+            }
+            if it.len() == 0 {
+                return Err(ResolveError::Resolve(format!("{}: interfaces need at least one of +c, +j, +o, +r or +w to say where they are implemented", id.value)));
+            }
+
+            self.typer.insert_type(&id.value, TypeDefinitionKind::UserObject(id.value.clone(), UserObjectKind::Interface(it.clone())))?;
+            self.annotation_check(&id.value, &stmt.annotations, None)?;
+            self.typer.insert_annotations(&id.value, &stmt.annotations);
             for s in &b.statements {
                 match s.stmtKind {
-                    StatementKind::Function(_, ref fm, ref i, ref p, ref dts) => {
+                    StatementKind::Function(_, ref fm, ref i, ref p, ref dts, ref throws) => {
+                        let dts = &self.typer.expand(&self.module, dts);
                         dup_checker.check(&i.value)?;
-                        self.type_check(&dts.get_name())?;
+                        // Static methods have no object to dispatch on, so C++ has to implement them.
+                        if *fm == FunctionModifier::Static && !it.contains(&InterfaceType::Cpp) {
+                            return Err(ResolveError::Resolve(format!("{}.{}: static methods are only allowed on +c interfaces", id.value, i.value)));
+                        }
+                        self.type_check(&id.value, &i.value, dts, Position::Return)?;
+                        // The `+w` wrapper would have to turn the promise back into the C++ future type.
+                        if let DataTypeStatement::Future(..) = *dts {
+                            if it.contains(&InterfaceType::Wasm) {
+                                return Err(ResolveError::Resolve(format!("{}.{}: +w interfaces can't return future<T>, C++ can't wait on a JavaScript promise",
+                                                                         id.value, i.value)));
+                            }
+                        }
+                        self.annotation_check(&format!("{}.{}", id.value, i.value), &s.annotations, Some(dts))?;
 
                         for param in p {
-                            self.type_check(&param.data_type.get_name())?;
+                            let param = &Parameter { data_type: self.typer.expand(&self.module, &param.data_type), ..param.clone() };
+                            self.type_check(&id.value, &i.value, &param.data_type, Position::Parameter)?;
+                            self.annotation_check(&format!("{}.{}.{}", id.value, i.value, param.ident.value), &param.annotations, Some(&param.data_type))?;
+                        }
+
+                        if let Some(ref e) = *throws {
+                            self.error_check(&id.value, &i.value, e)?;
                         }
                     },
+                    StatementKind::Const(_, ref i, _, _) => {
+                        self.annotation_check(&format!("{}.{}", id.value, i.value), &s.annotations, None)?;
+                    },
                     _ => {
                         // return Err(ResolveError::ExpectedEnumOption);
                     }
@@ -134,3 +506,217 @@ impl Resolver {
         Ok(())
     }
 }
+
+/// `Config` declared in `module net` is `net.Config`.
+fn qualified_name(module: &str, name: &String) -> String {
+    if module.len() > 0 { format!("{}.{}", module, name) } else { name.clone() }
+}
+
+fn expand_statement(stmt: &Statement, module: &str, top_level: bool, typer: &Typer) -> Statement {
+    let block = |b: &BlockStatement| BlockStatement {
+        token: b.token.clone(),
+        statements: b.statements.iter().map(|s| expand_statement(s, module, false, typer)).collect()
+    };
+    let declared = |i: &Identifier| if top_level {
+        Identifier { token: i.token.clone(), value: qualified_name(module, &i.value) }
+    } else {
+        i.clone()
+    };
+    let reference = |i: &Identifier| Identifier {
+        token: i.token.clone(),
+        value: typer.lookup(module, &i.value).unwrap_or(i.value.clone())
+    };
+    let kind = match stmt.stmtKind {
+        StatementKind::Enum(ref t, ref i, ref b) => StatementKind::Enum(t.clone(), declared(i), b.clone()),
+        StatementKind::Record(ref t, ref i, ref b, ref d) => StatementKind::Record(t.clone(), declared(i), block(b), d.clone()),
+        StatementKind::Interface(ref t, ref i, ref it, ref b, ref d) => {
+            StatementKind::Interface(t.clone(), declared(i), it.clone(), block(b), d.clone())
+        },
+        StatementKind::RecordMember(ref t, ref i, ref dts, ref v) => {
+            StatementKind::RecordMember(t.clone(), i.clone(), typer.expand(module, dts), v.clone())
+        },
+        StatementKind::Const(ref t, ref i, ref dts, ref v) => StatementKind::Const(t.clone(), i.clone(), typer.expand(module, dts), v.clone()),
+        StatementKind::Alias(ref t, ref i, ref dts) => StatementKind::Alias(t.clone(), declared(i), typer.expand(module, dts)),
+        StatementKind::Function(ref t, ref m, ref i, ref p, ref r, ref e) => {
+            let params = p.iter()
+                .map(|p| Parameter { data_type: typer.expand(module, &p.data_type), ..p.clone() })
+                .collect();
+            StatementKind::Function(t.clone(), m.clone(), i.clone(), params, typer.expand(module, r), e.as_ref().map(|e| reference(e)))
+        },
+        ref k => k.clone()
+    };
+
+    Statement { stmtKind: kind, annotations: stmt.annotations.clone() }
+}
+
+/// `dts` followed by every type nested in it, outermost first.
+pub fn nested_types(dts: &DataTypeStatement) -> Vec<&DataTypeStatement> {
+    let mut types = vec![dts];
+    match *dts {
+        DataTypeStatement::List(ref t) | DataTypeStatement::Array(ref t) | DataTypeStatement::Set(ref t) | DataTypeStatement::Optional(ref t) |
+        DataTypeStatement::Future(ref t) => types.extend(nested_types(t)),
+        DataTypeStatement::Map(ref k, ref v) => {
+            types.extend(nested_types(k));
+            types.extend(nested_types(v));
+        },
+        DataTypeStatement::Function(ref p, ref r) => {
+            for t in p.iter().chain(Some(&**r)) {
+                types.extend(nested_types(t));
+            }
+        },
+        _ => {}
+    }
+    types
+}
+
+/// Whether any record field, method parameter or method result in `program` is or holds a type
+/// `f` accepts.
+fn program_uses(program: &Program, f: fn(&DataTypeStatement) -> bool) -> bool {
+    let uses = |dts: &DataTypeStatement| nested_types(dts).into_iter().any(f);
+    program.statements.iter().any(|stmt| match stmt.stmtKind {
+        StatementKind::Record(_, _, ref bs, _) | StatementKind::Interface(_, _, _, ref bs, _) => {
+            bs.statements.iter().any(|s| match s.stmtKind {
+                StatementKind::RecordMember(_, _, ref dts, _) => uses(dts),
+                StatementKind::Function(_, _, _, ref params, ref ret, _) => uses(ret) || params.iter().any(|p| uses(&p.data_type)),
+                _ => false
+            })
+        },
+        _ => false
+    })
+}
+
+/// Whether `program` uses `optional<T>` anywhere.
+pub fn program_uses_optional(program: &Program) -> bool {
+    program_uses(program, |t| match *t { DataTypeStatement::Optional(..) => true, _ => false })
+}
+
+/// Whether any method in `program` is async, `type_check` allows `future<T>` nowhere else.
+pub fn program_uses_future(program: &Program) -> bool {
+    program_uses(program, |t| match *t { DataTypeStatement::Future(..) => true, _ => false })
+}
+
+/// Whether any method in `program` takes or returns a `fn(...) -> T`, `type_check` allows them
+/// nowhere else.
+pub fn program_uses_functions(program: &Program) -> bool {
+    program_uses(program, |t| match *t { DataTypeStatement::Function(..) => true, _ => false })
+}
+
+/// Whether any declaration, member or parameter in `program` carries the annotation `name`.
+pub fn program_uses_annotation(program: &Program, name: &str) -> bool {
+    program.statements.iter().any(|stmt| stmt.annotation(name).is_some() || match stmt.stmtKind {
+        StatementKind::Enum(_, _, ref bs) | StatementKind::Record(_, _, ref bs, _) | StatementKind::Interface(_, _, _, ref bs, _) => {
+            bs.statements.iter().any(|s| s.annotation(name).is_some() || match s.stmtKind {
+                StatementKind::Function(_, _, _, ref params, _, _) => params.iter().any(|p| p.annotation(name).is_some()),
+                _ => false
+            })
+        },
+        _ => false
+    })
+}
+
+/// Whether any file in `program` declares a `module`.
+pub fn program_uses_modules(program: &Program) -> bool {
+    program.statements.iter().any(|stmt| match stmt.stmtKind {
+        StatementKind::Module(_, ref m) => m.len() > 0,
+        _ => false
+    })
+}
+
+/// Whether any method in `program` declares the error it throws.
+pub fn program_uses_throws(program: &Program) -> bool {
+    program_errors(program).len() > 0
+}
+
+/// The error types thrown by methods in `program`, each once and in order of first use.
+pub fn program_errors(program: &Program) -> Vec<Identifier> {
+    let mut errors: Vec<Identifier> = Vec::new();
+    for stmt in &program.statements {
+        if let StatementKind::Interface(_, _, _, ref bs, _) = stmt.stmtKind {
+            for s in &bs.statements {
+                if let StatementKind::Function(_, _, _, _, _, Some(ref e)) = s.stmtKind {
+                    if !errors.iter().any(|known| known.value == e.value) {
+                        errors.push(e.clone());
+                    }
+                }
+            }
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use config::Config;
+    use generator::testing::{self, expect_error};
+
+    #[test]
+    fn test_declared_check() {
+        expect_error("store = interface +c { load(ids: map<string, list<missing_type>>); }", "type not found: missing_type");
+        expect_error("listener = interface +c { watch(filter: fn(missing) -> bool); }", "type not found: missing");
+        expect_error("nested = alias list<nested>;", "type not found: nested");
+        expect_error("settings = record { config: net.config; }", "type not found: net.config");
+
+        expect_error("frame = record { samples: array; }", "frame.samples: array needs 1 type argument, as in array<T>");
+        expect_error("sensor = interface +c { read(): optional<list>; }", "sensor.read: list needs 1 type argument, as in list<T>");
+        expect_error("sensor = interface +c { write(ids: set); }", "sensor.write: set needs 1 type argument, as in set<T>");
+        expect_error("tags = alias map;", "tags: map needs 2 type arguments, as in map<K, V>");
+        expect_error("store = interface +c { fetch(): future; }", "store.fetch: future needs 1 type argument, as in future<T>");
+    }
+
+    #[test]
+    fn test_type_check() {
+        expect_error("key = record { pending: future<i32>; }", "key.pending: future<T> can only be the return type of a method");
+        expect_error("key = record { on_change: fn(key); }", "key.on_change: fn types can only be used in method parameters and return types");
+        expect_error("frame = record { names: array<string>; }", "frame.names: array<T> can only hold i8, i16, i32, i64, f32 or f64, not string");
+
+        // The interface is declared after the records that hold it.
+        expect_error("job = record { l: listener; }\nlistener = interface +j { done(); }",
+                     "job.l: records can't hold interface listener by value, only in a list, set or map");
+        expect_error("job = record { l: optional<listener>; }\nlistener = interface +c { done(); }",
+                     "job.l: records can't hold interface listener by value, only in a list, set or map");
+        testing::compile(Config::default(), "job = record { listeners: list<listener>; by_name: map<string, listener>; }\n\
+                                             listener = interface +c { done(); }\n");
+    }
+
+    #[test]
+    fn test_derive_check() {
+        expect_error("tag = record { name: string; } deriving (hash)", "tag: deriving(hash) requires eq as well");
+        expect_error("tag = record { names: list<string>; } deriving (eq, hash)",
+                     "tag.names: deriving(hash) doesn't support list<string> fields, only primitives, strings, enums and records deriving hash \
+                      or optionals of those");
+        expect_error("point = record { x: i32; } deriving (eq)\nshape = record { p: point; } deriving (show)",
+                     "shape.p: deriving(show) doesn't support point fields, only primitives, strings, enums and records deriving show or optionals of those");
+        expect_error("key = record { tags: set<string>; } deriving (ord)",
+                     "key.tags: deriving(ord) can't order set<string> fields, maps, sets, binary and arrays have no order");
+        expect_error("key = record { data: optional<binary>; } deriving (eq, ord)",
+                     "key.data: deriving(ord) can't order optional<binary> fields, maps, sets, binary and arrays have no order");
+        expect_error("point = record { x: i32; }\nkey = record { points: map<string, point>; } deriving (eq)",
+                     "key.points: deriving(eq) doesn't support map<string, point> fields, only primitives, strings, binary, dates, arrays, enums, \
+                      records deriving eq or ord, and lists, sets and maps of them or optionals of those");
+
+        testing::compile(Config::default(), "point = record { x: i32; } deriving (ord)\n\
+                                             key = record { points: list<point>; when: optional<date>; data: binary; } deriving (eq)\n");
+    }
+
+    #[test]
+    fn test_interface_rules() {
+        expect_error("store = interface +c { close(); } deriving (eq)", "store: interfaces can't derive eq, only records can");
+        expect_error("store = interface { close(); }", "store: interfaces need at least one of +c, +j, +o, +r or +w to say where they are implemented");
+        expect_error("store = interface +j +o { static open(): store; }", "store.open: static methods are only allowed on +c interfaces");
+        expect_error("store = interface +c +w { fetch(): future<string>; }",
+                     "store.fetch: +w interfaces can't return future<T>, C++ can't wait on a JavaScript promise");
+        expect_error("store = interface +c { close() throws store; }", "store.close: throws store must name a record or an enum");
+
+        testing::compile(Config::default(), "store = interface +c { static open(): store; }");
+    }
+
+    #[test]
+    fn test_default_check() {
+        expect_error("limits = record { low: i8 = 300; }", "limits.low: default value 300 does not match type i8");
+    }
+
+    #[test]
+    fn test_annotation_check() {
+        expect_error("key = record { @nonnull id: i64; }", "key.id: @nonnull only applies to interface types");
+    }
+}
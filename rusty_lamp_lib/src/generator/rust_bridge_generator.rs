@@ -0,0 +1,1285 @@
+/*
+ * Copyright © 2002-2017 Bluebeam Software, Inc. All Rights Reserved.
+ * Creator: Jonathan Rothberg
+ */
+
+use std::io::{Write};
+use std::path::{PathBuf};
+use generator::generator::{Generate};
+use generator::spec::Spec;
+use generator::output::GeneratedOutput;
+use generator::cpp_marshaler::CppMarshaler;
+use generator::rust_marshaler::RustMarshaler;
+use parser::program::Program;
+use generator::resolver::program_uses_optional;
+use parser::ast::{StatementKind, DataTypeStatement, FunctionModifier, Identifier, InterfaceType, Parameter, BlockStatement};
+
+// The bridge modules live in `<rust out>/bridge`, one level below the generated types.
+const TYPES_PATH: &'static str = "super::super::";
+
+struct Method<'a> {
+    modifier: &'a FunctionModifier,
+    ident: &'a Identifier,
+    params: &'a Vec<Parameter>,
+    ret: &'a DataTypeStatement
+}
+
+impl<'a> Method<'a> {
+    fn all(bs: &'a BlockStatement) -> Vec<Method<'a>> {
+        bs.statements.iter()
+            .filter_map(|s| match s.stmtKind {
+                StatementKind::Function(_, ref m, ref i, ref p, ref r, _) => Some(Method { modifier: m, ident: i, params: p, ret: r }),
+                _ => None
+            })
+            .collect()
+    }
+
+    fn is_static(&self) -> bool {
+        *self.modifier == FunctionModifier::Static
+    }
+}
+
+/// Bridges `+c` interfaces into Rust and `+r` interfaces into C++.  Values cross the
+/// `extern "C"` boundary encoded into byte buffers, and interfaces cross as handles that
+/// own one strong reference: a heap allocated `std::shared_ptr` for C++ objects and a
+/// boxed `Arc` for Rust objects.
+pub struct RustBridgeGenerator {
+
+}
+
+impl RustBridgeGenerator {
+    pub fn new() -> RustBridgeGenerator {
+        RustBridgeGenerator {
+
+        }
+    }
+
+    fn write_header(&self, w: &mut Write) {
+        writeln!(w, "// AUTOGENERATED FILE - DO NOT MODIFY!");
+        writeln!(w, "// This file was generated by rusty_lamp");
+        writeln!(w, "");
+    }
+
+    fn rust_path(&self, spec: &Spec, file_name: &String) -> PathBuf {
+        let folder = PathBuf::from(spec.config.rust.out.as_ref().map_or("", |s| s.as_str())).join("bridge");
+        self.make_path(folder.to_str().unwrap_or(""), file_name, "rs")
+    }
+
+    fn cpp_path(&self, spec: &Spec, file_name: &str, ext: &str) -> PathBuf {
+        self.make_path(spec.config.rust.bridge_out.as_ref().map_or("", |s| s.as_str()), &file_name.to_string(), ext)
+    }
+
+    fn file_name(&self, spec: &Spec, i: &Identifier) -> String {
+        RustMarshaler::escape((spec.rust_file_ident_style)(i.value.clone()))
+    }
+
+    fn symbol(i: &Identifier, side: &str, method: &str) -> String {
+        format!("rl_bridge_{}_{}_{}", i.value, side, method)
+    }
+
+    fn is_bridged(it: &Vec<InterfaceType>) -> bool {
+        it.contains(&InterfaceType::Cpp) || it.contains(&InterfaceType::Rust)
+    }
+
+    fn write_cpp_wrapper(&self, w: &mut Write, marshaler: &RustMarshaler, id: &Identifier, methods: &Vec<Method>) {
+        let name = marshaler.type_ident(id);
+
+        writeln!(w, "unsafe extern \"C\" {{");
+        writeln!(w, "    fn {}(handle: *mut c_void);", RustBridgeGenerator::symbol(id, "cpp", "release"));
+        writeln!(w, "    fn {}(handle: *mut c_void) -> *mut c_void;", RustBridgeGenerator::symbol(id, "cpp", "retain"));
+        for m in methods {
+            writeln!(w, "    fn {}(handle: *mut c_void, args: *const u8, len: usize, out: *mut Buffer) -> i32;", RustBridgeGenerator::symbol(id, "cpp", &m.ident.value));
+        }
+        writeln!(w, "}}");
+        writeln!(w, "");
+
+        writeln!(w, "/// A C++ `{}` held through a `std::shared_ptr`; dropping it releases that reference.", id.value);
+        writeln!(w, "pub struct {}Cpp {{", name);
+        writeln!(w, "    handle: *mut c_void");
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "// Shared between threads the same way the `std::shared_ptr` it holds is.");
+        writeln!(w, "unsafe impl Send for {}Cpp {{}}", name);
+        writeln!(w, "unsafe impl Sync for {}Cpp {{}}", name);
+        writeln!(w, "");
+        writeln!(w, "impl Drop for {}Cpp {{", name);
+        writeln!(w, "    fn drop(&mut self) {{");
+        writeln!(w, "        unsafe {{ {}(self.handle) }}", RustBridgeGenerator::symbol(id, "cpp", "release"));
+        writeln!(w, "    }}");
+        writeln!(w, "}}");
+        writeln!(w, "");
+
+        writeln!(w, "impl {}{} for {}Cpp {{", TYPES_PATH, name, name);
+        for m in methods {
+            writeln!(w, "    {} {{", marshaler.method_signature(m.modifier, m.ident, m.params, m.ret, None));
+            let binding = if m.params.len() > 0 { "let mut rl_args" } else { "let rl_args" };
+            writeln!(w, "        {} = Writer::new();", binding);
+            for p in m.params {
+                writeln!(w, "        {}.encode(&mut rl_args);", marshaler.local_ident(&p.ident));
+            }
+
+            let handle = if m.is_static() { "std::ptr::null_mut()" } else { "self.handle" };
+            let call = format!("support::invoke(rl_args, |a, l, o| unsafe {{ {}({}, a, l, o) }})", RustBridgeGenerator::symbol(id, "cpp", &m.ident.value), handle);
+            match *m.ret {
+                DataTypeStatement::None => {
+                    writeln!(w, "        {};", call);
+                },
+                _ => {
+                    writeln!(w, "        let rl_result = {};", call);
+                    writeln!(w, "        Decode::decode(&mut Reader::new(&rl_result))");
+                }
+            }
+            writeln!(w, "    }}");
+            writeln!(w, "");
+        }
+        writeln!(w, "    fn cpp_proxy_handle(&self) -> *mut c_void {{");
+        writeln!(w, "        self.handle");
+        writeln!(w, "    }}");
+        writeln!(w, "}}");
+        writeln!(w, "");
+    }
+
+    fn write_rust_exports(&self, w: &mut Write, marshaler: &RustMarshaler, id: &Identifier, methods: &Vec<Method>) {
+        let arc = format!("Arc<dyn {}{}>", TYPES_PATH, marshaler.type_ident(id));
+
+        writeln!(w, "#[unsafe(no_mangle)]");
+        writeln!(w, "pub extern \"C\" fn {}(rl_handle: *mut c_void) {{", RustBridgeGenerator::symbol(id, "rust", "release"));
+        writeln!(w, "    unsafe {{ drop(Box::from_raw(rl_handle as *mut {})) }}", arc);
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "#[unsafe(no_mangle)]");
+        writeln!(w, "pub extern \"C\" fn {}(rl_handle: *mut c_void) -> *mut c_void {{", RustBridgeGenerator::symbol(id, "rust", "retain"));
+        writeln!(w, "    let rl_self = unsafe {{ &*(rl_handle as *const {}) }};", arc);
+        writeln!(w, "    Box::into_raw(Box::new(rl_self.clone())) as *mut c_void");
+        writeln!(w, "}}");
+        writeln!(w, "");
+
+        // Static methods have no Rust receiver to call, C++ implements those itself.
+        for m in methods.iter().filter(|m| !m.is_static()) {
+            let (args, len) = if m.params.len() > 0 { ("rl_args", "rl_len") } else { ("_rl_args", "_rl_len") };
+            writeln!(w, "#[unsafe(no_mangle)]");
+            writeln!(w, "pub extern \"C\" fn {}(rl_handle: *mut c_void, {}: *const u8, {}: usize, rl_out: *mut Buffer) -> i32 {{",
+                     RustBridgeGenerator::symbol(id, "rust", &m.ident.value), args, len);
+            writeln!(w, "    support::export(rl_out, || {{");
+            writeln!(w, "        let rl_self = unsafe {{ &*(rl_handle as *const {}) }};", arc);
+            if m.params.len() > 0 {
+                writeln!(w, "        let mut rl_reader = Reader::new(unsafe {{ support::args(rl_args, rl_len) }});");
+            }
+
+            let mut call_args = Vec::new();
+            for p in m.params {
+                let local = marshaler.local_ident(&p.ident);
+                writeln!(w, "        let {}: {} = Decode::decode(&mut rl_reader);", local, marshaler.type_name(&p.data_type));
+                if marshaler.param_type(&p.data_type).starts_with('&') {
+                    call_args.push(format!("&{}", local));
+                }
+                else {
+                    call_args.push(local);
+                }
+            }
+
+            let call = format!("rl_self.{}({})", marshaler.method_ident(m.ident), call_args.join(", "));
+            match *m.ret {
+                DataTypeStatement::None => {
+                    writeln!(w, "        {};", call);
+                    writeln!(w, "        Writer::new()");
+                },
+                _ => {
+                    writeln!(w, "        let mut rl_writer = Writer::new();");
+                    writeln!(w, "        {}.encode(&mut rl_writer);", call);
+                    writeln!(w, "        rl_writer");
+                }
+            }
+            writeln!(w, "    }})");
+            writeln!(w, "}}");
+            writeln!(w, "");
+        }
+    }
+
+    fn write_rust_interface_codec(&self, w: &mut Write, marshaler: &RustMarshaler, id: &Identifier, it: &Vec<InterfaceType>) {
+        let name = marshaler.type_ident(id);
+        let arc = format!("Arc<dyn {}{}>", TYPES_PATH, name);
+        let cpp = it.contains(&InterfaceType::Cpp);
+        let rust = it.contains(&InterfaceType::Rust);
+
+        writeln!(w, "impl Encode for {} {{", arc);
+        writeln!(w, "    fn encode(&self, w: &mut Writer) {{");
+        if cpp {
+            writeln!(w, "        let handle = self.cpp_proxy_handle();");
+            writeln!(w, "        if !handle.is_null() {{");
+            writeln!(w, "            0u8.encode(w);");
+            writeln!(w, "            (unsafe {{ {}(handle) }} as usize as u64).encode(w);", RustBridgeGenerator::symbol(id, "cpp", "retain"));
+            writeln!(w, "            return;");
+            writeln!(w, "        }}");
+        }
+        if rust {
+            if cpp {
+                writeln!(w, "");
+            }
+            writeln!(w, "        1u8.encode(w);");
+            writeln!(w, "        (Box::into_raw(Box::new(self.clone())) as usize as u64).encode(w);");
+        }
+        else {
+            writeln!(w, "        panic!(\"{} can only be implemented in C++\");", id.value);
+        }
+        writeln!(w, "    }}");
+        writeln!(w, "");
+        writeln!(w, "    fn encode_option(v: &Option<Self>, w: &mut Writer) {{");
+        writeln!(w, "        match *v {{");
+        writeln!(w, "            Some(ref v) => v.encode(w),");
+        writeln!(w, "            None => {{");
+        writeln!(w, "                2u8.encode(w);");
+        writeln!(w, "                0u64.encode(w);");
+        writeln!(w, "            }}");
+        writeln!(w, "        }}");
+        writeln!(w, "    }}");
+        writeln!(w, "}}");
+        writeln!(w, "");
+
+        writeln!(w, "impl Decode for {} {{", arc);
+        writeln!(w, "    fn decode(r: &mut Reader) -> Self {{");
+        writeln!(w, "        Self::decode_option(r).expect(\"rust bridge: expected a {} but got null\")", id.value);
+        writeln!(w, "    }}");
+        writeln!(w, "");
+        writeln!(w, "    fn decode_option(r: &mut Reader) -> Option<Self> {{");
+        writeln!(w, "        let tag = u8::decode(r);");
+        writeln!(w, "        let handle = u64::decode(r) as usize as *mut c_void;");
+        writeln!(w, "        match tag {{");
+        if cpp {
+            writeln!(w, "            0 => Some(Arc::new({}Cpp {{ handle: handle }}) as {}),", name, arc);
+        }
+        if rust {
+            writeln!(w, "            1 => Some(unsafe {{ *Box::from_raw(handle as *mut {}) }}),", arc);
+        }
+        writeln!(w, "            _ => None");
+        writeln!(w, "        }}");
+        writeln!(w, "    }}");
+        writeln!(w, "}}");
+    }
+
+    fn write_cpp_declarations(&self, w: &mut Write, spec: &Spec, program: &Program) {
+        let marshaler = CppMarshaler::new(spec);
+        writeln!(w, "namespace rusty_lamp_bridge {{");
+        if program_uses_optional(program) {
+            let optional = &spec.config.cpp.optional_template;
+            writeln!(w, "");
+            writeln!(w, "template <typename T>");
+            writeln!(w, "struct Codec<{}<T>> {{", optional);
+            writeln!(w, "    static void write(Writer & w, const {}<T> & v) {{", optional);
+            writeln!(w, "        Codec<bool>::write(w, static_cast<bool>(v));");
+            writeln!(w, "        if (v) {{");
+            writeln!(w, "            Codec<T>::write(w, *v);");
+            writeln!(w, "        }}");
+            writeln!(w, "    }}");
+            writeln!(w, "");
+            writeln!(w, "    static {}<T> read(Reader & r) {{", optional);
+            writeln!(w, "        if (!Codec<bool>::read(r)) {{");
+            writeln!(w, "            return {{}};");
+            writeln!(w, "        }}");
+            writeln!(w, "        return Codec<T>::read(r);");
+            writeln!(w, "    }}");
+            writeln!(w, "}};");
+        }
+        for stmt in &program.statements {
+            let ty = match stmt.stmtKind {
+                StatementKind::Record(_, ref i, _, _) => marshaler.fq_name(&i.value),
+                StatementKind::Interface(_, ref i, ref it, _, _) if RustBridgeGenerator::is_bridged(it) => {
+                    format!("std::shared_ptr<{}>", marshaler.fq_name(&i.value))
+                },
+                _ => continue
+            };
+
+            writeln!(w, "");
+            writeln!(w, "template <>");
+            writeln!(w, "struct Codec<{}> {{", ty);
+            writeln!(w, "    static void write(Writer & w, const {} & v);", ty);
+            writeln!(w, "    static {} read(Reader & r);", ty);
+            writeln!(w, "}};");
+        }
+        writeln!(w, "");
+        writeln!(w, "}} // namespace rusty_lamp_bridge");
+    }
+
+    fn write_cpp_rust_proxy(&self, w: &mut Write, marshaler: &CppMarshaler, id: &Identifier, methods: &Vec<Method>) {
+        writeln!(w, "class {}RustProxy final : public {} {{", id.value, marshaler.fq_name(&id.value));
+        writeln!(w, "public:");
+        writeln!(w, "    explicit {}RustProxy(void * handle) : m_handle(handle) {{}}", id.value);
+        writeln!(w, "    ~{}RustProxy() override {{ {}(m_handle); }}", id.value, RustBridgeGenerator::symbol(id, "rust", "release"));
+        writeln!(w, "    void * handle() const {{ return m_handle; }}");
+        for m in methods.iter().filter(|m| !m.is_static()) {
+            let params = m.params.iter()
+                .map(|p| format!("{} {}", self.fq_param_type(marshaler, &p.data_type), p.ident.value))
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(w, "");
+            writeln!(w, "    {} {}({}) override {{", marshaler.fq_type_name(m.ret), m.ident.value, params);
+            writeln!(w, "        Writer rl_args;");
+            for p in m.params {
+                writeln!(w, "        Codec<{}>::write(rl_args, {});", marshaler.fq_type_name(&p.data_type), p.ident.value);
+            }
+            let call = format!("invoke(rl_args, [this](const uint8_t * a, size_t l, Buffer * o) {{ return {}(m_handle, a, l, o); }})", RustBridgeGenerator::symbol(id, "rust", &m.ident.value));
+            match *m.ret {
+                DataTypeStatement::None => {
+                    writeln!(w, "        {};", call);
+                },
+                _ => {
+                    writeln!(w, "        auto rl_result = {};", call);
+                    writeln!(w, "        Reader rl_reader(rl_result.data(), rl_result.size());");
+                    writeln!(w, "        return Codec<{}>::read(rl_reader);", marshaler.fq_type_name(m.ret));
+                }
+            }
+            writeln!(w, "    }}");
+        }
+        writeln!(w, "");
+        writeln!(w, "private:");
+        writeln!(w, "    void * m_handle;");
+        writeln!(w, "}};");
+        writeln!(w, "");
+    }
+
+    fn fq_param_type(&self, marshaler: &CppMarshaler, dts: &DataTypeStatement) -> String {
+        let name = marshaler.fq_type_name(dts);
+        if marshaler.param_type(dts).starts_with("const ") {
+            format!("const {} &", name)
+        }
+        else {
+            name
+        }
+    }
+
+    fn write_cpp_interface_codec(&self, w: &mut Write, marshaler: &CppMarshaler, id: &Identifier, it: &Vec<InterfaceType>) {
+        let ty = format!("std::shared_ptr<{}>", marshaler.fq_name(&id.value));
+        let cpp = it.contains(&InterfaceType::Cpp);
+        let rust = it.contains(&InterfaceType::Rust);
+
+        writeln!(w, "void Codec<{}>::write(Writer & w, const {} & v) {{", ty, ty);
+        if rust {
+            writeln!(w, "    if (auto proxy = dynamic_cast<{}RustProxy *>(v.get())) {{", id.value);
+            writeln!(w, "        Codec<uint8_t>::write(w, 1);");
+            writeln!(w, "        write_handle(w, {}(proxy->handle()));", RustBridgeGenerator::symbol(id, "rust", "retain"));
+            writeln!(w, "        return;");
+            writeln!(w, "    }}");
+        }
+        writeln!(w, "    if (v) {{");
+        if cpp {
+            writeln!(w, "        Codec<uint8_t>::write(w, 0);");
+            writeln!(w, "        write_handle(w, new {}(v));", ty);
+            writeln!(w, "        return;");
+        }
+        else {
+            writeln!(w, "        throw std::logic_error(\"{} can only be implemented in Rust\");", id.value);
+        }
+        writeln!(w, "    }}");
+        writeln!(w, "    Codec<uint8_t>::write(w, 2);");
+        writeln!(w, "    write_handle(w, nullptr);");
+        writeln!(w, "}}");
+        writeln!(w, "");
+
+        writeln!(w, "{} Codec<{}>::read(Reader & r) {{", ty, ty);
+        writeln!(w, "    uint8_t tag = Codec<uint8_t>::read(r);");
+        writeln!(w, "    void * handle = read_handle(r);");
+        if cpp {
+            writeln!(w, "    if (tag == 0) {{");
+            writeln!(w, "        auto owned = static_cast<{} *>(handle);", ty);
+            writeln!(w, "        {} v = *owned;", ty);
+            writeln!(w, "        delete owned;");
+            writeln!(w, "        return v;");
+            writeln!(w, "    }}");
+        }
+        if rust {
+            writeln!(w, "    if (tag == 1) {{");
+            writeln!(w, "        return std::make_shared<{}RustProxy>(handle);", id.value);
+            writeln!(w, "    }}");
+        }
+        writeln!(w, "    return nullptr;");
+        writeln!(w, "}}");
+        writeln!(w, "");
+    }
+
+    fn write_cpp_record_codec(&self, w: &mut Write, marshaler: &CppMarshaler, id: &Identifier, bs: &BlockStatement) {
+        let ty = marshaler.fq_name(&id.value);
+        let fields = bs.statements.iter()
+            .filter_map(|s| match s.stmtKind { StatementKind::RecordMember(_, ref i, ref dts, _) => Some((i, dts)), _ => None })
+            .collect::<Vec<_>>();
+
+        writeln!(w, "void Codec<{}>::write(Writer & w, const {} & v) {{", ty, ty);
+        for &(i, dts) in &fields {
+            writeln!(w, "    Codec<{}>::write(w, v.{});", marshaler.fq_type_name(dts), i.value);
+        }
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "{} Codec<{}>::read(Reader & r) {{", ty, ty);
+        writeln!(w, "    {} v;", ty);
+        for &(i, dts) in &fields {
+            writeln!(w, "    v.{} = Codec<{}>::read(r);", i.value, marshaler.fq_type_name(dts));
+        }
+        writeln!(w, "    return v;");
+        writeln!(w, "}}");
+        writeln!(w, "");
+    }
+
+    fn write_cpp_shims(&self, w: &mut Write, marshaler: &CppMarshaler, id: &Identifier, methods: &Vec<Method>) {
+        let ptr = format!("std::shared_ptr<{}>", marshaler.fq_name(&id.value));
+
+        writeln!(w, "void {}(void * rl_handle) {{", RustBridgeGenerator::symbol(id, "cpp", "release"));
+        writeln!(w, "    delete static_cast<{} *>(rl_handle);", ptr);
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "void * {}(void * rl_handle) {{", RustBridgeGenerator::symbol(id, "cpp", "retain"));
+        writeln!(w, "    return new {}(*static_cast<{} *>(rl_handle));", ptr, ptr);
+        writeln!(w, "}}");
+        writeln!(w, "");
+
+        for m in methods {
+            // Static methods are called with a null handle.
+            let handle = if m.is_static() { "void *" } else { "void * rl_handle" };
+            writeln!(w, "int32_t {}({}, const uint8_t * rl_args, size_t rl_len, rusty_lamp_bridge::Buffer * rl_out) {{",
+                     RustBridgeGenerator::symbol(id, "cpp", &m.ident.value), handle);
+            writeln!(w, "    using namespace rusty_lamp_bridge;");
+            writeln!(w, "    return call(rl_args, rl_len, rl_out, [&](Reader & rl_reader, Writer & rl_writer) {{");
+            let target = if m.is_static() {
+                format!("{}::", marshaler.fq_name(&id.value))
+            }
+            else {
+                writeln!(w, "        auto & rl_self = *static_cast<{} *>(rl_handle);", ptr);
+                "rl_self->".to_string()
+            };
+            for p in m.params {
+                writeln!(w, "        auto {} = Codec<{}>::read(rl_reader);", p.ident.value, marshaler.fq_type_name(&p.data_type));
+            }
+
+            let call = format!("{}{}({})", target, m.ident.value, m.params.iter().map(|p| p.ident.value.clone()).collect::<Vec<_>>().join(", "));
+            match *m.ret {
+                DataTypeStatement::None => {
+                    writeln!(w, "        {};", call);
+                    writeln!(w, "        (void)rl_writer;");
+                },
+                _ => {
+                    writeln!(w, "        Codec<{}>::write(rl_writer, {});", marshaler.fq_type_name(m.ret), call);
+                }
+            }
+            if m.params.len() == 0 {
+                writeln!(w, "        (void)rl_reader;");
+            }
+            writeln!(w, "    }});");
+            writeln!(w, "}}");
+            writeln!(w, "");
+        }
+    }
+}
+
+impl Generate for RustBridgeGenerator {
+    fn write_enum(&self, e: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
+        if let StatementKind::Enum(_, ref i, ref b) = *e {
+            let marshaler = RustMarshaler::with_path(spec, TYPES_PATH);
+            let name = marshaler.type_name(&DataTypeStatement::Object(i.clone()));
+            let mut w = Vec::new();
+            self.write_header(&mut w);
+
+            writeln!(w, "use super::support::{{Decode, Encode, Reader, Writer}};");
+            writeln!(w, "");
+            writeln!(w, "impl Encode for {} {{", name);
+            writeln!(w, "    fn encode(&self, w: &mut Writer) {{");
+            writeln!(w, "        (*self as i32).encode(w)");
+            writeln!(w, "    }}");
+            writeln!(w, "}}");
+            writeln!(w, "");
+            writeln!(w, "impl Decode for {} {{", name);
+            writeln!(w, "    fn decode(r: &mut Reader) -> Self {{");
+            writeln!(w, "        match i32::decode(r) {{");
+            let members = b.statements.iter()
+                .filter_map(|s| match s.stmtKind { StatementKind::EnumMember(_, ref oi) => Some(oi), _ => None });
+            for (n, oi) in members.enumerate() {
+                writeln!(w, "            {} => {}::{},", n, name, RustMarshaler::escape((spec.rust_ident_style.enm)(oi.value.clone())));
+            }
+            writeln!(w, "            v => panic!(\"rust bridge: invalid {} value {{}}\", v)", i.value);
+            writeln!(w, "        }}");
+            writeln!(w, "    }}");
+            writeln!(w, "}}");
+
+            output.add_file(self.rust_path(spec, &self.file_name(spec, i)), w);
+        }
+    }
+
+    fn write_record(&self, r: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
+        if let StatementKind::Record(_, ref i, ref bs, _) = *r {
+            let marshaler = RustMarshaler::with_path(spec, TYPES_PATH);
+            let name = marshaler.type_name(&DataTypeStatement::Object(i.clone()));
+            let fields = bs.statements.iter()
+                .filter_map(|s| match s.stmtKind {
+                    StatementKind::RecordMember(_, ref fi, _, _) => Some(RustMarshaler::escape((spec.rust_ident_style.field)(fi.value.clone()))),
+                    _ => None
+                })
+                .collect::<Vec<_>>();
+            let (writer, reader) = if fields.len() > 0 { ("w", "r") } else { ("_w", "_r") };
+
+            let mut w = Vec::new();
+            self.write_header(&mut w);
+
+            writeln!(w, "use super::support::{{Decode, Encode, Reader, Writer}};");
+            writeln!(w, "");
+            writeln!(w, "impl Encode for {} {{", name);
+            writeln!(w, "    fn encode(&self, {}: &mut Writer) {{", writer);
+            for f in &fields {
+                writeln!(w, "        self.{}.encode(w);", f);
+            }
+            writeln!(w, "    }}");
+            writeln!(w, "}}");
+            writeln!(w, "");
+            writeln!(w, "impl Decode for {} {{", name);
+            writeln!(w, "    fn decode({}: &mut Reader) -> Self {{", reader);
+            writeln!(w, "        {} {{", name);
+            for f in &fields {
+                writeln!(w, "            {}: Decode::decode(r),", f);
+            }
+            writeln!(w, "        }}");
+            writeln!(w, "    }}");
+            writeln!(w, "}}");
+
+            output.add_file(self.rust_path(spec, &self.file_name(spec, i)), w);
+        }
+    }
+
+    fn write_interface(&self, i: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
+        if let StatementKind::Interface(_, ref id, ref it, ref bs, _) = *i {
+            if !RustBridgeGenerator::is_bridged(it) {
+                return;
+            }
+
+            let marshaler = RustMarshaler::with_path(spec, TYPES_PATH);
+            let methods = Method::all(bs);
+            let mut w = Vec::new();
+            self.write_header(&mut w);
+
+            writeln!(w, "#![allow(non_snake_case, clippy::not_unsafe_ptr_arg_deref)]");
+            writeln!(w, "");
+            writeln!(w, "use std::os::raw::c_void;");
+            writeln!(w, "use std::sync::Arc;");
+            writeln!(w, "#[allow(unused_imports)]");
+            writeln!(w, "use super::support::{{self, Buffer, Decode, Encode, Reader, Writer}};");
+            writeln!(w, "");
+
+            if it.contains(&InterfaceType::Cpp) {
+                self.write_cpp_wrapper(&mut w, &marshaler, id, &methods);
+            }
+            if it.contains(&InterfaceType::Rust) {
+                self.write_rust_exports(&mut w, &marshaler, id, &methods);
+            }
+            self.write_rust_interface_codec(&mut w, &marshaler, id, it);
+
+            output.add_file(self.rust_path(spec, &self.file_name(spec, id)), w);
+        }
+    }
+
+    fn finish(&self, spec: &Spec, program: &Program, output: &mut GeneratedOutput) {
+        let cpp_marshaler = CppMarshaler::new(spec);
+
+        // Rust module tree
+        let mut w = Vec::new();
+        self.write_header(&mut w);
+        writeln!(w, "pub mod support;");
+        for stmt in &program.statements {
+            match stmt.stmtKind {
+                StatementKind::Enum(_, ref i, _) | StatementKind::Record(_, ref i, _, _) => {
+                    writeln!(w, "mod {};", self.file_name(spec, i));
+                },
+                StatementKind::Interface(_, ref i, ref it, _, _) if RustBridgeGenerator::is_bridged(it) => {
+                    writeln!(w, "mod {};", self.file_name(spec, i));
+                    if it.contains(&InterfaceType::Cpp) {
+                        writeln!(w, "pub use self::{}::{}Cpp;", self.file_name(spec, i), (spec.rust_ident_style.ty)(i.value.clone()));
+                    }
+                },
+                _ => {}
+            }
+        }
+        output.add_file(self.rust_path(spec, &"mod".to_string()), w);
+
+        let mut w = Vec::new();
+        self.write_header(&mut w);
+        w.extend(RUST_SUPPORT.as_bytes());
+        output.add_file(self.rust_path(spec, &"support".to_string()), w);
+
+        let mut w = Vec::new();
+        self.write_header(&mut w);
+        w.extend(CPP_SUPPORT.as_bytes());
+        output.add_file(self.cpp_path(spec, "rust_bridge_support", &spec.config.cpp.header_ext), w);
+
+        // C++ header: codec declarations for every user type
+        let mut w = Vec::new();
+        self.write_header(&mut w);
+        writeln!(w, "#pragma once");
+        writeln!(w, "");
+        writeln!(w, "#include \"rust_bridge_support.{}\"", spec.config.cpp.header_ext);
+        for stmt in &program.statements {
+            match stmt.stmtKind {
+                StatementKind::Enum(_, ref i, _) | StatementKind::Record(_, ref i, _, _) => {
+                    writeln!(w, "#include {}", cpp_marshaler.header_include(&i.value));
+                },
+                StatementKind::Interface(_, ref i, ref it, _, _) if RustBridgeGenerator::is_bridged(it) => {
+                    writeln!(w, "#include {}", cpp_marshaler.header_include(&i.value));
+                },
+                _ => {}
+            }
+        }
+        writeln!(w, "");
+        self.write_cpp_declarations(&mut w, spec, program);
+        output.add_file(self.cpp_path(spec, "rust_bridge", &spec.config.cpp.header_ext), w);
+
+        // C++ source: Rust exports, proxies, codecs and the C++ exports
+        let interfaces = program.statements.iter()
+            .filter_map(|s| match s.stmtKind {
+                StatementKind::Interface(_, ref i, ref it, ref bs, _) if RustBridgeGenerator::is_bridged(it) => Some((i, it, Method::all(bs))),
+                _ => None
+            })
+            .collect::<Vec<_>>();
+
+        let mut w = Vec::new();
+        self.write_header(&mut w);
+        writeln!(w, "#include \"rust_bridge.{}\"", spec.config.cpp.header_ext);
+        writeln!(w, "");
+        writeln!(w, "extern \"C\" {{");
+        for &(i, _, ref methods) in interfaces.iter().filter(|&&(_, it, _)| it.contains(&InterfaceType::Rust)) {
+            writeln!(w, "void {}(void * handle);", RustBridgeGenerator::symbol(i, "rust", "release"));
+            writeln!(w, "void * {}(void * handle);", RustBridgeGenerator::symbol(i, "rust", "retain"));
+            for m in methods.iter().filter(|m| !m.is_static()) {
+                writeln!(w, "int32_t {}(void * handle, const uint8_t * args, size_t len, rusty_lamp_bridge::Buffer * out);", RustBridgeGenerator::symbol(i, "rust", &m.ident.value));
+            }
+        }
+        writeln!(w, "}}");
+        writeln!(w, "");
+
+        writeln!(w, "namespace rusty_lamp_bridge {{");
+        writeln!(w, "");
+        for &(i, it, ref methods) in &interfaces {
+            if it.contains(&InterfaceType::Rust) {
+                self.write_cpp_rust_proxy(&mut w, &cpp_marshaler, i, methods);
+            }
+        }
+        for stmt in &program.statements {
+            match stmt.stmtKind {
+                StatementKind::Record(_, ref i, ref bs, _) => self.write_cpp_record_codec(&mut w, &cpp_marshaler, i, bs),
+                StatementKind::Interface(_, ref i, ref it, _, _) if RustBridgeGenerator::is_bridged(it) => {
+                    self.write_cpp_interface_codec(&mut w, &cpp_marshaler, i, it)
+                },
+                _ => {}
+            }
+        }
+        writeln!(w, "}} // namespace rusty_lamp_bridge");
+        writeln!(w, "");
+
+        writeln!(w, "extern \"C\" {{");
+        writeln!(w, "");
+        for &(i, it, ref methods) in &interfaces {
+            if it.contains(&InterfaceType::Cpp) {
+                self.write_cpp_shims(&mut w, &cpp_marshaler, i, methods);
+            }
+        }
+        writeln!(w, "}}");
+        output.add_file(self.cpp_path(spec, "rust_bridge", &spec.config.cpp.ext), w);
+    }
+}
+
+const RUST_SUPPORT: &'static str = r#"//! Encoding shared by the generated Rust <-> C++ bridge.  Both sides run in the
+//! same process, so values are written in native byte order.
+
+#![allow(dead_code)]
+
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::os::raw::c_void;
+use std::panic;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+unsafe extern "C" {
+    fn malloc(size: usize) -> *mut c_void;
+    fn free(ptr: *mut c_void);
+}
+
+/// Bytes handed across the boundary.  They are allocated with `malloc` and freed by the receiver.
+#[repr(C)]
+pub struct Buffer {
+    pub data: *mut u8,
+    pub len: usize
+}
+
+impl Buffer {
+    fn from_bytes(bytes: &[u8]) -> Buffer {
+        unsafe {
+            let data = malloc(bytes.len().max(1)) as *mut u8;
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), data, bytes.len());
+            Buffer { data: data, len: bytes.len() }
+        }
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        if self.data.is_null() {
+            return Vec::new();
+        }
+
+        unsafe {
+            let bytes = std::slice::from_raw_parts(self.data, self.len).to_vec();
+            free(self.data as *mut c_void);
+            bytes
+        }
+    }
+}
+
+pub struct Writer {
+    bytes: Vec<u8>
+}
+
+impl Writer {
+    pub fn new() -> Writer {
+        Writer { bytes: Vec::new() }
+    }
+
+    pub fn raw(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub fn size(&mut self, len: usize) {
+        (len as u32).encode(self);
+    }
+}
+
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader { bytes: bytes, pos: 0 }
+    }
+
+    pub fn raw(&mut self, len: usize) -> &'a [u8] {
+        let end = self.pos + len;
+        if end > self.bytes.len() {
+            panic!("rust bridge: truncated buffer");
+        }
+
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        bytes
+    }
+
+    pub fn size(&mut self) -> usize {
+        u32::decode(self) as usize
+    }
+}
+
+pub trait Encode {
+    fn encode(&self, w: &mut Writer);
+
+    /// Optional values are prefixed with a presence flag; interfaces override this to
+    /// write a null handle instead, matching the `std::shared_ptr` they are in C++.
+    fn encode_option(v: &Option<Self>, w: &mut Writer) where Self: Sized {
+        match *v {
+            Some(ref v) => {
+                true.encode(w);
+                v.encode(w);
+            },
+            None => false.encode(w)
+        }
+    }
+}
+
+pub trait Decode: Sized {
+    fn decode(r: &mut Reader) -> Self;
+
+    fn decode_option(r: &mut Reader) -> Option<Self> {
+        if bool::decode(r) { Some(Self::decode(r)) } else { None }
+    }
+}
+
+macro_rules! number {
+    ($($t:ty),*) => {
+        $(
+            impl Encode for $t {
+                fn encode(&self, w: &mut Writer) {
+                    w.raw(&self.to_ne_bytes());
+                }
+            }
+
+            impl Decode for $t {
+                fn decode(r: &mut Reader) -> $t {
+                    let mut bytes = [0u8; std::mem::size_of::<$t>()];
+                    bytes.copy_from_slice(r.raw(std::mem::size_of::<$t>()));
+                    <$t>::from_ne_bytes(bytes)
+                }
+            }
+        )*
+    }
+}
+
+number!(u8, i8, i16, i32, u32, i64, u64, f32, f64);
+
+impl Encode for bool {
+    fn encode(&self, w: &mut Writer) {
+        (*self as u8).encode(w);
+    }
+}
+
+impl Decode for bool {
+    fn decode(r: &mut Reader) -> bool {
+        u8::decode(r) != 0
+    }
+}
+
+impl Encode for str {
+    fn encode(&self, w: &mut Writer) {
+        w.size(self.len());
+        w.raw(self.as_bytes());
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, w: &mut Writer) {
+        self.as_str().encode(w);
+    }
+}
+
+impl Decode for String {
+    fn decode(r: &mut Reader) -> String {
+        let len = r.size();
+        String::from_utf8(r.raw(len).to_vec()).expect("rust bridge: invalid utf-8")
+    }
+}
+
+impl<T: Encode> Encode for [T] {
+    fn encode(&self, w: &mut Writer) {
+        w.size(self.len());
+        for v in self {
+            v.encode(w);
+        }
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, w: &mut Writer) {
+        self.as_slice().encode(w);
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(r: &mut Reader) -> Vec<T> {
+        let len = r.size();
+        (0..len).map(|_| T::decode(r)).collect()
+    }
+}
+
+impl<T: Encode> Encode for HashSet<T> {
+    fn encode(&self, w: &mut Writer) {
+        w.size(self.len());
+        for v in self {
+            v.encode(w);
+        }
+    }
+}
+
+impl<T: Decode + Eq + Hash> Decode for HashSet<T> {
+    fn decode(r: &mut Reader) -> HashSet<T> {
+        let len = r.size();
+        (0..len).map(|_| T::decode(r)).collect()
+    }
+}
+
+impl<K: Encode, V: Encode> Encode for HashMap<K, V> {
+    fn encode(&self, w: &mut Writer) {
+        w.size(self.len());
+        for (k, v) in self {
+            k.encode(w);
+            v.encode(w);
+        }
+    }
+}
+
+impl<K: Decode + Eq + Hash, V: Decode> Decode for HashMap<K, V> {
+    fn decode(r: &mut Reader) -> HashMap<K, V> {
+        let len = r.size();
+        (0..len).map(|_| {
+            let k = K::decode(r);
+            (k, V::decode(r))
+        }).collect()
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, w: &mut Writer) {
+        T::encode_option(self, w);
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(r: &mut Reader) -> Option<T> {
+        T::decode_option(r)
+    }
+}
+
+// Dates cross as milliseconds since the Unix epoch.
+impl Encode for SystemTime {
+    fn encode(&self, w: &mut Writer) {
+        let millis = match self.duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_millis() as i64,
+            Err(e) => -(e.duration().as_millis() as i64)
+        };
+        millis.encode(w);
+    }
+}
+
+impl Decode for SystemTime {
+    fn decode(r: &mut Reader) -> SystemTime {
+        let millis = i64::decode(r);
+        if millis >= 0 {
+            UNIX_EPOCH + Duration::from_millis(millis as u64)
+        }
+        else {
+            UNIX_EPOCH - Duration::from_millis(millis.unsigned_abs())
+        }
+    }
+}
+
+/// The arguments C++ passed to an exported function.
+pub unsafe fn args<'a>(data: *const u8, len: usize) -> &'a [u8] {
+    if data.is_null() {
+        &[]
+    }
+    else {
+        unsafe { std::slice::from_raw_parts(data, len) }
+    }
+}
+
+/// Calls into C++, panicking with the message of any exception it throws.
+pub fn invoke<F>(args: Writer, call: F) -> Vec<u8> where F: FnOnce(*const u8, usize, *mut Buffer) -> i32 {
+    let mut out = Buffer { data: std::ptr::null_mut(), len: 0 };
+    let status = call(args.bytes.as_ptr(), args.bytes.len(), &mut out);
+    let bytes = out.into_bytes();
+    if status != 0 {
+        panic!("{}", String::from_utf8_lossy(&bytes));
+    }
+
+    bytes
+}
+
+/// Runs a Rust implementation on behalf of C++.  A panic is reported as an error status
+/// instead of unwinding across the boundary, and C++ rethrows it as an exception.
+pub fn export<F>(out: *mut Buffer, f: F) -> i32 where F: FnOnce() -> Writer {
+    let (status, bytes) = match panic::catch_unwind(panic::AssertUnwindSafe(f)) {
+        Ok(w) => (0, w.bytes),
+        Err(e) => {
+            let message = e.downcast_ref::<String>().cloned()
+                .or_else(|| e.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_else(|| "rust panic".to_string());
+            (1, message.into_bytes())
+        }
+    };
+
+    unsafe { *out = Buffer::from_bytes(&bytes); }
+    status
+}
+"#;
+
+const CPP_SUPPORT: &'static str = r#"// Encoding shared by the generated Rust <-> C++ bridge.  Both sides run in the
+// same process, so values are written in native byte order.
+#pragma once
+
+#include <chrono>
+#include <cstdint>
+#include <cstdlib>
+#include <cstring>
+#include <memory>
+#include <stdexcept>
+#include <string>
+#include <type_traits>
+#include <unordered_map>
+#include <unordered_set>
+#include <vector>
+
+namespace rusty_lamp_bridge {
+
+extern "C" {
+// Bytes handed across the boundary.  They are allocated with malloc and freed by the receiver.
+struct Buffer {
+    uint8_t * data;
+    size_t len;
+};
+}
+
+class Writer {
+public:
+    void raw(const void * data, size_t len) {
+        const uint8_t * bytes = static_cast<const uint8_t *>(data);
+        m_bytes.insert(m_bytes.end(), bytes, bytes + len);
+    }
+
+    const uint8_t * data() const { return m_bytes.data(); }
+    size_t size() const { return m_bytes.size(); }
+
+    static Buffer to_buffer(const void * data, size_t len) {
+        Buffer buffer { static_cast<uint8_t *>(std::malloc(len > 0 ? len : 1)), len };
+        if (len > 0) {
+            std::memcpy(buffer.data, data, len);
+        }
+        return buffer;
+    }
+
+private:
+    std::vector<uint8_t> m_bytes;
+};
+
+class Reader {
+public:
+    Reader(const uint8_t * data, size_t len) : m_data(data), m_len(len), m_pos(0) {}
+
+    void raw(void * out, size_t len) {
+        if (m_pos + len > m_len) {
+            throw std::out_of_range("rust bridge: truncated buffer");
+        }
+        std::memcpy(out, m_data + m_pos, len);
+        m_pos += len;
+    }
+
+private:
+    const uint8_t * m_data;
+    size_t m_len;
+    size_t m_pos;
+};
+
+template <typename T, typename Enable = void>
+struct Codec;
+
+template <typename T>
+struct Codec<T, typename std::enable_if<std::is_arithmetic<T>::value>::type> {
+    static void write(Writer & w, T v) { w.raw(&v, sizeof(v)); }
+    static T read(Reader & r) { T v; r.raw(&v, sizeof(v)); return v; }
+};
+
+template <typename T>
+struct Codec<T, typename std::enable_if<std::is_enum<T>::value>::type> {
+    static void write(Writer & w, T v) { Codec<int32_t>::write(w, static_cast<int32_t>(v)); }
+    static T read(Reader & r) { return static_cast<T>(Codec<int32_t>::read(r)); }
+};
+
+inline void write_size(Writer & w, size_t len) { Codec<uint32_t>::write(w, static_cast<uint32_t>(len)); }
+inline size_t read_size(Reader & r) { return Codec<uint32_t>::read(r); }
+
+inline void write_handle(Writer & w, void * handle) { Codec<uint64_t>::write(w, static_cast<uint64_t>(reinterpret_cast<uintptr_t>(handle))); }
+inline void * read_handle(Reader & r) { return reinterpret_cast<void *>(static_cast<uintptr_t>(Codec<uint64_t>::read(r))); }
+
+template <>
+struct Codec<std::string> {
+    static void write(Writer & w, const std::string & v) {
+        write_size(w, v.size());
+        w.raw(v.data(), v.size());
+    }
+
+    static std::string read(Reader & r) {
+        std::string v(read_size(r), '\0');
+        if (!v.empty()) {
+            r.raw(&v[0], v.size());
+        }
+        return v;
+    }
+};
+
+template <typename T>
+struct Codec<std::vector<T>> {
+    static void write(Writer & w, const std::vector<T> & v) {
+        write_size(w, v.size());
+        for (const auto & item : v) {
+            Codec<T>::write(w, item);
+        }
+    }
+
+    static std::vector<T> read(Reader & r) {
+        size_t len = read_size(r);
+        std::vector<T> v;
+        v.reserve(len);
+        for (size_t i = 0; i < len; ++i) {
+            v.push_back(Codec<T>::read(r));
+        }
+        return v;
+    }
+};
+
+template <typename T>
+struct Codec<std::unordered_set<T>> {
+    static void write(Writer & w, const std::unordered_set<T> & v) {
+        write_size(w, v.size());
+        for (const auto & item : v) {
+            Codec<T>::write(w, item);
+        }
+    }
+
+    static std::unordered_set<T> read(Reader & r) {
+        size_t len = read_size(r);
+        std::unordered_set<T> v;
+        for (size_t i = 0; i < len; ++i) {
+            v.insert(Codec<T>::read(r));
+        }
+        return v;
+    }
+};
+
+template <typename K, typename V>
+struct Codec<std::unordered_map<K, V>> {
+    static void write(Writer & w, const std::unordered_map<K, V> & v) {
+        write_size(w, v.size());
+        for (const auto & item : v) {
+            Codec<K>::write(w, item.first);
+            Codec<V>::write(w, item.second);
+        }
+    }
+
+    static std::unordered_map<K, V> read(Reader & r) {
+        size_t len = read_size(r);
+        std::unordered_map<K, V> v;
+        for (size_t i = 0; i < len; ++i) {
+            K key = Codec<K>::read(r);
+            v.emplace(std::move(key), Codec<V>::read(r));
+        }
+        return v;
+    }
+};
+
+// Dates cross as milliseconds since the Unix epoch.
+template <>
+struct Codec<std::chrono::system_clock::time_point> {
+    static void write(Writer & w, const std::chrono::system_clock::time_point & v) {
+        Codec<int64_t>::write(w, std::chrono::duration_cast<std::chrono::milliseconds>(v.time_since_epoch()).count());
+    }
+
+    static std::chrono::system_clock::time_point read(Reader & r) {
+        std::chrono::milliseconds millis(Codec<int64_t>::read(r));
+        return std::chrono::system_clock::time_point(std::chrono::duration_cast<std::chrono::system_clock::duration>(millis));
+    }
+};
+
+// Runs a C++ implementation on behalf of Rust.  Exceptions are reported as an error
+// status instead of unwinding across the boundary, and Rust turns them into a panic.
+template <typename F>
+int32_t call(const uint8_t * args, size_t len, Buffer * out, F && f) {
+    try {
+        Reader r(args, len);
+        Writer w;
+        f(r, w);
+        *out = Writer::to_buffer(w.data(), w.size());
+        return 0;
+    } catch (const std::exception & e) {
+        *out = Writer::to_buffer(e.what(), std::strlen(e.what()));
+        return 1;
+    } catch (...) {
+        static const char message[] = "unknown C++ exception";
+        *out = Writer::to_buffer(message, sizeof(message) - 1);
+        return 1;
+    }
+}
+
+// Calls into Rust, throwing std::runtime_error if the implementation panicked.
+template <typename F>
+std::vector<uint8_t> invoke(const Writer & args, F && f) {
+    Buffer out { nullptr, 0 };
+    int32_t status = f(args.data(), args.size(), &out);
+    std::vector<uint8_t> bytes(out.data, out.data + out.len);
+    std::free(out.data);
+    if (status != 0) {
+        throw std::runtime_error(std::string(bytes.begin(), bytes.end()));
+    }
+    return bytes;
+}
+
+} // namespace rusty_lamp_bridge
+"#;
+
+#[cfg(test)]
+mod tests {
+    use generator::testing;
+    use config::Config;
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.cpp.namespace = "demo".into();
+        config.rust.out = Some("out".into());
+        config.rust.bridge_out = Some("bridge".into());
+
+        config
+    }
+
+    const INPUT: &'static str = r#"
+        key = record {
+            id: i64;
+            name: string;
+        }
+
+        listener = interface +r {
+            on_key(k: key): bool;
+        }
+
+        key_store = interface +c {
+            static create(): key_store;
+            lookup(id: i64): key;
+            watch(l: listener);
+        }
+    "#;
+
+    #[test]
+    fn test_rust_side() {
+        let output = testing::compile(config(), INPUT);
+
+        let module = &output.get("out/mod.rs").expect("missing mod.rs").contents;
+        assert!(module.contains("pub mod bridge;"), "{}", module);
+
+        let bridge = &output.get("out/bridge/mod.rs").expect("missing bridge/mod.rs").contents;
+        assert!(bridge.contains("pub mod support;\nmod key;\nmod listener;\nmod key_store;\npub use self::key_store::KeyStoreCpp;"), "{}", bridge);
+
+        let key = &output.get("out/bridge/key.rs").expect("missing bridge/key.rs").contents;
+        assert!(key.contains("impl Decode for super::super::Key {"), "{}", key);
+        assert!(key.contains("            name: Decode::decode(r),"), "{}", key);
+
+        let store = &output.get("out/bridge/key_store.rs").expect("missing bridge/key_store.rs").contents;
+        assert!(store.contains("fn rl_bridge_key_store_cpp_lookup(handle: *mut c_void, args: *const u8, len: usize, out: *mut Buffer) -> i32;"), "{}", store);
+        assert!(store.contains("impl super::super::KeyStore for KeyStoreCpp {"), "{}", store);
+        assert!(store.contains("rl_bridge_key_store_cpp_create(std::ptr::null_mut(), a, l, o)"), "{}", store);
+        assert!(store.contains("panic!(\"key_store can only be implemented in C++\");"), "{}", store);
+
+        let listener = &output.get("out/bridge/listener.rs").expect("missing bridge/listener.rs").contents;
+        assert!(listener.contains("pub extern \"C\" fn rl_bridge_listener_rust_on_key(rl_handle: *mut c_void, rl_args: *const u8, rl_len: usize, rl_out: *mut Buffer) -> i32 {"), "{}", listener);
+        assert!(listener.contains("let k: super::super::Key = Decode::decode(&mut rl_reader);"), "{}", listener);
+        assert!(listener.contains("rl_self.on_key(&k).encode(&mut rl_writer);"), "{}", listener);
+        assert!(!listener.contains("ListenerCpp"), "{}", listener);
+
+        assert!(output.get("out/key_store.rs").unwrap().contents.contains("fn cpp_proxy_handle(&self)"));
+        assert!(!output.get("out/listener.rs").unwrap().contents.contains("fn cpp_proxy_handle(&self)"));
+    }
+
+    #[test]
+    fn test_cpp_side() {
+        let output = testing::compile(config(), INPUT);
+
+        let header = &output.get("bridge/rust_bridge.hpp").expect("missing rust_bridge.hpp").contents;
+        assert!(header.contains("#include \"rust_bridge_support.hpp\"\n#include \"key.hpp\""), "{}", header);
+        assert!(header.contains("struct Codec<std::shared_ptr<::demo::listener>> {"), "{}", header);
+
+        let source = &output.get("bridge/rust_bridge.cpp").expect("missing rust_bridge.cpp").contents;
+        assert!(source.contains("class listenerRustProxy final : public ::demo::listener {"), "{}", source);
+        assert!(source.contains("    bool on_key(const ::demo::key & k) override {"), "{}", source);
+        assert!(source.contains("    v.name = Codec<std::string>::read(r);"), "{}", source);
+        assert!(source.contains("int32_t rl_bridge_key_store_cpp_create(void *, "), "{}", source);
+        assert!(source.contains("::demo::key_store::create()"), "{}", source);
+        assert!(source.contains("        rl_self->watch(l);"), "{}", source);
+        assert!(source.contains("throw std::logic_error(\"listener can only be implemented in Rust\");"), "{}", source);
+
+        assert!(output.get("bridge/rust_bridge_support.hpp").is_some());
+        assert!(output.get("out/bridge/support.rs").is_some());
+    }
+
+    #[test]
+    fn test_disabled_without_bridge_out() {
+        let mut config = Config::default();
+        config.rust.out = Some("out".into());
+        let output = testing::compile(config, INPUT);
+
+        assert!(output.get("out/bridge/mod.rs").is_none());
+        assert!(!output.get("out/mod.rs").unwrap().contents.contains("pub mod bridge;"));
+    }
+}
@@ -0,0 +1,293 @@
+/*
+ * Copyright © 2002-2017 Bluebeam Software, Inc. All Rights Reserved.
+ * Creator: Jonathan Rothberg
+ */
+
+use std::io::{Write};
+use generator::generator::{Generate};
+use generator::spec::Spec;
+use generator::output::GeneratedOutput;
+use generator::rust_marshaler::RustMarshaler;
+use parser::program::Program;
+use parser::ast::{Statement, StatementKind, DataTypeStatement, FunctionModifier, Identifier, InterfaceType};
+
+pub struct RustGenerator {
+
+}
+
+impl RustGenerator {
+    pub fn new() -> RustGenerator {
+        RustGenerator {
+
+        }
+    }
+
+    fn write_header(&self, w: &mut Write) {
+        writeln!(w, "// AUTOGENERATED FILE - DO NOT MODIFY!");
+        writeln!(w, "// This file was generated by rusty_lamp");
+        writeln!(w, "");
+    }
+
+    fn add_file(&self, spec: &Spec, i: &Identifier, w: Vec<u8>, output: &mut GeneratedOutput) {
+        let file_name = (spec.rust_file_ident_style)(i.value.clone());
+        output.add_file(self.make_path(spec.config.rust.out.as_ref().map_or("", |s| s.as_str()), &file_name, "rs"), w);
+    }
+
+    fn write_comment(&self, w: &mut Write, indent: &str, comment: &String) {
+        writeln!(w, "{}///{}", indent, comment);
+    }
+
+    // Literal constants become associated consts; strings are borrowed since a
+    // `String` cannot be built in a const context.  Record constants are skipped.
+    fn write_const(&self, w: &mut Write, marshaler: &RustMarshaler, spec: &Spec, i: &Identifier, dts: &DataTypeStatement, value: &StatementKind) {
+        let name = RustMarshaler::escape((spec.rust_ident_style.cnst)(i.value.clone()));
+        let (ty, v) = match (dts, value) {
+            (&DataTypeStatement::String, &StatementKind::StringLiteral(_, ref s)) => ("&'static str".into(), format!("{:?}", s)),
+            (&DataTypeStatement::Bool, &StatementKind::Boolean(_, b)) => ("bool".into(), format!("{}", b)),
+            (&DataTypeStatement::Float32, &StatementKind::NumberLiteral(_, ref n)) |
+            (&DataTypeStatement::Float64, &StatementKind::NumberLiteral(_, ref n)) if !n.contains('.') => {
+                (marshaler.type_name(dts), format!("{}.0", n))
+            },
+            (_, &StatementKind::NumberLiteral(_, ref n)) => (marshaler.type_name(dts), n.clone()),
+            _ => {
+                writeln!(w, "    // const {}: {} is not representable as a Rust constant", i.value, dts);
+                return;
+            }
+        };
+
+        writeln!(w, "    pub const {}: {} = {};", name, ty, v);
+    }
+
+    fn write_consts(&self, w: &mut Write, marshaler: &RustMarshaler, spec: &Spec, target: &str, statements: &Vec<Statement>) {
+        let consts = statements.iter()
+            .filter(|s| match s.stmtKind { StatementKind::Const(..) => true, _ => false })
+            .collect::<Vec<_>>();
+        if consts.len() == 0 {
+            return;
+        }
+
+        writeln!(w, "");
+        writeln!(w, "impl {} {{", target);
+        for c in consts {
+            if let StatementKind::Const(_, ref i, ref dts, ref v) = c.stmtKind {
+                self.write_const(w, marshaler, spec, i, dts, &v.stmtKind);
+            }
+        }
+        writeln!(w, "}}");
+    }
+}
+
+impl Generate for RustGenerator {
+    fn write_enum(&self, e: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
+        if let StatementKind::Enum(_, ref i, ref b) = *e {
+            let marshaler = RustMarshaler::new(spec);
+            let mut w = Vec::new();
+            self.write_header(&mut w);
+
+            writeln!(w, "#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]");
+            writeln!(w, "pub enum {} {{", marshaler.type_ident(i));
+            for o in &b.statements {
+                match o.stmtKind {
+                    StatementKind::EnumMember(_, ref oi) => {
+                        writeln!(w, "    {},", RustMarshaler::escape((spec.rust_ident_style.enm)(oi.value.clone())));
+                    },
+                    StatementKind::Comment(_, ref c) => self.write_comment(&mut w, "    ", c),
+                    _ => {}
+                }
+            }
+            writeln!(w, "}}");
+
+            self.add_file(spec, i, w, output);
+        }
+    }
+
+    fn write_record(&self, r: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
+        if let StatementKind::Record(_, ref i, ref bs, ref dt) = *r {
+            let marshaler = RustMarshaler::new(spec);
+            let mut w = Vec::new();
+            self.write_header(&mut w);
+
+            let fields = bs.statements.iter()
+                .filter_map(|s| match s.stmtKind { StatementKind::RecordMember(_, _, ref dts, _) => Some(dts), _ => None })
+                .collect::<Vec<_>>();
+
+            let name = marshaler.type_ident(i);
+            writeln!(w, "#[derive({})]", marshaler.record_derives(dt, &fields).join(", "));
+            writeln!(w, "pub struct {} {{", name);
+            for f in &bs.statements {
+                match f.stmtKind {
+                    StatementKind::RecordMember(_, ref id, ref dts, _) => {
+                        let field = RustMarshaler::escape((spec.rust_ident_style.field)(id.value.clone()));
+                        writeln!(w, "    pub {}: {},", field, marshaler.type_name(dts));
+                    },
+                    StatementKind::Comment(_, ref c) => self.write_comment(&mut w, "    ", c),
+                    _ => {}
+                }
+            }
+            writeln!(w, "}}");
+
+            self.write_consts(&mut w, &marshaler, spec, &name, &bs.statements);
+            self.add_file(spec, i, w, output);
+        }
+    }
+
+    fn write_interface(&self, i: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
+        if let StatementKind::Interface(_, ref id, ref it, ref bs, _) = *i {
+            let marshaler = RustMarshaler::new(spec);
+            let mut w = Vec::new();
+            self.write_header(&mut w);
+
+            let name = marshaler.type_ident(id);
+            writeln!(w, "pub trait {}: Send + Sync {{", name);
+            for f in &bs.statements {
+                match f.stmtKind {
+                    StatementKind::Function(_, ref m, ref fi, ref params, ref ret, ref throws) => {
+                        // Static methods keep the trait object safe by requiring a concrete type.
+                        let bound = if *m == FunctionModifier::Static { " where Self: Sized" } else { "" };
+                        writeln!(w, "    {}{};", marshaler.method_signature(m, fi, params, ret, throws.as_ref()), bound);
+                    },
+                    StatementKind::Comment(_, ref c) => self.write_comment(&mut w, "    ", c),
+                    _ => {}
+                }
+            }
+            if spec.config.rust.bridge_out.is_some() && it.contains(&InterfaceType::Cpp) {
+                writeln!(w, "");
+                writeln!(w, "    /// The `std::shared_ptr` behind a C++ implementation, used by the bridge.");
+                writeln!(w, "    #[doc(hidden)]");
+                writeln!(w, "    fn cpp_proxy_handle(&self) -> *mut std::os::raw::c_void {{");
+                writeln!(w, "        std::ptr::null_mut()");
+                writeln!(w, "    }}");
+            }
+            writeln!(w, "}}");
+
+            self.write_consts(&mut w, &marshaler, spec, &format!("dyn {}", name), &bs.statements);
+            self.add_file(spec, id, w, output);
+        }
+    }
+
+    fn finish(&self, spec: &Spec, program: &Program, output: &mut GeneratedOutput) {
+        let mut w = Vec::new();
+        self.write_header(&mut w);
+
+        for stmt in &program.statements {
+            match stmt.stmtKind {
+                StatementKind::Enum(_, ref i, _) | StatementKind::Record(_, ref i, _, _) | StatementKind::Interface(_, ref i, _, _, _) => {
+                    let module = RustMarshaler::escape((spec.rust_file_ident_style)(i.value.clone()));
+                    writeln!(w, "mod {};", module);
+                    writeln!(w, "pub use self::{}::*;", module);
+                },
+                _ => {}
+            }
+        }
+
+        if spec.config.rust.bridge_out.is_some() {
+            writeln!(w, "pub mod bridge;");
+        }
+
+        let module = "mod".to_string();
+        output.add_file(self.make_path(spec.config.rust.out.as_ref().map_or("", |s| s.as_str()), &module, "rs"), w);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use generator::testing;
+    use config::Config;
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.rust.out = Some("out".into());
+
+        config
+    }
+
+    #[test]
+    fn test_write_record() {
+        let output = testing::compile(config(), r#"
+            color = enum {
+                light_red;
+            }
+
+            point = record {
+                x: f64;
+                tags: list<string>;
+                color: color;
+                const origin_name: string = "origin";
+            } deriving(eq, ord)
+        "#);
+
+        let color = &output.get("out/color.rs").expect("missing color.rs").contents;
+        assert!(color.contains("pub enum Color {\n    LightRed,\n}"), "{}", color);
+
+        let point = &output.get("out/point.rs").expect("missing point.rs").contents;
+        assert!(point.contains("#[derive(Clone, Debug, PartialEq, PartialOrd)]"), "{}", point);
+        assert!(point.contains("pub x: f64,"), "{}", point);
+        assert!(point.contains("pub tags: Vec<String>,"), "{}", point);
+        assert!(point.contains("pub color: super::Color,"), "{}", point);
+        assert!(point.contains("pub const ORIGIN_NAME: &'static str = \"origin\";"), "{}", point);
+
+        let module = &output.get("out/mod.rs").expect("missing mod.rs").contents;
+        assert!(module.contains("mod point;\npub use self::point::*;"), "{}", module);
+    }
+
+    #[test]
+    fn test_write_nested_record_derives() {
+        let output = testing::compile(config(), r#"
+            inner = record { x: f64; } deriving(eq)
+            outer = record { i: inner; n: i32; } deriving(eq)
+            wrapper = record { n: i32; } deriving(eq, ord)
+            holder = record { w: list<wrapper>; } deriving(eq, ord)
+        "#);
+
+        let inner = &output.get("out/inner.rs").expect("missing inner.rs").contents;
+        assert!(inner.contains("#[derive(Clone, Debug, PartialEq)]"), "{}", inner);
+        let outer = &output.get("out/outer.rs").expect("missing outer.rs").contents;
+        assert!(outer.contains("#[derive(Clone, Debug, PartialEq)]"), "{}", outer);
+        let holder = &output.get("out/holder.rs").expect("missing holder.rs").contents;
+        assert!(holder.contains("#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]"), "{}", holder);
+    }
+
+    #[test]
+    fn test_write_interface() {
+        let output = testing::compile(config(), r#"
+            key = record {
+                id: i64;
+                data: map<string, binary>;
+            } deriving(eq)
+
+            key_store = interface +c {
+                static create(): key_store;
+                lookup(key: key, fallback: string): date;
+                store(values: list<key>);
+            }
+        "#);
+
+        let key = &output.get("out/key.rs").expect("missing key.rs").contents;
+        assert!(key.contains("#[derive(Clone, Debug, PartialEq, Eq)]"), "{}", key);
+        assert!(key.contains("pub data: std::collections::HashMap<String, Vec<u8>>,"), "{}", key);
+
+        let store = &output.get("out/key_store.rs").expect("missing key_store.rs").contents;
+        assert!(store.contains("pub trait KeyStore: Send + Sync {"), "{}", store);
+        assert!(store.contains("fn create() -> std::sync::Arc<dyn super::KeyStore> where Self: Sized;"), "{}", store);
+        assert!(store.contains("fn lookup(&self, key: &super::Key, fallback: &str) -> std::time::SystemTime;"), "{}", store);
+        assert!(store.contains("fn store(&self, values: &[super::Key]);"), "{}", store);
+    }
+
+    #[test]
+    fn test_write_throws() {
+        let output = testing::compile(config(), r#"
+            store_error = enum { missing; }
+            store = interface +c {
+                load(id: i64): string throws store_error;
+                clear() throws store_error;
+                fetch(): future<string> throws store_error;
+            }
+        "#);
+
+        let store = &output.get("out/store.rs").expect("missing store.rs").contents;
+        assert!(store.contains("fn load(&self, id: i64) -> Result<String, super::StoreError>;"), "{}", store);
+        assert!(store.contains("fn clear(&self) -> Result<(), super::StoreError>;"), "{}", store);
+        assert!(store.contains("fn fetch(&self) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<String, super::StoreError>> + Send>>;"),
+                "{}", store);
+    }
+}
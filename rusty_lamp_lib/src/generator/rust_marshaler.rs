@@ -0,0 +1,222 @@
+/*
+ * Copyright © 2002-2017 Bluebeam Software, Inc. All Rights Reserved.
+ * Creator: Jonathan Rothberg
+ */
+
+use parser::ast::{DataTypeStatement, DeriveType, FunctionModifier, Identifier, Parameter};
+use generator::spec::Spec;
+use generator::typer::{TypeDefinitionKind, UserObjectKind};
+
+const KEYWORDS: &'static [&'static str] = &[
+    "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn", "for", "if",
+    "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "static",
+    "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while", "abstract",
+    "async", "await", "become", "box", "do", "dyn", "final", "macro", "override", "priv", "try",
+    "typeof", "unsized", "virtual", "yield"
+];
+
+/// The derivable traits a generated struct can carry.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum RustTrait {
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash
+}
+
+impl RustTrait {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            RustTrait::Debug => "Debug",
+            RustTrait::PartialEq => "PartialEq",
+            RustTrait::Eq => "Eq",
+            RustTrait::PartialOrd => "PartialOrd",
+            RustTrait::Ord => "Ord",
+            RustTrait::Hash => "Hash"
+        }
+    }
+}
+
+pub struct RustMarshaler<'a> {
+    spec: &'a Spec,
+    path: &'static str
+}
+
+impl<'a> RustMarshaler<'a> {
+    pub fn new(spec: &'a Spec) -> RustMarshaler<'a> {
+        RustMarshaler::with_path(spec, "super::")
+    }
+
+    /// `path` is the module path from the generated code back to the generated types.
+    pub fn with_path(spec: &'a Spec, path: &'static str) -> RustMarshaler<'a> {
+        RustMarshaler {
+            spec: spec,
+            path: path
+        }
+    }
+
+    pub fn escape(name: String) -> String {
+        if KEYWORDS.contains(&name.as_str()) {
+            format!("r#{}", name)
+        }
+        else {
+            name
+        }
+    }
+
+    pub fn type_ident(&self, i: &Identifier) -> String {
+        (self.spec.rust_ident_style.ty)(i.value.clone())
+    }
+
+    pub fn type_name(&self, dts: &DataTypeStatement) -> String {
+        match *dts {
+            DataTypeStatement::None => "()".into(),
+            DataTypeStatement::String => "String".into(),
+            DataTypeStatement::Integer8 => "i8".into(),
+            DataTypeStatement::Integer16 => "i16".into(),
+            DataTypeStatement::Integer32 => "i32".into(),
+            DataTypeStatement::Integer64 => "i64".into(),
+            DataTypeStatement::Float32 => "f32".into(),
+            DataTypeStatement::Float64 => "f64".into(),
+            DataTypeStatement::Bool => "bool".into(),
+            DataTypeStatement::Binary => "Vec<u8>".into(),
+            DataTypeStatement::Date => "std::time::SystemTime".into(),
+            DataTypeStatement::Set(ref t) => format!("std::collections::HashSet<{}>", self.type_name(t)),
+            DataTypeStatement::List(ref t) | DataTypeStatement::Array(ref t) => format!("Vec<{}>", self.type_name(t)),
+            DataTypeStatement::Map(ref k, ref v) => format!("std::collections::HashMap<{}, {}>", self.type_name(k), self.type_name(v)),
+            DataTypeStatement::Optional(ref t) => format!("Option<{}>", self.type_name(t)),
+            DataTypeStatement::Future(ref t) => format!("std::pin::Pin<Box<dyn std::future::Future<Output = {}> + Send>>", self.type_name(t)),
+            DataTypeStatement::Function(ref p, ref r) => {
+                let params: Vec<String> = p.iter().map(|t| self.type_name(t)).collect();
+                format!("std::sync::Arc<dyn Fn({}) -> {} + Send + Sync>", params.join(", "), self.type_name(r))
+            },
+            DataTypeStatement::Object(ref i) => {
+                match self.user_object_kind(i) {
+                    Some(UserObjectKind::Interface(..)) => format!("std::sync::Arc<dyn {}{}>", self.path, self.type_ident(i)),
+                    _ => format!("{}{}", self.path, self.type_ident(i))
+                }
+            }
+        }
+    }
+
+    // Parameters borrow anything that is not cheap to copy; interfaces are passed
+    // as an `Arc` the same way C++ passes a `std::shared_ptr`.
+    pub fn param_type(&self, dts: &DataTypeStatement) -> String {
+        match *dts {
+            DataTypeStatement::String => "&str".into(),
+            DataTypeStatement::Binary => "&[u8]".into(),
+            DataTypeStatement::List(ref t) => format!("&[{}]", self.type_name(t)),
+            DataTypeStatement::Set(..) | DataTypeStatement::Map(..) => format!("&{}", self.type_name(dts)),
+            DataTypeStatement::Optional(ref t) if self.param_type(t).starts_with('&') => format!("&{}", self.type_name(dts)),
+            DataTypeStatement::Object(ref i) => {
+                match self.user_object_kind(i) {
+                    Some(UserObjectKind::Record(..)) => format!("&{}", self.type_name(dts)),
+                    _ => self.type_name(dts)
+                }
+            },
+            _ => self.type_name(dts)
+        }
+    }
+
+    pub fn local_ident(&self, i: &Identifier) -> String {
+        RustMarshaler::escape((self.spec.rust_ident_style.local)(i.value.clone()))
+    }
+
+    pub fn method_ident(&self, i: &Identifier) -> String {
+        RustMarshaler::escape((self.spec.rust_ident_style.method)(i.value.clone()))
+    }
+
+    /// A method that throws returns a `Result` with the error type, inside the `Future` when it is
+    /// async.
+    pub fn method_signature(&self, m: &FunctionModifier, i: &Identifier, params: &Vec<Parameter>, ret: &DataTypeStatement,
+                            throws: Option<&Identifier>) -> String {
+        let mut args = Vec::new();
+        if *m != FunctionModifier::Static {
+            args.push("&self".to_string());
+        }
+        for p in params {
+            args.push(format!("{}: {}", self.local_ident(&p.ident), self.param_type(&p.data_type)));
+        }
+
+        let ret = match (ret, throws) {
+            (&DataTypeStatement::None, None) => "".into(),
+            (&DataTypeStatement::Future(ref t), Some(e)) => {
+                format!(" -> std::pin::Pin<Box<dyn std::future::Future<Output = {}> + Send>>", self.result_type(t, e))
+            },
+            (r, Some(e)) => format!(" -> {}", self.result_type(r, e)),
+            (r, None) => format!(" -> {}", self.type_name(r))
+        };
+
+        format!("fn {}({}){}", self.method_ident(i), args.join(", "), ret)
+    }
+
+    fn result_type(&self, dts: &DataTypeStatement, error: &Identifier) -> String {
+        let ok = match *dts {
+            DataTypeStatement::None => "()".into(),
+            _ => self.type_name(dts)
+        };
+        format!("Result<{}, {}>", ok, self.type_name(&DataTypeStatement::Object(error.clone())))
+    }
+
+    /// The traits to derive for a record, limited to the ones every field implements.
+    /// `deriving(ord)` implies equality since `PartialOrd` requires `PartialEq`.
+    pub fn record_derives(&self, derives: &Vec<DeriveType>, fields: &Vec<&DataTypeStatement>) -> Vec<&'static str> {
+        let mut result = vec!["Clone"];
+        result.extend(self.record_traits(derives, fields).iter().map(|t| t.name()));
+        result
+    }
+
+    fn record_traits(&self, derives: &Vec<DeriveType>, fields: &Vec<&DataTypeStatement>) -> Vec<RustTrait> {
+        let mut wanted = vec![RustTrait::Debug];
+        if derives.contains(&DeriveType::Eq) || derives.contains(&DeriveType::Ord) {
+            wanted.extend(&[RustTrait::PartialEq, RustTrait::Eq, RustTrait::Hash]);
+        }
+        if derives.contains(&DeriveType::Ord) {
+            wanted.extend(&[RustTrait::PartialOrd, RustTrait::Ord]);
+        }
+
+        wanted.into_iter()
+            .filter(|t| fields.iter().all(|f| self.implements(f, *t)))
+            .collect()
+    }
+
+    pub fn implements(&self, dts: &DataTypeStatement, t: RustTrait) -> bool {
+        match *dts {
+            DataTypeStatement::Float32 | DataTypeStatement::Float64 => {
+                t == RustTrait::Debug || t == RustTrait::PartialEq || t == RustTrait::PartialOrd
+            },
+            DataTypeStatement::Set(ref v) => {
+                self.hash_container_implements(t) && self.implements(v, t)
+            },
+            DataTypeStatement::Map(ref k, ref v) => {
+                self.hash_container_implements(t) && self.implements(k, t) && self.implements(v, t)
+            },
+            DataTypeStatement::List(ref v) | DataTypeStatement::Array(ref v) | DataTypeStatement::Optional(ref v) => self.implements(v, t),
+            DataTypeStatement::Object(ref i) => {
+                match self.user_object_kind(i) {
+                    Some(UserObjectKind::Enum) => true,
+                    // A nested record only has the traits its own fields allowed it to derive.
+                    Some(UserObjectKind::Record(ref d)) => {
+                        let fields = self.spec.typer.get_fields(&i.value);
+                        self.record_traits(d, &fields.iter().collect()).contains(&t)
+                    },
+                    _ => false
+                }
+            },
+            _ => true
+        }
+    }
+
+    fn hash_container_implements(&self, t: RustTrait) -> bool {
+        t == RustTrait::Debug || t == RustTrait::PartialEq || t == RustTrait::Eq
+    }
+
+    pub fn user_object_kind(&self, i: &Identifier) -> Option<UserObjectKind> {
+        match self.spec.typer.get(&i.value) {
+            TypeDefinitionKind::UserObject(_, k) => Some(k),
+            _ => None
+        }
+    }
+}
@@ -4,184 +4,137 @@
  */
 
 use std::sync::Arc;
-use std::io::{Write};
-use std::fs::File;
+use config::{Config, ConfigError, IdentConfig};
 use generator::typer::Typer;
-use generator::ident_style::{IdentStyleDefault, IdentConverter};
+use generator::ident_style::{IdentStyle, IdentStyleDefault, IdentConverter};
 
-pub struct Spec<'a> {
-    // pub root: String,
-    // pub cpp_root: String,
+pub struct Spec {
     pub typer: Typer,
-    pub java_out_folder: Option<&'a str>,
-    pub java_package: Option<&'a str>,
-    pub java_class_access_modifier: &'a str,
+    pub config: Config,
     pub java_ident_style: IdentStyleDefault,
-    pub java_cpp_exception: Option<&'a str>,
-    pub java_annotation: Option<&'a str>,
-    pub java_nullable_annotation: Option<&'a str>,
-    pub java_nonnullable_annotation: Option<&'a str>,
-    pub java_use_final_for_record: bool,
-    pub cpp_out_folder: Option<&'a str>,
-    pub cpp_header_out_folder: Option<&'a str>,
-    pub cpp_include_prefix: &'a str,
-    pub cpp_extended_record_include_prefix: &'a str,
-    pub cpp_namespace: &'a str,
+    pub kotlin_ident_style: IdentStyleDefault,
     pub cpp_ident_style: IdentStyleDefault,
     pub cpp_file_ident_style: Arc<IdentConverter>,
-    pub cpp_optional_template: &'a str,
-    pub cpp_optional_header: &'a str,
-    pub cpp_enum_has_workaround: bool,
-    pub cpp_nn_header: Option<&'a str>,
-    pub cpp_nn_type: Option<&'a str>,
-    pub cpp_nn_check_expression: Option<&'a str>,
-    pub cpp_use_wide_strings: bool,
-    pub jni_out_folder: Option<&'a str>,
-    pub jni_header_out_folder: Option<&'a str>,
-    pub jni_include_prefix: &'a str,
-    pub jni_include_cpp_prefix: &'a str,
-    pub jni_namespace: &'a str,
     pub jni_class_ident_style: Arc<IdentConverter>,
     pub jni_file_ident_style: Arc<IdentConverter>,
-    pub jni_base_lib_include_prefix: &'a str,
-    pub cpp_ext: &'a str,
-    pub cpp_header_ext: &'a str,
-    pub objc_out_folder: Option<&'a str>,
-    pub objc_cpp_out_folder: Option<&'a str>,
     pub objc_ident_style: IdentStyleDefault,
     pub objc_file_ident_style: Arc<IdentConverter>,
-    pub objc_cpp_ext: &'a str,
-    pub objc_header_ext: &'a str,
-    pub objc_include_prefix: &'a str,
-    pub objc_extended_record_include_prefix: &'a str,
-    pub objc_cpp_include_prefix: &'a str,
-    pub objc_cpp_include_cpp_prefix: &'a str,
-    pub objc_cpp_include_objc_prefix: &'a str,
-    pub objc_cpp_namespace: &'a str,
-    pub objc_base_lib_include_prefix: &'a str,
-    pub out_file_list_writer: Option<Arc<File>>,
-    pub skip_generation: bool,
-    pub yaml_out_folder: Option<&'a str>,
-    pub yaml_out_file: Option<&'a str>,
-    pub yaml_prefix: &'a str
+    pub rust_ident_style: IdentStyleDefault,
+    pub rust_file_ident_style: Arc<IdentConverter>,
+    pub c_ident_style: IdentStyleDefault,
+    pub swift_ident_style: IdentStyleDefault,
+    pub swift_file_ident_style: Arc<IdentConverter>,
+    pub wasm_ident_style: IdentStyleDefault,
+    pub python_ident_style: IdentStyleDefault,
+    pub csharp_ident_style: IdentStyleDefault,
+    pub dart_ident_style: IdentStyleDefault,
+    pub node_ident_style: IdentStyleDefault
 }
 
-impl<'a> Spec<'a> {
-    pub fn new(
-        // root: String,
-        //        cpp_root: String,
-               typer: Typer,
-               java_out_folder: Option<&'a str>,
-               java_package: Option<&'a str>,
-               java_class_access_modifier: &'a str,
-               java_ident_style: IdentStyleDefault,
-               java_cpp_exception: Option<&'a str>,
-               java_annotation: Option<&'a str>,
-               java_nullable_annotation: Option<&'a str>,
-               java_nonnullable_annotation: Option<&'a str>,
-               java_use_final_for_record: bool,
-               cpp_out_folder: Option<&'a str>,
-               cpp_header_out_folder: Option<&'a str>,
-               cpp_include_prefix: &'a str,
-               cpp_extended_record_include_prefix: &'a str,
-               cpp_namespace: &'a str,
-               cpp_ident_style: IdentStyleDefault,
-               cpp_file_ident_style: Arc<IdentConverter>,
-               cpp_optional_template: &'a str,
-               cpp_optional_header: &'a str,
-               cpp_enum_has_workaround: bool,
-               cpp_nn_header: Option<&'a str>,
-               cpp_nn_type: Option<&'a str>,
-               cpp_nn_check_expression: Option<&'a str>,
-               cpp_use_wide_strings: bool,
-               jni_out_folder: Option<&'a str>,
-               jni_header_out_folder: Option<&'a str>,
-               jni_include_prefix: &'a str,
-               jni_include_cpp_prefix: &'a str,
-               jni_namespace: &'a str,
-               jni_class_ident_style: Arc<IdentConverter>,
-               jni_file_ident_style: Arc<IdentConverter>,
-               jni_base_lib_include_prefix: &'a str,
-               cpp_ext: &'a str,
-               cpp_header_ext: &'a str,
-               objc_out_folder: Option<&'a str>,
-               objc_cpp_out_folder: Option<&'a str>,
-               objc_ident_style: IdentStyleDefault,
-               objc_file_ident_style: Arc<IdentConverter>,
-               objc_cpp_ext: &'a str,
-               objc_header_ext: &'a str,
-               objc_include_prefix: &'a str,
-               objc_extended_record_include_prefix: &'a str,
-               objc_cpp_include_prefix: &'a str,
-               objc_cpp_include_cpp_prefix: &'a str,
-               objc_cpp_include_objc_prefix: &'a str,
-               objc_cpp_namespace: &'a str,
-               objc_base_lib_include_prefix: &'a str,
-               out_file_list_writer: Option<Arc<File>>,
-               skip_generation: bool,
-               yaml_out_folder: Option<&'a str>,
-               yaml_out_file: Option<&'a str>,
-               yaml_prefix: &'a str
-            ) -> Spec<'a> {
-        Spec{
-            // root: root,
-            // cpp_root: cpp_root,
+impl Spec {
+    pub fn new(config: Config) -> Result<Spec, ConfigError> {
+        let ident_style = IdentStyle::new();
+
+        let mut java_ident_style = ident_style.java_style_default;
+        Spec::apply_ident_config("java", &config.java.ident, &mut java_ident_style)?;
+
+        let mut kotlin_ident_style = ident_style.kotlin_style_default;
+        Spec::apply_ident_config("kotlin", &config.kotlin.ident, &mut kotlin_ident_style)?;
+
+        let mut cpp_ident_style = ident_style.cpp_style_default;
+        Spec::apply_ident_config("cpp", &config.cpp.ident, &mut cpp_ident_style)?;
+        let cpp_file_ident_style = Spec::ident_or("cpp.file", &config.cpp.ident.file, &cpp_ident_style.ty)?;
+
+        let jni_class_ident_style = Spec::ident_or("jni.class", &config.jni.ident.class, &cpp_ident_style.ty)?;
+        let jni_file_ident_style = Spec::ident_or("jni.file", &config.jni.ident.file, &cpp_file_ident_style)?;
+
+        let mut objc_ident_style = ident_style.objc_style_default;
+        Spec::apply_ident_config("objc", &config.objc.ident, &mut objc_ident_style)?;
+        let objc_file_ident_style = Spec::ident_or("objc.file", &config.objc.ident.file, &objc_ident_style.ty)?;
+
+        let objc_type_prefix = config.objc.type_prefix.clone();
+        objc_ident_style.ty = IdentStyle::prefix(objc_type_prefix.clone(), objc_ident_style.ty.clone());
+        let objc_file_ident_style = IdentStyle::prefix(objc_type_prefix, objc_file_ident_style);
+
+        let mut rust_ident_style = ident_style.rust_style_default;
+        Spec::apply_ident_config("rust", &config.rust.ident, &mut rust_ident_style)?;
+        let under_lower: Arc<IdentConverter> = Arc::new(IdentStyle::under_lower);
+        let rust_file_ident_style = Spec::ident_or("rust.file", &config.rust.ident.file, &under_lower)?;
+
+        let mut c_ident_style = ident_style.c_style_default;
+        Spec::apply_ident_config("c", &config.c.ident, &mut c_ident_style)?;
+
+        let mut swift_ident_style = ident_style.swift_style_default;
+        Spec::apply_ident_config("swift", &config.swift.ident, &mut swift_ident_style)?;
+        let swift_file_ident_style = Spec::ident_or("swift.file", &config.swift.ident.file, &swift_ident_style.ty)?;
+
+        let mut wasm_ident_style = ident_style.wasm_style_default;
+        Spec::apply_ident_config("wasm", &config.wasm.ident, &mut wasm_ident_style)?;
+
+        let mut python_ident_style = ident_style.python_style_default;
+        Spec::apply_ident_config("python", &config.python.ident, &mut python_ident_style)?;
+
+        let mut csharp_ident_style = ident_style.csharp_style_default;
+        Spec::apply_ident_config("csharp", &config.csharp.ident, &mut csharp_ident_style)?;
+
+        let mut dart_ident_style = ident_style.dart_style_default;
+        Spec::apply_ident_config("dart", &config.dart.ident, &mut dart_ident_style)?;
+
+        let mut node_ident_style = ident_style.node_style_default;
+        Spec::apply_ident_config("node", &config.node.ident, &mut node_ident_style)?;
+
+        let mut typer = Typer::new();
+        typer.apply_overrides(&config.types)?;
+
+        Ok(Spec {
             typer: typer,
-            java_out_folder: java_out_folder,
-            java_package: java_package,
-            java_class_access_modifier: java_class_access_modifier,
+            config: config,
             java_ident_style: java_ident_style,
-            java_cpp_exception: java_cpp_exception,
-            java_annotation: java_annotation,
-            java_nullable_annotation: java_nullable_annotation,
-            java_nonnullable_annotation: java_nonnullable_annotation,
-            java_use_final_for_record: java_use_final_for_record,
-            cpp_out_folder: cpp_out_folder,
-            cpp_header_out_folder: cpp_header_out_folder,
-            cpp_include_prefix: cpp_include_prefix,
-            cpp_extended_record_include_prefix: cpp_extended_record_include_prefix,
-            cpp_namespace: cpp_namespace,
+            kotlin_ident_style: kotlin_ident_style,
             cpp_ident_style: cpp_ident_style,
             cpp_file_ident_style: cpp_file_ident_style,
-            cpp_optional_template: cpp_optional_template,
-            cpp_optional_header: cpp_optional_header,
-            cpp_enum_has_workaround: cpp_enum_has_workaround,
-            cpp_nn_header: cpp_nn_header,
-            cpp_nn_type: cpp_nn_type,
-            cpp_nn_check_expression: cpp_nn_check_expression,
-            cpp_use_wide_strings: cpp_use_wide_strings,
-            jni_out_folder: jni_out_folder,
-            jni_header_out_folder: jni_header_out_folder,
-            jni_include_prefix: jni_include_prefix,
-            jni_include_cpp_prefix: jni_include_cpp_prefix,
-            jni_namespace: jni_namespace,
             jni_class_ident_style: jni_class_ident_style,
             jni_file_ident_style: jni_file_ident_style,
-            jni_base_lib_include_prefix: jni_base_lib_include_prefix,
-            cpp_ext: cpp_ext,
-            cpp_header_ext: cpp_header_ext,
-            objc_out_folder: objc_out_folder,
-            objc_cpp_out_folder: objc_cpp_out_folder,
             objc_ident_style: objc_ident_style,
             objc_file_ident_style: objc_file_ident_style,
-            objc_cpp_ext: objc_cpp_ext,
-            objc_header_ext: objc_header_ext,
-            objc_include_prefix: objc_include_prefix,
-            objc_extended_record_include_prefix: objc_extended_record_include_prefix,
-            objc_cpp_include_prefix: objc_cpp_include_prefix,
-            objc_cpp_include_cpp_prefix: objc_cpp_include_cpp_prefix,
-            objc_cpp_include_objc_prefix: objc_cpp_include_objc_prefix,
-            objc_cpp_namespace: objc_cpp_namespace,
-            objc_base_lib_include_prefix: objc_base_lib_include_prefix,
-            out_file_list_writer: out_file_list_writer,
-            skip_generation: skip_generation,
-            yaml_out_folder: yaml_out_folder,
-            yaml_out_file: yaml_out_file,
-            yaml_prefix: yaml_prefix
+            rust_ident_style: rust_ident_style,
+            rust_file_ident_style: rust_file_ident_style,
+            c_ident_style: c_ident_style,
+            swift_ident_style: swift_ident_style,
+            swift_file_ident_style: swift_file_ident_style,
+            wasm_ident_style: wasm_ident_style,
+            python_ident_style: python_ident_style,
+            csharp_ident_style: csharp_ident_style,
+            dart_ident_style: dart_ident_style,
+            node_ident_style: node_ident_style
+        })
+    }
+
+    fn apply_ident_config(lang: &str, ident: &IdentConfig, style: &mut IdentStyleDefault) -> Result<(), ConfigError> {
+        style.ty = Spec::ident_or(&format!("{}.type", lang), &ident.ty, &style.ty)?;
+        style.enum_type = Spec::ident_or(&format!("{}.enum_type", lang), &ident.enum_type, &style.enum_type)?;
+        style.type_param = Spec::ident_or(&format!("{}.type_param", lang), &ident.type_param, &style.type_param)?;
+        style.method = Spec::ident_or(&format!("{}.method", lang), &ident.method, &style.method)?;
+        style.field = Spec::ident_or(&format!("{}.field", lang), &ident.field, &style.field)?;
+        style.local = Spec::ident_or(&format!("{}.local", lang), &ident.local, &style.local)?;
+        style.enm = Spec::ident_or(&format!("{}.enum", lang), &ident.enm, &style.enm)?;
+        style.cnst = Spec::ident_or(&format!("{}.const", lang), &ident.cnst, &style.cnst)?;
+
+        Ok(())
+    }
+
+    fn ident_or(key: &str, spec: &Option<String>, default: &Arc<IdentConverter>) -> Result<Arc<IdentConverter>, ConfigError> {
+        match *spec {
+            Some(ref s) if s.len() > 0 => {
+                IdentStyle::build_ident_style(s).map_err(|e| ConfigError::InvalidIdentStyle(key.into(), format!("{}", e)))
+            },
+            _ => Ok(default.clone())
         }
     }
+}
 
-    // fn pass_through(input: String) -> String {
-    //     input
-    // }
+impl Default for Spec {
+    fn default() -> Spec {
+        Spec::new(Config::default()).unwrap()
+    }
 }
@@ -0,0 +1,415 @@
+/*
+ * Copyright © 2002-2017 Bluebeam Software, Inc. All Rights Reserved.
+ * Creator: Jonathan Rothberg
+ */
+
+use std::io::{Write};
+use generator::generator::{Generate};
+use generator::spec::Spec;
+use generator::output::GeneratedOutput;
+use generator::swift_marshaler::{SwiftMarshaler, SwiftProtocol};
+use generator::resolver::nested_types;
+use parser::ast::{Statement, StatementKind, DataTypeStatement, FunctionModifier, Identifier, Parameter};
+
+pub struct SwiftGenerator {
+
+}
+
+impl SwiftGenerator {
+    pub fn new() -> SwiftGenerator {
+        SwiftGenerator {
+
+        }
+    }
+
+    fn write_header(&self, w: &mut Write) {
+        writeln!(w, "// AUTOGENERATED FILE - DO NOT MODIFY!");
+        writeln!(w, "// This file was generated by rusty_lamp");
+        writeln!(w, "");
+        writeln!(w, "import Foundation");
+        writeln!(w, "");
+    }
+
+    fn add_file(&self, spec: &Spec, i: &Identifier, w: Vec<u8>, output: &mut GeneratedOutput) {
+        let marshaler = SwiftMarshaler::new(spec);
+        output.add_file(self.make_path(spec.config.swift.out.as_ref().map_or("", |s| s.as_str()), &marshaler.file_name(i), "swift"), w);
+    }
+
+    fn write_comment(&self, w: &mut Write, indent: &str, comment: &String) {
+        writeln!(w, "{}///{}", indent, comment);
+    }
+
+    /// Whether `a` comes before `b`, both of type `dts`.  Arrays are ordered element by element
+    /// and optionals through `precedes`, which puts `nil` first.
+    fn precedes(&self, marshaler: &SwiftMarshaler, dts: &DataTypeStatement, a: &str, b: &str, depth: usize) -> String {
+        let (l, r) = (format!("l{}", depth), format!("r{}", depth));
+        let by = |t: &DataTypeStatement| if marshaler.conforms(t, SwiftProtocol::Comparable) {
+            "<".to_string()
+        }
+        else {
+            format!("{{ {}, {} in {} }}", l, r, self.precedes(marshaler, t, &l, &r, depth + 1))
+        };
+        match *dts {
+            _ if marshaler.conforms(dts, SwiftProtocol::Comparable) => format!("{} < {}", a, b),
+            DataTypeStatement::List(ref t) => format!("{}.lexicographicallyPrecedes({}, by: {})", a, b, by(t)),
+            DataTypeStatement::Optional(ref t) => format!("precedes({}, {}, by: {})", a, b, by(t)),
+            _ => format!("{} < {}", a, b)
+        }
+    }
+
+    fn write_consts(&self, w: &mut Write, marshaler: &SwiftMarshaler, statements: &Vec<Statement>) {
+        for s in statements {
+            if let StatementKind::Const(_, ref i, ref dts, ref v) = s.stmtKind {
+                let value = match v.stmtKind {
+                    StatementKind::StringLiteral(_, ref s) => format!("{:?}", s),
+                    StatementKind::Boolean(_, b) => format!("{}", b),
+                    StatementKind::NumberLiteral(_, ref n) => n.clone(),
+                    _ => {
+                        writeln!(w, "    // const {}: {} is not representable as a Swift constant", i.value, dts);
+                        continue;
+                    }
+                };
+                writeln!(w, "    public static let {}: {} = {}", marshaler.const_ident(i), marshaler.type_name(dts), value);
+            }
+        }
+    }
+
+    fn params(&self, marshaler: &SwiftMarshaler, params: &Vec<Parameter>, objc: bool) -> String {
+        params.iter()
+            .map(|p| {
+                let ty = if objc { marshaler.objc_type_name(&p.data_type) } else { marshaler.type_name(&p.data_type) };
+                format!("{}: {}", marshaler.local_ident(&p.ident), ty)
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn returns(&self, ty: String, ret: &DataTypeStatement) -> String {
+        match *ret {
+            DataTypeStatement::None => "".into(),
+            _ => format!(" -> {}", ty)
+        }
+    }
+
+    /// `target.lookup(key: convert(key), ...)` with each argument converted by `convert`.
+    fn call<F: Fn(&DataTypeStatement, &str) -> String>(&self, marshaler: &SwiftMarshaler, target: &str, method: &Identifier,
+                                                       params: &Vec<Parameter>, convert: F) -> String {
+        let args = params.iter()
+            .map(|p| {
+                let local = marshaler.local_ident(&p.ident);
+                format!("{}: {}", local, convert(&p.data_type, &local))
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("{}.{}({})", target, marshaler.method_ident(method), args)
+    }
+
+    fn write_return(&self, w: &mut Write, ret: &DataTypeStatement, value: String) {
+        match *ret {
+            DataTypeStatement::None => writeln!(w, "        {}", value),
+            _ => writeln!(w, "        return {}", value)
+        };
+    }
+}
+
+impl Generate for SwiftGenerator {
+    fn write_enum(&self, e: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
+        if let StatementKind::Enum(_, ref i, ref b) = *e {
+            let marshaler = SwiftMarshaler::new(spec);
+            let name = marshaler.type_ident(i);
+            let mut w = Vec::new();
+            self.write_header(&mut w);
+
+            // Raw value enums don't get a synthesized Comparable, ordering follows declaration order.
+            writeln!(w, "public enum {}: Int, CaseIterable, Comparable {{", name);
+            for o in &b.statements {
+                match o.stmtKind {
+                    StatementKind::EnumMember(_, ref oi) => {
+                        writeln!(w, "    case {}", marshaler.enum_member(oi));
+                    },
+                    StatementKind::Comment(_, ref c) => self.write_comment(&mut w, "    ", c),
+                    _ => {}
+                }
+            }
+            writeln!(w, "");
+            writeln!(w, "    public static func < (lhs: {}, rhs: {}) -> Bool {{", name, name);
+            writeln!(w, "        return lhs.rawValue < rhs.rawValue");
+            writeln!(w, "    }}");
+            writeln!(w, "}}");
+
+            self.add_file(spec, i, w, output);
+        }
+    }
+
+    fn write_record(&self, r: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
+        if let StatementKind::Record(_, ref i, ref bs, ref dt) = *r {
+            let marshaler = SwiftMarshaler::new(spec);
+            let name = marshaler.type_ident(i);
+            let mut w = Vec::new();
+            self.write_header(&mut w);
+
+            let fields = bs.statements.iter()
+                .filter_map(|s| match s.stmtKind { StatementKind::RecordMember(_, ref fi, ref dts, _) => Some((fi, dts)), _ => None })
+                .collect::<Vec<_>>();
+            let protocols = marshaler.record_protocols(dt, &fields.iter().map(|&(_, dts)| dts).collect());
+
+            if protocols.len() > 0 {
+                writeln!(w, "public struct {}: {} {{", name, protocols.iter().map(|p| p.name()).collect::<Vec<_>>().join(", "));
+            }
+            else {
+                writeln!(w, "public struct {} {{", name);
+            }
+            for f in &bs.statements {
+                match f.stmtKind {
+                    StatementKind::RecordMember(_, ref fi, ref dts, _) => {
+                        writeln!(w, "    public var {}: {}", marshaler.field_ident(fi), marshaler.type_name(dts));
+                    },
+                    StatementKind::Comment(_, ref c) => self.write_comment(&mut w, "    ", c),
+                    _ => {}
+                }
+            }
+            self.write_consts(&mut w, &marshaler, &bs.statements);
+
+            let params = fields.iter()
+                .map(|&(fi, dts)| format!("{}: {}", marshaler.local_ident(fi), marshaler.type_name(dts)))
+                .collect::<Vec<_>>();
+            writeln!(w, "");
+            writeln!(w, "    public init({}) {{", params.join(", "));
+            for &(fi, _) in &fields {
+                writeln!(w, "        self.{} = {}", marshaler.field_ident(fi), marshaler.local_ident(fi));
+            }
+            writeln!(w, "    }}");
+
+            let from = fields.iter()
+                .map(|&(fi, dts)| format!("{}: {}", marshaler.local_ident(fi), marshaler.from_objc(dts, &format!("objc.{}", marshaler.field_ident(fi)))))
+                .collect::<Vec<_>>();
+            writeln!(w, "");
+            writeln!(w, "    init(_ objc: {}) {{", marshaler.objc_ident(i));
+            writeln!(w, "        self.init({})", from.join(", "));
+            writeln!(w, "    }}");
+
+            let to = fields.iter()
+                .map(|&(fi, dts)| format!("{}: {}", marshaler.local_ident(fi), marshaler.to_objc(dts, &marshaler.field_ident(fi))))
+                .collect::<Vec<_>>();
+            writeln!(w, "");
+            writeln!(w, "    var objc: {} {{", marshaler.objc_ident(i));
+            writeln!(w, "        return {}({})", marshaler.objc_ident(i), to.join(", "));
+            writeln!(w, "    }}");
+
+            let comparable = protocols.contains(&SwiftProtocol::Comparable);
+            if comparable {
+                writeln!(w, "");
+                writeln!(w, "    public static func < (lhs: {}, rhs: {}) -> Bool {{", name, name);
+                for &(fi, dts) in &fields {
+                    let field = marshaler.field_ident(fi);
+                    writeln!(w, "        if lhs.{} != rhs.{} {{", field, field);
+                    writeln!(w, "            return {}", self.precedes(&marshaler, dts, &format!("lhs.{}", field), &format!("rhs.{}", field), 0));
+                    writeln!(w, "        }}");
+                }
+                writeln!(w, "        return false");
+                writeln!(w, "    }}");
+            }
+            writeln!(w, "}}");
+            let orders_optionals = fields.iter().any(|&(_, dts)| nested_types(dts).iter().any(|t| match **t {
+                DataTypeStatement::Optional(..) => true,
+                _ => false
+            }));
+            if comparable && orders_optionals {
+                writeln!(w, "");
+                writeln!(w, "fileprivate func precedes<T>(_ lhs: T?, _ rhs: T?, by areInIncreasingOrder: (T, T) -> Bool) -> Bool {{");
+                writeln!(w, "    switch (lhs, rhs) {{");
+                writeln!(w, "    case let (l?, r?): return areInIncreasingOrder(l, r)");
+                writeln!(w, "    case (nil, .some): return true");
+                writeln!(w, "    default: return false");
+                writeln!(w, "    }}");
+                writeln!(w, "}}");
+            }
+
+            self.add_file(spec, i, w, output);
+        }
+    }
+
+    fn write_interface(&self, i: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
+        if let StatementKind::Interface(_, ref id, _, ref bs, _) = *i {
+            let marshaler = SwiftMarshaler::new(spec);
+            let name = marshaler.type_ident(id);
+            let proxy = marshaler.proxy_ident(id);
+            let objc = marshaler.objc_ident(id);
+            let is_protocol = marshaler.is_protocol(id);
+            let mut w = Vec::new();
+            self.write_header(&mut w);
+
+            let methods = bs.statements.iter()
+                .filter_map(|s| match s.stmtKind {
+                    StatementKind::Function(_, ref m, ref fi, ref params, ref ret, _) => Some((m, fi, params, ret)),
+                    _ => None
+                })
+                .collect::<Vec<_>>();
+
+            writeln!(w, "public protocol {}: AnyObject {{", name);
+            for s in &bs.statements {
+                match s.stmtKind {
+                    StatementKind::Function(_, FunctionModifier::None, ref fi, ref params, ref ret, _) => {
+                        writeln!(w, "    func {}({}){}", marshaler.method_ident(fi), self.params(&marshaler, params, false),
+                                 self.returns(marshaler.type_name(ret), ret));
+                    },
+                    StatementKind::Comment(_, ref c) => self.write_comment(&mut w, "    ", c),
+                    _ => {}
+                }
+            }
+            writeln!(w, "}}");
+
+            // Wraps an object implemented behind the Objective-C type, static methods live here too.
+            writeln!(w, "");
+            writeln!(w, "public final class {}: {} {{", proxy, name);
+            self.write_consts(&mut w, &marshaler, &bs.statements);
+            writeln!(w, "    let objc: {}", objc);
+            writeln!(w, "");
+            writeln!(w, "    init(_ objc: {}) {{", objc);
+            writeln!(w, "        self.objc = objc");
+            writeln!(w, "    }}");
+            for &(m, fi, params, ret) in &methods {
+                if *m == FunctionModifier::Static && is_protocol {
+                    writeln!(w, "");
+                    writeln!(w, "    // static {} can't be called through an Objective-C protocol", fi.value);
+                    continue;
+                }
+                let (modifier, target) = if *m == FunctionModifier::Static { ("static ", objc.as_str()) } else { ("", "objc") };
+                let call = self.call(&marshaler, target, fi, params, |dts, e| marshaler.to_objc(dts, e));
+                writeln!(w, "");
+                writeln!(w, "    public {}func {}({}){} {{", modifier, marshaler.method_ident(fi), self.params(&marshaler, params, false),
+                         self.returns(marshaler.type_name(ret), ret));
+                self.write_return(&mut w, ret, marshaler.from_objc(ret, &call));
+                writeln!(w, "    }}");
+            }
+            writeln!(w, "");
+            writeln!(w, "    static func fromObjC(_ objc: {}?) -> {}? {{", objc, name);
+            writeln!(w, "        guard let objc = objc else {{");
+            writeln!(w, "            return nil");
+            writeln!(w, "        }}");
+            if is_protocol {
+                writeln!(w, "        if let adapter = objc as? {} {{", marshaler.adapter_ident(id));
+                writeln!(w, "            return adapter.value");
+                writeln!(w, "        }}");
+            }
+            writeln!(w, "        return {}(objc)", proxy);
+            writeln!(w, "    }}");
+            writeln!(w, "");
+            writeln!(w, "    static func toObjC(_ value: {}?) -> {}? {{", name, objc);
+            writeln!(w, "        guard let value = value else {{");
+            writeln!(w, "            return nil");
+            writeln!(w, "        }}");
+            writeln!(w, "        if let proxy = value as? {} {{", proxy);
+            writeln!(w, "            return proxy.objc");
+            writeln!(w, "        }}");
+            if is_protocol {
+                writeln!(w, "        return {}(value)", marshaler.adapter_ident(id));
+            }
+            else {
+                writeln!(w, "        fatalError(\"{} can only be implemented in C++\")", name);
+            }
+            writeln!(w, "    }}");
+            writeln!(w, "}}");
+
+            // Lets a Swift implementation stand in wherever Objective-C expects the protocol.
+            if is_protocol {
+                writeln!(w, "");
+                writeln!(w, "final class {}: NSObject, {} {{", marshaler.adapter_ident(id), objc);
+                writeln!(w, "    let value: {}", name);
+                writeln!(w, "");
+                writeln!(w, "    init(_ value: {}) {{", name);
+                writeln!(w, "        self.value = value");
+                writeln!(w, "    }}");
+                for &(m, fi, params, ret) in &methods {
+                    if *m == FunctionModifier::Static {
+                        continue;
+                    }
+                    let call = self.call(&marshaler, "value", fi, params, |dts, e| marshaler.from_objc(dts, e));
+                    writeln!(w, "");
+                    writeln!(w, "    func {}({}){} {{", marshaler.method_ident(fi), self.params(&marshaler, params, true),
+                             self.returns(marshaler.objc_type_name(ret), ret));
+                    self.write_return(&mut w, ret, marshaler.to_objc(ret, &call));
+                    writeln!(w, "    }}");
+                }
+                writeln!(w, "}}");
+            }
+
+            self.add_file(spec, id, w, output);
+        }
+    }
+
+    fn write_alias(&self, a: &StatementKind, spec: &Spec, output: &mut GeneratedOutput) {
+        if !spec.config.swift.aliases {
+            return;
+        }
+
+        if let StatementKind::Alias(_, ref i, ref dts) = *a {
+            let marshaler = SwiftMarshaler::new(spec);
+            let mut w = Vec::new();
+            self.write_header(&mut w);
+
+            writeln!(w, "public typealias {} = {}", marshaler.type_ident(i), marshaler.type_name(dts));
+
+            self.add_file(spec, i, w, output);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::Config;
+    use generator::testing;
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.objc.out = Some("objc".into());
+        config.objc.type_prefix = "BB".into();
+        config.swift.out = Some("swift".into());
+
+        config
+    }
+
+    #[test]
+    fn test_write_record() {
+        let output = testing::compile(config(), "color = enum { red; dark_blue; }\n\
+                                                 point = record { x: i32; col: color; } deriving(ord)\n\
+                                                 key = record { id: i64; name: string; tags: list<color>; groups: optional<list<color>>; const max_id: i64 = 99; } deriving(ord)\n");
+
+        let point = &output.get("swift/Point.swift").unwrap().contents;
+        assert!(point.contains("public struct Point: Equatable, Hashable, Comparable {"), "{}", point);
+        assert!(point.contains("if lhs.col != rhs.col {\n            return lhs.col < rhs.col"), "{}", point);
+
+        // Arrays and optionals aren't Comparable, so they are ordered element by element and nil first.
+        let key = &output.get("swift/Key.swift").unwrap().contents;
+        assert!(key.contains("public struct Key: Equatable, Hashable, Comparable {"), "{}", key);
+        assert!(key.contains("if lhs.tags != rhs.tags {\n            return lhs.tags.lexicographicallyPrecedes(rhs.tags, by: <)"), "{}", key);
+        assert!(key.contains("if lhs.groups != rhs.groups {\n            return precedes(lhs.groups, rhs.groups, by: \
+                              { l0, r0 in l0.lexicographicallyPrecedes(r0, by: <) })"), "{}", key);
+        assert!(key.contains("fileprivate func precedes<T>(_ lhs: T?, _ rhs: T?, by areInIncreasingOrder: (T, T) -> Bool) -> Bool {"), "{}", key);
+        assert!(!point.contains("precedes"), "{}", point);
+        assert!(key.contains("public var tags: [Color]"), "{}", key);
+        assert!(key.contains("public static let maxId: Int64 = 99"), "{}", key);
+        assert!(key.contains("init(_ objc: KeyObjC) {\n        self.init(id: objc.id, name: objc.name, tags: objc.tags.map { Color(rawValue: $0.intValue)! }, groups: objc.groups.map { $0.map { Color(rawValue: $0.intValue)! } })"), "{}", key);
+        assert!(key.contains("return KeyObjC(id: id, name: name, tags: tags.map { NSNumber(value: $0.rawValue) }, groups: groups.map { $0.map { NSNumber(value: $0.rawValue) } })"), "{}", key);
+
+        let color = &output.get("swift/Color.swift").unwrap().contents;
+        assert!(color.contains("public enum Color: Int, CaseIterable, Comparable {\n    case red\n    case darkBlue\n"), "{}", color);
+    }
+
+    #[test]
+    fn test_write_interface() {
+        let output = testing::compile(config(), "key = record { id: i64; }\n\
+                                                 listener = interface +o { on_key(key: key); }\n\
+                                                 key_store = interface +c +o { static create(): key_store; lookup(id: i64, l: listener): key; }\n");
+
+        // Without +o the Objective-C type would be a class nothing implements, so only the protocol is wrapped.
+        let store = &output.get("swift/KeyStore.swift").unwrap().contents;
+        assert!(store.contains("func lookup(id: Int64, l: Listener?) -> Key\n"), "{}", store);
+        assert!(store.contains("    // static create can't be called through an Objective-C protocol\n"), "{}", store);
+        assert!(store.contains("return Key(objc.lookup(id: id, l: ListenerProxy.toObjC(l)))"), "{}", store);
+
+        let listener = &output.get("swift/Listener.swift").unwrap().contents;
+        assert!(listener.contains("final class ListenerAdapter: NSObject, ListenerObjC {"), "{}", listener);
+        assert!(listener.contains("func onKey(key: KeyObjC) {\n        value.onKey(key: Key(key))"), "{}", listener);
+    }
+}
@@ -0,0 +1,344 @@
+/*
+ * Copyright © 2002-2017 Bluebeam Software, Inc. All Rights Reserved.
+ * Creator: Jonathan Rothberg
+ */
+
+use parser::ast::{DataTypeStatement, DeriveType, Identifier};
+use generator::spec::Spec;
+use generator::objc_marshaler::ObjcMarshaler;
+use generator::typer::{TypeDefinitionKind, UserObjectKind};
+
+const KEYWORDS: &'static [&'static str] = &[
+    "associatedtype", "class", "deinit", "enum", "extension", "fileprivate", "func", "import", "init",
+    "inout", "internal", "let", "open", "operator", "private", "protocol", "public", "rethrows", "static",
+    "struct", "subscript", "typealias", "var", "break", "case", "continue", "default", "defer", "do",
+    "else", "fallthrough", "for", "guard", "if", "in", "repeat", "return", "switch", "where", "while",
+    "as", "Any", "catch", "false", "is", "nil", "super", "self", "Self", "throw", "throws", "true", "try"
+];
+
+/// The protocols a generated struct can conform to.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum SwiftProtocol {
+    Equatable,
+    Hashable,
+    Comparable
+}
+
+impl SwiftProtocol {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            SwiftProtocol::Equatable => "Equatable",
+            SwiftProtocol::Hashable => "Hashable",
+            SwiftProtocol::Comparable => "Comparable"
+        }
+    }
+}
+
+pub struct SwiftMarshaler<'a> {
+    spec: &'a Spec,
+    objc: ObjcMarshaler<'a>
+}
+
+impl<'a> SwiftMarshaler<'a> {
+    pub fn new(spec: &'a Spec) -> SwiftMarshaler<'a> {
+        SwiftMarshaler {
+            spec: spec,
+            objc: ObjcMarshaler::new(spec)
+        }
+    }
+
+    pub fn escape(name: String) -> String {
+        if KEYWORDS.contains(&name.as_str()) {
+            format!("`{}`", name)
+        }
+        else {
+            name
+        }
+    }
+
+    pub fn type_ident(&self, i: &Identifier) -> String {
+        (self.spec.swift_ident_style.ty)(i.value.clone())
+    }
+
+    pub fn file_name(&self, i: &Identifier) -> String {
+        (self.spec.swift_file_ident_style)(i.value.clone())
+    }
+
+    pub fn method_ident(&self, i: &Identifier) -> String {
+        SwiftMarshaler::escape((self.spec.swift_ident_style.method)(i.value.clone()))
+    }
+
+    pub fn field_ident(&self, i: &Identifier) -> String {
+        SwiftMarshaler::escape((self.spec.swift_ident_style.field)(i.value.clone()))
+    }
+
+    pub fn local_ident(&self, i: &Identifier) -> String {
+        SwiftMarshaler::escape((self.spec.swift_ident_style.local)(i.value.clone()))
+    }
+
+    pub fn enum_member(&self, i: &Identifier) -> String {
+        SwiftMarshaler::escape((self.spec.swift_ident_style.enm)(i.value.clone()))
+    }
+
+    pub fn const_ident(&self, i: &Identifier) -> String {
+        SwiftMarshaler::escape((self.spec.swift_ident_style.cnst)(i.value.clone()))
+    }
+
+    /// The class wrapping an Objective-C object behind the interface protocol.
+    pub fn proxy_ident(&self, i: &Identifier) -> String {
+        format!("{}Proxy", self.type_ident(i))
+    }
+
+    /// The Objective-C object wrapping a Swift implementation of an interface.
+    pub fn adapter_ident(&self, i: &Identifier) -> String {
+        format!("{}Adapter", self.type_ident(i))
+    }
+
+    /// The name Swift imports the Objective-C type under.
+    pub fn objc_ident(&self, i: &Identifier) -> String {
+        self.objc.swift_name(i)
+    }
+
+    pub fn is_protocol(&self, i: &Identifier) -> bool {
+        self.objc.is_protocol(i)
+    }
+
+    /// Interfaces may be nil, except inside collections.
+    pub fn type_name(&self, dts: &DataTypeStatement) -> String {
+        match *dts {
+            DataTypeStatement::Object(ref i) if self.is_interface(i) => format!("{}?", self.type_ident(i)),
+            DataTypeStatement::Optional(ref t) => format!("{}?", self.element_type_name(t)),
+            _ => self.element_type_name(dts)
+        }
+    }
+
+    fn element_type_name(&self, dts: &DataTypeStatement) -> String {
+        match *dts {
+            DataTypeStatement::List(ref t) | DataTypeStatement::Array(ref t) => format!("[{}]", self.element_type_name(t)),
+            DataTypeStatement::Set(ref t) => format!("Set<{}>", self.element_type_name(t)),
+            DataTypeStatement::Map(ref k, ref v) => format!("[{}: {}]", self.element_type_name(k), self.element_type_name(v)),
+            DataTypeStatement::Object(ref i) => self.type_ident(i),
+            _ => self.builtin_type_name(dts)
+        }
+    }
+
+    /// The type Swift sees for the Objective-C spelling of `dts`.
+    pub fn objc_type_name(&self, dts: &DataTypeStatement) -> String {
+        match *dts {
+            DataTypeStatement::Object(ref i) if self.is_interface(i) => format!("{}?", self.objc_ident(i)),
+            DataTypeStatement::Optional(ref t) => format!("{}?", self.objc_element_type_name(t, true)),
+            _ => self.objc_element_type_name(dts, false)
+        }
+    }
+
+    fn objc_element_type_name(&self, dts: &DataTypeStatement, boxed: bool) -> String {
+        match *dts {
+            DataTypeStatement::List(ref t) => format!("[{}]", self.objc_element_type_name(t, true)),
+            DataTypeStatement::Array(..) => "Data".into(),
+            DataTypeStatement::Set(ref t) => format!("Set<{}>", self.objc_element_type_name(t, true)),
+            DataTypeStatement::Map(ref k, ref v) => format!("[{}: {}]", self.objc_element_type_name(k, true), self.objc_element_type_name(v, true)),
+            DataTypeStatement::Object(ref i) if boxed && self.is_enum(i) => "NSNumber".into(),
+            DataTypeStatement::Object(ref i) => self.objc_ident(i),
+            _ if boxed && self.is_primitive(dts) => "NSNumber".into(),
+            _ => self.builtin_type_name(dts)
+        }
+    }
+
+    fn builtin_type_name(&self, dts: &DataTypeStatement) -> String {
+        match *dts {
+            DataTypeStatement::None => "Void",
+            DataTypeStatement::Integer8 => "Int8",
+            DataTypeStatement::Integer16 => "Int16",
+            DataTypeStatement::Integer32 => "Int32",
+            DataTypeStatement::Integer64 => "Int64",
+            DataTypeStatement::Float32 => "Float",
+            DataTypeStatement::Float64 => "Double",
+            DataTypeStatement::Bool => "Bool",
+            DataTypeStatement::String => "String",
+            DataTypeStatement::Binary => "Data",
+            DataTypeStatement::Date => "Date",
+            _ => ""
+        }.into()
+    }
+
+    /// Converts `expr`, a value as imported from Objective-C, to the Swift type.  An `array<T>`
+    /// is copied out of the `Data` holding its elements.
+    pub fn from_objc(&self, dts: &DataTypeStatement, expr: &str) -> String {
+        self.convert_from(dts, expr, false)
+    }
+
+    /// Converts `expr`, a Swift value, to the type Objective-C expects.
+    pub fn to_objc(&self, dts: &DataTypeStatement, expr: &str) -> String {
+        self.convert_to(dts, expr, false)
+    }
+
+    fn convert_from(&self, dts: &DataTypeStatement, expr: &str, boxed: bool) -> String {
+        match *dts {
+            DataTypeStatement::Optional(ref t) => {
+                match **t {
+                    DataTypeStatement::Object(ref i) if self.is_interface(i) => format!("{}.fromObjC({})", self.proxy_ident(i), expr),
+                    _ => self.map(expr, &self.convert_from(t, "$0", true))
+                }
+            },
+            DataTypeStatement::List(ref t) => self.map(expr, &self.convert_from(t, "$0", true)),
+            DataTypeStatement::Array(ref t) => format!("{}.withUnsafeBytes {{ Array($0.bindMemory(to: {}.self)) }}", expr, self.builtin_type_name(t)),
+            DataTypeStatement::Set(ref t) => {
+                let element = self.convert_from(t, "$0", true);
+                if element == "$0" { expr.into() } else { format!("Set({})", self.map(expr, &element)) }
+            },
+            DataTypeStatement::Map(ref k, ref v) => {
+                self.map_dictionary(expr, &self.convert_from(k, "$0.key", true), &self.convert_from(v, "$0.value", true))
+            },
+            DataTypeStatement::Object(ref i) => {
+                match self.user_object_kind(i) {
+                    Some(UserObjectKind::Enum) if boxed => format!("{}(rawValue: {}.intValue)!", self.type_ident(i), expr),
+                    Some(UserObjectKind::Enum) => format!("{}(rawValue: {}.rawValue)!", self.type_ident(i), expr),
+                    Some(UserObjectKind::Record(..)) => format!("{}({})", self.type_ident(i), expr),
+                    Some(UserObjectKind::Interface(..)) if boxed => format!("{}.fromObjC({})!", self.proxy_ident(i), expr),
+                    Some(UserObjectKind::Interface(..)) => format!("{}.fromObjC({})", self.proxy_ident(i), expr),
+                    None => expr.into()
+                }
+            },
+            _ if boxed && self.is_primitive(dts) => format!("{}.{}", expr, self.number_accessor(dts)),
+            _ => expr.into()
+        }
+    }
+
+    fn convert_to(&self, dts: &DataTypeStatement, expr: &str, boxed: bool) -> String {
+        match *dts {
+            DataTypeStatement::Optional(ref t) => {
+                match **t {
+                    DataTypeStatement::Object(ref i) if self.is_interface(i) => format!("{}.toObjC({})", self.proxy_ident(i), expr),
+                    _ => self.map(expr, &self.convert_to(t, "$0", true))
+                }
+            },
+            DataTypeStatement::List(ref t) => self.map(expr, &self.convert_to(t, "$0", true)),
+            DataTypeStatement::Array(..) => format!("{}.withUnsafeBufferPointer {{ Data(buffer: $0) }}", expr),
+            DataTypeStatement::Set(ref t) => {
+                let element = self.convert_to(t, "$0", true);
+                if element == "$0" { expr.into() } else { format!("Set({})", self.map(expr, &element)) }
+            },
+            DataTypeStatement::Map(ref k, ref v) => {
+                self.map_dictionary(expr, &self.convert_to(k, "$0.key", true), &self.convert_to(v, "$0.value", true))
+            },
+            DataTypeStatement::Object(ref i) => {
+                match self.user_object_kind(i) {
+                    Some(UserObjectKind::Enum) if boxed => format!("NSNumber(value: {}.rawValue)", expr),
+                    Some(UserObjectKind::Enum) => format!("{}(rawValue: {}.rawValue)!", self.objc_ident(i), expr),
+                    Some(UserObjectKind::Record(..)) => format!("{}.objc", expr),
+                    Some(UserObjectKind::Interface(..)) if boxed => format!("{}.toObjC({})!", self.proxy_ident(i), expr),
+                    Some(UserObjectKind::Interface(..)) => format!("{}.toObjC({})", self.proxy_ident(i), expr),
+                    None => expr.into()
+                }
+            },
+            _ if boxed && self.is_primitive(dts) => format!("NSNumber(value: {})", expr),
+            _ => expr.into()
+        }
+    }
+
+    fn map(&self, expr: &str, element: &str) -> String {
+        if element == "$0" {
+            expr.into()
+        }
+        else {
+            format!("{}.map {{ {} }}", expr, element)
+        }
+    }
+
+    fn map_dictionary(&self, expr: &str, key: &str, value: &str) -> String {
+        if key == "$0.key" && value == "$0.value" {
+            expr.into()
+        }
+        else {
+            format!("Dictionary(uniqueKeysWithValues: {}.map {{ ({}, {}) }})", expr, key, value)
+        }
+    }
+
+    fn number_accessor(&self, dts: &DataTypeStatement) -> &'static str {
+        match *dts {
+            DataTypeStatement::Integer8 => "int8Value",
+            DataTypeStatement::Integer16 => "int16Value",
+            DataTypeStatement::Integer32 => "int32Value",
+            DataTypeStatement::Integer64 => "int64Value",
+            DataTypeStatement::Float32 => "floatValue",
+            DataTypeStatement::Float64 => "doubleValue",
+            _ => "boolValue"
+        }
+    }
+
+    /// The protocols a record conforms to, limited to the ones every field conforms to.
+    /// `deriving(eq)` also makes a record hashable.
+    pub fn record_protocols(&self, derives: &Vec<DeriveType>, fields: &Vec<&DataTypeStatement>) -> Vec<SwiftProtocol> {
+        let mut wanted = vec![];
+        if derives.contains(&DeriveType::Eq) || derives.contains(&DeriveType::Ord) {
+            wanted.extend(&[SwiftProtocol::Equatable, SwiftProtocol::Hashable]);
+        }
+        if derives.contains(&DeriveType::Ord) {
+            wanted.push(SwiftProtocol::Comparable);
+        }
+
+        wanted.into_iter()
+            .filter(|p| fields.iter().all(|f| if *p == SwiftProtocol::Comparable { self.is_orderable(f) } else { self.conforms(f, *p) }))
+            .collect()
+    }
+
+    /// Whether a record field of type `dts` can be ordered.  Arrays and optionals aren't
+    /// Comparable, so they are ordered element by element and with `nil` first.
+    pub fn is_orderable(&self, dts: &DataTypeStatement) -> bool {
+        match *dts {
+            DataTypeStatement::List(ref t) | DataTypeStatement::Optional(ref t) => self.is_orderable(t),
+            _ => self.conforms(dts, SwiftProtocol::Comparable)
+        }
+    }
+
+    pub fn conforms(&self, dts: &DataTypeStatement, p: SwiftProtocol) -> bool {
+        match *dts {
+            DataTypeStatement::Binary | DataTypeStatement::Array(..) => p != SwiftProtocol::Comparable,
+            DataTypeStatement::Optional(ref v) => p != SwiftProtocol::Comparable && self.conforms(v, p),
+            DataTypeStatement::List(ref v) | DataTypeStatement::Set(ref v) => {
+                p != SwiftProtocol::Comparable && self.conforms(v, p)
+            },
+            DataTypeStatement::Map(ref k, ref v) => {
+                p != SwiftProtocol::Comparable && self.conforms(k, p) && self.conforms(v, p)
+            },
+            DataTypeStatement::Object(ref i) => {
+                match self.user_object_kind(i) {
+                    Some(UserObjectKind::Enum) => true,
+                    Some(UserObjectKind::Record(ref d)) => {
+                        match p {
+                            SwiftProtocol::Comparable => d.contains(&DeriveType::Ord),
+                            _ => d.contains(&DeriveType::Eq) || d.contains(&DeriveType::Ord)
+                        }
+                    },
+                    _ => false
+                }
+            },
+            _ => true
+        }
+    }
+
+    pub fn is_interface(&self, i: &Identifier) -> bool {
+        match self.user_object_kind(i) {
+            Some(UserObjectKind::Interface(..)) => true,
+            _ => false
+        }
+    }
+
+    fn is_enum(&self, i: &Identifier) -> bool {
+        self.user_object_kind(i) == Some(UserObjectKind::Enum)
+    }
+
+    fn is_primitive(&self, dts: &DataTypeStatement) -> bool {
+        match self.spec.typer.get_from_data_type(dts) {
+            TypeDefinitionKind::Primitive(..) => true,
+            _ => false
+        }
+    }
+
+    pub fn user_object_kind(&self, i: &Identifier) -> Option<UserObjectKind> {
+        match self.spec.typer.get(&i.value) {
+            TypeDefinitionKind::UserObject(_, k) => Some(k),
+            _ => None
+        }
+    }
+}
@@ -0,0 +1,66 @@
+/*
+ * Copyright © 2002-2017 Bluebeam Software, Inc. All Rights Reserved.
+ * Creator: Jonathan Rothberg
+ */
+
+//! Fixtures shared by the generator tests.
+
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::process::{self, Command};
+use compiler::{Compiler, CompilerOptions, Diagnostic};
+use config::Config;
+use generator::output::GeneratedOutput;
+
+/// Compiles `input` as `test.djinni` with `config`, failing the test on any diagnostic.
+pub fn compile(config: Config, input: &str) -> GeneratedOutput {
+    Compiler::new(CompilerOptions::from_config(config).unwrap())
+        .source("test.djinni", input)
+        .compile()
+        .expect("compile failed")
+}
+
+/// Compiles `input` as `test.djinni` with `config`, failing the test if it compiles.
+pub fn compile_errors(config: Config, input: &str) -> Vec<Diagnostic> {
+    match Compiler::new(CompilerOptions::from_config(config).unwrap()).source("test.djinni", input).compile() {
+        Ok(_) => panic!("expected compile of {:?} to fail", input),
+        Err(diagnostics) => diagnostics
+    }
+}
+
+/// Compiles `input` with the default config, failing the test unless the first diagnostic is `message`.
+pub fn expect_error(input: &str, message: &str) {
+    let diagnostics = compile_errors(Config::default(), input);
+    assert!(diagnostics[0].message == message, "{:?}: {}", input, diagnostics[0].message);
+}
+
+/// The program the bindings on top of the C API are tested with: an enum, a record with an
+/// optional and a constant, and an interface with a static factory taking and returning containers.
+pub const C_BINDINGS_INPUT: &'static str = "color = enum { red; dark_blue; }\n\
+                                            key = record { id: i64; name: string; col: color; from: optional<date>; const max_id: i64 = 99; }\n\
+                                            key_store = interface +c { static create(): key_store; lookup(names: list<string>, fuzzy: bool): map<string, key>; }\n";
+
+/// Compiles `main` against the C++ files of `output`, with `include` on the include path, using
+/// the system C++ compiler.  Does nothing when there is no compiler to run.
+pub fn check_cpp(name: &str, output: &GeneratedOutput, include: &str, main: &str) {
+    let dir = env::temp_dir().join(format!("rusty_lamp_{}_{}", name, process::id()));
+    let mut files = output.clone();
+    for file in &mut files.files {
+        file.path = dir.join(&file.path);
+    }
+    files.add_file(dir.join("main.cpp"), main.as_bytes().to_vec());
+    files.write_to_disk().expect("unable to write the generated files");
+
+    let result = Command::new("c++")
+        .args(&["-std=c++17", "-fsyntax-only", "-I"])
+        .arg(dir.join(include))
+        .arg(dir.join("main.cpp"))
+        .output();
+    let _ = fs::remove_dir_all(&dir);
+    match result {
+        Ok(out) => assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr)),
+        Err(ref e) if e.kind() == ErrorKind::NotFound => {},
+        Err(e) => panic!("unable to run c++: {}", e)
+    }
+}
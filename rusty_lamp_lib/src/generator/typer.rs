@@ -3,10 +3,14 @@
  * Creator: Jonathan Rothberg
  */
 
-use std::collections::{ HashMap, HashSet };
+use std::collections::{ BTreeMap, HashMap, HashSet };
 use std::fmt;
-use parser::ast::{Statement, StatementKind, DataTypeStatement};
+use std::io;
+use std::io::Write;
+use std::sync::Arc;
+use parser::ast::{Identifier, Statement, StatementKind, DataTypeStatement, DeriveType, InterfaceType, Annotation, find_annotation};
 use generator::resolver::ResolveError;
+use config::{ConfigError, TypeOverride};
 
 pub struct TypeDefinition {
     identifier: String,
@@ -14,26 +18,63 @@ pub struct TypeDefinition {
     
 }
 
+/// The per-language spelling of a primitive idl type.
+#[derive(PartialEq, Eq, Clone, Debug, Hash)]
+pub struct PrimitiveType {
+    pub name: String,
+    pub java: String,
+    pub java_boxed: String,
+    pub jni: String,
+    pub jni_signature: String,
+    pub cpp: String,
+    pub cpp_header: Option<String>,
+    pub objc: String,
+    pub objc_boxed: String
+}
+
+// (java, jni, jni signature, java boxed) for every JVM primitive.
+const JVM_PRIMITIVES: &'static [(&'static str, &'static str, &'static str, &'static str)] = &[
+    ("byte", "jbyte", "B", "Byte"),
+    ("short", "jshort", "S", "Short"),
+    ("int", "jint", "I", "Integer"),
+    ("long", "jlong", "J", "Long"),
+    ("float", "jfloat", "F", "Float"),
+    ("double", "jdouble", "D", "Double"),
+    ("boolean", "jboolean", "Z", "Boolean"),
+    ("char", "jchar", "C", "Character")
+];
+
+#[derive(PartialEq, Eq, Clone, Debug, Hash)]
+pub enum UserObjectKind {
+    Enum,
+    Record(Vec<DeriveType>),
+    Interface(Vec<InterfaceType>)
+}
+
 #[derive(PartialEq, Eq, Clone, Debug, Hash)]
 pub enum TypeDefinitionKind {
     None,
-    Primitive(String, String, String, String, String, String, String, String),
+    Primitive(PrimitiveType),
     String,
     Binary,
     Optional,
+    Future,
+    Function,
     Date,
     List,
+    Array,
     Set,
     Map,
-    UserObject(String)
+    UserObject(String, UserObjectKind),
+    Alias(DataTypeStatement)
 }
 
 impl fmt::Display for TypeDefinitionKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let printable = match *self {
             TypeDefinitionKind::None => "none".into(),
-            TypeDefinitionKind::Primitive(ref n, ..) => {
-                format!("{}", n)
+            TypeDefinitionKind::Primitive(ref p) => {
+                format!("{}", p.name)
             },
             TypeDefinitionKind::String => {
                 "string".into()
@@ -44,20 +85,32 @@ impl fmt::Display for TypeDefinitionKind {
             TypeDefinitionKind::Optional => {
                 "optional".into()
             },
+            TypeDefinitionKind::Future => {
+                "future".into()
+            },
+            TypeDefinitionKind::Function => {
+                "fn".into()
+            },
             TypeDefinitionKind::Date => {
                 "date".into()
             },
             TypeDefinitionKind::List => {
                 "list".into()
             },
+            TypeDefinitionKind::Array => {
+                "array".into()
+            },
             TypeDefinitionKind::Set => {
                 "set".into()
             },
             TypeDefinitionKind::Map => {
                 "map".into()
             },
-            TypeDefinitionKind::UserObject(ref n) => {
+            TypeDefinitionKind::UserObject(ref n, _) => {
                 format!("{}", n)
+            },
+            TypeDefinitionKind::Alias(ref t) => {
+                format!("alias {}", t)
             }
         };
 
@@ -69,7 +122,7 @@ impl TypeDefinitionKind {
     pub fn num_params(&self) -> i8 {
         match *self {
             TypeDefinitionKind::Map => 2,
-            TypeDefinitionKind::List | TypeDefinitionKind::Set | TypeDefinitionKind::Optional => 1,
+            TypeDefinitionKind::List | TypeDefinitionKind::Array | TypeDefinitionKind::Set | TypeDefinitionKind::Optional | TypeDefinitionKind::Future => 1,
             _ => 0
         }
     }
@@ -87,12 +140,18 @@ impl TypeDefinitionKind {
 #[derive(Clone)]
 pub struct Typer {
     table: HashMap<String, TypeDefinitionKind>,
+    overrides: HashMap<String, TypeOverride>,
+    annotations: HashMap<String, Vec<Annotation>>,
+    fields: HashMap<String, Vec<DataTypeStatement>>
 }
 
 impl Typer {
     pub fn new() -> Typer {
         let mut t = Typer {
             table: HashMap::new(),
+            overrides: HashMap::new(),
+            annotations: HashMap::new(),
+            fields: HashMap::new()
         };
 
         t.populate_builtin_types();
@@ -101,27 +160,186 @@ impl Typer {
     }
 
     fn populate_builtin_types(&mut self) {
-        self.table.insert("i8".into(), TypeDefinitionKind::Primitive("i8".into(), "byte".into(), "jbyte".into(), "int8_t".into(), "Byte".into(), "B".into(), "int8_t".into(), "NSNumber".into()));
-        self.table.insert("i16".into(), TypeDefinitionKind::Primitive("i16".into(), "short".into(), "short".into(), "int16_6".into(), "Short".into(), "S".into(), "int16_t".into(), "NSNumber".into()));
-        self.table.insert("i32".into(), TypeDefinitionKind::Primitive("i32".into(), "int".into(), "jint".into(), "int32_t".into(), "Integer".into(), "I".into(), "int32_t".into(), "NSNumber".into()));
-        self.table.insert("i64".into(), TypeDefinitionKind::Primitive("i64".into(), "long".into(), "jlong".into(), "int64_t".into(), "Long".into(), "J".into(), "int64_t".into(), "NSNumber".into()));
-        self.table.insert("f32".into(), TypeDefinitionKind::Primitive("f32".into(), "float".into(), "jfloat".into(), "float".into(), "Float".into(), "F".into(), "float".into(), "NSNumber".into()));
-        self.table.insert("f64".into(), TypeDefinitionKind::Primitive("f64".into(), "double".into(), "jdouble".into(), "double".into(), "Double".into(), "D".into(), "double".into(), "NSNumber".into()));
-        self.table.insert("bool".into(), TypeDefinitionKind::Primitive("bool".into(), "boolean".into(), "jboolean".into(), "bool".into(), "Boolean".into(), "Z".into(), "BOOL".into(), "NSNumber".into()));
+        self.insert_primitive(PrimitiveType {
+            name: "i8".into(),
+            java: "byte".into(),
+            java_boxed: "Byte".into(),
+            jni: "jbyte".into(),
+            jni_signature: "B".into(),
+            cpp: "int8_t".into(),
+            cpp_header: Some("<cstdint>".into()),
+            objc: "int8_t".into(),
+            objc_boxed: "NSNumber".into()
+        });
+        self.insert_primitive(PrimitiveType {
+            name: "i16".into(),
+            java: "short".into(),
+            java_boxed: "Short".into(),
+            jni: "jshort".into(),
+            jni_signature: "S".into(),
+            cpp: "int16_t".into(),
+            cpp_header: Some("<cstdint>".into()),
+            objc: "int16_t".into(),
+            objc_boxed: "NSNumber".into()
+        });
+        self.insert_primitive(PrimitiveType {
+            name: "i32".into(),
+            java: "int".into(),
+            java_boxed: "Integer".into(),
+            jni: "jint".into(),
+            jni_signature: "I".into(),
+            cpp: "int32_t".into(),
+            cpp_header: Some("<cstdint>".into()),
+            objc: "int32_t".into(),
+            objc_boxed: "NSNumber".into()
+        });
+        self.insert_primitive(PrimitiveType {
+            name: "i64".into(),
+            java: "long".into(),
+            java_boxed: "Long".into(),
+            jni: "jlong".into(),
+            jni_signature: "J".into(),
+            cpp: "int64_t".into(),
+            cpp_header: Some("<cstdint>".into()),
+            objc: "int64_t".into(),
+            objc_boxed: "NSNumber".into()
+        });
+        self.insert_primitive(PrimitiveType {
+            name: "f32".into(),
+            java: "float".into(),
+            java_boxed: "Float".into(),
+            jni: "jfloat".into(),
+            jni_signature: "F".into(),
+            cpp: "float".into(),
+            cpp_header: None,
+            objc: "float".into(),
+            objc_boxed: "NSNumber".into()
+        });
+        self.insert_primitive(PrimitiveType {
+            name: "f64".into(),
+            java: "double".into(),
+            java_boxed: "Double".into(),
+            jni: "jdouble".into(),
+            jni_signature: "D".into(),
+            cpp: "double".into(),
+            cpp_header: None,
+            objc: "double".into(),
+            objc_boxed: "NSNumber".into()
+        });
+        self.insert_primitive(PrimitiveType {
+            name: "bool".into(),
+            java: "boolean".into(),
+            java_boxed: "Boolean".into(),
+            jni: "jboolean".into(),
+            jni_signature: "Z".into(),
+            cpp: "bool".into(),
+            cpp_header: None,
+            objc: "BOOL".into(),
+            objc_boxed: "NSNumber".into()
+        });
         self.table.insert("string".into(), TypeDefinitionKind::String);
         self.table.insert("binary".into(), TypeDefinitionKind::Binary);
         self.table.insert("optional".into(), TypeDefinitionKind::Optional);
+        self.table.insert("future".into(), TypeDefinitionKind::Future);
+        self.table.insert("fn".into(), TypeDefinitionKind::Function);
         self.table.insert("date".into(), TypeDefinitionKind::Date);
         self.table.insert("list".into(), TypeDefinitionKind::List);
+        self.table.insert("array".into(), TypeDefinitionKind::Array);
         self.table.insert("set".into(), TypeDefinitionKind::Set);
         self.table.insert("map".into(), TypeDefinitionKind::Map);
     }
 
+    fn insert_primitive(&mut self, p: PrimitiveType) {
+        self.table.insert(p.name.clone(), TypeDefinitionKind::Primitive(p));
+    }
+
+    /// Applies the `[types.<name>]` sections of the configuration.  Primitives
+    /// are rewritten in place; string, binary and date keep their kind and the
+    /// override is looked up by the marshalers through `get_override`.
+    pub fn apply_overrides(&mut self, overrides: &BTreeMap<String, TypeOverride>) -> Result<(), ConfigError> {
+        for (name, o) in overrides {
+            let err = |msg: String| ConfigError::InvalidTypeOverride(name.clone(), msg);
+            if let Some(field) = o.find_empty() {
+                return Err(err(format!("`{}` must not be empty", field)));
+            }
+
+            match self.get(name) {
+                TypeDefinitionKind::Primitive(mut p) => {
+                    Typer::override_primitive(&mut p, o).map_err(&err)?;
+                    self.insert_primitive(p);
+                },
+                TypeDefinitionKind::String | TypeDefinitionKind::Binary | TypeDefinitionKind::Date => {
+                    if let Some(field) = o.find_primitive_only() {
+                        return Err(err(format!("`{}` can only be overridden for primitive types", field)));
+                    }
+                    self.overrides.insert(name.clone(), o.clone());
+                },
+                TypeDefinitionKind::None => return Err(err("unknown builtin type".into())),
+                _ => return Err(err("only primitives, string, binary and date can be overridden".into()))
+            }
+        }
+
+        Ok(())
+    }
+
+    fn override_primitive(p: &mut PrimitiveType, o: &TypeOverride) -> Result<(), String> {
+        if let Some(ref java) = o.java {
+            let &(_, jni, signature, boxed) = JVM_PRIMITIVES.iter()
+                .find(|&&(j, ..)| j == java)
+                .ok_or_else(|| format!("java type `{}` is not a JVM primitive", java))?;
+            p.java = java.clone();
+            p.jni = jni.into();
+            p.jni_signature = signature.into();
+            p.java_boxed = boxed.into();
+        }
+
+        if let Some(ref v) = o.java_boxed { p.java_boxed = v.clone(); }
+        if let Some(ref v) = o.jni { p.jni = v.clone(); }
+        if let Some(ref v) = o.jni_signature { p.jni_signature = v.clone(); }
+        if let Some(ref v) = o.cpp { p.cpp = v.clone(); }
+        if let Some(ref v) = o.cpp_header { p.cpp_header = Some(v.clone()); }
+        if let Some(ref v) = o.objc { p.objc = v.clone(); }
+        if let Some(ref v) = o.objc_boxed { p.objc_boxed = v.clone(); }
+
+        let consistent = JVM_PRIMITIVES.iter()
+            .any(|&(java, jni, signature, _)| java == p.java && jni == p.jni && signature == p.jni_signature);
+        if !consistent {
+            return Err(format!("jni type `{}` with signature `{}` does not match java type `{}`", p.jni, p.jni_signature, p.java));
+        }
+
+        Ok(())
+    }
+
+    pub fn get_override(&self, key: &str) -> Option<&TypeOverride> {
+        self.overrides.get(key)
+    }
+
     pub fn insert_type(&mut self, key: &String, td: TypeDefinitionKind) -> Result<(), ResolveError> {
         self.table.insert(key.clone(), td.clone());
         return Ok(());
     }
 
+    /// Keeps the annotations of a user type so generators can look them up wherever it is used.
+    pub fn insert_annotations(&mut self, key: &String, annotations: &Vec<Annotation>) {
+        if annotations.len() > 0 {
+            self.annotations.insert(key.clone(), annotations.clone());
+        }
+    }
+
+    pub fn get_annotation(&self, key: &String, name: &str) -> Option<&Annotation> {
+        self.annotations.get(key).and_then(|a| find_annotation(a, name))
+    }
+
+    /// Keeps the field types of a record, resolved, for generators whose output for a record
+    /// depends on what the records it contains hold.
+    pub fn insert_fields(&mut self, key: &String, fields: Vec<DataTypeStatement>) {
+        self.fields.insert(key.clone(), fields);
+    }
+
+    pub fn get_fields(&self, key: &String) -> Vec<DataTypeStatement> {
+        self.fields.get(key).cloned().unwrap_or_default()
+    }
+
     pub fn get(&self, key: &String) -> TypeDefinitionKind {
         match self.table.get(key) {
             Some(e) => e.clone(),
@@ -129,6 +347,44 @@ impl Typer {
         }
     }
 
+    /// The table key `name` refers to from inside `module`: a qualified name is looked up as is,
+    /// otherwise the module's own type comes before a top level one.
+    pub fn lookup(&self, module: &str, name: &String) -> Option<String> {
+        if !name.contains('.') && module.len() > 0 {
+            let local = format!("{}.{}", module, name);
+            if self.table.contains_key(&local) {
+                return Some(local);
+            }
+        }
+
+        if self.table.contains_key(name) { Some(name.clone()) } else { None }
+    }
+
+    /// Resolves the user types in `dts` to their table keys and replaces every alias with the
+    /// type it stands for.  Aliases are stored already resolved, so one pass is enough.  Names
+    /// that aren't found are kept for the resolver to report.
+    pub fn expand(&self, module: &str, dts: &DataTypeStatement) -> DataTypeStatement {
+        match *dts {
+            DataTypeStatement::Object(ref i) => match self.lookup(module, &i.value) {
+                Some(key) => match self.table.get(&key) {
+                    Some(&TypeDefinitionKind::Alias(ref t)) => t.clone(),
+                    _ => DataTypeStatement::Object(Identifier { token: i.token.clone(), value: key })
+                },
+                None => dts.clone()
+            },
+            DataTypeStatement::Set(ref t) => DataTypeStatement::Set(Arc::new(self.expand(module, t))),
+            DataTypeStatement::List(ref t) => DataTypeStatement::List(Arc::new(self.expand(module, t))),
+            DataTypeStatement::Array(ref t) => DataTypeStatement::Array(Arc::new(self.expand(module, t))),
+            DataTypeStatement::Optional(ref t) => DataTypeStatement::Optional(Arc::new(self.expand(module, t))),
+            DataTypeStatement::Future(ref t) => DataTypeStatement::Future(Arc::new(self.expand(module, t))),
+            DataTypeStatement::Map(ref k, ref v) => DataTypeStatement::Map(Arc::new(self.expand(module, k)), Arc::new(self.expand(module, v))),
+            DataTypeStatement::Function(ref p, ref r) => {
+                DataTypeStatement::Function(p.iter().map(|t| self.expand(module, t)).collect(), Arc::new(self.expand(module, r)))
+            },
+            _ => dts.clone()
+        }
+    }
+
     pub fn get_from_data_type(&self, dts: &DataTypeStatement) -> TypeDefinitionKind {
         match self.table.get(&dts.get_name()) {
             Some(t) => t.clone(),
@@ -136,13 +392,27 @@ impl Typer {
         }
     }
 
-    pub fn dump(&self) {
-        for (key, value) in &self.table {
-            println!("{} -> {}", key, value);
+    pub fn dump(&self, w: &mut Write) -> io::Result<()> {
+        let mut keys = self.table.keys().collect::<Vec<_>>();
+        keys.sort();
+        for key in keys {
+            writeln!(w, "{} -> {}", key, self.table[key])?;
         }
+
+        Ok(())
     }
 }
 
+/// The module of the table key `name`, `net` for `net.Config`, or `None` at the top level.
+pub fn module_name(name: &str) -> Option<&str> {
+    name.rfind('.').map(|n| &name[..n])
+}
+
+/// The name `name` was declared with, `Config` for `net.Config`.
+pub fn local_name(name: &str) -> &str {
+    name.rfind('.').map_or(name, |n| &name[n + 1..])
+}
+
 pub struct DuplicateChecker {
     identifiers: HashSet<String>,
     label: String
@@ -165,3 +435,82 @@ impl DuplicateChecker {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn primitive(typer: &Typer, name: &str) -> PrimitiveType {
+        match typer.get(&name.to_string()) {
+            TypeDefinitionKind::Primitive(p) => p,
+            t => panic!("expected a primitive, got={:?}", t)
+        }
+    }
+
+    fn overrides(name: &str, o: TypeOverride) -> BTreeMap<String, TypeOverride> {
+        let mut m = BTreeMap::new();
+        m.insert(name.to_string(), o);
+        m
+    }
+
+    #[test]
+    fn test_builtin_primitives() {
+        let typer = Typer::new();
+
+        let i16 = primitive(&typer, "i16");
+        assert!(i16.cpp == "int16_t", "{}", i16.cpp);
+        assert!(i16.jni == "jshort", "{}", i16.jni);
+        assert!(primitive(&typer, "bool").objc == "BOOL");
+        assert!(primitive(&typer, "f64").cpp_header == None);
+    }
+
+    #[test]
+    fn test_override_primitive() {
+        let mut typer = Typer::new();
+        let o = TypeOverride { cpp: Some("long long".into()), cpp_header: Some("<cstddef>".into()), ..TypeOverride::default() };
+        typer.apply_overrides(&overrides("i64", o)).unwrap();
+
+        let i64 = primitive(&typer, "i64");
+        assert!(i64.cpp == "long long", "{}", i64.cpp);
+        assert!(i64.cpp_header == Some("<cstddef>".into()));
+        assert!(i64.java == "long");
+
+        let o = TypeOverride { java: Some("int".into()), ..TypeOverride::default() };
+        typer.apply_overrides(&overrides("i16", o)).unwrap();
+
+        let i16 = primitive(&typer, "i16");
+        assert!(i16.jni == "jint" && i16.jni_signature == "I" && i16.java_boxed == "Integer", "{:?}", i16);
+    }
+
+    #[test]
+    fn test_override_builtin() {
+        let mut typer = Typer::new();
+        let o = TypeOverride { cpp: Some("my::time".into()), ..TypeOverride::default() };
+        typer.apply_overrides(&overrides("date", o)).unwrap();
+
+        assert!(typer.get_override("date").and_then(|o| o.cpp.clone()) == Some("my::time".into()));
+        assert!(typer.get(&"date".to_string()) == TypeDefinitionKind::Date);
+    }
+
+    #[test]
+    fn test_invalid_overrides() {
+        let tests = vec![
+            ("u64", TypeOverride { cpp: Some("uint64_t".into()), ..TypeOverride::default() }, "unknown builtin type"),
+            ("list", TypeOverride { cpp: Some("my::list".into()), ..TypeOverride::default() }, "only primitives, string, binary and date can be overridden"),
+            ("i32", TypeOverride { cpp: Some(" ".into()), ..TypeOverride::default() }, "`cpp` must not be empty"),
+            ("i32", TypeOverride { java: Some("Integer".into()), ..TypeOverride::default() }, "java type `Integer` is not a JVM primitive"),
+            ("i32", TypeOverride { jni_signature: Some("J".into()), ..TypeOverride::default() }, "jni type `jint` with signature `J` does not match java type `int`"),
+            ("date", TypeOverride { jni: Some("jobject".into()), ..TypeOverride::default() }, "`jni` can only be overridden for primitive types"),
+        ];
+
+        for (name, o, expected) in tests {
+            match Typer::new().apply_overrides(&overrides(name, o)) {
+                Err(ConfigError::InvalidTypeOverride(ref n, ref msg)) => {
+                    assert!(n == name, "expected={}, got={}", name, n);
+                    assert!(msg == expected, "expected={}, got={}", expected, msg);
+                },
+                r => assert!(false, "expected an error for {}, got={:?}", name, r)
+            }
+        }
+    }
+}
@@ -0,0 +1,644 @@
+/*
+ * Copyright © 2002-2017 Bluebeam Software, Inc. All Rights Reserved.
+ * Creator: Jonathan Rothberg
+ */
+
+use std::io::{Write};
+use generator::generator::{Generate};
+use generator::spec::Spec;
+use generator::output::GeneratedOutput;
+use generator::cpp_marshaler::CppMarshaler;
+use generator::wasm_marshaler::WasmMarshaler;
+use generator::resolver::{program_uses_optional, program_uses_future};
+use parser::program::Program;
+use parser::ast::{Statement, StatementKind, DataTypeStatement, FunctionModifier, Identifier, InterfaceType, BlockStatement, Parameter};
+
+/// Exposes the generated C++ classes to JavaScript through Emscripten's embind, written as a
+/// single registration source plus the TypeScript declarations of the module.  Records become
+/// `value_object`s, enums `enum_`s and interfaces `class_`es held by `std::shared_ptr`.  Types
+/// embind doesn't know (containers, binaries, dates and optionals) are converted to JavaScript
+/// values by the `Marshal<T>` templates written at the top of the source, and futures settle a
+/// `Promise`.
+pub struct WasmGenerator {
+
+}
+
+type Method<'p> = (&'p FunctionModifier, &'p Identifier, &'p Vec<Parameter>, &'p DataTypeStatement);
+
+impl WasmGenerator {
+    pub fn new() -> WasmGenerator {
+        WasmGenerator {
+
+        }
+    }
+
+    fn write_header(&self, w: &mut Write) {
+        writeln!(w, "// AUTOGENERATED FILE - DO NOT MODIFY!");
+        writeln!(w, "// This file was generated by rusty_lamp");
+    }
+
+    fn methods<'p>(&self, bs: &'p BlockStatement) -> Vec<Method<'p>> {
+        bs.statements.iter()
+            .filter_map(|s| match s.stmtKind {
+                StatementKind::Function(_, ref m, ref fi, ref params, ref ret, _) => Some((m, fi, params, ret)),
+                _ => None
+            })
+            .collect()
+    }
+
+    fn fields<'b>(&self, bs: &'b BlockStatement) -> Vec<(&'b Identifier, &'b DataTypeStatement)> {
+        bs.statements.iter()
+            .filter_map(|s| match s.stmtKind { StatementKind::RecordMember(_, ref i, ref dts, _) => Some((i, dts)), _ => None })
+            .collect()
+    }
+
+    fn to_js(&self, m: &WasmMarshaler, cpp: &CppMarshaler, dts: &DataTypeStatement, expr: &str) -> String {
+        if m.needs_marshal(dts) {
+            format!("Marshal<{}>::to_js({})", cpp.fq_type_name(dts), expr)
+        }
+        else {
+            expr.into()
+        }
+    }
+
+    fn from_js(&self, m: &WasmMarshaler, cpp: &CppMarshaler, dts: &DataTypeStatement, expr: &str) -> String {
+        if m.needs_marshal(dts) {
+            format!("Marshal<{}>::from_js({})", cpp.fq_type_name(dts), expr)
+        }
+        else {
+            expr.into()
+        }
+    }
+
+    /// `constant(...)` registrations for the literal consts of a type.
+    fn consts(&self, m: &WasmMarshaler, cpp: &CppMarshaler, ty: &Identifier, statements: &Vec<Statement>) -> Vec<String> {
+        let mut result = Vec::new();
+        for s in statements {
+            if let StatementKind::Const(_, ref i, ref dts, ref v) = s.stmtKind {
+                let value = match v.stmtKind {
+                    StatementKind::StringLiteral(_, ref s) => format!("{:?}", s),
+                    StatementKind::Boolean(_, b) => format!("{}", b),
+                    StatementKind::NumberLiteral(_, ref n) => n.clone(),
+                    _ => {
+                        result.push(format!("// const {}: {} is not representable as an embind constant", i.value, dts));
+                        continue;
+                    }
+                };
+                result.push(format!("constant(\"{}\", {}({}));", m.const_name(ty, i), cpp.fq_type_name(dts), value));
+            }
+        }
+        result
+    }
+
+    fn ts_consts(&self, m: &WasmMarshaler, ty: &Identifier, statements: &Vec<Statement>) -> Vec<String> {
+        statements.iter()
+            .filter_map(|s| match s.stmtKind {
+                StatementKind::Const(_, ref i, ref dts, ref v) => match v.stmtKind {
+                    StatementKind::StringLiteral(..) | StatementKind::Boolean(..) | StatementKind::NumberLiteral(..) => {
+                        Some(format!("{}: {};", m.const_name(ty, i), m.ts_type_name(dts)))
+                    },
+                    _ => None
+                },
+                _ => None
+            })
+            .collect()
+    }
+
+    fn ts_params(&self, m: &WasmMarshaler, params: &Vec<Parameter>) -> String {
+        params.iter()
+            .map(|p| format!("{}: {}", m.local_ident(&p.ident), m.ts_type_name(&p.data_type)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn write_marshal_support(&self, w: &mut Write, spec: &Spec, cpp: &CppMarshaler, uses_optional: bool, uses_future: bool) {
+        let binary = cpp.fq_type_name(&DataTypeStatement::Binary);
+        let date = cpp.fq_type_name(&DataTypeStatement::Date);
+
+        writeln!(w, "// Registered types convert themselves, everything else is built from plain JavaScript values.");
+        writeln!(w, "template <typename T>");
+        writeln!(w, "struct Marshal {{");
+        writeln!(w, "    static val to_js(const T & v) {{ return val(v); }}");
+        writeln!(w, "    static T from_js(const val & v) {{ return v.as<T>(); }}");
+        writeln!(w, "}};");
+        writeln!(w, "");
+        writeln!(w, "template <>");
+        writeln!(w, "struct Marshal<{}> {{", binary);
+        writeln!(w, "    static val to_js(const {} & v) {{", binary);
+        writeln!(w, "        return val::global(\"Uint8Array\").new_(typed_memory_view(v.size(), v.data()));");
+        writeln!(w, "    }}");
+        writeln!(w, "    static {} from_js(const val & v) {{", binary);
+        writeln!(w, "        return convertJSArrayToNumberVector<uint8_t>(v);");
+        writeln!(w, "    }}");
+        writeln!(w, "}};");
+        writeln!(w, "");
+        writeln!(w, "template <>");
+        writeln!(w, "struct Marshal<{}> {{", date);
+        writeln!(w, "    static val to_js(const {} & v) {{", date);
+        writeln!(w, "        return val::global(\"Date\").new_(static_cast<double>(std::chrono::duration_cast<std::chrono::milliseconds>(v.time_since_epoch()).count()));");
+        writeln!(w, "    }}");
+        writeln!(w, "    static {} from_js(const val & v) {{", date);
+        writeln!(w, "        return {}(std::chrono::milliseconds(static_cast<int64_t>(v.call<double>(\"getTime\"))));", date);
+        writeln!(w, "    }}");
+        writeln!(w, "}};");
+        writeln!(w, "");
+        writeln!(w, "template <typename T>");
+        writeln!(w, "struct Marshal<std::vector<T>> {{");
+        writeln!(w, "    static val to_js(const std::vector<T> & v) {{");
+        writeln!(w, "        val r = val::array();");
+        writeln!(w, "        for (const auto & e : v) {{");
+        writeln!(w, "            r.call<void>(\"push\", Marshal<T>::to_js(e));");
+        writeln!(w, "        }}");
+        writeln!(w, "        return r;");
+        writeln!(w, "    }}");
+        writeln!(w, "    static std::vector<T> from_js(const val & v) {{");
+        writeln!(w, "        std::vector<T> r;");
+        writeln!(w, "        const auto length = v[\"length\"].as<unsigned>();");
+        writeln!(w, "        r.reserve(length);");
+        writeln!(w, "        for (unsigned i = 0; i < length; ++i) {{");
+        writeln!(w, "            r.push_back(Marshal<T>::from_js(v[i]));");
+        writeln!(w, "        }}");
+        writeln!(w, "        return r;");
+        writeln!(w, "    }}");
+        writeln!(w, "}};");
+        writeln!(w, "");
+        writeln!(w, "template <typename T>");
+        writeln!(w, "struct Marshal<std::unordered_set<T>> {{");
+        writeln!(w, "    static val to_js(const std::unordered_set<T> & v) {{");
+        writeln!(w, "        val r = val::global(\"Set\").new_();");
+        writeln!(w, "        for (const auto & e : v) {{");
+        writeln!(w, "            r.call<void>(\"add\", Marshal<T>::to_js(e));");
+        writeln!(w, "        }}");
+        writeln!(w, "        return r;");
+        writeln!(w, "    }}");
+        writeln!(w, "    static std::unordered_set<T> from_js(const val & v) {{");
+        writeln!(w, "        std::unordered_set<T> r;");
+        writeln!(w, "        const val values = val::global(\"Array\").call<val>(\"from\", v);");
+        writeln!(w, "        const auto length = values[\"length\"].as<unsigned>();");
+        writeln!(w, "        for (unsigned i = 0; i < length; ++i) {{");
+        writeln!(w, "            r.insert(Marshal<T>::from_js(values[i]));");
+        writeln!(w, "        }}");
+        writeln!(w, "        return r;");
+        writeln!(w, "    }}");
+        writeln!(w, "}};");
+        writeln!(w, "");
+        writeln!(w, "template <typename K, typename V>");
+        writeln!(w, "struct Marshal<std::unordered_map<K, V>> {{");
+        writeln!(w, "    static val to_js(const std::unordered_map<K, V> & v) {{");
+        writeln!(w, "        val r = val::global(\"Map\").new_();");
+        writeln!(w, "        for (const auto & e : v) {{");
+        writeln!(w, "            r.call<void>(\"set\", Marshal<K>::to_js(e.first), Marshal<V>::to_js(e.second));");
+        writeln!(w, "        }}");
+        writeln!(w, "        return r;");
+        writeln!(w, "    }}");
+        writeln!(w, "    static std::unordered_map<K, V> from_js(const val & v) {{");
+        writeln!(w, "        std::unordered_map<K, V> r;");
+        writeln!(w, "        const val entries = val::global(\"Array\").call<val>(\"from\", v.call<val>(\"entries\"));");
+        writeln!(w, "        const auto length = entries[\"length\"].as<unsigned>();");
+        writeln!(w, "        for (unsigned i = 0; i < length; ++i) {{");
+        writeln!(w, "            const val e = entries[i];");
+        writeln!(w, "            r.emplace(Marshal<K>::from_js(e[0]), Marshal<V>::from_js(e[1]));");
+        writeln!(w, "        }}");
+        writeln!(w, "        return r;");
+        writeln!(w, "    }}");
+        writeln!(w, "}};");
+
+        if uses_optional {
+            let optional = &spec.config.cpp.optional_template;
+            writeln!(w, "");
+            writeln!(w, "template <typename T>");
+            writeln!(w, "struct Marshal<{}<T>> {{", optional);
+            writeln!(w, "    static val to_js(const {}<T> & v) {{", optional);
+            writeln!(w, "        return v ? Marshal<T>::to_js(*v) : val::undefined();");
+            writeln!(w, "    }}");
+            writeln!(w, "    static {}<T> from_js(const val & v) {{", optional);
+            writeln!(w, "        if (v.isUndefined() || v.isNull()) {{");
+            writeln!(w, "            return {{}};");
+            writeln!(w, "        }}");
+            writeln!(w, "        return Marshal<T>::from_js(v);");
+            writeln!(w, "    }}");
+            writeln!(w, "}};");
+        }
+
+        if uses_future {
+            self.write_future_marshal(w, spec);
+        }
+    }
+
+    /// The module runs on the JavaScript thread, which can't block, so an async result is polled
+    /// from the event loop until it is ready and then settles a `Promise`.  Promises can't be
+    /// cancelled, so nothing is propagated back to the future.
+    fn write_future_marshal(&self, w: &mut Write, spec: &Spec) {
+        let future = &spec.config.cpp.future_template;
+        writeln!(w, "");
+        writeln!(w, "template <typename T>");
+        writeln!(w, "struct Marshal<{}<T>> {{", future);
+        writeln!(w, "    struct Pending {{");
+        writeln!(w, "        {}<T> future;", future);
+        writeln!(w, "        val resolvers;");
+        writeln!(w, "    }};");
+        writeln!(w, "");
+        writeln!(w, "    static val to_js({}<T> v) {{", future);
+        writeln!(w, "        auto pending = new Pending{{std::move(v), val::global(\"Promise\").call<val>(\"withResolvers\")}};");
+        writeln!(w, "        val promise = pending->resolvers[\"promise\"];");
+        writeln!(w, "        poll(pending);");
+        writeln!(w, "        return promise;");
+        writeln!(w, "    }}");
+        writeln!(w, "");
+        writeln!(w, "    static void poll(void * arg) {{");
+        writeln!(w, "        auto pending = static_cast<Pending *>(arg);");
+        writeln!(w, "        if (pending->future.wait_for(std::chrono::seconds(0)) == std::future_status::timeout) {{");
+        writeln!(w, "            emscripten_async_call(&Marshal::poll, arg, 1);");
+        writeln!(w, "            return;");
+        writeln!(w, "        }}");
+        writeln!(w, "        std::unique_ptr<Pending> settled(pending);");
+        writeln!(w, "        try {{");
+        writeln!(w, "            settled->resolvers.call<void>(\"resolve\", Marshal<T>::to_js(settled->future.get()));");
+        writeln!(w, "        }} catch (const std::exception & e) {{");
+        writeln!(w, "            settled->resolvers.call<void>(\"reject\", val::global(\"Error\").new_(std::string(e.what())));");
+        writeln!(w, "        }} catch (...) {{");
+        writeln!(w, "            settled->resolvers.call<void>(\"reject\", val::global(\"Error\").new_(std::string(\"unknown error\")));");
+        writeln!(w, "        }}");
+        writeln!(w, "    }}");
+        writeln!(w, "}};");
+    }
+
+    /// Forwards every virtual method of a `+w` interface to the JavaScript object implementing it.
+    fn write_wrapper(&self, w: &mut Write, m: &WasmMarshaler, cpp: &CppMarshaler, i: &Identifier, methods: &Vec<Method>) {
+        let wrapper = m.wrapper_ident(i);
+        writeln!(w, "");
+        writeln!(w, "struct {} : public wrapper<{}> {{", wrapper, cpp.fq_name(&i.value));
+        writeln!(w, "    EMSCRIPTEN_WRAPPER({});", wrapper);
+        for &(modifier, fi, params, ret) in methods {
+            if *modifier == FunctionModifier::Static {
+                continue;
+            }
+            let decl = params.iter()
+                .map(|p| format!("{} {}", cpp.fq_param_type(&p.data_type), p.ident.value))
+                .collect::<Vec<_>>();
+            let mut args = vec![format!("\"{}\"", m.method_ident(fi))];
+            args.extend(params.iter().map(|p| self.to_js(m, cpp, &p.data_type, &p.ident.value)));
+
+            writeln!(w, "");
+            writeln!(w, "    {} {}({}) override {{", cpp.fq_type_name(ret), fi.value, decl.join(", "));
+            match *ret {
+                DataTypeStatement::None => writeln!(w, "        call<void>({});", args.join(", ")),
+                _ if m.needs_marshal(ret) => writeln!(w, "        return {};", self.from_js(m, cpp, ret, &format!("call<val>({})", args.join(", ")))),
+                _ => writeln!(w, "        return call<{}>({});", cpp.fq_type_name(ret), args.join(", "))
+            };
+            writeln!(w, "    }}");
+        }
+        writeln!(w, "}};");
+    }
+
+    /// `.function(...)` or `.class_function(...)`, through a lambda when arguments need marshaling.
+    fn method_binding(&self, m: &WasmMarshaler, cpp: &CppMarshaler, i: &Identifier, method: &Method) -> String {
+        let (modifier, fi, params, ret) = *method;
+        let class = cpp.fq_name(&i.value);
+        let register = if *modifier == FunctionModifier::Static { "class_function" } else { "function" };
+        if !m.needs_marshal(ret) && !params.iter().any(|p| m.needs_marshal(&p.data_type)) {
+            return format!(".{}(\"{}\", &{}::{})", register, m.method_ident(fi), class, fi.value);
+        }
+
+        let mut decl = Vec::new();
+        let target = if *modifier == FunctionModifier::Static {
+            format!("{}::", class)
+        }
+        else {
+            decl.push(format!("{} & self", class));
+            "self.".to_string()
+        };
+        for p in params {
+            let ty = if m.needs_marshal(&p.data_type) { "val".to_string() } else { cpp.fq_param_type(&p.data_type) };
+            decl.push(format!("{} {}", ty, p.ident.value));
+        }
+        let args = params.iter()
+            .map(|p| self.from_js(m, cpp, &p.data_type, &p.ident.value))
+            .collect::<Vec<_>>();
+        let call = format!("{}{}({})", target, fi.value, args.join(", "));
+        let body = match *ret {
+            DataTypeStatement::None => format!("{};", call),
+            _ => format!("return {};", self.to_js(m, cpp, ret, &call))
+        };
+        format!(".{}(\"{}\", optional_override([]({}) {{ {} }}))", register, m.method_ident(fi), decl.join(", "), body)
+    }
+
+    fn write_chain(&self, w: &mut Write, head: String, links: Vec<String>) {
+        writeln!(w, "");
+        if links.len() == 0 {
+            writeln!(w, "    {};", head);
+            return;
+        }
+        writeln!(w, "    {}", head);
+        for (n, l) in links.iter().enumerate() {
+            writeln!(w, "        {}{}", l, if n + 1 == links.len() { ";" } else { "" });
+        }
+    }
+
+    fn write_source(&self, w: &mut Write, spec: &Spec, program: &Program) {
+        let m = WasmMarshaler::new(spec);
+        let cpp = CppMarshaler::new(spec);
+        let uses_optional = program_uses_optional(program);
+        let uses_future = program_uses_future(program);
+
+        self.write_header(w);
+        for s in &program.statements {
+            match s.stmtKind {
+                StatementKind::Enum(_, ref i, _) | StatementKind::Record(_, ref i, _, _) | StatementKind::Interface(_, ref i, _, _, _) => {
+                    writeln!(w, "#include {}", cpp.header_include(&i.value));
+                },
+                _ => {}
+            }
+        }
+        let mut includes = vec!["<chrono>", "<cstdint>", "<memory>", "<string>", "<unordered_map>", "<unordered_set>", "<vector>",
+                                "<emscripten/bind.h>", "<emscripten/val.h>"];
+        if uses_optional {
+            includes.push(spec.config.cpp.optional_header.as_str());
+        }
+        if uses_future {
+            includes.push(spec.config.cpp.future_header.as_str());
+            includes.push("<emscripten.h>");
+        }
+        for include in includes {
+            writeln!(w, "#include {}", include);
+        }
+        writeln!(w, "");
+        writeln!(w, "using namespace emscripten;");
+        writeln!(w, "");
+        writeln!(w, "namespace {{");
+        writeln!(w, "");
+        self.write_marshal_support(w, spec, &cpp, uses_optional, uses_future);
+        for s in &program.statements {
+            if let StatementKind::Interface(_, ref i, ref it, ref bs, _) = s.stmtKind {
+                if it.contains(&InterfaceType::Wasm) {
+                    self.write_wrapper(w, &m, &cpp, i, &self.methods(bs));
+                }
+            }
+        }
+        writeln!(w, "");
+        writeln!(w, "}} // namespace");
+        writeln!(w, "");
+
+        writeln!(w, "EMSCRIPTEN_BINDINGS({}) {{", spec.config.wasm.module);
+        let mut consts = Vec::new();
+        for s in &program.statements {
+            match s.stmtKind {
+                StatementKind::Enum(_, ref i, ref bs) => {
+                    let values = bs.statements.iter()
+                        .filter_map(|o| match o.stmtKind {
+                            StatementKind::EnumMember(_, ref oi) => {
+                                Some(format!(".value(\"{}\", {}::{})", m.enum_member(oi), cpp.fq_name(&i.value), oi.value))
+                            },
+                            _ => None
+                        })
+                        .collect();
+                    self.write_chain(w, format!("enum_<{}>(\"{}\")", cpp.fq_name(&i.value), m.type_ident(i)), values);
+                },
+                StatementKind::Record(_, ref i, ref bs, _) => {
+                    let class = cpp.fq_name(&i.value);
+                    let fields = self.fields(bs).iter()
+                        .map(|&(fi, dts)| {
+                            if m.needs_marshal(dts) {
+                                format!(".field(\"{}\", +[](const {} & r) {{ return {}; }}, +[]({} & r, val v) {{ r.{} = {}; }})",
+                                        m.field_ident(fi), class, self.to_js(&m, &cpp, dts, &format!("r.{}", fi.value)),
+                                        class, fi.value, self.from_js(&m, &cpp, dts, "v"))
+                            }
+                            else {
+                                format!(".field(\"{}\", &{}::{})", m.field_ident(fi), class, fi.value)
+                            }
+                        })
+                        .collect();
+                    self.write_chain(w, format!("value_object<{}>(\"{}\")", class, m.type_ident(i)), fields);
+                    consts.extend(self.consts(&m, &cpp, i, &bs.statements));
+                },
+                StatementKind::Interface(_, ref i, ref it, ref bs, _) => {
+                    let class = cpp.fq_name(&i.value);
+                    let mut links = vec![format!(".smart_ptr<std::shared_ptr<{}>>(\"{}Ptr\")", class, m.type_ident(i))];
+                    if it.contains(&InterfaceType::Wasm) {
+                        let wrapper = m.wrapper_ident(i);
+                        links.push(format!(".allow_subclass<{}, std::shared_ptr<{}>>(\"{}\", \"{}Ptr\")", wrapper, wrapper, wrapper, wrapper));
+                    }
+                    if it.contains(&InterfaceType::Cpp) {
+                        for method in &self.methods(bs) {
+                            links.push(self.method_binding(&m, &cpp, i, method));
+                        }
+                    }
+                    self.write_chain(w, format!("class_<{}>(\"{}\")", class, m.type_ident(i)), links);
+                    consts.extend(self.consts(&m, &cpp, i, &bs.statements));
+                },
+                _ => {}
+            }
+        }
+        if consts.len() > 0 {
+            writeln!(w, "");
+            for c in consts {
+                writeln!(w, "    {}", c);
+            }
+        }
+        writeln!(w, "}}");
+    }
+
+    fn write_ts_comments(&self, w: &mut Write, statements: &Vec<Statement>) {
+        for s in statements {
+            if let StatementKind::Comment(_, ref c) = s.stmtKind {
+                writeln!(w, "    //{}", c);
+            }
+        }
+    }
+
+    fn write_declarations(&self, w: &mut Write, spec: &Spec, program: &Program) {
+        let m = WasmMarshaler::new(spec);
+        let mut module = Vec::new();
+
+        self.write_header(w);
+        for s in &program.statements {
+            match s.stmtKind {
+                StatementKind::Enum(_, ref i, ref bs) => {
+                    let name = m.type_ident(i);
+                    let members = bs.statements.iter()
+                        .filter_map(|o| match o.stmtKind { StatementKind::EnumMember(_, ref oi) => Some(oi), _ => None })
+                        .collect::<Vec<_>>();
+                    // embind enum values are objects carrying the underlying value.
+                    writeln!(w, "");
+                    writeln!(w, "export interface {}Value<T extends number> {{", name);
+                    writeln!(w, "    value: T;");
+                    writeln!(w, "}}");
+                    let values = (0..members.len()).map(|n| format!("{}Value<{}>", name, n)).collect::<Vec<_>>();
+                    writeln!(w, "export type {} = {};", name, if values.len() > 0 { values.join(" | ") } else { "never".into() });
+                    let entries = members.iter().enumerate()
+                        .map(|(n, oi)| format!("{}: {}Value<{}>", m.enum_member(oi), name, n))
+                        .collect::<Vec<_>>();
+                    module.push(format!("{}: {{ {} }};", name, entries.join(", ")));
+                },
+                StatementKind::Record(_, ref i, ref bs, _) => {
+                    writeln!(w, "");
+                    writeln!(w, "export type {} = {{", m.type_ident(i));
+                    self.write_ts_comments(w, &bs.statements);
+                    for (fi, dts) in self.fields(bs) {
+                        writeln!(w, "    {}: {};", m.field_ident(fi), m.ts_type_name(dts));
+                    }
+                    writeln!(w, "}};");
+                    module.extend(self.ts_consts(&m, i, &bs.statements));
+                },
+                StatementKind::Interface(_, ref i, ref it, ref bs, _) => {
+                    let name = m.type_ident(i);
+                    let methods = self.methods(bs);
+                    let wasm = it.contains(&InterfaceType::Wasm);
+                    let cpp = it.contains(&InterfaceType::Cpp);
+                    let signature = |fi: &Identifier, params: &Vec<Parameter>, ret: &DataTypeStatement| {
+                        format!("{}({}): {};", m.method_ident(fi), self.ts_params(&m, params), m.ts_type_name(ret))
+                    };
+
+                    if wasm {
+                        writeln!(w, "");
+                        writeln!(w, "export interface {}Impl {{", name);
+                        self.write_ts_comments(w, &bs.statements);
+                        for &(modifier, fi, params, ret) in &methods {
+                            if *modifier != FunctionModifier::Static {
+                                writeln!(w, "    {}", signature(fi, params, ret));
+                            }
+                        }
+                        writeln!(w, "}}");
+                    }
+
+                    writeln!(w, "");
+                    writeln!(w, "export interface {} {{", name);
+                    if cpp {
+                        if !wasm {
+                            self.write_ts_comments(w, &bs.statements);
+                        }
+                        for &(modifier, fi, params, ret) in &methods {
+                            if *modifier != FunctionModifier::Static {
+                                writeln!(w, "    {}", signature(fi, params, ret));
+                            }
+                        }
+                    }
+                    writeln!(w, "    delete(): void;");
+                    writeln!(w, "}}");
+
+                    let mut statics = Vec::new();
+                    if cpp {
+                        for &(modifier, fi, params, ret) in &methods {
+                            if *modifier == FunctionModifier::Static {
+                                statics.push(signature(fi, params, ret));
+                            }
+                        }
+                    }
+                    if wasm {
+                        statics.push(format!("implement(impl: {}Impl): {};", name, name));
+                    }
+                    if statics.len() > 0 {
+                        module.push(format!("{}: {{\n        {}\n    }};", name, statics.join("\n        ")));
+                    }
+                    module.extend(self.ts_consts(&m, i, &bs.statements));
+                },
+                _ => {}
+            }
+        }
+
+        writeln!(w, "");
+        writeln!(w, "interface EmbindModule {{");
+        for entry in module {
+            writeln!(w, "    {}", entry);
+        }
+        writeln!(w, "}}");
+        writeln!(w, "");
+        writeln!(w, "export type MainModule = EmbindModule;");
+        writeln!(w, "export default function MainModuleFactory(options?: unknown): Promise<MainModule>;");
+    }
+}
+
+impl Generate for WasmGenerator {
+    // Everything is written by `finish`, embind registers the whole program in one place.
+    fn write_enum(&self, _e: &StatementKind, _spec: &Spec, _output: &mut GeneratedOutput) {
+    }
+
+    fn write_record(&self, _r: &StatementKind, _spec: &Spec, _output: &mut GeneratedOutput) {
+    }
+
+    fn write_interface(&self, _i: &StatementKind, _spec: &Spec, _output: &mut GeneratedOutput) {
+    }
+
+    fn finish(&self, spec: &Spec, program: &Program, output: &mut GeneratedOutput) {
+        let mut w = Vec::new();
+        self.write_source(&mut w, spec, program);
+        output.add_file(self.make_path(spec.config.wasm.out.as_ref().map_or("", |s| s.as_str()), &spec.config.wasm.module, &spec.config.cpp.ext), w);
+
+        let mut w = Vec::new();
+        self.write_declarations(&mut w, spec, program);
+        output.add_file(self.make_path(spec.config.wasm.ts_out().map_or("", |s| s.as_str()), &spec.config.wasm.module, "d.ts"), w);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::Config;
+    use generator::testing;
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.cpp.namespace = "demo".into();
+        config.wasm.out = Some("wasm".into());
+
+        config
+    }
+
+    const INPUT: &'static str = "color = enum { red; dark_blue; }\n\
+                                 key = record { id: i64; tags: list<color>; data: binary; seen: optional<date>; const max_id: i64 = 99; }\n\
+                                 listener = interface +w { on_key(key: key): bool; }\n\
+                                 key_store = interface +c { static create(): key_store; lookup(id: i64, l: listener): key; all(): set<string>; }\n";
+
+    #[test]
+    fn test_write_bindings() {
+        let output = testing::compile(config(), INPUT);
+        let source = &output.get("wasm/lamp.cpp").unwrap().contents;
+
+        assert!(source.contains("#include \"key.hpp\""), "{}", source);
+        assert!(source.contains("enum_<::demo::color>(\"Color\")\n        .value(\"RED\", ::demo::color::red)\n        .value(\"DARK_BLUE\", ::demo::color::dark_blue);"), "{}", source);
+        assert!(source.contains("value_object<::demo::key>(\"Key\")\n        .field(\"id\", &::demo::key::id)\n"), "{}", source);
+        assert!(source.contains(".field(\"tags\", +[](const ::demo::key & r) { return Marshal<std::vector<::demo::color>>::to_js(r.tags); }, \
+                                 +[](::demo::key & r, val v) { r.tags = Marshal<std::vector<::demo::color>>::from_js(v); })"), "{}", source);
+        assert!(source.contains("struct Marshal<std::optional<T>> {"), "{}", source);
+        assert!(source.contains("constant(\"Key_MAX_ID\", int64_t(99));"), "{}", source);
+
+        // Methods embind can bind directly don't need a lambda.
+        assert!(source.contains(".class_function(\"create\", &::demo::key_store::create)"), "{}", source);
+        assert!(source.contains(".function(\"lookup\", &::demo::key_store::lookup)"), "{}", source);
+        assert!(source.contains(".function(\"all\", optional_override([](::demo::key_store & self) { \
+                                 return Marshal<std::unordered_set<std::string>>::to_js(self.all()); }))"), "{}", source);
+
+        assert!(source.contains("struct ListenerWasm : public wrapper<::demo::listener> {"), "{}", source);
+        assert!(source.contains("    bool on_key(const ::demo::key & key) override {\n        return call<bool>(\"onKey\", key);"), "{}", source);
+        assert!(source.contains(".allow_subclass<ListenerWasm, std::shared_ptr<ListenerWasm>>(\"ListenerWasm\", \"ListenerWasmPtr\")"), "{}", source);
+    }
+
+    #[test]
+    fn test_write_declarations() {
+        let output = testing::compile(config(), INPUT);
+        let ts = &output.get("wasm/lamp.d.ts").unwrap().contents;
+
+        assert!(ts.contains("export type Color = ColorValue<0> | ColorValue<1>;"), "{}", ts);
+        assert!(ts.contains("export type Key = {\n    id: bigint;\n    tags: Color[];\n    data: Uint8Array;\n    seen: Date | undefined;\n};"), "{}", ts);
+        assert!(ts.contains("export interface ListenerImpl {\n    onKey(key: Key): boolean;\n}"), "{}", ts);
+        assert!(ts.contains("export interface KeyStore {\n    lookup(id: bigint, l: Listener): Key;\n    all(): Set<string>;\n    delete(): void;\n}"), "{}", ts);
+        assert!(ts.contains("    Color: { RED: ColorValue<0>, DARK_BLUE: ColorValue<1> };\n"), "{}", ts);
+        assert!(ts.contains("    KeyStore: {\n        create(): KeyStore;\n    };\n"), "{}", ts);
+        assert!(ts.contains("    Listener: {\n        implement(impl: ListenerImpl): Listener;\n    };\n"), "{}", ts);
+        assert!(ts.contains("    Key_MAX_ID: bigint;\n"), "{}", ts);
+    }
+
+    #[test]
+    fn test_write_futures() {
+        let output = testing::compile(config(), "key = record { id: i64; }\n\
+                                                 key_store = interface +c { fetch(id: i64): future<key>; }\n");
+        let source = &output.get("wasm/lamp.cpp").unwrap().contents;
+        assert!(source.contains("#include <future>\n#include <emscripten.h>\n"), "{}", source);
+        assert!(source.contains("struct Marshal<std::future<T>> {"), "{}", source);
+        assert!(source.contains("emscripten_async_call(&Marshal::poll, arg, 1);"), "{}", source);
+        assert!(source.contains(".function(\"fetch\", optional_override([](::demo::key_store & self, int64_t id) { \
+                                 return Marshal<std::future<::demo::key>>::to_js(self.fetch(id)); }))"), "{}", source);
+
+        let ts = &output.get("wasm/lamp.d.ts").unwrap().contents;
+        assert!(ts.contains("    fetch(id: bigint): Promise<Key>;\n"), "{}", ts);
+    }
+}
@@ -1,106 +1,49 @@
-#![feature(conservative_impl_trait)]
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate toml;
 
 pub mod parser;
 pub mod generator;
-use parser::lexer::Lexer;
-use parser::parser::Parser;
-use parser::program::Program;
-use generator::resolver::Resolver;
-use generator::generator::{ Generator, Generate};
-use generator::cpp_generator::{CppGenerator};
-use generator::java_generator::{JavaGenerator};
-use generator::jni_generator::{JniGenerator};
-use generator::objc_generator::{ObjcGenerator};
-use generator::spec::Spec;
+pub mod compiler;
+pub mod config;
 use parser::djinni_fmt::LampFmt;
 
-use std::io::{ Read, Write };
-use std::fs;
-use std::fs::File;
-use std::env;
+use std::io::{ Write };
 
+pub use compiler::{Compiler, CompilerOptions, Diagnostic, Severity};
+pub use config::{Config, ConfigError};
+pub use generator::output::{GeneratedOutput, GeneratedFile};
 
-
-pub fn process(input: String, output: &mut Write) -> String {
-    // let lexer = Lexer::new(input);
-    // let mut parser = Parser::new(lexer);
-    // let program = parser.parse_program().unwrap_or_default();
-
+/// Formats `input`, read from the file `name`, into `output`.  Nothing is written if it doesn't parse.
+pub fn process(name: &str, input: String, output: &mut Write) -> Result<(), Vec<Diagnostic>> {
     let mut fmt = LampFmt::new(input.clone(), output);
-    fmt.fmt();
-
-    return String::new();
+    fmt.fmt().map_err(|errors| errors.iter().map(|e| Diagnostic::from_parse_error(name, e)).collect())
 }
 
-pub fn compile(main_file: String, spec: &Spec) {
-    println!("Parsing...");
-    let program = parse_file(main_file);
-
-    // println!("Statement Count: {}", program.statements.len());
-    // for s in program.statements {
-    //     println!("{}", s.stmtKind);
-    // }
-
-    println!("Resolving...");
-    let mut resolver = Resolver::new(spec.typer.clone());
-    let result = resolver.resolve(&program);
-    match result {
-        Err(err) => {
-            println!("Error: {:?}", err);
-        },
-        Ok(typer) => {
-            println!("Generating...");
-            // let spec = Spec::new("generated-src".into(), "cpp".into(), typer);
-            setup_directories(spec);
-            let mut cpp_generator = Generator::new(CppGenerator::new());
-            cpp_generator.generate(spec, &program);
-
-            let mut java_generator = Generator::new(JavaGenerator::new());
-            java_generator.generate(spec, &program);
-
-            let mut jni_generator = Generator::new(JniGenerator::new());
-            jni_generator.generate(spec, &program);
-
-            let mut objc_generator = Generator::new(ObjcGenerator::new());
-            objc_generator.generate(spec, &program);
-            // generator.generate::<JavaGenerator>(&program);
-            // generator.generate::<JniGenerator>(&program);
-            // generator.generate::<ObjcGenerator>(&program);
-        }
-    }
-
-}
-
-fn setup_directories(spec: &Spec) {
-    fs::remove_dir_all("generated-src").unwrap_or_default();
-    // fs::create_dir_all(format!("{}/{}", spec.root, spec.cpp_root)).unwrap_or_default();
-    fs::create_dir_all("generated-src/java").unwrap_or_default();
-    fs::create_dir_all("generated-src/objc").unwrap_or_default();
+#[test]
+fn it_works() {
 }
 
-
-fn parse_file(file: String) -> Program {
-    let p = env::current_dir().unwrap();
-    let full_path = format!("{}/{}", p.display(), file);
-    // println!("Path: {}", full_path);
-    let mut file = File::open(full_path).unwrap();
-    let mut contents = String::new();
-    file.read_to_string(&mut contents).unwrap();
-
-    let lexer = Lexer::new(contents);
-    let mut parser = Parser::new(lexer);
-    let program = parser.parse_program();
-
-    match program {
-        Some(p) => {
-            return p;
-        },
-        None => {
-            panic!("Error parsing Djinni file...");
+#[test]
+fn test_process_reports_parse_errors() {
+    let mut output = Vec::new();
+    process("test.djinni", "point = record { x: i32; }\n".into(), &mut output).expect("format failed");
+    assert!(String::from_utf8(output).unwrap() == "point = record {\n    x: i32;\n}\n\n");
+
+    let inputs = vec![
+        ("point = record { @foo(bar) x: i32; }", "expected a string value for @foo, got bar instead."),
+        ("point = record { x: i32; }\n@ s = record { y: i32; }", "expected a declaration after @s, got = instead.")
+    ];
+    for (input, expected) in inputs {
+        let mut output = Vec::new();
+        match process("test.djinni", input.into(), &mut output) {
+            Err(diagnostics) => {
+                assert!(diagnostics[0].message == expected, "{}", diagnostics[0].message);
+                assert!(diagnostics[0].file == Some("test.djinni".into()), "{:?}", diagnostics[0].file);
+            },
+            Ok(_) => assert!(false, "expected formatting {:?} to fail", input)
         }
+        assert!(output.len() == 0, "wrote {:?}", String::from_utf8(output));
     }
 }
-
-#[test]
-fn it_works() {
-}
@@ -12,6 +12,7 @@ use std::sync::Arc;
 #[derive(PartialEq, Eq, Clone, Debug, Hash)]
 pub struct Statement {
     pub stmtKind: StatementKind,
+    pub annotations: Vec<Annotation>
     // pub resolved: TypeDefinitionKind
 }
 
@@ -19,6 +20,7 @@ impl Default for Statement {
     fn default() -> Statement {
         Statement {
             stmtKind: StatementKind::Noop,
+            annotations: Vec::new()
             // resolved: TypeDefinitionKind::None
         }
     }
@@ -28,10 +30,15 @@ impl Statement {
     pub fn new() -> Statement {
         Statement {
             stmtKind: StatementKind::Noop,
+            annotations: Vec::new()
             // resolved: TypeDefinitionKind::None
         }
     }
 
+    pub fn annotation(&self, name: &str) -> Option<&Annotation> {
+        find_annotation(&self.annotations, name)
+    }
+
     // pub fn new(stmt_kind: StatementKind) -> Statement {
     //     Statement {
     //         stmtKind: stmt_kind,
@@ -44,11 +51,13 @@ impl Statement {
 pub enum StatementKind {
     Noop,
     Import(Token, String),
+    Module(Token, String),
     Interface(Token, Identifier, Vec<InterfaceType>, BlockStatement, Vec<DeriveType>),
     Record(Token, Identifier, BlockStatement, Vec<DeriveType>),
     Enum(Token, Identifier, BlockStatement),
+    Alias(Token, Identifier, DataTypeStatement),
     EnumMember(Token, Identifier),
-    RecordMember(Token, Identifier, DataTypeStatement),
+    RecordMember(Token, Identifier, DataTypeStatement, Option<Arc<Statement>>),
     Comment(Token, String),
     StringLiteral(Token, String),
     NumberLiteral(Token, String),
@@ -57,7 +66,7 @@ pub enum StatementKind {
     Const(Token, Identifier, DataTypeStatement, Arc<Statement>),
     Definition(Identifier, Arc<Statement>),
     Ident(Token, String),
-    Function(Token, FunctionModifier, Identifier, Vec<Parameter>, DataTypeStatement)
+    Function(Token, FunctionModifier, Identifier, Vec<Parameter>, DataTypeStatement, Option<Identifier>)
 }
 
 impl fmt::Display for StatementKind {
@@ -103,13 +112,22 @@ impl fmt::Display for StatementKind {
 
                 result
             },
+            StatementKind::Module(ref t, ref m) => {
+                format!("module {};", m)
+            },
+            StatementKind::Alias(ref t, ref i, ref d) => {
+                format!("{} = alias {};", i, d)
+            },
             StatementKind::EnumMember(ref t, ref i) => {
                 format!("{};", i)
             },
-            StatementKind::RecordMember(ref t, ref i, ref d) => {
-                format!("{}: {};", i, d)
+            StatementKind::RecordMember(ref t, ref i, ref d, ref v) => {
+                match *v {
+                    Some(ref v) => format!("{}: {} = {};", i, d, v.stmtKind),
+                    None => format!("{}: {};", i, d)
+                }
             },
-            StatementKind::Function(ref t, ref m, ref i, ref p, ref r) => {
+            StatementKind::Function(ref t, ref m, ref i, ref p, ref r, ref e) => {
                 let mut result = String::new();
 
                 result.push_str(format!("{} {}(", m, i).as_str());
@@ -120,7 +138,11 @@ impl fmt::Display for StatementKind {
                 }
 
                 result.push_str(parameters.join(", ").as_str());
-                result.push_str(format!("): {};", r).as_str());
+                result.push_str(format!("): {}", r).as_str());
+                if let Some(ref e) = *e {
+                    result.push_str(format!(" throws {}", e).as_str());
+                }
+                result.push_str(";");
 
                 result
             },
@@ -161,7 +183,14 @@ impl fmt::Display for StatementKind {
 #[derive(PartialEq, Eq, Clone, Debug, Hash)]
 pub struct Parameter {
     pub ident: Identifier,
-    pub data_type: DataTypeStatement
+    pub data_type: DataTypeStatement,
+    pub annotations: Vec<Annotation>
+}
+
+impl Parameter {
+    pub fn annotation(&self, name: &str) -> Option<&Annotation> {
+        find_annotation(&self.annotations, name)
+    }
 }
 
 impl fmt::Display for Parameter {
@@ -171,11 +200,35 @@ impl fmt::Display for Parameter {
     }
 }
 
+/// `@name` or `@name("value")` in front of a declaration, a member or a parameter.  The
+/// generators act on the ones they know and ignore the rest.
+#[derive(PartialEq, Eq, Clone, Debug, Hash)]
+pub struct Annotation {
+    pub ident: Identifier,
+    pub value: Option<String>
+}
+
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.value {
+            Some(ref v) => write!(f, "@{}(\"{}\")", self.ident.value, v),
+            None => write!(f, "@{}", self.ident.value)
+        }
+    }
+}
+
+pub fn find_annotation<'a>(annotations: &'a Vec<Annotation>, name: &str) -> Option<&'a Annotation> {
+    annotations.iter().find(|a| a.ident.value == name)
+}
+
 #[derive(PartialEq, Eq, Clone, Debug, Hash)]
 pub enum DeriveType {
     None,
     Ord,
-    Eq
+    Eq,
+    Hash,
+    Show,
+    Json
 }
 
 impl fmt::Display for DeriveType {
@@ -183,7 +236,10 @@ impl fmt::Display for DeriveType {
         let printable = match *self {
             DeriveType::None => "",
             DeriveType::Ord => "ord",
-            DeriveType::Eq => "eq"
+            DeriveType::Eq => "eq",
+            DeriveType::Hash => "hash",
+            DeriveType::Show => "show",
+            DeriveType::Json => "json"
         };
 
         write!(f, "{}", printable)
@@ -211,7 +267,9 @@ impl fmt::Display for FunctionModifier {
 pub enum InterfaceType {
     Java,
     ObjectiveC,
-    Cpp
+    Cpp,
+    Rust,
+    Wasm
 }
 
 impl fmt::Display for InterfaceType {
@@ -219,7 +277,9 @@ impl fmt::Display for InterfaceType {
         let printable = match *self {
             InterfaceType::Java => "+j",
             InterfaceType::ObjectiveC => "+o",
-            InterfaceType::Cpp => "+c"
+            InterfaceType::Cpp => "+c",
+            InterfaceType::Rust => "+r",
+            InterfaceType::Wasm => "+w"
         };
 
         write!(f, "{}", printable)
@@ -251,7 +311,11 @@ pub enum DataTypeStatement {
     Bool,
     Set(Arc<DataTypeStatement>),
     List(Arc<DataTypeStatement>),
+    Array(Arc<DataTypeStatement>),
     Map(Arc<DataTypeStatement>, Arc<DataTypeStatement>),
+    Optional(Arc<DataTypeStatement>),
+    Future(Arc<DataTypeStatement>),
+    Function(Vec<DataTypeStatement>, Arc<DataTypeStatement>),
     Object(Identifier)
 }
 
@@ -287,7 +351,11 @@ impl DataTypeStatement {
             DataTypeStatement::Bool => "bool".into(),
             DataTypeStatement::Set(..) => "set".into(),
             DataTypeStatement::List(..) => "list".into(),
+            DataTypeStatement::Array(..) => "array".into(),
             DataTypeStatement::Map(..) => "map".into(),
+            DataTypeStatement::Optional(..) => "optional".into(),
+            DataTypeStatement::Future(..) => "future".into(),
+            DataTypeStatement::Function(..) => "fn".into(),
             DataTypeStatement::Object(ref i) => format!("{}", i.value)
         }
     }
@@ -313,9 +381,25 @@ impl fmt::Display for DataTypeStatement {
             DataTypeStatement::List(ref dt) => {
                 format!("list<{}>", dt)
             },
+            DataTypeStatement::Array(ref dt) => {
+                format!("array<{}>", dt)
+            },
             DataTypeStatement::Map(ref k, ref v) => {
                 format!("map<{}, {}>", k, v)
             },
+            DataTypeStatement::Optional(ref dt) => {
+                format!("optional<{}>", dt)
+            },
+            DataTypeStatement::Future(ref dt) => {
+                format!("future<{}>", dt)
+            },
+            DataTypeStatement::Function(ref params, ref ret) => {
+                let params: Vec<String> = params.iter().map(|p| format!("{}", p)).collect();
+                match **ret {
+                    DataTypeStatement::None => format!("fn({})", params.join(", ")),
+                    _ => format!("fn({}) -> {}", params.join(", "), ret)
+                }
+            },
             DataTypeStatement::Object(ref i) => {
                 format!("{}", i)
             }
@@ -5,7 +5,7 @@
 
 use std::io::Write;
 
-use parser::parser::{ Parser, ParserOptions };
+use parser::parser::{ Parser, ParserOptions, ParseError };
 use parser::lexer::Lexer;
 use parser::ast::{Statement, StatementKind, BlockStatement,
                   FunctionModifier, DeriveType, DataTypeStatement };
@@ -23,21 +23,33 @@ impl<'a> LampFmt<'a> {
         }
     }
 
-    pub fn fmt(&'a mut self) {
+    /// Prints the formatted input, or nothing if it doesn't parse, since whatever the parser
+    /// skipped over would be missing from the output.
+    pub fn fmt(&'a mut self) -> Result<(), Vec<ParseError>> {
         let lexer = Lexer::new(self.input.clone());
         let parser_options = ParserOptions {
-            process_imports: false
+            process_imports: false,
+            ..ParserOptions::default()
         };
         let mut parser = Parser::new_with_options(lexer, parser_options);
         let program = parser.parse_program().unwrap_or_default();
+        if parser.errors().len() > 0 {
+            return Err(parser.errors().clone());
+        }
 
         let indent = 0;
         for stmt in program.statements {
             self.print_statement(&stmt, indent);
         }
+
+        Ok(())
     }
 
     fn print_statement(&mut self, stmt: &Statement, indent: i32) {
+        for a in &stmt.annotations {
+            self.print_spaces(indent);
+            writeln!(self.output, "{}", a);
+        }
         self.print_statement_kind(&stmt.stmtKind, indent);
     }
 
@@ -67,6 +79,14 @@ impl<'a> LampFmt<'a> {
             StatementKind::Function(..) => {
                 self.print_function_statement(stmt_kind, indent);
             },
+            StatementKind::Alias(..) => {
+                self.print_alias_statement(stmt_kind);
+                self.print_newline();
+            },
+            StatementKind::Module(..) => {
+                self.print_module_statement(stmt_kind);
+                self.print_newline();
+            },
             StatementKind::Import(..) => {
                 self.print_import_statement(stmt_kind);
                 self.print_newline();
@@ -99,6 +119,12 @@ impl<'a> LampFmt<'a> {
         }
     }
 
+    fn print_alias_statement(&mut self, stmt_kind: &StatementKind) {
+        if let StatementKind::Alias(_, ref i, ref dt) = *stmt_kind {
+            writeln!(self.output, "{} = alias {};", i.value, dt);
+        }
+    }
+
     fn print_enum_member(&mut self, stmt_kind: &StatementKind, indent: i32) {
         if let StatementKind::EnumMember(_, ref i) = *stmt_kind {
             self.print_spaces(indent);
@@ -118,10 +144,13 @@ impl<'a> LampFmt<'a> {
     }
 
     fn print_record_member(&mut self, stmt_kind: &StatementKind, indent: i32) {
-        if let StatementKind::RecordMember(_, ref i, ref dt) = *stmt_kind {
+        if let StatementKind::RecordMember(_, ref i, ref dt, ref default) = *stmt_kind {
             self.print_spaces(indent);
 
-            writeln!(self.output, "{}: {};", i.value, dt);
+            match *default {
+                Some(ref v) => writeln!(self.output, "{}: {} = {};", i.value, dt, v.stmtKind),
+                None => writeln!(self.output, "{}: {};", i.value, dt)
+            };
         }
     }
 
@@ -160,7 +189,7 @@ impl<'a> LampFmt<'a> {
     }
 
     fn print_function_statement(&mut self, stmt_kind: &StatementKind, indent: i32) {
-        if let StatementKind::Function(_, ref fm, ref i, ref p, ref dt) = *stmt_kind {
+        if let StatementKind::Function(_, ref fm, ref i, ref p, ref dt, ref throws) = *stmt_kind {
             self.print_spaces(indent);
             if *fm != FunctionModifier::None {
                 write!(self.output, "{} ", fm);
@@ -172,6 +201,9 @@ impl<'a> LampFmt<'a> {
                     write!(self.output, ", ");
                 }
 
+                for a in &param.annotations {
+                    write!(self.output, "{} ", a);
+                }
                 write!(self.output, "{}: {}", param.ident, param.data_type);
                 first = false;
             }
@@ -185,6 +217,10 @@ impl<'a> LampFmt<'a> {
                 }
             }
 
+            if let Some(ref e) = *throws {
+                write!(self.output, " throws {}", e.value);
+            }
+
             writeln!(self.output, ";");
         }
     }
@@ -202,6 +238,12 @@ impl<'a> LampFmt<'a> {
         }
     }
 
+    fn print_module_statement(&mut self, stmt_kind: &StatementKind) {
+        if let StatementKind::Module(_, ref m) = *stmt_kind {
+            writeln!(self.output, "module {};", m);
+        }
+    }
+
     fn print_const_statement(&mut self, stmt_kind: &StatementKind, mut indent: i32) {
         if let StatementKind::Const(_, ref i, ref dt, ref v) = *stmt_kind {
             self.print_spaces(indent);
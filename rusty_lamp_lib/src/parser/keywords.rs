@@ -17,8 +17,11 @@ impl Keywords {
             "enum" => Token::Enum,
             "record" => Token::Record,
             "interface" => Token::Interface,
+            "alias" => Token::Alias,
+            "module" => Token::Module,
             "import" => Token::Import,
             "static" => Token::Static,
+            "throws" => Token::Throws,
             "const" => Token::Const,
             "deriving" => Token::Dervive,
             "true" => Token::True,
@@ -32,12 +35,15 @@ impl Keywords {
             "f32" => Token::Type(DataType::Float32, "f32".into()),
             "f64" => Token::Type(DataType::Float64, "f64".into()),
             "list" => Token::Type(DataType::List, "list".into()),
+            "array" => Token::Type(DataType::Array, "array".into()),
             "map" => Token::Type(DataType::Map, "map".into()),
             "set" => Token::Type(DataType::Set, "set".into()),
             "date" => Token::Type(DataType::Date, "date".into()),
             "binary" => Token::Type(DataType::Binary, "binary".into()),
             "bool" => Token::Type(DataType::Bool, "bool".into()),
             "optional" => Token::Type(DataType::Optional, "optional".into()),
+            "future" => Token::Type(DataType::Future, "future".into()),
+            "fn" => Token::Type(DataType::Function, "fn".into()),
             "string" => Token::Type(DataType::String, "string".into()),
             _ => Token::Ident(ident.clone())
         }
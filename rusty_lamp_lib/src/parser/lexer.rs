@@ -14,6 +14,9 @@ pub struct Lexer {
     position: usize,
     read_position: usize,
     ch: char,
+    // The 1-based line of `ch`, and of the start of the last token returned.
+    line: usize,
+    token_line: usize,
     keywords: Keywords
 }
 
@@ -24,6 +27,8 @@ impl Lexer {
             position: 0,
             read_position: 0,
             ch: '\0',
+            line: 1,
+            token_line: 1,
             keywords: Keywords::new()
        };
 
@@ -33,6 +38,10 @@ impl Lexer {
     }
 
     fn read_char(&mut self) {
+        if self.ch == '\n' {
+            self.line += 1;
+        }
+
         if self.read_position >= self.input.len() {
             self.ch = '\0';
         }
@@ -53,8 +62,14 @@ impl Lexer {
         }
     }
 
+    /// The line the last token returned by `next_token` starts on.
+    pub fn line(&self) -> usize {
+        self.token_line
+    }
+
     pub fn next_token(&mut self) -> Token {
         self.skip_whitespace();
+        self.token_line = self.line;
         
         let tok = match self.ch {
             '=' => {
@@ -66,6 +81,8 @@ impl Lexer {
                     'j' => Token::JavaInterface,
                     'o' => Token::ObjCInterface,
                     'c' => Token::CppInterface,
+                    'r' => Token::RustInterface,
+                    'w' => Token::WasmInterface,
                     _ => Token::Illegal
                 };
 
@@ -75,6 +92,22 @@ impl Lexer {
 
                 interface_type
             },
+            '-' => {
+                if self.peek_char() == '>' {
+                    self.read_char();
+                    Token::Arrow
+                }
+                else if Lexer::is_digit(self.peek_char()) {
+                    self.read_char();
+                    return match self.read_number() {
+                        Token::Number(n) => Token::Number(format!("-{}", n)),
+                        t => t
+                    };
+                }
+                else {
+                    Token::Illegal
+                }
+            },
             ';' => Token::Semicolon,
             '(' => Token::LParen,
             ')' => Token::RParen,
@@ -159,8 +192,9 @@ impl Lexer {
     fn read_identifier(&mut self) -> Token {
         let pos = self.position;
 
+        // `net.Config` is one qualified name.
         let mut result = String::new();
-        while Lexer::is_alphanumeric(self.ch) {
+        while Lexer::is_alphanumeric(self.ch) || (self.ch == '.' && Lexer::is_letter(self.peek_char())) {
             result.push(self.ch);
             self.read_char();
         }
@@ -231,7 +265,7 @@ mod tests {
     fn test_next_token() {
         let input = r#"@  import  enum (){}<>,; list<i32> map<string, i64>;
                        # this is a test of a comment
-                           string date set<f32> record interface = +c{};"#;
+                           string date set<f32> record interface = +c +r +w{};"#;
 
         let test_cases = vec![
             token_test_case{expected_token: Token::AtSign, expected_literal: "@".into()},
@@ -267,6 +301,8 @@ mod tests {
             token_test_case{expected_token: Token::Interface, expected_literal: "interface".into()},
             token_test_case{expected_token: Token::Equal, expected_literal: "=".into()},
             token_test_case{expected_token: Token::CppInterface, expected_literal: "+c".into()},
+            token_test_case{expected_token: Token::RustInterface, expected_literal: "+r".into()},
+            token_test_case{expected_token: Token::WasmInterface, expected_literal: "+w".into()},
             token_test_case{expected_token: Token::LBrace, expected_literal: "{".into()},
             token_test_case{expected_token: Token::RBrace, expected_literal: "}".into()},
             token_test_case{expected_token: Token::Semicolon, expected_literal: ";".into()},
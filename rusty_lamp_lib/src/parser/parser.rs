@@ -7,21 +7,65 @@ use parser::token::{Token, DataType};
 use parser::lexer::Lexer;
 use parser::ast::{BlockStatement, Statement, StatementKind,
                   Identifier, DataTypeStatement, InterfaceType,
-                  Parameter, FunctionModifier, DeriveType };
+                  Parameter, FunctionModifier, DeriveType, Annotation };
 use parser::program::Program;
 use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::mem;
 use std::default::Default;
-use parse_file;
 
+#[derive(Clone)]
 pub struct ParserOptions {
-    pub process_imports: bool
+    pub process_imports: bool,
+    pub base_dir: Option<PathBuf>,
+    pub include_paths: Vec<PathBuf>
 }
 
 impl Default for ParserOptions {
     fn default() -> ParserOptions {
         return ParserOptions {
-            process_imports: true
+            process_imports: true,
+            base_dir: None,
+            include_paths: Vec::new()
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ParseError {
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    pub message: String
+}
+
+impl ParseError {
+    pub fn new(file: Option<String>, message: String) -> ParseError {
+        ParseError {
+            file: file,
+            line: None,
+            message: message
+        }
+    }
+
+    pub fn at(line: usize, message: String) -> ParseError {
+        ParseError {
+            file: None,
+            line: Some(line),
+            message: message
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.file, self.line) {
+            (&Some(ref file), Some(line)) => write!(f, "{}:{}: {}", file, line, self.message),
+            (&Some(ref file), None) => write!(f, "{}: {}", file, self.message),
+            (&None, Some(line)) => write!(f, "line {}: {}", line, self.message),
+            (&None, None) => write!(f, "{}", self.message)
         }
     }
 }
@@ -30,9 +74,14 @@ pub struct Parser {
     lexer: Lexer,
     cur_token: Token,
     peek_token: Token,
-    errors: Vec<String>,
+    // The lines `cur_token` and `peek_token` start on.
+    cur_line: usize,
+    peek_line: usize,
+    errors: Vec<ParseError>,
     options: ParserOptions,
-    program: Program
+    program: Program,
+    // The `module` this file declared, if any.
+    module: Option<String>
 }
 
 impl Parser {
@@ -41,9 +90,12 @@ impl Parser {
             lexer: lexer,
             cur_token: Token::Illegal,
             peek_token: Token::Illegal,
+            cur_line: 1,
+            peek_line: 1,
             errors: Vec::new(),
             options: ParserOptions::default(),
-            program: Program::new()
+            program: Program::new(),
+            module: None
         };
 
         p.init();
@@ -56,9 +108,12 @@ impl Parser {
             lexer: lexer,
             cur_token: Token::Illegal,
             peek_token: Token::Illegal,
+            cur_line: 1,
+            peek_line: 1,
             errors: Vec::new(),
             options: options,
-            program: Program::new()
+            program: Program::new(),
+            module: None
         };
 
         p.init();
@@ -66,6 +121,10 @@ impl Parser {
         p
     }
 
+    pub fn errors(&self) -> &Vec<ParseError> {
+        &self.errors
+    }
+
     fn init(&mut self) {
         self.next_token();
         self.next_token();
@@ -73,7 +132,9 @@ impl Parser {
 
     fn next_token(&mut self) {
         self.cur_token = self.peek_token.clone();
+        self.cur_line = self.peek_line;
         self.peek_token = self.lexer.next_token();
+        self.peek_line = self.lexer.line();
     }
 
     pub fn parse_program(&mut self) -> Option<Program> {
@@ -107,12 +168,26 @@ impl Parser {
     fn parse_statement(&mut self) -> Option<Statement> {
         let tok = self.cur_token.clone();
         match tok {
-            Token::AtSign => {
+            Token::AtSign if self.peek_token_is(Token::Import) => {
                 return self.parse_import_statement();
             },
+            Token::AtSign => {
+                let annotations = self.parse_annotations();
+                let stmt = self.build_block_statements();
+                if stmt.is_none() {
+                    self.dangling_annotations(&annotations);
+                }
+                return stmt.map(|mut s| {
+                    s.annotations = annotations;
+                    s
+                });
+            },
             Token::Comment(ref s) => {
                 return Some(self.parse_comment_statement());
             },
+            Token::Module => {
+                return self.parse_module_statement();
+            },
             _ => {
                 return self.build_block_statements();
             }
@@ -124,7 +199,8 @@ impl Parser {
         match self.cur_token {
             Token::Comment(ref s) => {
                 return Statement{
-                    stmtKind: StatementKind::Comment(self.cur_token.clone(), s.clone())
+                    stmtKind: StatementKind::Comment(self.cur_token.clone(), s.clone()),
+                    annotations: Vec::new()
                 };
             },
             _ => {
@@ -133,6 +209,31 @@ impl Parser {
         }
     }
 
+    /// `module net;` puts the declarations that follow in the file into `net`.
+    fn parse_module_statement(&mut self) -> Option<Statement> {
+        let tok = self.cur_token.clone();
+        self.next_token();
+
+        let name = match self.cur_token {
+            Token::Ident(ref s) => s.clone(),
+            ref t => {
+                self.errors.push(ParseError::new(None, format!("expected a module name, got {} instead.", t)));
+                return None;
+            }
+        };
+        if let Some(ref m) = self.module {
+            self.errors.push(ParseError::new(None, format!("module {} is already declared for this file.", m)));
+            return None;
+        }
+        self.expect_peek(Token::Semicolon);
+
+        self.module = Some(name.clone());
+        Some(Statement {
+            stmtKind: StatementKind::Module(tok, name),
+            annotations: Vec::new()
+        })
+    }
+
     fn parse_import_statement(&mut self) -> Option<Statement> {
         let tok = self.cur_token.clone();
 
@@ -157,7 +258,8 @@ impl Parser {
                 }
 
                 return Some(Statement {
-                    stmtKind: StatementKind::Import(import_tok, literal)
+                    stmtKind: StatementKind::Import(import_tok, literal),
+                    annotations: Vec::new()
                 })
             }
         }
@@ -166,9 +268,61 @@ impl Parser {
     }
 
     fn process_import(&mut self, import_file: &String) {
-        let program = parse_file(import_file.clone());
+        let path = match self.find_import(import_file) {
+            Some(p) => p,
+            None => {
+                self.errors.push(ParseError::new(None, format!("unable to find import \"{}\"", import_file)));
+                return;
+            }
+        };
+
+        let mut contents = String::new();
+        let read = File::open(&path).and_then(|mut f| f.read_to_string(&mut contents));
+        if let Err(e) = read {
+            self.errors.push(ParseError::new(Some(path.display().to_string()), format!("unable to read import: {}", e)));
+            return;
+        }
+
+        let mut options = self.options.clone();
+        options.base_dir = path.parent().map(|p| p.to_path_buf());
+
+        let mut parser = Parser::new_with_options(Lexer::new(contents), options);
+        let program = parser.parse_program().unwrap_or_default();
+        for e in parser.errors() {
+            let file = e.file.clone().or(Some(path.display().to_string()));
+            self.errors.push(ParseError { file: file, line: e.line, message: e.message.clone() });
+        }
 
+        // Each file's module only covers that file, so the imported statements are bracketed by
+        // `module` statements switching to the top level and back.
+        let nested = program.statements.iter().any(|s| match s.stmtKind { StatementKind::Module(..) => true, _ => false });
+        if self.module.is_some() {
+            self.program.statements.push(Statement { stmtKind: StatementKind::Module(Token::Module, "".into()), annotations: Vec::new() });
+        }
         self.program.statements.append(&mut program.statements.clone());
+        if self.module.is_some() || nested {
+            let module = self.module.clone().unwrap_or_default();
+            self.program.statements.push(Statement { stmtKind: StatementKind::Module(Token::Module, module), annotations: Vec::new() });
+        }
+    }
+
+    fn find_import(&self, import_file: &String) -> Option<PathBuf> {
+        let import_path = Path::new(import_file);
+        if import_path.is_absolute() {
+            return if import_path.is_file() { Some(import_path.to_path_buf()) } else { None };
+        }
+
+        let mut candidates = Vec::new();
+        match self.options.base_dir {
+            Some(ref b) => candidates.push(b.join(import_path)),
+            None => candidates.push(import_path.to_path_buf())
+        }
+
+        for include in &self.options.include_paths {
+            candidates.push(include.join(import_path));
+        }
+
+        candidates.into_iter().find(|c| c.is_file())
     }
 
     fn build_block_statements(&mut self) -> Option<Statement> {
@@ -195,6 +349,9 @@ impl Parser {
                 Token::Interface  => {
                     return Some(self.parse_interface_statement(ident_tok));
                 },
+                Token::Alias => {
+                    return Some(self.parse_alias_statement(ident_tok));
+                },
                 _ => {
                     return Some(Statement::new());
                 }
@@ -203,6 +360,23 @@ impl Parser {
         return None;
     }
 
+    /// `name = alias map<string, list<item>>;`
+    fn parse_alias_statement(&mut self, token: Token) -> Statement {
+        let ident_tok = token;
+
+        self.next_token();
+        let data_type = self.parse_type();
+        self.expect_peek(Token::Semicolon);
+
+        return Statement {
+            stmtKind: StatementKind::Alias(Token::Alias, Identifier {
+                token: ident_tok.clone(),
+                value: ident_tok.to_str()
+            }, data_type),
+            annotations: Vec::new()
+        }
+    }
+
     fn parse_enum_statement(&mut self, token: Token) -> Statement {
         let ident_tok = token;
         // println!("{}", ident_tok.to_str());
@@ -210,7 +384,8 @@ impl Parser {
         self.next_token();
 
         let mut block = BlockStatement {token: Token::Enum, statements: Vec::new() };
-        while !self.cur_token_is(Token::RBrace) {
+        let mut annotations = Vec::new();
+        while !self.cur_token_is(Token::RBrace) && !self.cur_token_is(Token::Eof) {
             let tok = self.cur_token.clone();
             match tok {
                 Token::Ident(ref s) => {
@@ -219,10 +394,15 @@ impl Parser {
                             stmtKind: StatementKind::EnumMember(tok.clone(), Identifier {
                                 token: tok.clone(),
                                 value: s.clone()
-                            })
+                            }),
+                            annotations: mem::replace(&mut annotations, Vec::new())
                         });
                     }
                 },
+                Token::AtSign => {
+                    annotations = self.parse_annotations();
+                    continue;
+                },
                 Token::Comment(_) => {
                     block.statements.push(self.parse_comment_statement());
                 }
@@ -234,11 +414,14 @@ impl Parser {
             self.next_token();
         }
 
+        self.dangling_annotations(&annotations);
+
         return Statement {
             stmtKind: StatementKind::Enum(Token::Enum, Identifier {
                 token: ident_tok.clone(),
                 value: ident_tok.to_str()
-            }, block)
+            }, block),
+            annotations: Vec::new()
         }
     }
 
@@ -249,17 +432,20 @@ impl Parser {
         self.next_token();
 
         let mut block = BlockStatement {token: Token::Record, statements: Vec::new() };
-        while !self.cur_token_is(Token::RBrace) {
+        let mut annotations = Vec::new();
+        while !self.cur_token_is(Token::RBrace) && !self.cur_token_is(Token::Eof) {
             let tok = self.cur_token.clone();
             match tok {
                 Token::Ident(ref s) => {
                     if self.expect_peek(Token::Colon) {
                         self.next_token();
+                        let data_type = self.parse_type();
                         block.statements.push(Statement {
                             stmtKind: StatementKind::RecordMember(tok.clone(), Identifier {
                                 token: tok.clone(),
                                 value: s.clone()
-                            }, self.parse_type())
+                            }, data_type, self.parse_default_value()),
+                            annotations: mem::replace(&mut annotations, Vec::new())
                         });
                     }
                 },
@@ -267,7 +453,13 @@ impl Parser {
                     block.statements.push(self.parse_comment_statement());
                 },
                 Token::Const => {
-                    block.statements.push(self.parse_const_statement());
+                    let mut c = self.parse_const_statement();
+                    c.annotations = mem::replace(&mut annotations, Vec::new());
+                    block.statements.push(c);
+                },
+                Token::AtSign => {
+                    annotations = self.parse_annotations();
+                    continue;
                 },
                 _ => {
                 }
@@ -276,16 +468,30 @@ impl Parser {
             self.next_token();
         }
 
+        self.dangling_annotations(&annotations);
+
         let derived = self.parse_derives();
 
         return Statement {
             stmtKind: StatementKind::Record(Token::Record, Identifier {
                 token: ident_tok.clone(),
                 value: ident_tok.to_str()
-            }, block, derived)
+            }, block, derived),
+            annotations: Vec::new()
         }
     }
 
+    /// The optional `= value` after a record field's type, written like a const value.
+    fn parse_default_value(&mut self) -> Option<Arc<Statement>> {
+        if !self.peek_token_is(Token::Equal) {
+            return None;
+        }
+
+        self.next_token();
+        self.next_token();
+        Some(Arc::new(self.parse_const_value()))
+    }
+
     fn get_ident_string(ident: Token) -> String {
         let ident_name = match ident {
             Token::Ident(ref s) => {
@@ -333,7 +539,8 @@ impl Parser {
             stmtKind: StatementKind::Const(const_tok, Identifier {
                 token: ident,
                 value: ident_name},
-                                           const_type, Arc::new(value))
+                                           const_type, Arc::new(value)),
+            annotations: Vec::new()
         }
     }
 
@@ -343,22 +550,26 @@ impl Parser {
         match tok {
             Token::StringToken(ref s) => {
                 return Statement {
-                    stmtKind: StatementKind::StringLiteral(tok.clone(), s.clone())
+                    stmtKind: StatementKind::StringLiteral(tok.clone(), s.clone()),
+                    annotations: Vec::new()
                 };
             },
             Token::True => {
                 return Statement {
-                    stmtKind: StatementKind::Boolean(tok.clone(), true)
+                    stmtKind: StatementKind::Boolean(tok.clone(), true),
+                    annotations: Vec::new()
                 };
             },
             Token::False => {
                 return Statement {
-                    stmtKind: StatementKind::Boolean(tok.clone(), false)
+                    stmtKind: StatementKind::Boolean(tok.clone(), false),
+                    annotations: Vec::new()
                 };
             },
             Token::Number(ref s) => {
                 return Statement {
-                    stmtKind: StatementKind::NumberLiteral(tok.clone(), s.clone())
+                    stmtKind: StatementKind::NumberLiteral(tok.clone(), s.clone()),
+                    annotations: Vec::new()
                 };
             },
             Token::LBrace => {
@@ -367,7 +578,7 @@ impl Parser {
             _ => {}
         }
 
-        println!("Parsing cont value, should not get here...");
+        self.errors.push(ParseError::new(None, format!("expected a constant value, got {} instead.", tok)));
         Statement::new()
     }
 
@@ -389,7 +600,8 @@ impl Parser {
             let value = self.parse_const_value();
             let definition = Statement{ stmtKind: StatementKind::Definition(Identifier{
                 token: ident.clone(),
-                value: Parser::get_ident_string(ident) }, Arc::new(value))
+                value: Parser::get_ident_string(ident) }, Arc::new(value)),
+                annotations: Vec::new()
             };
 
             block_statements.push(definition);
@@ -405,7 +617,8 @@ impl Parser {
             stmtKind: StatementKind::Block(BlockStatement {
                 token: Token::Const,
                 statements: block_statements
-            })
+            }),
+            annotations: Vec::new()
         }
     }
 
@@ -414,6 +627,10 @@ impl Parser {
 
         // println!("type_name: {}", type_name);
 
+        if type_name == Token::Type(DataType::Function, "fn".into()) {
+            return self.parse_function_type();
+        }
+
         if self.peek_token_is(Token::Lt) {
             self.next_token();
             self.next_token();
@@ -440,9 +657,18 @@ impl Parser {
                             // println!("List Type: {}", tok);
                             DataTypeStatement::List(Arc::new(self.parse_generic_type(&tok)))
                         },
+                        DataType::Array => {
+                            DataTypeStatement::Array(Arc::new(self.parse_generic_type(&tok)))
+                        },
                         DataType::Set => {
                             // println!("Set Type: {}", tok);
                             DataTypeStatement::Set(Arc::new(self.parse_generic_type(&tok)))
+                        },
+                        DataType::Optional => {
+                            DataTypeStatement::Optional(Arc::new(self.parse_generic_type(&tok)))
+                        },
+                        DataType::Future => {
+                            DataTypeStatement::Future(Arc::new(self.parse_generic_type(&tok)))
                         }
                         _ => {
                             DataTypeStatement::from_data_type(&d)
@@ -467,6 +693,11 @@ impl Parser {
         }
         else {
             let data_type = match type_name {
+                // A generic type without its arguments is kept by name for the resolver to report.
+                Token::Type(DataType::List, ref s) | Token::Type(DataType::Array, ref s) | Token::Type(DataType::Set, ref s) |
+                Token::Type(DataType::Map, ref s) | Token::Type(DataType::Optional, ref s) | Token::Type(DataType::Future, ref s) => {
+                    DataTypeStatement::Object(Identifier{token: type_name.clone(), value: s.clone()})
+                },
                 Token::Type(ref d, ref s) => {
                     DataTypeStatement::from_data_type(&d)
                 },
@@ -487,11 +718,39 @@ impl Parser {
         DataTypeStatement::None
     }
 
+    /// `fn(a, b) -> r`, where the `-> r` is left out for functions returning nothing.
+    fn parse_function_type(&mut self) -> DataTypeStatement {
+        if !self.expect_peek(Token::LParen) {
+            return DataTypeStatement::None;
+        }
+
+        let mut params = Vec::new();
+        self.next_token();
+        while !self.cur_token_is(Token::RParen) && !self.cur_token_is(Token::Eof) {
+            params.push(self.parse_type());
+            self.next_token();
+            if self.cur_token_is(Token::Comma) {
+                self.next_token();
+            }
+        }
+
+        let ret = if self.peek_token_is(Token::Arrow) {
+            self.next_token();
+            self.next_token();
+            self.parse_type()
+        }
+        else {
+            DataTypeStatement::None
+        };
+
+        DataTypeStatement::Function(params, Arc::new(ret))
+    }
+
     fn parse_generic_type(&mut self, tok: &Token) -> DataTypeStatement {
         let result = match *tok {
             Token::Type(ref tt, ref ss) => {
                 match *tt {
-                    DataType::List | DataType::Set | DataType::Map => {
+                    DataType::List | DataType::Array | DataType::Set | DataType::Map | DataType::Optional | DataType::Future | DataType::Function => {
                         self.parse_type()
                     },
                     _ => {
@@ -530,6 +789,12 @@ impl Parser {
                 Token::CppInterface => {
                     interface_types.push(InterfaceType::Cpp);
                 },
+                Token::RustInterface => {
+                    interface_types.push(InterfaceType::Rust);
+                },
+                Token::WasmInterface => {
+                    interface_types.push(InterfaceType::Wasm);
+                },
                 _ => {
                 }
             }
@@ -539,7 +804,8 @@ impl Parser {
 
         let mut modifier = FunctionModifier::None;
         let mut block = BlockStatement {token: Token::Record, statements: Vec::new() };
-        while !self.cur_token_is(Token::RBrace) {
+        let mut annotations = Vec::new();
+        while !self.cur_token_is(Token::RBrace) && !self.cur_token_is(Token::Eof) {
             let tok = self.cur_token.clone();
             match tok {
                 Token::Static => {
@@ -552,15 +818,23 @@ impl Parser {
                             stmtKind: StatementKind::Function(tok.clone(), modifier.clone(), Identifier {
                                 token: tok.clone(),
                                 value: s.clone()
-                            }, self.parse_parameters(), self.parse_return_type())
+                            }, self.parse_parameters(), self.parse_return_type(), self.parse_throws()),
+                            annotations: mem::replace(&mut annotations, Vec::new())
                         });
                     }
+                    modifier = FunctionModifier::None;
                 },
                 Token::Comment(_) => {
                     block.statements.push(self.parse_comment_statement());
                 },
                 Token::Const => {
-                    block.statements.push(self.parse_const_statement());
+                    let mut c = self.parse_const_statement();
+                    c.annotations = mem::replace(&mut annotations, Vec::new());
+                    block.statements.push(c);
+                },
+                Token::AtSign => {
+                    annotations = self.parse_annotations();
+                    continue;
                 },
                 _ => {
                 }
@@ -569,13 +843,16 @@ impl Parser {
             self.next_token();
         }
 
+        self.dangling_annotations(&annotations);
+
         let derived = self.parse_derives();
 
         return Statement {
             stmtKind: StatementKind::Interface(Token::Record, Identifier {
                 token: ident_tok.clone(),
                 value: ident_tok.to_str()
-            }, interface_types, block, derived)
+            }, interface_types, block, derived),
+            annotations: Vec::new()
         }
     }
 
@@ -597,7 +874,18 @@ impl Parser {
                         Token::Ord => {
                             derives.push(DeriveType::Ord)
                         },
-                        _ => {
+                        // Not keywords, so fields and types can still be named after them.
+                        Token::Ident(ref s) if s == "hash" => {
+                            derives.push(DeriveType::Hash)
+                        },
+                        Token::Ident(ref s) if s == "show" => {
+                            derives.push(DeriveType::Show)
+                        },
+                        Token::Ident(ref s) if s == "json" => {
+                            derives.push(DeriveType::Json)
+                        },
+                        ref t => {
+                            self.errors.push(ParseError::new(None, format!("expected eq, ord, hash, show or json to derive, got {} instead.", t.to_str())));
                         }
                     }
 
@@ -621,6 +909,52 @@ impl Parser {
         derived
     }
 
+    /// The `@name` and `@name("value")` annotations starting at the current token.  Leaves the
+    /// parser on the token after the last one.
+    fn parse_annotations(&mut self) -> Vec<Annotation> {
+        let mut annotations = Vec::new();
+        while self.cur_token_is(Token::AtSign) {
+            let name = match self.peek_token {
+                Token::Ident(ref s) => s.clone(),
+                ref t => {
+                    self.errors.push(ParseError::at(self.peek_line, format!("expected an annotation name after @, got {} instead.", t.to_str())));
+                    self.next_token();
+                    return annotations;
+                }
+            };
+            self.next_token();
+            let ident = Identifier { token: self.cur_token.clone(), value: name };
+
+            let mut value = None;
+            if self.peek_token_is(Token::LParen) {
+                self.next_token();
+                self.next_token();
+                match self.cur_token {
+                    Token::StringToken(ref s) => value = Some(s.clone()),
+                    ref t => {
+                        self.errors.push(ParseError::at(self.cur_line, format!("expected a string value for @{}, got {} instead.", ident.value, t.to_str())));
+                    }
+                }
+                if !self.expect_peek(Token::RParen) {
+                    return annotations;
+                }
+            }
+
+            annotations.push(Annotation { ident: ident, value: value });
+            self.next_token();
+        }
+
+        annotations
+    }
+
+    /// Reports annotations that nothing follows, such as `@deprecated }` at the end of a body.
+    fn dangling_annotations(&mut self, annotations: &Vec<Annotation>) {
+        if let Some(a) = annotations.last() {
+            let msg = format!("expected a declaration after @{}, got {} instead.", a.ident.value, self.cur_token.to_str());
+            self.errors.push(ParseError::at(self.cur_line, msg));
+        }
+    }
+
     fn parse_parameters(&mut self) -> Vec<Parameter> {
         let mut parameters = Vec::new();
 
@@ -632,7 +966,8 @@ impl Parser {
         // self.next_token();
 
         let mut cur_tok = self.cur_token.clone();
-        while !self.cur_token_is(Token::RParen) {
+        let mut annotations = Vec::new();
+        while !self.cur_token_is(Token::RParen) && !self.cur_token_is(Token::Eof) {
             // println!("cur_tok: {}", self.cur_token);
             cur_tok = self.cur_token.clone();
             match cur_tok {
@@ -643,7 +978,8 @@ impl Parser {
                         let t = self.parse_type();
                         let p = Parameter {
                             ident: id,
-                            data_type: t
+                            data_type: t,
+                            annotations: mem::replace(&mut annotations, Vec::new())
                         };
 
                         parameters.push(p);
@@ -653,6 +989,10 @@ impl Parser {
                         }
                     }
                 },
+                Token::AtSign => {
+                    annotations = self.parse_annotations();
+                    continue;
+                },
                 _ => {
                 }
             }
@@ -660,6 +1000,8 @@ impl Parser {
             self.next_token();
         }
 
+        self.dangling_annotations(&annotations);
+
         parameters
     }
 
@@ -669,13 +1011,35 @@ impl Parser {
         if self.cur_token_is(Token::Colon) {
             self.next_token();
             let t = self.parse_type();
-            if self.expect_peek(Token::Semicolon) {
+            // `parse_throws` consumes the rest of the declaration.
+            if self.peek_token_is(Token::Throws) || self.expect_peek(Token::Semicolon) {
                 return t;
             }
         }
         DataTypeStatement::None
     }
 
+    /// The optional `throws error_type` ending a method declaration, after its return type if it has one.
+    fn parse_throws(&mut self) -> Option<Identifier> {
+        if self.peek_token_is(Token::Throws) {
+            self.next_token();
+        }
+
+        if !self.cur_token_is(Token::Throws) || !self.peek_token_is_ident() {
+            return None;
+        }
+
+        let error = Identifier {
+            token: self.cur_token.clone(),
+            value: self.cur_token.to_str()
+        };
+        if !self.expect_peek(Token::Semicolon) {
+            return None;
+        }
+
+        Some(error)
+    }
+
     fn peek_token_is_ident(&mut self) -> bool {
         if let Token::Ident(..) = self.peek_token {
             self.next_token();
@@ -711,7 +1075,7 @@ impl Parser {
 
     fn peek_error(&mut self, t: Token) {
         let msg = format!("expected next token to be {}, got {} instead.", t, self.peek_token);
-        self.errors.push(msg);
+        self.errors.push(ParseError::new(None, msg));
     }
 }
 
@@ -840,7 +1204,7 @@ mod tests {
                 let block = b.clone();
                 for s in block.statements {
                     match s.stmtKind {
-                        StatementKind::RecordMember(ref t, ref i, ref d) => {
+                        StatementKind::RecordMember(ref t, ref i, ref d, _) => {
                             assert!(i.value == test_cases[index].expected_ident, "record mumber did not match: {} != {}", i.value, test_cases[index].expected_ident);
                             let t = format!("{}", d);
                             assert!(t == test_cases[index].expected_type, "types do not match: {} != {}", t, test_cases[index].expected_type);
@@ -917,7 +1281,7 @@ mod tests {
                 let mut index = 0;
                 for bs in b.statements.clone() {
                     match bs.stmtKind {
-                        StatementKind::Function(ref t, ref m, ref i, ref p, ref d) => {
+                        StatementKind::Function(ref t, ref m, ref i, ref p, ref d, _) => {
                             assert!(i.value == test_cases[index].expected_ident, "Identifier did not match: {} != {}", i.value, test_cases[index].expected_ident);
                             index = index + 1;
                         },
@@ -943,4 +1307,226 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_parse_throws() {
+        let input = r#"store = interface +c {
+                           save(k: key) throws store_error;
+                           fetch(id: i64): key throws store_error;
+                           close();
+                       }"#;
+
+        let lexer = Lexer::new(input.into());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap_or_default();
+        assert!(parser.errors().len() == 0, "unexpected errors: {:?}", parser.errors());
+
+        let expected = vec![("save", DataTypeStatement::None, Some("store_error")),
+                            ("fetch", DataTypeStatement::Object(Identifier { token: Token::Ident("key".into()), value: "key".into() }), Some("store_error")),
+                            ("close", DataTypeStatement::None, None)];
+        match program.statements[0].stmtKind {
+            StatementKind::Interface(_, _, _, ref b, _) => {
+                assert!(b.statements.len() == expected.len(), "got {} statements", b.statements.len());
+                for (s, &(name, ref ret, error)) in b.statements.iter().zip(expected.iter()) {
+                    match s.stmtKind {
+                        StatementKind::Function(_, _, ref i, _, ref r, ref e) => {
+                            assert!(i.value == name, "{} != {}", i.value, name);
+                            assert!(r.get_name() == ret.get_name(), "{}: {} != {}", name, r, ret);
+                            assert!(e.as_ref().map(|e| e.value.as_str()) == error, "{}: {:?}", name, e);
+                        },
+                        _ => assert!(false, "expected a function, got={}", s.stmtKind)
+                    }
+                }
+            },
+            _ => assert!(false, "expected Interface statement, got={}.", program.statements[0].stmtKind)
+        }
+    }
+
+    #[test]
+    fn test_parse_function_types() {
+        let input = r#"listener = interface +c {
+                           watch(filter: fn(i32, string) -> bool, done: fn());
+                           mapper(): fn(list<key>) -> map<string, fn(i64)>;
+                       }"#;
+
+        let lexer = Lexer::new(input.into());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap_or_default();
+        assert!(parser.errors().len() == 0, "unexpected errors: {:?}", parser.errors());
+
+        match program.statements[0].stmtKind {
+            StatementKind::Interface(_, _, _, ref b, _) => {
+                match b.statements[0].stmtKind {
+                    StatementKind::Function(_, _, _, ref p, _, _) => {
+                        let params: Vec<String> = p.iter().map(|p| format!("{}", p.data_type)).collect();
+                        assert!(params == vec!["fn(i32, string) -> bool", "fn()"], "got {:?}", params);
+                    },
+                    _ => assert!(false, "expected a function, got={}", b.statements[0].stmtKind)
+                }
+                match b.statements[1].stmtKind {
+                    StatementKind::Function(_, _, _, _, ref r, _) => {
+                        assert!(format!("{}", r) == "fn(list<key>) -> map<string, fn(i64)>", "got {}", r);
+                    },
+                    _ => assert!(false, "expected a function, got={}", b.statements[1].stmtKind)
+                }
+            },
+            _ => assert!(false, "expected Interface statement, got={}.", program.statements[0].stmtKind)
+        }
+    }
+
+    #[test]
+    fn test_parse_annotations() {
+        let input = r#"@import "base.djinni"
+                       @deprecated("use bar") @since("2.3")
+                       foo = record {
+                           @java_name("fooId") id: i64;
+                       }
+                       store = interface +c {
+                           @deprecated
+                           save(@nonnull owner: store, @custom k: key);
+                       }"#;
+
+        let lexer = Lexer::new(input.into());
+        let options = ParserOptions { process_imports: false, ..ParserOptions::default() };
+        let mut parser = Parser::new_with_options(lexer, options);
+        let program = parser.parse_program().unwrap_or_default();
+        assert!(parser.errors().len() == 0, "unexpected errors: {:?}", parser.errors());
+        assert!(program.statements.len() == 3, "got {} statements", program.statements.len());
+
+        let names = |a: &Vec<Annotation>| a.iter().map(|a| format!("{}", a)).collect::<Vec<_>>();
+        let record = &program.statements[1];
+        assert!(names(&record.annotations) == vec!["@deprecated(\"use bar\")", "@since(\"2.3\")"], "got {:?}", record.annotations);
+        match record.stmtKind {
+            StatementKind::Record(_, _, ref b, _) => {
+                assert!(names(&b.statements[0].annotations) == vec!["@java_name(\"fooId\")"], "got {:?}", b.statements[0].annotations);
+            },
+            _ => assert!(false, "expected Record statement, got={}.", record.stmtKind)
+        }
+
+        match program.statements[2].stmtKind {
+            StatementKind::Interface(_, _, _, ref b, _) => {
+                assert!(names(&b.statements[0].annotations) == vec!["@deprecated"], "got {:?}", b.statements[0].annotations);
+                match b.statements[0].stmtKind {
+                    StatementKind::Function(_, _, _, ref p, _, _) => {
+                        assert!(names(&p[0].annotations) == vec!["@nonnull"], "got {:?}", p[0].annotations);
+                        assert!(names(&p[1].annotations) == vec!["@custom"], "got {:?}", p[1].annotations);
+                    },
+                    _ => assert!(false, "expected a function, got={}", b.statements[0].stmtKind)
+                }
+            },
+            _ => assert!(false, "expected Interface statement, got={}.", program.statements[2].stmtKind)
+        }
+    }
+
+    #[test]
+    fn test_parse_dangling_annotations() {
+        let errors = vec![
+            ("point = record { x: i32; }\n@ s = record { y: i32; }", ParseError::at(2, "expected a declaration after @s, got = instead.".into())),
+            ("s = record {\n    y: i32;\n    @deprecated(\"x\") }", ParseError::at(3, "expected a declaration after @deprecated, got } instead.".into())),
+            ("color = enum { red; @deprecated }", ParseError::at(1, "expected a declaration after @deprecated, got } instead.".into())),
+            ("store = interface +c { save(@nonnull); }", ParseError::at(1, "expected a declaration after @nonnull, got ) instead.".into())),
+            ("point = record { @foo(bar) x: i32; }", ParseError::at(1, "expected a string value for @foo, got bar instead.".into())),
+            ("store = interface +c { close(); }\n\n@deprecated", ParseError::at(3, "expected a declaration after @deprecated, got EOF instead.".into()))
+        ];
+        for (input, expected) in errors {
+            let lexer = Lexer::new(input.into());
+            let mut parser = Parser::new(lexer);
+            parser.parse_program();
+            assert!(parser.errors().first() == Some(&expected), "{:?}: got {:?}", input, parser.errors());
+        }
+    }
+
+    #[test]
+    fn test_parse_record_defaults() {
+        let input = "settings = record { name: string; retries: i32 = 3; offset: i64 = -20; label: optional<string> = \"none\"; }";
+
+        let lexer = Lexer::new(input.into());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap_or_default();
+        assert!(parser.errors().len() == 0, "unexpected errors: {:?}", parser.errors());
+
+        match program.statements[0].stmtKind {
+            StatementKind::Record(_, _, ref b, _) => {
+                let defaults = b.statements.iter()
+                    .map(|s| match s.stmtKind {
+                        StatementKind::RecordMember(_, _, _, ref d) => d.as_ref().map(|d| format!("{}", d.stmtKind)),
+                        _ => None
+                    })
+                    .collect::<Vec<_>>();
+                assert!(defaults == vec![None, Some("3".into()), Some("-20".into()), Some("\"none\"".into())], "got {:?}", defaults);
+            },
+            _ => assert!(false, "expected Record statement, got={}.", program.statements[0].stmtKind)
+        }
+    }
+
+    #[test]
+    fn test_parse_alias() {
+        let input = "device_map = alias map<string, list<device_info>>;\nfoo = record { devices: device_map; }";
+
+        let lexer = Lexer::new(input.into());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap_or_default();
+        assert!(parser.errors().len() == 0, "unexpected errors: {:?}", parser.errors());
+        assert!(program.statements.len() == 2, "got {} statements", program.statements.len());
+
+        match program.statements[0].stmtKind {
+            StatementKind::Alias(_, ref i, ref dts) => {
+                assert!(i.value == "device_map", "got {}", i.value);
+                assert!(format!("{}", dts) == "map<string, list<device_info>>", "got {}", dts);
+            },
+            _ => assert!(false, "expected Alias statement, got={}.", program.statements[0].stmtKind)
+        }
+    }
+
+    #[test]
+    fn test_parse_module() {
+        let input = "module net;\nsettings = record { config: store.config; }\nmodule store;";
+
+        let lexer = Lexer::new(input.into());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap_or_default();
+        assert!(program.statements.len() == 2, "got {} statements", program.statements.len());
+        assert!(parser.errors().len() == 1, "expected one error, got {:?}", parser.errors());
+        assert!(parser.errors()[0].message == "module net is already declared for this file.", "got {}", parser.errors()[0].message);
+
+        match program.statements[0].stmtKind {
+            StatementKind::Module(_, ref name) => assert!(name == "net", "got {}", name),
+            _ => assert!(false, "expected Module statement, got={}.", program.statements[0].stmtKind)
+        }
+        let record = format!("{}", program.statements[1].stmtKind);
+        assert!(record.contains("config: store.config"), "got {}", record);
+    }
+
+    #[test]
+    fn test_parse_array() {
+        let input = "sensor_frame = record { samples: array<f32>; ticks: optional<array<i64>>; }";
+
+        let lexer = Lexer::new(input.into());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap_or_default();
+        assert!(parser.errors().len() == 0, "unexpected errors: {:?}", parser.errors());
+
+        let record = format!("{}", program.statements[0].stmtKind);
+        assert!(record.contains("samples: array<f32>"), "got {}", record);
+        assert!(record.contains("ticks: optional<array<i64>>"), "got {}", record);
+    }
+
+    #[test]
+    fn test_parse_derives() {
+        let input = "point = record { hash: i32; } deriving (eq, hash, show, json)\nshape = record { x: i32; } deriving (eq, debug)";
+
+        let lexer = Lexer::new(input.into());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap_or_default();
+        assert!(parser.errors().len() == 1, "expected one error, got {:?}", parser.errors());
+        assert!(parser.errors()[0].message == "expected eq, ord, hash, show or json to derive, got debug instead.", "got {}", parser.errors()[0].message);
+
+        match program.statements[0].stmtKind {
+            StatementKind::Record(_, _, ref b, ref d) => {
+                assert!(*d == vec![DeriveType::Eq, DeriveType::Hash, DeriveType::Show, DeriveType::Json], "got {:?}", d);
+                assert!(b.statements.len() == 1, "a field can still be named hash");
+            },
+            _ => assert!(false, "expected Record statement, got={}.", program.statements[0].stmtKind)
+        }
+    }
 }
@@ -17,11 +17,14 @@ pub enum DataType {
     Bool,
     Map,
     List,
+    Array,
     String,
     Binary,
     Date,
     Set,
     Optional,
+    Future,
+    Function,
     Object(String)
 }
 
@@ -43,6 +46,8 @@ pub enum Token {
     Enum,
     Record,
     Interface,
+    Alias,
+    Module,
     Colon,
     Comment(String),
     Semicolon,
@@ -53,6 +58,7 @@ pub enum Token {
     RParen,
     Lt,
     Gt,
+    Arrow,
     Equal,
     StringToken(String),
     Number(String),
@@ -63,7 +69,10 @@ pub enum Token {
     JavaInterface,
     ObjCInterface,
     CppInterface,
+    RustInterface,
+    WasmInterface,
     Static,
+    Throws,
     Const,
     Dervive,
     Eq,
@@ -92,6 +101,8 @@ impl fmt::Display for Token {
             Token::Enum => "enum".into(),
             Token::Record => "record".into(),
             Token::Interface => "interface".into(),
+            Token::Alias => "alias".into(),
+            Token::Module => "module".into(),
             Token::Colon => ":".into(),
             Token::Comment(ref c) => format!("#{}", c),
             Token::Semicolon => ";".into(),
@@ -102,6 +113,7 @@ impl fmt::Display for Token {
             Token::RBrace => "}".into(),
             Token::Lt => "<".into(),
             Token::Gt => ">".into(),
+            Token::Arrow => "->".into(),
             Token::Equal => "=".into(),
             Token::StringToken(ref s) => format!("{}", s),
             Token::Type(ref t, _) => format!("{}", t),
@@ -111,7 +123,10 @@ impl fmt::Display for Token {
             Token::JavaInterface => "+j".into(),
             Token::ObjCInterface => "+o".into(),
             Token::CppInterface => "+c".into(),
+            Token::RustInterface => "+r".into(),
+            Token::WasmInterface => "+w".into(),
             Token::Static => "static".into(),
+            Token::Throws => "throws".into(),
             Token::Const => "const".into(),
             Token::Dervive => "deriving".into(),
             Token::Number(ref s) => format!("{}", s),
@@ -137,10 +152,13 @@ impl fmt::Display for DataType {
             DataType::Bool => "bool",
             DataType::Date => "data",
             DataType::List => "list",
+            DataType::Array => "array",
             DataType::Map => "map",
             DataType::Set => "set",
             DataType::String => "string",
             DataType::Optional => "optional",
+            DataType::Future => "future",
+            DataType::Function => "fn",
             DataType::Object(_) => "object"
         };
 
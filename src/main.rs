@@ -1,581 +1,854 @@
 extern crate rusty_lamp_lib;
 
-#[macro_use]
 extern crate clap;
 
-use clap::{ App, Arg, ArgMatches };
-use rusty_lamp_lib::parser;
-use rusty_lamp_lib::generator::spec::{Spec};
-use rusty_lamp_lib::generator::typer::{Typer};
-use rusty_lamp_lib::generator::ident_style::{IdentStyle, IdentConverter};
+use clap::{ App, AppSettings, Arg, ArgGroup, ArgMatches, ErrorKind, SubCommand };
+use rusty_lamp_lib::{Compiler, CompilerOptions, Config, Diagnostic, Severity};
 
 use std::env;
-use std::path::Path;
 use std::fs::{ File };
-use std::io::{ Read, Write, BufWriter };
+use std::io::{ Read, Write };
 use std::io;
-use std::sync::{Arc};
+use std::process;
 
-fn main() {
-    println!("Hello, world!");
+const EXIT_OK: i32 = 0;
+const EXIT_DIAGNOSTICS: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_IO: i32 = 3;
+
+struct Log {
+    level: u64
+}
+
+impl Log {
+    fn new(matches: &ArgMatches) -> Log {
+        let level = if matches.is_present("quiet") {
+            0
+        }
+        else {
+            1 + matches.occurrences_of("verbose")
+        };
+
+        Log {
+            level
+        }
+    }
+
+    fn info(&self, msg: &str) {
+        if self.level > 1 {
+            eprintln!("{}", msg);
+        }
+    }
+
+    fn error(&self, msg: &str) {
+        eprintln!("error: {}", msg);
+    }
+
+    fn diagnostics(&self, diagnostics: &Vec<Diagnostic>) {
+        for d in diagnostics {
+            if d.severity == Severity::Error || self.level > 0 {
+                eprintln!("{}", d);
+            }
+        }
+    }
+}
 
+fn main() {
     let matches = App::new("rusty_lamp").about("Djinni implmentation in Rust")
         .author("Jonathan Rothberg")
-        .arg(Arg::with_name("idl")
-             .help("The IDF file with the type definitions, typically with extension \".djinni\".")
-             .long("idl")
-             .takes_value(true)
-             .required(true))
-        .arg(Arg::with_name("fmt")
-             .help("Format a Djinni file.")
-             .long("fmt")
-             // .takes_value(true)
-             .required(false))
-        .arg(Arg::with_name("include-path")
-             .help("An include path to search for Djinni @import directives. Can specify multiple paths.")
-             .takes_value(true)
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .after_help("EXIT CODES:\n    0    Success\n    1    The IDL contains errors\n    2    Invalid arguments or configuration\n    3    Unable to read or write files")
+        .arg(Arg::with_name("quiet")
+             .help("Only print errors.")
+             .short("q")
+             .long("quiet")
+             .global(true)
+             .conflicts_with("verbose"))
+        .arg(Arg::with_name("verbose")
+             .help("Print progress information.")
+             .short("v")
+             .long("verbose")
              .multiple(true)
-             .default_value("")
-             .long("idl-include-path"))
-        .arg(Arg::with_name("java-out")
-             .help("The output for the Java files (Generator disabled if unspecified.)")
-             .takes_value(true)
-             .default_value("")
-             .long("java-out"))
-        .arg(Arg::with_name("java-package")
-             .help("The package name to use for generated Java classes.")
-             .takes_value(true)
-             .long("java-package"))
-        .arg(Arg::with_name("java-class-access-modifier")
-             .help("The access modifier to use for generated Java classes.")
-             .takes_value(true)
-             .long("java-class-access-modifier")
-             .default_value("public"))
-        .arg(Arg::with_name("java-cpp-exception")
-             .help("The type for translated C++ exceptions in Java")
-             .takes_value(true)
-             .long("java-cpp-exception")
-             .default_value("java.lang.RuntimeException this is not checked"))
-        .arg(Arg::with_name("java-annotation")
-             .help("Java annotation (@Foo) to place on all generated Java classes")
-             .takes_value(true)
-             .long("java-annotation"))
-        .arg(Arg::with_name("java-nullable-annotation")
-             .help("Java annotation (@Nullable) to place all fields and return values that are optional.")
-             .long("java-nullable-annotation"))
-        .arg(Arg::with_name("java-nonnull-annotation")
-             .help("Java annotation (@Notnull) to place on all fields and return values that are not optional")
-             .long("java-nonnull-annotation"))
-        .arg(Arg::with_name("java-use-final-for-record")
-             .help("Whether generated Java classes for records should be marked 'final'")
-             .long("java-use-final-for-record")
-             .takes_value(true)
-             .default_value("true"))
-
-        // C++
-        .arg(Arg::with_name("cpp-out")
-             .help("The output folder for C++ files (Generator disabled if unspecified).")
-             .long("cpp-out")
-             .default_value(""))
-        .arg(Arg::with_name("cpp-header-out")
-             .help("The output folder for C++ header files")
-             .long("cpp-header-out")
-             .takes_value(true)
-             .default_value("same as --cpp-out"))
-        .arg(Arg::with_name("cpp-include-prefix")
-             .help("The prefix for #includes of header files from C++ files.")
-             .long("cpp-include-prefix")
-             .takes_value(true))
-        .arg(Arg::with_name("cpp-namespace")
-             .help("The namespace name to use for generated C++ classes.")
-             .long("cpp-namespace")
-             .takes_value(true))
-        .arg(Arg::with_name("cpp-ext")
-             .help("The filename extension for C++ files.")
-             .long("cpp-ext")
-             .takes_value(true)
-             .default_value("\"cpp\""))
-        .arg(Arg::with_name("hpp-ext")
-             .help("The filename extension for C++ header files.")
-             .long("hpp-ext")
-             .takes_value(true)
-             .default_value("\"hpp\""))
-        .arg(Arg::with_name("cpp-optional-template")
-             .help("The template to use for optional values.")
-             .long("cpp-optional-template")
-             .takes_value(true)
-             .default_value("\"std::optional\""))
-        .arg(Arg::with_name("cpp-optional-header")
-             .help("The header to use for optional values.")
-             .long("cpp-optional-header")
-             .takes_value(true)
-             .default_value("\"<optional>\""))
-        .arg(Arg::with_name("cpp-enum-hash-workaround")
-             .help("Work around LWG-2148 by generating std::hash specializatins for C++ enums.")
-             .long("cpp-enum-hash-workaround")
+             .global(true))
+        .arg(Arg::with_name("config")
+             .help("The configuration file to load (defaults to rusty_lamp.toml in the working directory).")
+             .long("config")
              .takes_value(true)
-             .default_value("true"))
-        .arg(Arg::with_name("cpp-nn-header")
-             .help("The header to use for non-nullable pointers")
-             .long("cpp-nn-header"))
-        .arg(Arg::with_name("cpp-nn-type")
-             .help("The type to use for non-nullable pointers (as a substitute for std::shared_ptr).")
-             .long("cpp-nn-type"))
-        .arg(Arg::with_name("cpp-nn-check-expression")
-             .help("The expression to use for building non-nullable pointers")
-             .long("cpp-nn-check-expression"))
-        .arg(Arg::with_name("cpp-use-wide-strings")
-             .help("Use wide strings in C++ code")
-             .long("cpp-use-wide-strings")
-             .takes_value(true)
-             .default_value("false"))
-
-        //JNI
-        .arg(Arg::with_name("jni-out")
-             .help("The folder for JNI C++ output files (Generator disabled if unspecified).")
-             .long("jni-out")
-             .default_value(""))
-        .arg(Arg::with_name("jni-header-out")
-             .help("The folder for JNI C++ header files.")
-             .long("jni-header-out")
-             .default_value("same as --jni-out"))
-        .arg(Arg::with_name("jni-include-prefix")
-             .help("The prefix for #includes of header files from JNI C++ files.")
-             .long("jni-include-prefix")
-             .takes_value(true))
-        .arg(Arg::with_name("jni-namespace")
-             .help("The namespace name to use for generated JNI C++ classes.")
-             .long("jni-namespace")
-             .takes_value(true))
-        .arg(Arg::with_name("jni-base-lib-include-prefix")
-             .help("The JNI base library's include path, relative to the JNI C++ classes.")
-             .takes_value(true)
-             .long("jni-base-lib-include-prefix"))
-        //Objc
-        .arg(Arg::with_name("objc-out")
-             .help("The folder for Objective-C output files (Generator disabled if unspecified).")
-             .default_value("")
-             .long("objc-out"))
-        .arg(Arg::with_name("objc-h-ext")
-             .help("The filename extension for Objective-C[++] header files.")
-             .long("objc-h-ext")
-             .takes_value(true)
-             .default_value("\"h\""))
-        .arg(Arg::with_name("objc-type-prefix")
-             .help("The prefix for Objective-C data types (usually two or three letters).")
-             .long("objc-type-prefix")
-             .takes_value(true))
-        .arg(Arg::with_name("objc-include-prefix")
-             .help("The prefix for #import of header files from Objective-C files.")
-             .long("objc-include-prefix"))
-
-       //Objective-C++
-        .arg(Arg::with_name("objcpp-out")
-             .help("The output folder for private Objective-C files (Generator disabled if unspecified).")
-             .default_value("")
-             .long("objcpp-out"))
-        .arg(Arg::with_name("ojcpp-ext")
-             .help("The filename extension for Objective-C++ files.")
-             .long("objcpp-ext")
-             .default_value("\"mm\""))
-        .arg(Arg::with_name("objcpp-include-prefix")
-             .help("The prefix for #import of header files from Objective-C++ files.")
-             .long("objcpp-include-prefix")
-             .takes_value(true))
-        .arg(Arg::with_name("objcpp-include-cpp-prefix")
-             .help("The prefix for #include of the main C++ header file from Objective-C++ files.")
-             .long("objcpp-include-cpp-prefix")
-             .takes_value(true))
-        .arg(Arg::with_name("objcpp-include-objc-prefix")
-             .help("The prefix for #import of the Objective-C header files from Objective-C++ files.")
-             .long("objcpp-include-objc-prefix")
-             .takes_value(true)
-             .default_value("same as --objcpp-include-prefix"))
-        .arg(Arg::with_name("cpp-extended-record-include-prefix")
-             .help("The prefix path for #include of the extended record C++ header (.hpp) files.")
-             .long("cpp-extended-record-include-prefix")
-             .takes_value(true))
-        .arg(Arg::with_name("objc-extended-record-include-prefix")
-             .help("The prefix path for #import of the extended record Objective-C header (.h) files.")
-             .long("objc-extended-record-include-prefix")
-             .takes_value(true))
-        .arg(Arg::with_name("objcpp-namespace")
-             .help("The namespace name to use for generated Objective-C++ classes.")
-             .long("objcpp-namespace")
-             .takes_value(true))
-        .arg(Arg::with_name("objc-base-lib-include-prefix")
-             .help("The Objective-C++ base library's include path, relative to the Objective-C++ classes.")
-             .takes_value(true)
-             .long("objc-base-lib-include-prefix"))
-
-        //YAML
-        .arg(Arg::with_name("yaml-out")
-             .help("The output folder for YAML files (Generator disabled if unspecified).")
-             .long("yaml-out"))
-        .arg(Arg::with_name("yaml-out-file")
-             .help("If specified all types are merged into a single YAML file instead of generating one file per type (relative to --yaml-out)")
-             .long("yaml-out-file"))
-        .arg(Arg::with_name("yaml-prefix")
-             .help("THe prefix to add to type names stored in YAML files.")
-             .long("yaml-prefix")
-             .default_value(""))
-
-       // Misc
-        .arg(Arg::with_name("list-in-files")
-             .help("Optional file in which to write the list of input files parsed.")
-             .long("list-in-files"))
-        .arg(Arg::with_name("list-out-files")
-             .help("Optional file in which to write the list of output files produced.")
-             .long("list-out-files"))
-        .arg(Arg::with_name("skip-generation")
-             .help("Way of specifyiing if the file generation should be skipped.")
-             .long("skip-generation")
-             .default_value("false"))
-
-       // Ident Style Flags
-        .arg(Arg::with_name("ident-java-enum")
-             .default_value("")
-             .long("ident-java-enum"))
-        .arg(Arg::with_name("ident-java-field")
-             .default_value("")
-             .long("ident-java-field"))
-        .arg(Arg::with_name("ident-java-type")
-             .default_value("")
-             .long("ident-java-type"))
-
-        .arg(Arg::with_name("ident-cpp-enum")
-             .default_value("")
-             .long("ident-cpp-enum"))
-        .arg(Arg::with_name("ident-cpp-field")
-             .default_value("")
-             .long("ident-cpp-field"))
-        .arg(Arg::with_name("ident-cpp-method")
-             .default_value("")
-             .long("ident-cpp-method"))
-        .arg(Arg::with_name("ident-cpp-type")
-             .default_value("")
-             .long("ident-cpp-type"))
-        .arg(Arg::with_name("ident-cpp-enum-type")
-             .default_value("")
-             .long("ident-cpp-enum-type"))
-        .arg(Arg::with_name("ident-cpp-type-param")
-             .default_value("")
-             .long("ident-cpp-type-param"))
-        .arg(Arg::with_name("ident-cpp-local")
-             .default_value("")
-             .long("ident-cpp-local"))
-        .arg(Arg::with_name("ident-cpp-file")
-             .default_value("")
-             .long("ident-cpp-file"))
-
-        .arg(Arg::with_name("ident-jni-class")
-             .long("ident-jni-class")
-             .default_value(""))
-        .arg(Arg::with_name("ident-jni-file")
-             .long("ident-jni-file")
-             .default_value(""))
-
-        .arg(Arg::with_name("ident-objc-enum")
-             .default_value("")
-             .long("ident-objc-enum"))
-        .arg(Arg::with_name("ident-objc-field")
-             .default_value("")
-             .long("ident-objc-field"))
-        .arg(Arg::with_name("ident-objc-method")
-             .default_value("")
-             .long("ident-objc-method"))
-        .arg(Arg::with_name("ident-objc-type")
-             .default_value("")
-             .long("ident-objc-type"))
-        .arg(Arg::with_name("ident-objc-type-param")
-             .default_value("")
-             .long("ident-objc-type-param"))
-        .arg(Arg::with_name("ident-objc-local")
-             .default_value("")
-             .long("ident-objc-local"))
-        .arg(Arg::with_name("ident-objc-file")
-             .default_value("")
-             .long("ident-objc-file")).get_matches();
-
-
-    // let matches = clap_app!(rusty_lamp =>
-    //                         (version: "0.1")
-    //                         (author: "Jonathan Rothberg")
-    //                         (about: "Djinni implementation in Rust")
-    //                         (@arg IDL: +required "The IDL file with the type definitions, typically with extension \".djinni\".")
-    //                         (@arg FMT: --fmt "Format a Djinni file")
-    //                         (@arg include_path: --("idl-include-path") "An include path to search for Djinni @import directives. Can specify multiple paths.")
-    //                         (@arg java_out: --("java-out") "The output for the Java files (Generator disabled if unspecified)")
-    // ).get_matches();
-
-    if let Some(ref ann) = matches.value_of("java_use_final_for_record") {
-        println!("Package: {:?}", ann.parse::<bool>());
-    }
-
-    match matches.value_of("idl") {
-        Some(i) => {
-            match matches.occurrences_of("FMT") {
-                1 => {
-                    let cwd = match env::var("PWD") {
-                        Ok(c) => c,
-                        Err(_) => String::new()
-                    };
-                    println!("Working Directory: {}", cwd);
-
-                    let mut file = File::open(i).unwrap();
-                    let mut contents = String::new();
-                    file.read_to_string(&mut contents).unwrap();
-                    let mut stdout = io::stdout();
-
-                    rusty_lamp_lib::process(contents, &mut stdout);
+             .global(true))
+        .subcommand(input_args(SubCommand::with_name("generate")
+                               .about("Generate code for the enabled languages."))
+                    .arg(Arg::with_name("java-out")
+                         .help("The output for the Java files (Generator disabled if unspecified.)")
+                         .long("java-out")
+                         .takes_value(true))
+                    .arg(Arg::with_name("java-package")
+                         .help("The package name to use for generated Java classes.")
+                         .long("java-package")
+                         .takes_value(true))
+                    .arg(Arg::with_name("java-class-access-modifier")
+                         .help("The access modifier to use for generated Java classes.")
+                         .long("java-class-access-modifier")
+                         .takes_value(true))
+                    .arg(Arg::with_name("java-cpp-exception")
+                         .help("The type for translated C++ exceptions in Java")
+                         .long("java-cpp-exception")
+                         .takes_value(true))
+                    .arg(Arg::with_name("java-annotation")
+                         .help("Java annotation (@Foo) to place on all generated Java classes")
+                         .long("java-annotation")
+                         .takes_value(true))
+                    .arg(Arg::with_name("java-nullable-annotation")
+                         .help("Java annotation (@Nullable) to place all fields and return values that are optional.")
+                         .long("java-nullable-annotation")
+                         .takes_value(true))
+                    .arg(Arg::with_name("java-nonnull-annotation")
+                         .help("Java annotation (@Notnull) to place on all fields and return values that are not optional")
+                         .long("java-nonnull-annotation")
+                         .takes_value(true))
+                    .arg(Arg::with_name("java-use-final-for-record")
+                         .help("Whether generated Java classes for records should be marked 'final'")
+                         .long("java-use-final-for-record")
+                         .takes_value(true))
+
+                    // C++
+                    .arg(Arg::with_name("cpp-out")
+                         .help("The output folder for C++ files (Generator disabled if unspecified).")
+                         .long("cpp-out")
+                         .takes_value(true))
+                    .arg(Arg::with_name("cpp-header-out")
+                         .help("The output folder for C++ header files (same as --cpp-out if unspecified).")
+                         .long("cpp-header-out")
+                         .takes_value(true))
+                    .arg(Arg::with_name("cpp-include-prefix")
+                         .help("The prefix for #includes of header files from C++ files.")
+                         .long("cpp-include-prefix")
+                         .takes_value(true))
+                    .arg(Arg::with_name("cpp-namespace")
+                         .help("The namespace name to use for generated C++ classes.")
+                         .long("cpp-namespace")
+                         .takes_value(true))
+                    .arg(Arg::with_name("cpp-ext")
+                         .help("The filename extension for C++ files.")
+                         .long("cpp-ext")
+                         .takes_value(true))
+                    .arg(Arg::with_name("hpp-ext")
+                         .help("The filename extension for C++ header files.")
+                         .long("hpp-ext")
+                         .takes_value(true))
+                    .arg(Arg::with_name("cpp-optional-template")
+                         .help("The template to use for optional values.")
+                         .long("cpp-optional-template")
+                         .takes_value(true))
+                    .arg(Arg::with_name("cpp-optional-header")
+                         .help("The header to use for optional values.")
+                         .long("cpp-optional-header")
+                         .takes_value(true))
+                    .arg(Arg::with_name("cpp-future-template")
+                         .help("The template to use for the results of async methods.")
+                         .long("cpp-future-template")
+                         .takes_value(true))
+                    .arg(Arg::with_name("cpp-future-header")
+                         .help("The header to use for the results of async methods.")
+                         .long("cpp-future-header")
+                         .takes_value(true))
+                    .arg(Arg::with_name("cpp-expected-template")
+                         .help("The template to use for the results of methods that throw.")
+                         .long("cpp-expected-template")
+                         .takes_value(true))
+                    .arg(Arg::with_name("cpp-expected-header")
+                         .help("The header to use for the results of methods that throw.")
+                         .long("cpp-expected-header")
+                         .takes_value(true))
+                    .arg(Arg::with_name("cpp-json-type")
+                         .help("The JSON value type records deriving json are converted to and from.")
+                         .long("cpp-json-type")
+                         .takes_value(true))
+                    .arg(Arg::with_name("cpp-json-header")
+                         .help("The header declaring the JSON value type.")
+                         .long("cpp-json-header")
+                         .takes_value(true))
+                    .arg(Arg::with_name("cpp-enum-hash-workaround")
+                         .help("Work around LWG-2148 by generating std::hash specializatins for C++ enums.")
+                         .long("cpp-enum-hash-workaround")
+                         .takes_value(true))
+                    .arg(Arg::with_name("cpp-nn-header")
+                         .help("The header to use for non-nullable pointers")
+                         .long("cpp-nn-header")
+                         .takes_value(true))
+                    .arg(Arg::with_name("cpp-nn-type")
+                         .help("The type to use for non-nullable pointers (as a substitute for std::shared_ptr).")
+                         .long("cpp-nn-type")
+                         .takes_value(true))
+                    .arg(Arg::with_name("cpp-nn-check-expression")
+                         .help("The expression to use for building non-nullable pointers")
+                         .long("cpp-nn-check-expression")
+                         .takes_value(true))
+                    .arg(Arg::with_name("cpp-use-wide-strings")
+                         .help("Use wide strings in C++ code")
+                         .long("cpp-use-wide-strings")
+                         .takes_value(true))
+                    .arg(Arg::with_name("cpp-aliases")
+                         .help("Declare IDL aliases as C++ `using` aliases.")
+                         .long("cpp-aliases")
+                         .takes_value(true))
+                    .arg(Arg::with_name("cpp-extended-record-include-prefix")
+                         .help("The prefix path for #include of the extended record C++ header (.hpp) files.")
+                         .long("cpp-extended-record-include-prefix")
+                         .takes_value(true))
+
+                    //JNI
+                    .arg(Arg::with_name("jni-out")
+                         .help("The folder for JNI C++ output files (Generator disabled if unspecified).")
+                         .long("jni-out")
+                         .takes_value(true))
+                    .arg(Arg::with_name("jni-header-out")
+                         .help("The folder for JNI C++ header files (same as --jni-out if unspecified).")
+                         .long("jni-header-out")
+                         .takes_value(true))
+                    .arg(Arg::with_name("jni-include-prefix")
+                         .help("The prefix for #includes of header files from JNI C++ files.")
+                         .long("jni-include-prefix")
+                         .takes_value(true))
+                    .arg(Arg::with_name("jni-include-cpp-prefix")
+                         .help("The prefix for #includes of the main header files from JNI C++ files.")
+                         .long("jni-include-cpp-prefix")
+                         .takes_value(true))
+                    .arg(Arg::with_name("jni-namespace")
+                         .help("The namespace name to use for generated JNI C++ classes.")
+                         .long("jni-namespace")
+                         .takes_value(true))
+                    .arg(Arg::with_name("jni-base-lib-include-prefix")
+                         .help("The JNI base library's include path, relative to the JNI C++ classes.")
+                         .long("jni-base-lib-include-prefix")
+                         .takes_value(true))
+
+                    //Objc
+                    .arg(Arg::with_name("objc-out")
+                         .help("The folder for Objective-C output files (Generator disabled if unspecified).")
+                         .long("objc-out")
+                         .takes_value(true))
+                    .arg(Arg::with_name("objc-h-ext")
+                         .help("The filename extension for Objective-C[++] header files.")
+                         .long("objc-h-ext")
+                         .takes_value(true))
+                    .arg(Arg::with_name("objc-type-prefix")
+                         .help("The prefix for Objective-C data types (usually two or three letters).")
+                         .long("objc-type-prefix")
+                         .takes_value(true))
+                    .arg(Arg::with_name("objc-include-prefix")
+                         .help("The prefix for #import of header files from Objective-C files.")
+                         .long("objc-include-prefix")
+                         .takes_value(true))
+                    .arg(Arg::with_name("objc-extended-record-include-prefix")
+                         .help("The prefix path for #import of the extended record Objective-C header (.h) files.")
+                         .long("objc-extended-record-include-prefix")
+                         .takes_value(true))
+                    .arg(Arg::with_name("objc-base-lib-include-prefix")
+                         .help("The Objective-C++ base library's include path, relative to the Objective-C++ classes.")
+                         .long("objc-base-lib-include-prefix")
+                         .takes_value(true))
+
+                    // Kotlin
+                    .arg(Arg::with_name("kotlin-out")
+                         .help("The output folder for Kotlin files, an alternative to the Java output (Generator disabled if unspecified).")
+                         .long("kotlin-out")
+                         .takes_value(true))
+                    .arg(Arg::with_name("kotlin-aliases")
+                         .help("Declare IDL aliases as Kotlin `typealias`es.")
+                         .long("kotlin-aliases")
+                         .takes_value(true))
+
+                    // Swift
+                    .arg(Arg::with_name("swift-out")
+                         .help("The output folder for Swift wrappers of the Objective-C output (Generator disabled if unspecified).")
+                         .long("swift-out")
+                         .takes_value(true))
+                    .arg(Arg::with_name("swift-aliases")
+                         .help("Declare IDL aliases as Swift `typealias`es.")
+                         .long("swift-aliases")
+                         .takes_value(true))
+
+                    //Objective-C++
+                    .arg(Arg::with_name("objcpp-out")
+                         .help("The output folder for private Objective-C files (Generator disabled if unspecified).")
+                         .long("objcpp-out")
+                         .takes_value(true))
+                    .arg(Arg::with_name("objcpp-ext")
+                         .help("The filename extension for Objective-C++ files.")
+                         .long("objcpp-ext")
+                         .takes_value(true))
+                    .arg(Arg::with_name("objcpp-include-prefix")
+                         .help("The prefix for #import of header files from Objective-C++ files.")
+                         .long("objcpp-include-prefix")
+                         .takes_value(true))
+                    .arg(Arg::with_name("objcpp-include-cpp-prefix")
+                         .help("The prefix for #include of the main C++ header file from Objective-C++ files.")
+                         .long("objcpp-include-cpp-prefix")
+                         .takes_value(true))
+                    .arg(Arg::with_name("objcpp-include-objc-prefix")
+                         .help("The prefix for #import of the Objective-C header files from Objective-C++ files (same as --objcpp-include-prefix if unspecified).")
+                         .long("objcpp-include-objc-prefix")
+                         .takes_value(true))
+                    .arg(Arg::with_name("objcpp-namespace")
+                         .help("The namespace name to use for generated Objective-C++ classes.")
+                         .long("objcpp-namespace")
+                         .takes_value(true))
+
+                    //YAML
+                    .arg(Arg::with_name("yaml-out")
+                         .help("The output folder for YAML files (Generator disabled if unspecified).")
+                         .long("yaml-out")
+                         .takes_value(true))
+                    .arg(Arg::with_name("yaml-out-file")
+                         .help("If specified all types are merged into a single YAML file instead of generating one file per type (relative to --yaml-out)")
+                         .long("yaml-out-file")
+                         .takes_value(true))
+                    .arg(Arg::with_name("yaml-prefix")
+                         .help("THe prefix to add to type names stored in YAML files.")
+                         .long("yaml-prefix")
+                         .takes_value(true))
+
+                    // Rust
+                    .arg(Arg::with_name("rust-out")
+                         .help("The output folder for Rust files (Generator disabled if unspecified).")
+                         .long("rust-out")
+                         .takes_value(true))
+                    .arg(Arg::with_name("rust-bridge-out")
+                         .help("The output folder for the C++ half of the Rust bridge (Bridge disabled if unspecified).")
+                         .long("rust-bridge-out")
+                         .takes_value(true))
+
+                    // C
+                    .arg(Arg::with_name("c-out")
+                         .help("The output folder for the C API source (Generator disabled if unspecified).")
+                         .long("c-out")
+                         .takes_value(true))
+                    .arg(Arg::with_name("c-header-out")
+                         .help("The output folder for the C API header (same as --c-out if unspecified).")
+                         .long("c-header-out")
+                         .takes_value(true))
+                    .arg(Arg::with_name("c-prefix")
+                         .help("The prefix for every C type and function name (default: lamp_).")
+                         .long("c-prefix")
+                         .takes_value(true))
+
+                    // C#
+                    .arg(Arg::with_name("csharp-out")
+                         .help("The output folder for C# bindings over the C API, requires --c-out (Generator disabled if unspecified).")
+                         .long("csharp-out")
+                         .takes_value(true))
+                    .arg(Arg::with_name("csharp-namespace")
+                         .help("The namespace for the generated C# classes (default: Lamp).")
+                         .long("csharp-namespace")
+                         .takes_value(true))
+                    .arg(Arg::with_name("csharp-library")
+                         .help("The native library the C API is loaded from (default: the C file name).")
+                         .long("csharp-library")
+                         .takes_value(true))
+
+                    // Dart
+                    .arg(Arg::with_name("dart-out")
+                         .help("The output folder for Dart FFI bindings over the C API, requires --c-out (Generator disabled if unspecified).")
+                         .long("dart-out")
+                         .takes_value(true))
+                    .arg(Arg::with_name("dart-library")
+                         .help("The native library the C API is loaded from, also naming the Dart file (default: the C file name).")
+                         .long("dart-library")
+                         .takes_value(true))
+
+                    // WebAssembly
+                    .arg(Arg::with_name("wasm-out")
+                         .help("The output folder for the Emscripten embind source (Generator disabled if unspecified).")
+                         .long("wasm-out")
+                         .takes_value(true))
+                    .arg(Arg::with_name("wasm-ts-out")
+                         .help("The output folder for the TypeScript declarations (same as --wasm-out if unspecified).")
+                         .long("wasm-ts-out")
+                         .takes_value(true))
+                    .arg(Arg::with_name("wasm-module")
+                         .help("The embind module name, also used for the generated file names (default: lamp).")
+                         .long("wasm-module")
+                         .takes_value(true))
+
+                    // Node.js
+                    .arg(Arg::with_name("node-out")
+                         .help("The output folder for the N-API addon source (Generator disabled if unspecified).")
+                         .long("node-out")
+                         .takes_value(true))
+                    .arg(Arg::with_name("node-ts-out")
+                         .help("The output folder for index.d.ts (same as --node-out if unspecified).")
+                         .long("node-ts-out")
+                         .takes_value(true))
+                    .arg(Arg::with_name("node-module")
+                         .help("The addon module name, also used for the generated source name (default: lamp).")
+                         .long("node-module")
+                         .takes_value(true))
+
+                    // Python
+                    .arg(Arg::with_name("python-out")
+                         .help("The output folder for the pybind11 module and its .pyi stub (Generator disabled if unspecified).")
+                         .long("python-out")
+                         .takes_value(true))
+                    .arg(Arg::with_name("python-module")
+                         .help("The Python module name, also used for the generated file names (default: lamp).")
+                         .long("python-module")
+                         .takes_value(true))
+
+                    // Misc
+                    .arg(Arg::with_name("list-in-files")
+                         .help("Optional file in which to write the list of input files parsed.")
+                         .long("list-in-files")
+                         .takes_value(true))
+                    .arg(Arg::with_name("list-out-files")
+                         .help("Optional file in which to write the list of output files produced.")
+                         .long("list-out-files")
+                         .takes_value(true))
+                    .arg(Arg::with_name("skip-generation")
+                         .help("Way of specifyiing if the file generation should be skipped.")
+                         .long("skip-generation")
+                         .takes_value(true))
+
+                    // Ident Style Flags
+                    .arg(Arg::with_name("ident-java-enum")
+                         .long("ident-java-enum")
+                         .takes_value(true))
+                    .arg(Arg::with_name("ident-java-field")
+                         .long("ident-java-field")
+                         .takes_value(true))
+                    .arg(Arg::with_name("ident-java-type")
+                         .long("ident-java-type")
+                         .takes_value(true))
+                    .arg(Arg::with_name("ident-cpp-enum")
+                         .long("ident-cpp-enum")
+                         .takes_value(true))
+                    .arg(Arg::with_name("ident-cpp-field")
+                         .long("ident-cpp-field")
+                         .takes_value(true))
+                    .arg(Arg::with_name("ident-cpp-method")
+                         .long("ident-cpp-method")
+                         .takes_value(true))
+                    .arg(Arg::with_name("ident-cpp-type")
+                         .long("ident-cpp-type")
+                         .takes_value(true))
+                    .arg(Arg::with_name("ident-cpp-enum-type")
+                         .long("ident-cpp-enum-type")
+                         .takes_value(true))
+                    .arg(Arg::with_name("ident-cpp-type-param")
+                         .long("ident-cpp-type-param")
+                         .takes_value(true))
+                    .arg(Arg::with_name("ident-cpp-local")
+                         .long("ident-cpp-local")
+                         .takes_value(true))
+                    .arg(Arg::with_name("ident-cpp-file")
+                         .long("ident-cpp-file")
+                         .takes_value(true))
+                    .arg(Arg::with_name("ident-jni-class")
+                         .long("ident-jni-class")
+                         .takes_value(true))
+                    .arg(Arg::with_name("ident-jni-file")
+                         .long("ident-jni-file")
+                         .takes_value(true))
+                    .arg(Arg::with_name("ident-objc-enum")
+                         .long("ident-objc-enum")
+                         .takes_value(true))
+                    .arg(Arg::with_name("ident-objc-field")
+                         .long("ident-objc-field")
+                         .takes_value(true))
+                    .arg(Arg::with_name("ident-objc-method")
+                         .long("ident-objc-method")
+                         .takes_value(true))
+                    .arg(Arg::with_name("ident-objc-type")
+                         .long("ident-objc-type")
+                         .takes_value(true))
+                    .arg(Arg::with_name("ident-objc-type-param")
+                         .long("ident-objc-type-param")
+                         .takes_value(true))
+                    .arg(Arg::with_name("ident-objc-local")
+                         .long("ident-objc-local")
+                         .takes_value(true))
+                    .arg(Arg::with_name("ident-objc-file")
+                         .long("ident-objc-file")
+                         .takes_value(true)))
+        .subcommand(SubCommand::with_name("fmt")
+                    .about("Format a Djinni file.")
+                    .arg(Arg::with_name("file")
+                         .help("The Djinni file to format.")
+                         .required(true))
+                    .arg(Arg::with_name("write")
+                         .help("Write the formatted output back to the file instead of stdout.")
+                         .short("w")
+                         .long("write")))
+        .subcommand(input_args(SubCommand::with_name("check")
+                               .about("Parse and resolve the IDL, reporting diagnostics without generating code.")))
+        .subcommand(input_args(SubCommand::with_name("dump")
+                               .about("Print the parsed AST or the resolved type table."))
+                    .arg(Arg::with_name("ast")
+                         .help("Print the parsed AST.")
+                         .long("ast"))
+                    .arg(Arg::with_name("types")
+                         .help("Print the resolved type table.")
+                         .long("types"))
+                    .group(ArgGroup::with_name("dump-kind")
+                           .args(&["ast", "types"])
+                           .required(true)))
+        .get_matches_safe()
+        .unwrap_or_else(|e| {
+            match e.kind {
+                ErrorKind::HelpDisplayed | ErrorKind::VersionDisplayed => {
+                    println!("{}", e.message);
+                    process::exit(EXIT_OK);
                 },
                 _ => {
-                    println!("IDL: {}", i);
-                    let idl_include_paths: Vec<&str> = match matches.values_of("id-include-path") {
-                        Some(f) => f.collect(),
-                        None => Vec::new()
-                    };
-                    let java_out_folder = matches.value_of("java-out");
-                    println!("Java Out: {:?}", java_out_folder);
-                    let java_package = matches.value_of("java-package");
-                    // let ident_java_field = matches.value_of("ident-java-field").unwrap();
-                    let java_class_access_modifier = matches.value_of("java-class-access-modifier").unwrap_or("");
-                    let java_cpp_exception = matches.value_of("java-cpp-exception");
-                    let java_annotation = matches.value_of("java-annotation");
-                    let java_nullable_annotation = matches.value_of("java-nullable-annotation");
-                    let java_nonnull_annotation = matches.value_of("java-nonnull-annotation");
-                    let java_use_final_for_record = match matches.value_of("java-use-final-for-record") {
-                        Some(f) => f.parse::<bool>().unwrap_or(false),
-                        None => false
-                    };
-
-                    let cpp_out_folder = matches.value_of("cpp-out");
-                    let cpp_header_out = matches.value_of("cpp-header-out");
-                    let cpp_include_prefix = matches.value_of("cpp-include-prefix").unwrap_or("");
-                    let cpp_namespace = matches.value_of("cpp-namespace").unwrap_or("");
-                    let cpp_ext = matches.value_of("cpp-ext").unwrap_or("cpp");
-                    let cpp_header_ext = matches.value_of("hpp-ext").unwrap_or("hpp");
-                    let cpp_optional_template = matches.value_of("cpp-optional-template").unwrap_or("std::optional");
-                    let cpp_optional_header = matches.value_of("cpp-optional-header").unwrap_or("<optional");
-                    let cpp_enum_hash_workaround = match matches.value_of("cpp-enum-hash-workaround") {
-                        Some(f) => f.parse::<bool>().unwrap_or(false),
-                        None => false
-                    };
-                    let cpp_nn_header = matches.value_of("cpp-nn-header");
-                    let cpp_nn_type = matches.value_of("cpp-nn-type");
-                    let cpp_nn_check_expression = matches.value_of("cpp-nn-check-expression");
-                    let cpp_use_wide_strings = match matches.value_of("cpp-use-wide-strings") {
-                        Some(f) => f.parse::<bool>().unwrap_or(false),
-                        None => false
-                    };
-
-                    let jni_out_folder = matches.value_of("jni-out");
-                    let jni_header_out = matches.value_of("jni-header-out");
-                    let jni_include_prefix = matches.value_of("jni-include-prefix").unwrap_or("");
-                    let jni_include_cpp_prefix = matches.value_of("jni-include-cpp-prefix").unwrap_or("");
-                    let jni_namespace = matches.value_of("jni-namespace").unwrap_or("djinni_generated");
-                    let jni_base_lib_include_prefix = matches.value_of("jni-base-lib-include-prefix").unwrap_or("");
-
-
-                    let objc_out_folder = matches.value_of("objc-out");
-                    let objc_header_ext = matches.value_of("objc-h-ext").unwrap_or("h");
-                    let objc_type_prefix = matches.value_of("objc-type-prefix").unwrap_or("");
-                    println!("Objective-C Prefix: {}", objc_type_prefix);
-                    let objc_include_prefix = matches.value_of("objc-include-prefix").unwrap_or("");
-
-                    let objcpp_out_folder = matches.value_of("objcpp-out");
-                    let objcpp_ext = matches.value_of("objcpp-ext").unwrap_or("mm");
-                    let objcpp_include_prefix = matches.value_of("objcpp-include-prefix").unwrap_or("");
-                    let objcpp_include_cpp_prefix = matches.value_of("objcpp-include-cpp-prefix").unwrap_or("");
-                    let objcpp_include_objc_prefix_optional = matches.value_of("objcpp-include-objc-prefix");
-                    let cpp_extended_record_include_prefix = matches.value_of("cpp-extended-record-include-prefix").unwrap_or("");
-                    let objc_extended_record_include_prefix = matches.value_of("objc-extended-record-include-prefix").unwrap_or("");
-                    let objcpp_namespace = matches.value_of("objcpp-namespace").unwrap_or("djinni_generated");
-                    let objc_base_lib_include_prefix = matches.value_of("objc-base-lib-include-prefix").unwrap_or("");
-
-                    let yaml_out = matches.value_of("yaml-out");
-                    let yaml_out_file = matches.value_of("yaml-out-file");
-                    let yaml_prefix = matches.value_of("yaml-prefix").unwrap_or("");
-
-                    let list_in_files = matches.value_of("list-in-files");
-                    let list_out_files = matches.value_of("list-out-files");
-                    let skip_generation = match matches.value_of("skip-generation") {
-                        Some(f) => f.parse::<bool>().unwrap_or(false),
-                        None => false
-                    };
-
-                    // let ident_jni_class = matches.value_of("ident-jni-class").unwrap();
-                    // let ident_jni_class_str = String::from(ident_jni_class);
-                    // let java_ident_style = IdentStyle::build_ident_style(&ident_jni_class_str);
-                    let mut ident_style = IdentStyle::new();
-                    let mut cpp_ident_style = ident_style.cpp_style_default;
-                    let mut java_ident_style = ident_style.java_style_default; 
-                    cpp_ident_style.enm = Arc::new(IdentStyle::camel_lower);
-                    get_ident_style(&matches, "ident-java-enum", |c| java_ident_style.enm = c);
-                    get_ident_style(&matches, "ident-java-field", |c| java_ident_style.field = c);
-                    get_ident_style(&matches, "ident-java-type", |c| java_ident_style.ty = c);
-
-                    get_ident_style(&matches, "ident-cpp-enum", |c| cpp_ident_style.enm = c);
-                    get_ident_style(&matches, "ident-cpp-field", |c| cpp_ident_style.field = c);
-                    get_ident_style(&matches, "ident-cpp-method", |c| cpp_ident_style.field = c);
-                    get_ident_style(&matches, "ident-cpp-type", |c| cpp_ident_style.field = c);
-                    let mut cpp_type_enum_ident_style: Option<Arc<IdentConverter>> = None;
-                    get_ident_style(&matches, "ident-cpp-enum-type", |c| cpp_type_enum_ident_style = Some(c));
-                    get_ident_style(&matches, "ident-cpp-type-param", |c| cpp_ident_style.type_param = c);
-                    get_ident_style(&matches, "ident-cpp-local", |c| cpp_ident_style.local = c);
-                    let mut cpp_file_ident_style_optional: Option<Arc<IdentConverter>> = None;
-                    get_ident_style(&matches, "ident-cpp-file", |c| cpp_file_ident_style_optional = Some(c));
-                    let mut cpp_file_ident_style = cpp_file_ident_style_optional.unwrap_or(cpp_ident_style.ty.clone());
-
-                    let mut jni_class_ident_style_optional: Option<Arc<IdentConverter>> = None;
-                    get_ident_style(&matches, "ident-jni-class", |c| jni_class_ident_style_optional = Some(c));
-                    let mut jni_file_ident_style: Option<Arc<IdentConverter>> = None;
-                    get_ident_style(&matches, "ident-jni-file", |c| jni_file_ident_style = Some(c));
-
-                    let mut objc_ident_style = ident_style.objc_style_default;
-                    get_ident_style(&matches, "ident-objc-enum", |c| objc_ident_style.enm = c);
-                    get_ident_style(&matches, "ident-objc-field", |c| objc_ident_style.field = c);
-                    get_ident_style(&matches, "ident-objc-method", |c| objc_ident_style.method = c);
-                    get_ident_style(&matches, "ident-objc-type", |c| objc_ident_style.ty = c);
-                    get_ident_style(&matches, "ident-objc-type-param", |c| objc_ident_style.type_param = c);
-                    get_ident_style(&matches, "ident-objc-local", |c| objc_ident_style.local = c);
-                    let mut objc_file_ident_style_optional: Option<Arc<IdentConverter>> = None;
-                    get_ident_style(&matches, "ident-objc-file", |c| objc_file_ident_style_optional = Some(c));
-
-                    let cpp_header_out_folder = match cpp_header_out {
-                        Some(_) => cpp_header_out,
-                        None => cpp_out_folder.clone()
-                    };
-
-                    let jni_header_out_folder = match jni_header_out {
-                        Some(_) => jni_header_out,
-                        None => jni_out_folder.clone()
-                    };
-
-                    let jni_class_ident_style = match jni_class_ident_style_optional {
-                        Some(jcis) => jcis,
-                        None => cpp_ident_style.ty.clone()
-                    };
-
-                    let jni_base_lib_class_ident_style = jni_class_ident_style.clone();
-
-                    let jni_file_ident_style = match jni_file_ident_style {
-                        Some(s) => s,
-                        None => cpp_file_ident_style.clone()
-                    };
-
-                    let mut objc_file_ident_style = match objc_file_ident_style_optional {
-                        Some(s) => s,
-                        None => objc_ident_style.ty.clone()
-                    };
-
-                    let objcpp_include_objc_prefix = match objcpp_include_objc_prefix_optional {
-                        Some(s) => s,
-                        None => objcpp_include_objc_prefix_optional.unwrap()
-                    };
-
-                    let prefix_func = objc_ident_style.ty.clone();
-                    objc_ident_style.ty = IdentStyle::prefix(objc_type_prefix.into(), prefix_func);
-
-                    objc_file_ident_style = IdentStyle::prefix(objc_type_prefix.into(), objc_file_ident_style.clone());
-
-                    match cpp_type_enum_ident_style {
-                        Some(s) => cpp_ident_style.enum_type = s.clone(),
-                        None => {}
-                    }
-                                        
-                    // let ident_jni_file = matches.value_of("ident-jni-file").unwrap();
-                    println!("C++ Optional Template: {}", cpp_optional_template);
-
-                    let out_file_list_writer = match list_out_files {
-                        Some(p) => {
-                            let p = Path::new(p);
-                            let f = File::create(p.parent().unwrap()).expect("Unable to create file.");
-                            // let mut bw = BufWriter::new(f);
-                            Some(Arc::new(f))
-                        },
-                        None => None
-                    };
-
-                    let typer = Typer::new();
-                    // let spec = Spec::new("generated-src".into(), "cpp".into(), typer);
-                    let spec = Spec::new(typer,
-                                         java_out_folder,
-                                         java_package,
-                                         java_class_access_modifier,
-                                         java_ident_style,
-                                         java_cpp_exception,
-                                         java_annotation,
-                                         java_nullable_annotation,
-                                         java_nonnull_annotation,
-                                         java_use_final_for_record,
-                                         cpp_out_folder,
-                                         cpp_header_out_folder,
-                                         cpp_include_prefix,
-                                         cpp_extended_record_include_prefix,
-                                         cpp_namespace,
-                                         cpp_ident_style,
-                                         cpp_file_ident_style,
-                                         cpp_optional_template,
-                                         cpp_optional_header,
-                                         cpp_enum_hash_workaround,
-                                         cpp_nn_header,
-                                         cpp_nn_type,
-                                         cpp_nn_check_expression,
-                                         cpp_use_wide_strings,
-                                         jni_out_folder,
-                                         jni_header_out_folder,
-                                         jni_include_prefix,
-                                         jni_include_cpp_prefix,
-                                         jni_namespace,
-                                         jni_class_ident_style,
-                                         jni_file_ident_style,
-                                         jni_base_lib_include_prefix,
-                                         cpp_ext,
-                                         cpp_header_ext,
-                                         objc_out_folder,
-                                         objcpp_out_folder,
-                                         objc_ident_style,
-                                         objc_file_ident_style,
-                                         objcpp_ext,
-                                         objc_header_ext,
-                                         objc_include_prefix,
-                                         objc_extended_record_include_prefix,
-                                         objcpp_include_prefix,
-                                         objcpp_include_cpp_prefix,
-                                         objcpp_include_objc_prefix,
-                                         objcpp_namespace,
-                                         objc_base_lib_include_prefix,
-                                         out_file_list_writer,
-                                         skip_generation,
-                                         yaml_out,
-                                         yaml_out_file,
-                                         yaml_prefix);
-                    rusty_lamp_lib::compile(i.into(), &spec);
+                    eprintln!("{}", e.message);
+                    process::exit(EXIT_USAGE);
                 }
             }
-        },
-        None => {
+        });
+
+    let code = match matches.subcommand() {
+        ("generate", Some(m)) => run_generate(m),
+        ("fmt", Some(m)) => run_fmt(m),
+        ("check", Some(m)) => run_check(m),
+        ("dump", Some(m)) => run_dump(m),
+        _ => EXIT_USAGE
+    };
+
+    process::exit(code);
+}
+
+fn input_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+    app.arg(Arg::with_name("idl")
+            .help("The IDL file with the type definitions, typically with extension \".djinni\".")
+            .long("idl")
+            .takes_value(true))
+       .arg(Arg::with_name("include-path")
+            .help("An include path to search for Djinni @import directives. Can specify multiple paths.")
+            .long("idl-include-path")
+            .multiple(true)
+            .takes_value(true))
+}
+
+fn run_generate(matches: &ArgMatches) -> i32 {
+    let log = Log::new(matches);
+    let mut config = match load_config(matches) {
+        Ok(c) => c,
+        Err(code) => return code
+    };
+
+    if let Err(e) = apply_overrides(matches, &mut config) {
+        log.error(&e);
+        return EXIT_USAGE;
+    }
+
+    let compiler = match build_compiler(&log, config.clone()) {
+        Ok(c) => c,
+        Err(code) => return code
+    };
+
+    log.info("Generating...");
+    let output = match compiler.compile() {
+        Ok(o) => o,
+        Err(diagnostics) => {
+            log.diagnostics(&diagnostics);
+            return EXIT_DIAGNOSTICS;
+        }
+    };
+
+    if let Some(ref list) = config.list_out_files {
+        let written = File::create(list).and_then(|mut f| {
+            for file in &output.files {
+                writeln!(f, "{}", file.path.display())?;
+            }
+            Ok(())
+        });
+
+        if let Err(e) = written {
+            log.error(&format!("unable to write {}: {}", list, e));
+            return EXIT_IO;
+        }
+    }
+
+    if config.skip_generation {
+        return EXIT_OK;
+    }
+
+    for file in &output.files {
+        log.info(&format!("Writing {}", file.path.display()));
+    }
 
+    if let Err(e) = output.write_to_disk() {
+        log.error(&format!("unable to write generated files: {}", e));
+        return EXIT_IO;
+    }
+
+    EXIT_OK
+}
+
+fn run_fmt(matches: &ArgMatches) -> i32 {
+    let log = Log::new(matches);
+    let path = matches.value_of("file").unwrap();
+
+    let mut contents = String::new();
+    if let Err(e) = File::open(path).and_then(|mut f| f.read_to_string(&mut contents)) {
+        log.error(&format!("unable to read {}: {}", path, e));
+        return EXIT_IO;
+    }
+
+    // Formatted into a buffer first so a file that doesn't parse is never overwritten.
+    let mut formatted = Vec::new();
+    if let Err(diagnostics) = rusty_lamp_lib::process(path, contents, &mut formatted) {
+        log.diagnostics(&diagnostics);
+        return EXIT_DIAGNOSTICS;
+    }
+
+    let written = if matches.is_present("write") {
+        log.info(&format!("Writing {}", path));
+        File::create(path).and_then(|mut f| f.write_all(&formatted))
+    }
+    else {
+        io::stdout().write_all(&formatted)
+    };
+
+    if let Err(e) = written {
+        log.error(&format!("unable to write {}: {}", path, e));
+        return EXIT_IO;
+    }
+
+    EXIT_OK
+}
+
+fn run_check(matches: &ArgMatches) -> i32 {
+    let log = Log::new(matches);
+    let config = match load_config(matches) {
+        Ok(c) => c,
+        Err(code) => return code
+    };
+
+    let compiler = match build_compiler(&log, config) {
+        Ok(c) => c,
+        Err(code) => return code
+    };
+
+    log.info("Parsing...");
+    let result = compiler.parse().and_then(|program| {
+        log.info("Resolving...");
+        compiler.resolve(&program)
+    });
+
+    match result {
+        Ok(_) => {
+            log.info("No errors found.");
+            EXIT_OK
+        },
+        Err(diagnostics) => {
+            log.diagnostics(&diagnostics);
+            EXIT_DIAGNOSTICS
         }
     }
 }
 
-fn get_ident_style<S: AsRef<str>, F>(matches: &ArgMatches, flag_name: S, mut update: F) where F: FnMut(Arc<IdentConverter>){
-    println!("Flag Name: {}", flag_name.as_ref());
-    let m = matches.value_of(flag_name.as_ref());
-    println!("M: {:?}", m);
-    match matches.value_of(flag_name) {
-        Some(f) if f.len() > 0 => {
-            println!("Infering...{}", f);
-            let ident_str = String::from(f);
-            let ident_style = IdentStyle::build_ident_style(&ident_str);
-            match ident_style {
-                Some(func) => update(func),
-                None => panic!("Invalid ident spec: {}", ident_str)
+fn run_dump(matches: &ArgMatches) -> i32 {
+    let log = Log::new(matches);
+    let config = match load_config(matches) {
+        Ok(c) => c,
+        Err(code) => return code
+    };
+
+    let compiler = match build_compiler(&log, config) {
+        Ok(c) => c,
+        Err(code) => return code
+    };
+
+    let program = match compiler.parse() {
+        Ok(p) => p,
+        Err(diagnostics) => {
+            log.diagnostics(&diagnostics);
+            return EXIT_DIAGNOSTICS;
+        }
+    };
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let written = if matches.is_present("ast") {
+        program.statements.iter().map(|s| writeln!(out, "{:#?}", s)).collect::<io::Result<Vec<_>>>().map(|_| ())
+    }
+    else {
+        match compiler.resolve(&program) {
+            Ok(typer) => typer.dump(&mut out),
+            Err(diagnostics) => {
+                log.diagnostics(&diagnostics);
+                return EXIT_DIAGNOSTICS;
             }
-        },
-        _ => {}
+        }
     };
-    
+
+    match written {
+        Ok(_) => EXIT_OK,
+        Err(_) => EXIT_IO
+    }
+}
+
+fn load_config(matches: &ArgMatches) -> Result<Config, i32> {
+    let path = match matches.value_of("config") {
+        Some(p) => Some(p.into()),
+        None => env::current_dir().ok().and_then(Config::find)
+    };
+
+    let mut config = match path {
+        Some(p) => Config::load(p).map_err(|e| {
+            eprintln!("error: {}", e);
+            EXIT_USAGE
+        })?,
+        None => Config::default()
+    };
+
+    override_option(matches, "idl", &mut config.idl);
+    override_list(matches, "include-path", &mut config.idl_include_paths);
+
+    Ok(config)
+}
+
+fn build_compiler(log: &Log, config: Config) -> Result<Compiler, i32> {
+    let idl = match config.idl.clone() {
+        Some(i) => i,
+        None => {
+            log.error("no IDL file specified, use --idl or set `idl` in the configuration file.");
+            return Err(EXIT_USAGE);
+        }
+    };
+
+    match CompilerOptions::from_config(config) {
+        Ok(options) => Ok(Compiler::new(options).file(idl)),
+        Err(e) => {
+            log.error(&format!("{}", e));
+            Err(EXIT_USAGE)
+        }
+    }
+}
+
+fn apply_overrides(matches: &ArgMatches, config: &mut Config) -> Result<(), String> {
+    override_option(matches, "java-out", &mut config.java.out);
+    override_option(matches, "java-package", &mut config.java.package);
+    override_string(matches, "java-class-access-modifier", &mut config.java.class_access_modifier);
+    override_string(matches, "java-cpp-exception", &mut config.java.cpp_exception);
+    override_option(matches, "java-annotation", &mut config.java.annotation);
+    override_option(matches, "java-nullable-annotation", &mut config.java.nullable_annotation);
+    override_option(matches, "java-nonnull-annotation", &mut config.java.nonnull_annotation);
+    override_bool(matches, "java-use-final-for-record", &mut config.java.use_final_for_record)?;
+    override_option(matches, "cpp-out", &mut config.cpp.out);
+    override_option(matches, "cpp-header-out", &mut config.cpp.header_out);
+    override_string(matches, "cpp-include-prefix", &mut config.cpp.include_prefix);
+    override_string(matches, "cpp-namespace", &mut config.cpp.namespace);
+    override_string(matches, "cpp-ext", &mut config.cpp.ext);
+    override_string(matches, "hpp-ext", &mut config.cpp.header_ext);
+    override_string(matches, "cpp-optional-template", &mut config.cpp.optional_template);
+    override_string(matches, "cpp-optional-header", &mut config.cpp.optional_header);
+    override_string(matches, "cpp-future-template", &mut config.cpp.future_template);
+    override_string(matches, "cpp-future-header", &mut config.cpp.future_header);
+    override_string(matches, "cpp-expected-template", &mut config.cpp.expected_template);
+    override_string(matches, "cpp-expected-header", &mut config.cpp.expected_header);
+    override_string(matches, "cpp-json-type", &mut config.cpp.json_type);
+    override_string(matches, "cpp-json-header", &mut config.cpp.json_header);
+    override_bool(matches, "cpp-enum-hash-workaround", &mut config.cpp.enum_hash_workaround)?;
+    override_option(matches, "cpp-nn-header", &mut config.cpp.nn_header);
+    override_option(matches, "cpp-nn-type", &mut config.cpp.nn_type);
+    override_option(matches, "cpp-nn-check-expression", &mut config.cpp.nn_check_expression);
+    override_bool(matches, "cpp-use-wide-strings", &mut config.cpp.use_wide_strings)?;
+    override_bool(matches, "cpp-aliases", &mut config.cpp.aliases)?;
+    override_string(matches, "cpp-extended-record-include-prefix", &mut config.cpp.extended_record_include_prefix);
+    override_option(matches, "jni-out", &mut config.jni.out);
+    override_option(matches, "jni-header-out", &mut config.jni.header_out);
+    override_string(matches, "jni-include-prefix", &mut config.jni.include_prefix);
+    override_string(matches, "jni-include-cpp-prefix", &mut config.jni.include_cpp_prefix);
+    override_string(matches, "jni-namespace", &mut config.jni.namespace);
+    override_string(matches, "jni-base-lib-include-prefix", &mut config.jni.base_lib_include_prefix);
+    override_option(matches, "objc-out", &mut config.objc.out);
+    override_string(matches, "objc-h-ext", &mut config.objc.h_ext);
+    override_string(matches, "objc-type-prefix", &mut config.objc.type_prefix);
+    override_string(matches, "objc-include-prefix", &mut config.objc.include_prefix);
+    override_string(matches, "objc-extended-record-include-prefix", &mut config.objc.extended_record_include_prefix);
+    override_string(matches, "objc-base-lib-include-prefix", &mut config.objc.base_lib_include_prefix);
+    override_option(matches, "objcpp-out", &mut config.objcpp.out);
+    override_string(matches, "objcpp-ext", &mut config.objcpp.ext);
+    override_string(matches, "objcpp-include-prefix", &mut config.objcpp.include_prefix);
+    override_string(matches, "objcpp-include-cpp-prefix", &mut config.objcpp.include_cpp_prefix);
+    override_option(matches, "objcpp-include-objc-prefix", &mut config.objcpp.include_objc_prefix);
+    override_string(matches, "objcpp-namespace", &mut config.objcpp.namespace);
+    override_option(matches, "yaml-out", &mut config.yaml.out);
+    override_option(matches, "yaml-out-file", &mut config.yaml.out_file);
+    override_string(matches, "yaml-prefix", &mut config.yaml.prefix);
+    override_option(matches, "rust-out", &mut config.rust.out);
+    override_option(matches, "kotlin-out", &mut config.kotlin.out);
+    override_bool(matches, "kotlin-aliases", &mut config.kotlin.aliases)?;
+    override_option(matches, "swift-out", &mut config.swift.out);
+    override_bool(matches, "swift-aliases", &mut config.swift.aliases)?;
+    override_option(matches, "rust-bridge-out", &mut config.rust.bridge_out);
+    override_option(matches, "c-out", &mut config.c.out);
+    override_option(matches, "c-header-out", &mut config.c.header_out);
+    override_string(matches, "c-prefix", &mut config.c.prefix);
+    override_option(matches, "csharp-out", &mut config.csharp.out);
+    override_string(matches, "csharp-namespace", &mut config.csharp.namespace);
+    override_option(matches, "csharp-library", &mut config.csharp.library);
+    override_option(matches, "dart-out", &mut config.dart.out);
+    override_option(matches, "dart-library", &mut config.dart.library);
+    override_option(matches, "wasm-out", &mut config.wasm.out);
+    override_option(matches, "wasm-ts-out", &mut config.wasm.ts_out);
+    override_string(matches, "wasm-module", &mut config.wasm.module);
+    override_option(matches, "node-out", &mut config.node.out);
+    override_option(matches, "node-ts-out", &mut config.node.ts_out);
+    override_string(matches, "node-module", &mut config.node.module);
+    override_option(matches, "python-out", &mut config.python.out);
+    override_string(matches, "python-module", &mut config.python.module);
+    override_option(matches, "list-in-files", &mut config.list_in_files);
+    override_option(matches, "list-out-files", &mut config.list_out_files);
+    override_bool(matches, "skip-generation", &mut config.skip_generation)?;
+
+    override_option(matches, "ident-java-enum", &mut config.java.ident.enm);
+    override_option(matches, "ident-java-field", &mut config.java.ident.field);
+    override_option(matches, "ident-java-type", &mut config.java.ident.ty);
+    override_option(matches, "ident-cpp-enum", &mut config.cpp.ident.enm);
+    override_option(matches, "ident-cpp-field", &mut config.cpp.ident.field);
+    override_option(matches, "ident-cpp-method", &mut config.cpp.ident.method);
+    override_option(matches, "ident-cpp-type", &mut config.cpp.ident.ty);
+    override_option(matches, "ident-cpp-enum-type", &mut config.cpp.ident.enum_type);
+    override_option(matches, "ident-cpp-type-param", &mut config.cpp.ident.type_param);
+    override_option(matches, "ident-cpp-local", &mut config.cpp.ident.local);
+    override_option(matches, "ident-cpp-file", &mut config.cpp.ident.file);
+    override_option(matches, "ident-jni-class", &mut config.jni.ident.class);
+    override_option(matches, "ident-jni-file", &mut config.jni.ident.file);
+    override_option(matches, "ident-objc-enum", &mut config.objc.ident.enm);
+    override_option(matches, "ident-objc-field", &mut config.objc.ident.field);
+    override_option(matches, "ident-objc-method", &mut config.objc.ident.method);
+    override_option(matches, "ident-objc-type", &mut config.objc.ident.ty);
+    override_option(matches, "ident-objc-type-param", &mut config.objc.ident.type_param);
+    override_option(matches, "ident-objc-local", &mut config.objc.ident.local);
+    override_option(matches, "ident-objc-file", &mut config.objc.ident.file);
+
+    Ok(())
+}
+
+fn override_option(matches: &ArgMatches, name: &str, value: &mut Option<String>) {
+    if let Some(v) = matches.value_of(name) {
+        *value = Some(v.into());
+    }
+}
+
+fn override_string(matches: &ArgMatches, name: &str, value: &mut String) {
+    if let Some(v) = matches.value_of(name) {
+        *value = v.into();
+    }
+}
+
+fn override_list(matches: &ArgMatches, name: &str, value: &mut Vec<String>) {
+    if let Some(v) = matches.values_of(name) {
+        value.extend(v.map(|s| s.to_string()));
+    }
+}
+
+fn override_bool(matches: &ArgMatches, name: &str, value: &mut bool) -> Result<(), String> {
+    if let Some(v) = matches.value_of(name) {
+        *value = v.parse::<bool>().map_err(|_| format!("invalid value for --{}: {}", name, v))?;
+    }
+
+    Ok(())
 }
@@ -12,7 +12,8 @@ namespace="core_gen"
 objc_prefix="BB"
 djinni_file="djinni-src/all.djinni"
 
-cargo run -- --java-out $java_out \
+cargo run -- generate \
+   --java-out $java_out \
    --java-package $java_package \
    --ident-java-field fooBar \
    \